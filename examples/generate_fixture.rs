@@ -0,0 +1,221 @@
+// generate_fixture.rs
+//! Builds the synthetic chain behind `tests/fixtures/chain.bin`.
+//!
+//! The chain is mined from genesis through snap-coin's own `Blockchain::add_block`, so every block
+//! passes consensus: coinbases pay `get_block_reward(height)` with the dev fee split, inputs are
+//! signed, hashes, nonces and previous hashes are real and difficulty follows the block times.
+//! Keys and timestamps are seeded, proof of work nonces are not, so hashes change between runs.
+//!
+//! ```sh
+//! cargo run --example generate_fixture [blocks] [recorded] [path]
+//! ```
+use anyhow::{Context, Result, anyhow};
+use num_bigint::BigUint;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use snap_coin::{
+    core::{
+        block::Block,
+        blockchain::Blockchain,
+        transaction::{Transaction, TransactionInput, TransactionOutput},
+    },
+    crypto::{
+        Signature,
+        address_inclusion_filter::AddressInclusionFilter,
+        keys::{Private, Public},
+        merkle_tree::MerkleTree,
+    },
+    economics::{DEV_WALLET, calculate_dev_fee, get_block_reward},
+};
+use snap_coin_utils::fixture::Fixture;
+use std::{env, path::PathBuf};
+
+/// Timestamp of the genesis block, October 2025
+const GENESIS_TIMESTAMP: u64 = 1_760_000_358;
+/// Miners taking turns finding blocks
+const MINERS: u8 = 5;
+/// Every this many blocks a miner spends an older reward
+const TRANSFER_EVERY: usize = 3;
+/// How many blocks back the spent reward was mined
+const TRANSFER_AGE: usize = 10;
+/// Block times speed up while the block target is above `max / EASIEST` and slow down below it,
+/// so difficulty moves without mining getting slow
+const EASIEST: u32 = 32;
+
+fn key(seed: u8) -> Private {
+    Private::new_from_buf(&[seed; 32])
+}
+
+fn miner(height: usize) -> Private {
+    key(1 + (height % MINERS as usize) as u8)
+}
+
+/// Sign and mine `tx` the way a wallet would
+fn finish(
+    mut tx: Transaction,
+    signers: &mut [Private],
+    difficulty: &[u8; 32],
+) -> Result<Transaction> {
+    let signing_buf = tx.get_input_signing_buf()?;
+    for (input, key) in tx.inputs.iter_mut().zip(signers.iter_mut()) {
+        input.signature = Some(Signature::new_signature(key, &signing_buf));
+    }
+    tx.compute_pow(difficulty, None)?;
+    Ok(tx)
+}
+
+/// A miner sends most of the reward it got `TRANSFER_AGE` blocks ago to a fresh address
+fn transfer(
+    chain: &Blockchain,
+    height: usize,
+    timestamp: u64,
+    rng: &mut StdRng,
+) -> Result<Transaction> {
+    let funding_height = height - TRANSFER_AGE;
+    let funding = chain
+        .block_store()
+        .get_block_by_height(funding_height)
+        .ok_or_else(|| anyhow!("Missing block {}", funding_height))?;
+    let coinbase = funding
+        .transactions
+        .iter()
+        .find(|tx| tx.inputs.is_empty())
+        .ok_or_else(|| anyhow!("Block {} has no coinbase", funding_height))?;
+    let owner = miner(funding_height);
+    let reward = coinbase.outputs[1];
+    let sent = reward.amount / 100 * rng.random_range(50..=99);
+    let receiver = Private::new_from_buf(&rng.random()).to_public();
+    let tx = Transaction {
+        inputs: vec![TransactionInput {
+            transaction_id: coinbase.transaction_id.context("Coinbase has no id")?,
+            output_index: 1,
+            signature: None,
+            output_owner: owner.to_public(),
+        }],
+        outputs: vec![
+            TransactionOutput {
+                amount: sent,
+                receiver,
+            },
+            TransactionOutput {
+                amount: reward.amount - sent,
+                receiver: owner.to_public(),
+            },
+        ],
+        transaction_id: None,
+        nonce: 0,
+        timestamp: timestamp - rng.random_range(1..=30),
+    };
+    finish(tx, &mut [owner], &chain.get_transaction_difficulty())
+}
+
+fn coinbase(
+    chain: &Blockchain,
+    height: usize,
+    miner: Public,
+    timestamp: u64,
+) -> Result<Transaction> {
+    let reward = get_block_reward(height);
+    let tx = Transaction {
+        inputs: vec![],
+        outputs: vec![
+            TransactionOutput {
+                amount: calculate_dev_fee(reward),
+                receiver: DEV_WALLET,
+            },
+            TransactionOutput {
+                amount: reward - calculate_dev_fee(reward),
+                receiver: miner,
+            },
+        ],
+        transaction_id: None,
+        nonce: 0,
+        timestamp,
+    };
+    finish(tx, &mut [], &chain.get_transaction_difficulty())
+}
+
+/// Assemble and mine the next block, coinbase last like `snap_coin::build_block`
+fn mine(chain: &Blockchain, height: usize, timestamp: u64, rng: &mut StdRng) -> Result<Block> {
+    let mut transactions = Vec::new();
+    if height >= TRANSFER_AGE && height.is_multiple_of(TRANSFER_EVERY) {
+        transactions.push(transfer(chain, height, timestamp, rng)?);
+    }
+    transactions.push(coinbase(
+        chain,
+        height,
+        miner(height).to_public(),
+        timestamp,
+    )?);
+
+    let ids: Vec<_> = transactions
+        .iter()
+        .filter_map(|tx| tx.transaction_id)
+        .collect();
+    let filter = AddressInclusionFilter::create_filter(&transactions)?;
+    let mut block = Block::new_block_now(
+        transactions,
+        &chain.get_block_difficulty(),
+        &chain.get_transaction_difficulty(),
+        chain.block_store().get_last_block_hash(),
+        &MerkleTree::build(&ids).root_hash(),
+        filter,
+    );
+    block.timestamp = timestamp;
+    #[allow(deprecated)]
+    block.compute_pow()?;
+    Ok(block)
+}
+
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    let blocks: usize = args.next().map_or(Ok(600), |arg| arg.parse())?;
+    let recorded: usize = args.next().map_or(Ok(40), |arg| arg.parse())?;
+    let path = args
+        .next()
+        .map_or_else(|| PathBuf::from("tests/fixtures/chain.bin"), PathBuf::from);
+
+    let dir = env::temp_dir().join(format!(
+        "snap-coin-utils-generate-fixture-{}",
+        std::process::id()
+    ));
+    let chain = Blockchain::new(dir.to_str().context("Temp dir is not UTF-8")?);
+    let mut rng = StdRng::seed_from_u64(382);
+    let easiest = BigUint::from_bytes_be(&[u8::MAX; 32]) / EASIEST;
+
+    let mut timestamp = GENESIS_TIMESTAMP;
+    for height in 0..blocks {
+        let block = mine(&chain, height, timestamp, &mut rng)?;
+        chain
+            .add_block(block)
+            .map_err(|e| anyhow!("Block {} was rejected: {}", height, e))?;
+        timestamp += if BigUint::from_bytes_be(&chain.get_block_difficulty()) > easiest {
+            rng.random_range(12..=24)
+        } else {
+            rng.random_range(22..=30)
+        };
+    }
+
+    let start_height = blocks.saturating_sub(recorded);
+    let fixture = Fixture {
+        start_height,
+        blocks: (start_height..blocks)
+            .map(|height| {
+                chain
+                    .block_store()
+                    .get_block_by_height(height)
+                    .ok_or_else(|| anyhow!("Missing block {}", height))
+            })
+            .collect::<Result<_>>()?,
+        block_difficulty: chain.get_block_difficulty(),
+        transaction_difficulty: chain.get_transaction_difficulty(),
+    };
+    fixture.save(&path)?;
+    std::fs::remove_dir_all(&dir)?;
+    println!(
+        "Wrote blocks {}..{} to {}",
+        start_height,
+        blocks,
+        path.display()
+    );
+    Ok(())
+}
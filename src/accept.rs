@@ -0,0 +1,160 @@
+// accept.rs
+use anyhow::{Context, Result};
+use num_bigint::BigUint;
+use serde::Serialize;
+use snap_coin::{
+    blockchain_data_provider::BlockchainDataProvider,
+    core::{
+        blockchain::{BlockchainError, validate_transaction_timestamp},
+        transaction::{MAX_TRANSACTION_IO, Transaction, TransactionError, TransactionId},
+    },
+    economics::EXPIRATION_TIME,
+};
+use std::{collections::HashSet, fs, io};
+use tracing::debug;
+
+use crate::{
+    ids::STDIN_ID,
+    node::NodeClient,
+    output::{Output, OutputFormat},
+};
+
+/// Whether the node would take a transaction into its mempool
+#[derive(Debug, Serialize)]
+pub struct Acceptance {
+    pub transaction_id: Option<TransactionId>,
+    pub accepted: bool,
+    /// The error the node would answer with, worded as the node words it
+    pub reason: Option<String>,
+}
+
+/// Run the node's mempool admission checks against its current UTXOs, difficulties and mempool,
+/// without submitting the transaction. The node has no request for this, so the checks are
+/// repeated here in the node's order, and each UTXO is looked up through the transaction holding it.
+pub async fn test_accept(client: &NodeClient, tx: &Transaction) -> Result<Acceptance> {
+    let verdict = check(client, tx).await?;
+    Ok(Acceptance {
+        transaction_id: tx.transaction_id,
+        accepted: verdict.is_ok(),
+        reason: verdict.err().map(|e| e.to_string()),
+    })
+}
+
+async fn check(client: &NodeClient, tx: &Transaction) -> Result<Result<(), BlockchainError>> {
+    let rejected = |e: TransactionError| Ok(Err(e.into()));
+    if let Err(e) = tx.check_completeness() {
+        return rejected(e);
+    }
+    let Some(id) = tx.transaction_id else {
+        return rejected(TransactionError::MissingId);
+    };
+    let live = client.get_live_transaction_difficulty().await?;
+    if BigUint::from_bytes_be(&live) < BigUint::from_bytes_be(&*id) {
+        return Ok(Err(BlockchainError::LiveTransactionDifficulty));
+    }
+    // The node's check underflows on timestamps this old, they are long expired
+    if tx.timestamp < EXPIRATION_TIME {
+        return Ok(Err(BlockchainError::InvalidTimestamp));
+    }
+    if let Err(e) = validate_transaction_timestamp(tx) {
+        return Ok(Err(e));
+    }
+
+    if !id.compare_with_data(&tx.get_tx_hashing_buf()?) {
+        return rejected(TransactionError::InvalidHash(id.dump_base36()));
+    }
+    let difficulty = client.get_transaction_difficulty().await?;
+    if BigUint::from_bytes_be(&*id) > BigUint::from_bytes_be(&difficulty) {
+        return rejected(TransactionError::InsufficientDifficulty(id.dump_base36()));
+    }
+    if tx.inputs.is_empty() {
+        return rejected(TransactionError::NoInputs);
+    }
+    if tx.inputs.len() + tx.outputs.len() > MAX_TRANSACTION_IO {
+        return rejected(TransactionError::TooMuchIO);
+    }
+
+    let signing_buf = tx.get_input_signing_buf()?;
+    let mut used = HashSet::new();
+    let mut input_sum = 0u64;
+    for input in &tx.inputs {
+        debug!("get_transaction {}", input.transaction_id.dump_base36());
+        let Some(funding) = client.get_transaction(&input.transaction_id).await? else {
+            return rejected(TransactionError::InputNotFound(id.dump_base36()));
+        };
+        let Some(output) = funding.outputs.get(input.output_index) else {
+            return rejected(TransactionError::InvalidInputIndex {
+                tx_id: id.dump_base36(),
+                input_tx_id: input.transaction_id.dump_base36(),
+            });
+        };
+        let unspent = client
+            .get_available_transaction_outputs(output.receiver)
+            .await?
+            .iter()
+            .any(|(tx_id, _, index)| {
+                *tx_id == input.transaction_id && *index == input.output_index
+            });
+        if !unspent {
+            return rejected(TransactionError::SpentInputIndex);
+        }
+        let signed = input.signature.is_some_and(|signature| {
+            signature
+                .validate_with_public(&output.receiver, &signing_buf)
+                .unwrap_or(false)
+        });
+        if !signed {
+            return rejected(TransactionError::InvalidSignature(id.dump_base36()));
+        }
+        if output.receiver != input.output_owner {
+            return rejected(TransactionError::IncorrectOutputOwner(id.dump_base36()));
+        }
+        if !used.insert((input.transaction_id, input.output_index)) {
+            return rejected(TransactionError::DoubleSpend(id.dump_base36()));
+        }
+        input_sum += output.amount;
+    }
+    if tx.outputs.iter().any(|output| output.amount == 0) {
+        return rejected(TransactionError::ZeroOutput(id.dump_base36()));
+    }
+    if input_sum != tx.outputs.iter().map(|output| output.amount).sum::<u64>() {
+        return rejected(TransactionError::SumMismatch(id.dump_base36()));
+    }
+
+    // Inputs already spent by a transaction waiting in the mempool, itself included
+    let mempool = client.get_mempool().await?;
+    let conflict = mempool.iter().any(|pending| {
+        pending.inputs.iter().any(|spent| {
+            tx.inputs.iter().any(|input| {
+                input.transaction_id == spent.transaction_id
+                    && input.output_index == spent.output_index
+            })
+        })
+    });
+    if conflict {
+        return rejected(TransactionError::DoubleSpend(id.dump_base36()));
+    }
+    Ok(Ok(()))
+}
+
+/// Check whether the node would accept `tx`, a JSON transaction, a file holding one or `-` for stdin
+pub async fn run_test_accept(client: &NodeClient, tx: &str, out: &Output) -> Result<()> {
+    let json = if tx == STDIN_ID {
+        io::read_to_string(io::stdin())?
+    } else if tx.trim_start().starts_with('{') {
+        tx.to_string()
+    } else {
+        fs::read_to_string(tx).with_context(|| format!("Could not read {tx}"))?
+    };
+    let tx = serde_json::from_str(&json).context("Could not parse the transaction")?;
+    let acceptance = test_accept(client, &tx).await?;
+    match out.format {
+        OutputFormat::Text => match &acceptance.reason {
+            None => println!("Accepted"),
+            Some(reason) => println!("Rejected: {}", reason),
+        },
+        OutputFormat::Debug => println!("{:#?}", acceptance),
+        OutputFormat::Json => out.json(&acceptance)?,
+    }
+    Ok(())
+}
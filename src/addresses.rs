@@ -0,0 +1,208 @@
+// addresses.rs
+use anyhow::{Result, anyhow};
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::core::block::Block;
+use std::{
+    collections::{BTreeMap, HashSet},
+    ops::Range,
+    time::Duration,
+};
+use tracing::info;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    chart::{self, PlotFiles, Series},
+    format_timestamp,
+    output::{Output, OutputFormat},
+    tables::{align_right, new_table},
+};
+
+/// Addresses appearing in the blocks timestamped within one bucket of time
+#[derive(Debug, Serialize)]
+pub struct AddressBucket {
+    /// Unix time the bucket starts at, a multiple of the bucket length
+    pub start: u64,
+    /// Lowest height of the bucket's blocks
+    pub first_height: usize,
+    pub blocks: usize,
+    /// Distinct addresses spending or receiving in the bucket
+    pub active: usize,
+    /// Active addresses never seen in an earlier block
+    pub new: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressActivity {
+    pub bucket_secs: u64,
+    pub buckets: Vec<AddressBucket>,
+    /// Distinct addresses active anywhere in the scanned blocks
+    pub active: usize,
+    pub new: usize,
+    /// Addresses seen since the first scanned block, the measured ones included
+    pub known: usize,
+}
+
+impl AddressActivity {
+    /// Active and new addresses of every bucket against the first height in it
+    pub fn series(&self) -> Vec<Series> {
+        let series = |name: &str, count: fn(&AddressBucket) -> usize| Series {
+            name: format!("{} addresses per {}s", name, self.bucket_secs),
+            points: self
+                .buckets
+                .iter()
+                .map(|bucket| (bucket.first_height, count(bucket) as f64))
+                .collect(),
+        };
+        vec![
+            series("Active", |bucket| bucket.active),
+            series("New", |bucket| bucket.new),
+        ]
+    }
+}
+
+/// Every address a block's transactions spend from or pay to
+pub fn block_addresses(block: &Block) -> HashSet<[u8; 32]> {
+    block
+        .transactions
+        .iter()
+        .flat_map(|tx| {
+            tx.inputs
+                .iter()
+                .map(|input| *input.output_owner.dump_buf())
+                .chain(tx.outputs.iter().map(|output| *output.receiver.dump_buf()))
+        })
+        .collect()
+}
+
+/// Group blocks starting at `start_height`, given as their timestamp and addresses, into buckets of
+/// `bucket_secs` aligned to the unix epoch. Addresses in `seen` are not new.
+pub fn address_buckets(
+    start_height: usize,
+    blocks: &[(u64, HashSet<[u8; 32]>)],
+    mut seen: HashSet<[u8; 32]>,
+    bucket_secs: u64,
+) -> Result<AddressActivity> {
+    if bucket_secs == 0 {
+        return Err(anyhow!("Buckets must be at least a second long"));
+    }
+    let mut buckets: BTreeMap<u64, (AddressBucket, HashSet<[u8; 32]>)> = BTreeMap::new();
+    let mut active = HashSet::new();
+    let mut new = 0;
+    for (offset, (timestamp, addresses)) in blocks.iter().enumerate() {
+        let start = timestamp - timestamp % bucket_secs;
+        let (bucket, bucket_addresses) = buckets.entry(start).or_insert_with(|| {
+            (
+                AddressBucket {
+                    start,
+                    first_height: start_height + offset,
+                    blocks: 0,
+                    active: 0,
+                    new: 0,
+                },
+                HashSet::new(),
+            )
+        });
+        bucket.blocks += 1;
+        for address in addresses {
+            bucket_addresses.insert(*address);
+            active.insert(*address);
+            if seen.insert(*address) {
+                bucket.new += 1;
+                new += 1;
+            }
+        }
+    }
+    Ok(AddressActivity {
+        bucket_secs,
+        buckets: buckets
+            .into_values()
+            .map(|(mut bucket, addresses)| {
+                bucket.active = addresses.len();
+                bucket
+            })
+            .collect(),
+        active: active.len(),
+        new,
+        known: seen.len(),
+    })
+}
+
+/// Active and new addresses of the blocks from `from` to the end of `history` in buckets of
+/// `bucket_secs`. The blocks of `history` before `from` only mark addresses as seen, so new
+/// addresses are only never seen before when `history` starts at genesis.
+pub async fn address_activity<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    history: Range<usize>,
+    from: usize,
+    bucket_secs: u64,
+) -> Result<AddressActivity> {
+    info!(
+        "Scanning blocks {}..{} for active addresses from {}",
+        history.start, history.end, from
+    );
+    let mut seen = HashSet::new();
+    let mut blocks = Vec::with_capacity(history.end.saturating_sub(from));
+    for_each_block(provider, history, |height, block| {
+        let addresses = block_addresses(&block);
+        if height < from {
+            seen.extend(addresses);
+        } else {
+            blocks.push((block.timestamp, addresses));
+        }
+        Ok(())
+    })
+    .await?;
+    address_buckets(from, &blocks, seen, bucket_secs)
+}
+
+pub fn activity_table(activity: &AddressActivity) -> Table {
+    let mut table = new_table(&["Start", "First Block", "Blocks", "Active", "New"]);
+    for bucket in &activity.buckets {
+        table.add_row(vec![
+            format_timestamp(bucket.start),
+            bucket.first_height.to_string(),
+            bucket.blocks.to_string(),
+            bucket.active.to_string(),
+            bucket.new.to_string(),
+        ]);
+    }
+    align_right(&mut table, &[1, 2, 3, 4]);
+    table
+}
+
+pub fn print_address_activity(activity: &AddressActivity) {
+    println!("{}", activity_table(activity));
+    println!(
+        "{} distinct active addresses, {} of them new, {} addresses seen in total",
+        activity.active, activity.new, activity.known
+    );
+}
+
+/// Count the active and new addresses of the past `blocks` blocks in buckets of `bucket`
+pub async fn run_addresses<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    blocks: usize,
+    bucket: Duration,
+    sparkline: bool,
+    plots: &PlotFiles,
+    out: &Output,
+) -> Result<()> {
+    let height = provider.get_height().await?;
+    let activity = address_activity(
+        provider,
+        0..height,
+        height.saturating_sub(blocks),
+        bucket.as_secs(),
+    )
+    .await?;
+    let chart_series = activity.series();
+    plots.write(&chart_series)?;
+    match out.format {
+        OutputFormat::Text if sparkline => chart::print_sparklines(&chart_series),
+        OutputFormat::Text => print_address_activity(&activity),
+        OutputFormat::Debug => println!("{:#?}", activity),
+        OutputFormat::Json => out.json(&activity)?,
+    }
+    Ok(())
+}
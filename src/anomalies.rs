@@ -0,0 +1,232 @@
+// anomalies.rs
+use anyhow::Result;
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::crypto::keys::Public;
+use std::{collections::HashMap, ops::Range};
+use tracing::info;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    miners::block_miner,
+    normalize_difficulty,
+    output::{Output, OutputFormat},
+    tables::new_table,
+};
+
+/// What a block is scanned for anomalies by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScannedBlock {
+    pub height: usize,
+    pub timestamp: u64,
+    pub difficulty: f64,
+    pub miner: Option<[u8; 32]>,
+}
+
+/// Thresholds beyond which events are flagged
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnomalyOptions {
+    /// Standard deviations from the mean block interval
+    pub sigma: f64,
+    /// Factor the difficulty may rise or fall by between two blocks
+    pub difficulty_jump: f64,
+    /// Blocks in every window miner shares are compared over
+    pub share_window: usize,
+    /// Percentage points a miner's share may gain from one window to the next
+    pub share_jump: f64,
+}
+
+impl Default for AnomalyOptions {
+    fn default() -> Self {
+        AnomalyOptions {
+            sigma: 3.0,
+            difficulty_jump: 2.0,
+            share_window: 100,
+            share_jump: 25.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AnomalyKind {
+    Interval,
+    Difficulty,
+    TimestampReversal,
+    MinerShare,
+}
+
+impl AnomalyKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            AnomalyKind::Interval => "interval",
+            AnomalyKind::Difficulty => "difficulty",
+            AnomalyKind::TimestampReversal => "timestamp reversal",
+            AnomalyKind::MinerShare => "miner share",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Anomaly {
+    pub height: usize,
+    pub kind: AnomalyKind,
+    pub explanation: String,
+}
+
+/// Flag the unusual events among `blocks`, given in height order, sorted by height:
+/// block intervals more than `sigma` standard deviations from the mean, difficulty rising or
+/// falling by more than `difficulty_jump` times in one block, timestamps before the previous
+/// block's, and miners whose share of a `share_window` block window gains more than
+/// `share_jump` points over the window before it.
+pub fn detect_anomalies(blocks: &[ScannedBlock], options: &AnomalyOptions) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+
+    let intervals: Vec<f64> = blocks
+        .windows(2)
+        .map(|pair| pair[1].timestamp as f64 - pair[0].timestamp as f64)
+        .collect();
+    let mean = intervals.iter().sum::<f64>() / intervals.len().max(1) as f64;
+    let std_dev = (intervals.iter().map(|i| (i - mean).powi(2)).sum::<f64>()
+        / intervals.len().max(1) as f64)
+        .sqrt();
+
+    for (pair, &interval) in blocks.windows(2).zip(&intervals) {
+        let (previous, block) = (&pair[0], &pair[1]);
+        if block.timestamp < previous.timestamp {
+            anomalies.push(Anomaly {
+                height: block.height,
+                kind: AnomalyKind::TimestampReversal,
+                explanation: format!(
+                    "timestamp is {}s before block {}'s",
+                    previous.timestamp - block.timestamp,
+                    previous.height
+                ),
+            });
+        } else if std_dev > 0.0 && (interval - mean).abs() > options.sigma * std_dev {
+            anomalies.push(Anomaly {
+                height: block.height,
+                kind: AnomalyKind::Interval,
+                explanation: format!(
+                    "{:.0}s after the previous block, {:.1} standard deviations from the {:.1}s mean",
+                    interval,
+                    (interval - mean) / std_dev,
+                    mean
+                ),
+            });
+        }
+
+        if previous.difficulty > 0.0 && block.difficulty > 0.0 {
+            let ratio = block.difficulty / previous.difficulty;
+            if ratio > options.difficulty_jump || ratio < 1.0 / options.difficulty_jump {
+                anomalies.push(Anomaly {
+                    height: block.height,
+                    kind: AnomalyKind::Difficulty,
+                    explanation: format!(
+                        "difficulty went from {:.2} to {:.2}, {:.2}x",
+                        previous.difficulty, block.difficulty, ratio
+                    ),
+                });
+            }
+        }
+    }
+
+    let windows: Vec<&[ScannedBlock]> = blocks.chunks(options.share_window.max(1)).collect();
+    for pair in windows.windows(2) {
+        let (before, after) = (shares(pair[0]), shares(pair[1]));
+        let mut jumps: Vec<_> = after
+            .iter()
+            .map(|(miner, share)| (miner, before.get(miner).copied().unwrap_or(0.0), *share))
+            .filter(|(_, from, to)| to - from > options.share_jump)
+            .collect();
+        jumps.sort_by(|a, b| a.0.cmp(b.0));
+        let (first, last) = (pair[1][0].height, pair[1][pair[1].len() - 1].height);
+        for (miner, from, to) in jumps {
+            anomalies.push(Anomaly {
+                height: first,
+                kind: AnomalyKind::MinerShare,
+                explanation: format!(
+                    "{} went from {:.1}% to {:.1}% of blocks {}..{}",
+                    Public::new_from_buf(miner).dump_base36(),
+                    from,
+                    to,
+                    first,
+                    last + 1
+                ),
+            });
+        }
+    }
+
+    anomalies.sort_by_key(|anomaly| anomaly.height);
+    anomalies
+}
+
+/// Percentage of the blocks with a miner that every miner mined
+fn shares(blocks: &[ScannedBlock]) -> HashMap<[u8; 32], f64> {
+    let mut counts: HashMap<[u8; 32], usize> = HashMap::new();
+    for miner in blocks.iter().filter_map(|block| block.miner) {
+        *counts.entry(miner).or_default() += 1;
+    }
+    let total: usize = counts.values().sum();
+    counts
+        .into_iter()
+        .map(|(miner, blocks)| (miner, blocks as f64 / total as f64 * 100.0))
+        .collect()
+}
+
+/// The timestamp, difficulty and miner of the blocks at `heights`
+pub async fn scan_blocks<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+) -> Result<Vec<ScannedBlock>> {
+    info!(
+        "Scanning blocks {}..{} for anomalies",
+        heights.start, heights.end
+    );
+    let mut blocks = Vec::with_capacity(heights.len());
+    for_each_block(provider, heights, |height, block| {
+        blocks.push(ScannedBlock {
+            height,
+            timestamp: block.timestamp,
+            difficulty: normalize_difficulty(&block.meta.block_pow_difficulty),
+            miner: block_miner(&block),
+        });
+        Ok(())
+    })
+    .await?;
+    Ok(blocks)
+}
+
+pub fn anomalies_table(anomalies: &[Anomaly]) -> Table {
+    let mut table = new_table(&["Height", "Kind", "Explanation"]);
+    for anomaly in anomalies {
+        table.add_row(vec![
+            anomaly.height.to_string(),
+            anomaly.kind.name().to_string(),
+            anomaly.explanation.clone(),
+        ]);
+    }
+    table
+}
+
+/// Scan the past `blocks` blocks and list their anomalies
+pub async fn run_anomalies<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    blocks: usize,
+    options: &AnomalyOptions,
+    out: &Output,
+) -> Result<()> {
+    let height = provider.get_height().await?;
+    let start = height.saturating_sub(blocks);
+    let scanned = scan_blocks(provider, start..height).await?;
+    let anomalies = detect_anomalies(&scanned, options);
+    match out.format {
+        OutputFormat::Text if anomalies.is_empty() => {
+            println!("No anomalies in blocks {}..{}", start, height)
+        }
+        OutputFormat::Text => println!("{}", anomalies_table(&anomalies)),
+        OutputFormat::Debug => println!("{:#?}", anomalies),
+        OutputFormat::Json => out.json(&anomalies)?,
+    }
+    Ok(())
+}
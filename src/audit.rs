@@ -0,0 +1,166 @@
+// audit.rs
+use anyhow::{Result, anyhow};
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::core::{block::Block, difficulty::DifficultyState};
+use std::{ops::Range, sync::RwLock};
+use tracing::info;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    normalize_difficulty,
+    output::{Output, OutputFormat},
+    tables::{align_right, new_table},
+};
+
+/// The block and transaction difficulty the rules give the block after `previous`, adjusting its
+/// difficulties by its interval since the block before it, mined at `before_timestamp`, and by
+/// its transaction count. The node's own adjustment is applied, so the audit follows the rules of
+/// the snap-coin version this is built against.
+pub fn expected_difficulties(previous: &Block, before_timestamp: u64) -> ([u8; 32], [u8; 32]) {
+    let state = DifficultyState {
+        block_difficulty: RwLock::new(previous.meta.block_pow_difficulty),
+        transaction_difficulty: RwLock::new(previous.meta.tx_pow_difficulty),
+        last_timestamp: RwLock::new(before_timestamp),
+    };
+    state.update_difficulty(previous);
+    let block = *state.block_difficulty.read().unwrap();
+    let tx = *state.transaction_difficulty.read().unwrap();
+    (block, tx)
+}
+
+/// A block whose difficulties are not what the rules give it
+#[derive(Debug, Serialize)]
+pub struct RetargetMismatch {
+    pub height: usize,
+    /// Normalized difficulties
+    pub expected_block_difficulty: f64,
+    pub actual_block_difficulty: f64,
+    pub expected_tx_difficulty: f64,
+    pub actual_tx_difficulty: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RetargetAudit {
+    pub start_height: usize,
+    pub end_height: usize,
+    pub mismatches: Vec<RetargetMismatch>,
+}
+
+impl RetargetAudit {
+    pub fn checked(&self) -> usize {
+        self.end_height - self.start_height
+    }
+}
+
+/// Recompute the difficulties of the blocks at `heights` from the two blocks before each and
+/// compare them with the ones the blocks carry. Only the difficulty adjustment is checked, not the
+/// proof of work.
+pub async fn audit_retargets<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+) -> Result<RetargetAudit> {
+    if heights.start < 2 {
+        return Err(anyhow!(
+            "Blocks below height 2 have no two blocks before them to check against"
+        ));
+    }
+    info!(
+        "Checking the difficulty adjustments of blocks {}..{}",
+        heights.start, heights.end
+    );
+    let mut before_timestamp = None;
+    let mut previous: Option<Block> = None;
+    let mut mismatches = Vec::new();
+    for_each_block(provider, heights.start - 2..heights.end, |height, block| {
+        if let (Some(previous), Some(before_timestamp)) = (&previous, before_timestamp) {
+            let (block_difficulty, tx_difficulty) =
+                expected_difficulties(previous, before_timestamp);
+            if block.meta.block_pow_difficulty != block_difficulty
+                || block.meta.tx_pow_difficulty != tx_difficulty
+            {
+                mismatches.push(RetargetMismatch {
+                    height,
+                    expected_block_difficulty: normalize_difficulty(&block_difficulty),
+                    actual_block_difficulty: normalize_difficulty(&block.meta.block_pow_difficulty),
+                    expected_tx_difficulty: normalize_difficulty(&tx_difficulty),
+                    actual_tx_difficulty: normalize_difficulty(&block.meta.tx_pow_difficulty),
+                });
+            }
+        }
+        before_timestamp = previous.as_ref().map(|previous| previous.timestamp);
+        previous = Some(block);
+        Ok(())
+    })
+    .await?;
+    Ok(RetargetAudit {
+        start_height: heights.start,
+        end_height: heights.end,
+        mismatches,
+    })
+}
+
+pub fn mismatches_table(audit: &RetargetAudit) -> Table {
+    let mut table = new_table(&["Height", "Block Diff", "Expected", "TX Diff", "Expected"]);
+    for mismatch in &audit.mismatches {
+        table.add_row(vec![
+            mismatch.height.to_string(),
+            format!("{:.2}", mismatch.actual_block_difficulty),
+            format!("{:.2}", mismatch.expected_block_difficulty),
+            format!("{:.2}", mismatch.actual_tx_difficulty),
+            format!("{:.2}", mismatch.expected_tx_difficulty),
+        ]);
+    }
+    align_right(&mut table, &[1, 2, 3, 4]);
+    table
+}
+
+pub fn print_retarget_audit(audit: &RetargetAudit) {
+    if audit.mismatches.is_empty() {
+        println!(
+            "All {} blocks {}..{} carry the difficulties the rules give them",
+            audit.checked(),
+            audit.start_height,
+            audit.end_height
+        );
+        return;
+    }
+    println!("{}", mismatches_table(audit));
+    println!(
+        "{} of {} blocks {}..{} deviate from the difficulty adjustment rules",
+        audit.mismatches.len(),
+        audit.checked(),
+        audit.start_height,
+        audit.end_height
+    );
+}
+
+/// Audit the retargets of `range`, or of the past `blocks` blocks, failing with `check` on a mismatch
+pub async fn run_retarget_audit<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    blocks: usize,
+    range: Option<Range<usize>>,
+    check: bool,
+    out: &Output,
+) -> Result<()> {
+    let heights = match range {
+        Some(range) => range,
+        None => {
+            let height = provider.get_height().await?;
+            height.saturating_sub(blocks).max(2)..height
+        }
+    };
+    let audit = audit_retargets(provider, heights).await?;
+    match out.format {
+        OutputFormat::Text => print_retarget_audit(&audit),
+        OutputFormat::Debug => println!("{:#?}", audit),
+        OutputFormat::Json => out.json(&audit)?,
+    }
+    if check && !audit.mismatches.is_empty() {
+        return Err(anyhow!(
+            "{} blocks deviate from the difficulty adjustment rules",
+            audit.mismatches.len()
+        ));
+    }
+    Ok(())
+}
@@ -1,59 +1,429 @@
 // averages.rs
 use anyhow::{Result, anyhow};
 use bincode::encode_to_vec;
-use snap_coin::{
-    api::client::Client, blockchain_data_provider::BlockchainDataProvider, core::{transaction::Transaction},
+use serde::{Serialize, Serializer};
+use snap_coin::{core::block::MAX_TRANSACTIONS_PER_BLOCK, crypto::keys::Public};
+use std::ops::Range;
+use tracing::info;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    chart::{self, PlotFiles, Series},
+    color,
+    counts::AddressCounter,
+    format_snap,
+    headers::BlockHeader,
+    miners::{block_miner, coinbase},
+    normalize_difficulty,
+    output::{Output, OutputFormat},
+    patterns::PatternMix,
+    price::{self, Quote},
+    rewards::supply_at,
 };
-use std::collections::HashMap;
 
-use crate::normalize_difficulty;
+const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 60.0 * 60.0;
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct BlockAverages {
     pub average: f64,
     pub std_dev: f64,
     pub median: f64,
     pub min: f64,
     pub max: f64,
+    #[serde(rename = "sample_size")]
     pub _sample_size: usize,
+    /// 95% confidence interval of `average`
+    pub confidence: ConfidenceInterval,
 }
 
-#[derive(Debug)]
+/// Samples below which an average is too rough to quote on its own
+pub const MIN_ADEQUATE_SAMPLES: usize = 30;
+
+/// Margin, as a fraction of the estimate, above which an average is too rough to quote on its own
+pub const MAX_ADEQUATE_MARGIN: f64 = 0.1;
+
+/// Two sided 95% critical values of Student's t for 1 to 30 degrees of freedom
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// 95% confidence interval of an estimate
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ConfidenceInterval {
+    pub low: f64,
+    pub high: f64,
+    /// Half the width of the interval
+    pub margin: f64,
+    pub samples: usize,
+    /// Whether there are at least `MIN_ADEQUATE_SAMPLES` samples and the margin is within
+    /// `MAX_ADEQUATE_MARGIN` of the estimate
+    pub adequate: bool,
+}
+
+impl ConfidenceInterval {
+    /// Interval of `estimate` with standard error `std_error` from `samples` samples, using
+    /// Student's t for small samples
+    pub fn new(estimate: f64, std_error: f64, samples: usize) -> Self {
+        let critical = match samples.saturating_sub(1) {
+            0 => f64::INFINITY,
+            df if df <= T_95.len() => T_95[df - 1],
+            _ => 1.96,
+        };
+        let margin = if std_error == 0.0 {
+            0.0
+        } else {
+            critical * std_error
+        };
+        ConfidenceInterval {
+            low: estimate - margin,
+            high: estimate + margin,
+            margin,
+            samples,
+            adequate: samples >= MIN_ADEQUATE_SAMPLES
+                && margin <= MAX_ADEQUATE_MARGIN * estimate.abs(),
+        }
+    }
+
+    /// Interval of the mean of `values`
+    pub fn of_mean(values: &[f64]) -> Self {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n.max(1.0);
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+        ConfidenceInterval::new(mean, (variance / n.max(1.0)).sqrt(), values.len())
+    }
+
+    /// Interval of the ratio of the sums of `numerators` and `denominators`, paired per sample,
+    /// by the delta method
+    pub fn of_ratio(numerators: &[f64], denominators: &[f64]) -> Self {
+        let n = numerators.len().min(denominators.len());
+        let count = n as f64;
+        let mean = |values: &[f64]| values[..n].iter().sum::<f64>() / count.max(1.0);
+        let (x, y) = (mean(numerators), mean(denominators));
+        let ratio = if y == 0.0 { 0.0 } else { x / y };
+        // Residuals of every sample against the ratio, whose spread is the ratio's
+        let variance = numerators
+            .iter()
+            .zip(denominators)
+            .map(|(a, b)| (a - ratio * b).powi(2))
+            .sum::<f64>()
+            / (count - 1.0).max(1.0);
+        let std_error = if y == 0.0 {
+            0.0
+        } else {
+            (variance / count.max(1.0)).sqrt() / y
+        };
+        ConfidenceInterval::new(ratio, std_error, n)
+    }
+
+    /// Why the estimate should not be quoted as precise, if it shouldn't
+    pub fn note(&self) -> Option<String> {
+        if self.adequate {
+            return None;
+        }
+        let estimate = (self.low + self.high) / 2.0;
+        let relative = if estimate == 0.0 {
+            0.0
+        } else {
+            self.margin / estimate.abs() * 100.0
+        };
+        Some(if self.samples < MIN_ADEQUATE_SAMPLES {
+            format!(
+                "only {} samples, at least {} are needed for a usable estimate",
+                self.samples, MIN_ADEQUATE_SAMPLES
+            )
+        } else {
+            format!(
+                "the estimate is only good to within {:.0}%, scan more blocks to narrow it",
+                relative
+            )
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct ChainStats {
+    /// Height of the first block in the window
+    pub start_height: usize,
     pub block_time: BlockAverages,
     pub avg_txs_per_block: f64,
     pub avg_io_per_block: f64,
     pub avg_block_size_bytes: f64,
+    /// Mean share of the transactions a block may hold, in percent
+    pub avg_utilization: f64,
     pub tps: f64,
+    /// 95% confidence interval of `tps`, over the blocks after the first
+    pub tps_confidence: ConfidenceInterval,
+    /// Transactions of the window by input and output pattern, to tell what the TPS is made of
+    pub pattern_mix: PatternMix,
 
     pub avg_block_difficulty: f64,
     pub avg_tx_difficulty: f64,
 
+    /// Coins minted by coinbase transactions in the window (nano)
+    pub issued: u64,
+    /// Total coins minted up to the end of the window (nano)
+    pub circulating_supply: u64,
+    /// Issuance rate extrapolated to a year, as a fraction of circulating supply
+    pub annual_inflation: f64,
+
+    #[serde(serialize_with = "serialize_address_counts")]
     pub top_miners: Vec<([u8; 32], usize)>,
+    #[serde(serialize_with = "serialize_address_counts")]
     pub top_addresses: Vec<([u8; 32], usize)>,
 
     pub block_difficulty_series: Vec<f64>,
     pub tx_difficulty_series: Vec<f64>,
+    /// Seconds since the previous block, starting at `start_height + 1`
+    pub block_time_series: Vec<f64>,
+    /// Block transactions over its block time, starting at `start_height + 1`
+    pub tps_series: Vec<f64>,
+    /// Encoded size of every block in bytes
+    pub block_size_series: Vec<f64>,
+    /// Transactions of every block as a percentage of `MAX_TRANSACTIONS_PER_BLOCK`, the only
+    /// limit on block size the protocol has
+    pub utilization_series: Vec<f64>,
+}
+
+/// The issuance part of the chain stats, printed by `inflation`
+#[derive(Debug, Serialize)]
+pub struct Inflation {
+    /// Blocks asked to sample
+    pub blocks: usize,
+    pub issued: u64,
+    pub circulating_supply: u64,
+    pub annual_inflation: f64,
+}
+
+impl Inflation {
+    pub fn new(stats: &ChainStats, blocks: usize) -> Inflation {
+        Inflation {
+            blocks,
+            issued: stats.issued,
+            circulating_supply: stats.circulating_supply,
+            annual_inflation: stats.annual_inflation,
+        }
+    }
+}
+
+/// Time and difficulty statistics computed from block headers only, by `averages --headers-only`
+#[derive(Debug, Serialize)]
+pub struct HeaderStats {
+    /// Height of the first block in the window
+    pub start_height: usize,
+    pub block_time: BlockAverages,
+    pub avg_block_difficulty: f64,
+    pub avg_tx_difficulty: f64,
+    pub block_difficulty_series: Vec<f64>,
+    pub tx_difficulty_series: Vec<f64>,
+    /// Seconds since the previous block, starting at `start_height + 1`
+    pub block_time_series: Vec<f64>,
+}
+
+/// Serialize address frequency tables with base36 addresses instead of raw byte arrays
+fn serialize_address_counts<S: Serializer>(
+    counts: &[([u8; 32], usize)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(
+        counts
+            .iter()
+            .map(|(addr, count)| (Public::new_from_buf(addr).dump_base36(), count)),
+    )
+}
+
+/// Per block series of the stats window, as plotted by `--plot-out` and exported by `--plot-data`
+pub fn chart_series(stats: &ChainStats) -> Vec<Series> {
+    let start = stats.start_height;
+    vec![
+        Series::from_heights("Block Difficulty", start, &stats.block_difficulty_series),
+        Series::from_heights("TX Difficulty", start, &stats.tx_difficulty_series),
+        Series::from_heights("Block Time (s)", start + 1, &stats.block_time_series),
+        Series::from_heights("TPS", start + 1, &stats.tps_series),
+        Series::from_heights("Block Size (bytes)", start, &stats.block_size_series),
+        Series::from_heights("Utilization (%)", start, &stats.utilization_series),
+    ]
+}
+
+/// Per block series of a header only stats window
+pub fn header_chart_series(stats: &HeaderStats) -> Vec<Series> {
+    let start = stats.start_height;
+    vec![
+        Series::from_heights("Block Difficulty", start, &stats.block_difficulty_series),
+        Series::from_heights("TX Difficulty", start, &stats.tx_difficulty_series),
+        Series::from_heights("Block Time (s)", start + 1, &stats.block_time_series),
+    ]
+}
+
+/// Scaling options for `plot_difficulties`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PlotOptions {
+    /// Plot log10 of the values so single spikes don't flatten everything else
+    pub log_scale: bool,
+    /// Scale both columns against the same maximum instead of each their own
+    pub shared_scale: bool,
+    /// Average consecutive blocks together so at most this many rows are printed
+    pub max_rows: Option<usize>,
+}
+
+/// Average values into buckets of `bucket` consecutive blocks, labelled by their first block
+fn downsample(blocks: &[usize], values: &[f64], bucket: usize) -> (Vec<usize>, Vec<f64>) {
+    let labels = blocks.chunks(bucket).map(|chunk| chunk[0]).collect();
+    let values = values
+        .chunks(bucket)
+        .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+        .collect();
+    (labels, values)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutlierKind {
+    BlockTime,
+    BlockDifficulty,
+    TxDifficulty,
+}
+
+impl OutlierKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            OutlierKind::BlockTime => "block time",
+            OutlierKind::BlockDifficulty => "block difficulty",
+            OutlierKind::TxDifficulty => "TX difficulty",
+        }
+    }
 }
 
-/// Return top N items from a frequency map
-fn top_n(map: HashMap<[u8; 32], usize>, n: usize) -> Vec<([u8; 32], usize)> {
-    let mut v: Vec<_> = map.into_iter().collect();
-    v.sort_by(|a, b| b.1.cmp(&a.1));
-    v.truncate(n);
-    v
+/// A block whose interval or difficulty is far from the window's mean
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Outlier {
+    pub height: usize,
+    pub kind: OutlierKind,
+    pub value: f64,
+    /// Standard deviations from the mean, negative below it
+    pub z_score: f64,
 }
 
-pub fn plot_difficulties(blocks: &[usize], block_diff: &[f64], tx_diff: &[f64]) {
+/// Indices and z-scores of the values more than `sigma` standard deviations from their mean
+fn z_outliers(values: &[f64], sigma: f64) -> Vec<(usize, f64)> {
+    let count = values.len().max(1) as f64;
+    let mean = values.iter().sum::<f64>() / count;
+    let std_dev = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count).sqrt();
+    if std_dev == 0.0 {
+        return Vec::new();
+    }
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (i, (v - mean) / std_dev))
+        .filter(|(_, z)| z.abs() > sigma)
+        .collect()
+}
+
+/// Blocks from `start_height` whose difficulties, or interval since the previous block, are more
+/// than `sigma` standard deviations from the mean, in height order. `block_times` starts at
+/// `start_height + 1` like `ChainStats::block_time_series`.
+pub fn find_outliers(
+    start_height: usize,
+    block_diffs: &[f64],
+    tx_diffs: &[f64],
+    block_times: &[f64],
+    sigma: f64,
+) -> Vec<Outlier> {
+    let mut outliers: Vec<Outlier> = [
+        (OutlierKind::BlockTime, start_height + 1, block_times),
+        (OutlierKind::BlockDifficulty, start_height, block_diffs),
+        (OutlierKind::TxDifficulty, start_height, tx_diffs),
+    ]
+    .into_iter()
+    .flat_map(|(kind, first, values)| {
+        z_outliers(values, sigma)
+            .into_iter()
+            .map(move |(i, z_score)| Outlier {
+                height: first + i,
+                kind,
+                value: values[i],
+                z_score,
+            })
+    })
+    .collect();
+    outliers.sort_by_key(|outlier| outlier.height);
+    outliers
+}
+
+/// Print the outliers below a chart, so its spikes can be traced to blocks
+pub fn print_outliers(outliers: &[Outlier]) {
+    if outliers.is_empty() {
+        return;
+    }
+    println!("\nOutliers:");
+    for outlier in outliers {
+        let value = match outlier.kind {
+            OutlierKind::BlockTime => format!("{:.0}s", outlier.value),
+            _ => format!("{:.2}", outlier.value),
+        };
+        println!(
+            "{:>7} {} {} ({:+.1} std dev)",
+            outlier.height,
+            outlier.kind.name(),
+            value,
+            outlier.z_score
+        );
+    }
+}
+
+/// Bar chart of the block and TX difficulty of every block. Rows holding an `outliers` block are
+/// marked with a `!` after the block number and their outlying difficulty bars are highlighted.
+pub fn plot_difficulties(
+    blocks: &[usize],
+    block_diff: &[f64],
+    tx_diff: &[f64],
+    outliers: &[Outlier],
+    options: PlotOptions,
+) {
     let blocks_chars = ["", "▏", "▎", "▍", "▌", "▋", "▊", "▉", "█"];
     let term_width = match term_size::dimensions() {
         Some((w, _)) => w,
         None => 80,
     };
 
+    let bucket = match options.max_rows {
+        Some(rows) => blocks.len().div_ceil(rows.max(1)).max(1),
+        None => 1,
+    };
+    let (labels, mut block_diff) = downsample(blocks, block_diff, bucket);
+    let (_, mut tx_diff) = downsample(blocks, tx_diff, bucket);
+    let blocks = labels;
+    if options.log_scale {
+        for v in block_diff.iter_mut().chain(tx_diff.iter_mut()) {
+            *v = (1.0 + *v).log10();
+        }
+    }
+
+    // Row every outlier falls in, the last one labelled at or below its height
+    let mut marked = vec![(false, false, false); blocks.len()];
+    for outlier in outliers {
+        let row = blocks.partition_point(|&block| block <= outlier.height);
+        if row == 0 {
+            continue;
+        }
+        let (any, block, tx) = &mut marked[row - 1];
+        *any = true;
+        match outlier.kind {
+            OutlierKind::BlockDifficulty => *block = true,
+            OutlierKind::TxDifficulty => *tx = true,
+            OutlierKind::BlockTime => {}
+        }
+    }
+
     let bar_max_width = (term_width - 7 - 3 - 3) / 2; // 6 for block #, 3 for separators, divide remaining
-    let max_block = block_diff.iter().cloned().fold(0.0, f64::max);
-    let max_tx = tx_diff.iter().cloned().fold(0.0, f64::max);
+    let mut max_block = block_diff.iter().cloned().fold(0.0, f64::max);
+    let mut max_tx = tx_diff.iter().cloned().fold(0.0, f64::max);
+    if options.shared_scale {
+        max_block = max_block.max(max_tx);
+        max_tx = max_block;
+    }
 
     println!(
         "{:>6} | {:<width$} | {:<width$}",
@@ -81,40 +451,32 @@ pub fn plot_difficulties(blocks: &[usize], block_diff: &[f64], tx_diff: &[f64])
         let full_tx = scale_tx.floor() as usize;
         let partial_tx = ((scale_tx - full_tx as f64) * 8.0).round() as usize;
 
-        let block_bar = format!("{}{}", "█".repeat(full_block), blocks_chars[partial_block]);
-        let tx_bar = format!("{}{}", "█".repeat(full_tx), blocks_chars[partial_tx]);
-
+        // Padded before highlighting, escape codes would throw the padding off
+        let bar = |full: usize, partial: usize, highlight: bool| {
+            let bar = format!(
+                "{:<width$}",
+                format!("{}{}", "█".repeat(full), blocks_chars[partial]),
+                width = bar_max_width
+            );
+            if highlight { color::warning(bar) } else { bar }
+        };
+        let (outlier, block_outlier, tx_outlier) = marked[i];
         println!(
-            "{:>6} | {:<width$} | {:<width$}",
+            "{:>6}{}| {} | {}",
             blocks[i],
-            block_bar,
-            tx_bar,
-            width = bar_max_width
+            if outlier {
+                color::warning("!")
+            } else {
+                " ".to_string()
+            },
+            bar(full_block, partial_block, block_outlier),
+            bar(full_tx, partial_tx, tx_outlier)
         );
     }
 }
 
-/// Calculate block time averages
-pub async fn calculate_block_averages(
-    client: &Client,
-    block_count: usize,
-) -> Result<BlockAverages> {
-    if block_count < 2 {
-        return Err(anyhow!("At least 2 blocks required"));
-    }
-
-    let height = client.get_height().await?;
-    let start = height.saturating_sub(block_count);
-    let mut timestamps = Vec::with_capacity(block_count);
-
-    for h in start..height {
-        let block = client
-            .get_block_by_height(h)
-            .await?
-            .ok_or_else(|| anyhow!("Block {} missing", h))?;
-        timestamps.push(block.timestamp as f64);
-    }
-
+/// Block time averages over the intervals between consecutive timestamps
+fn block_averages(timestamps: &[f64]) -> Result<BlockAverages> {
     if timestamps.len() < 2 {
         return Err(anyhow!("Not enough blocks"));
     }
@@ -125,8 +487,9 @@ pub async fn calculate_block_averages(
     let average = deltas.iter().sum::<f64>() / count;
     let variance = deltas.iter().map(|d| (d - average).powi(2)).sum::<f64>() / count;
     let std_dev = variance.sqrt();
+    let confidence = ConfidenceInterval::of_mean(&deltas);
     deltas.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
-    let median = if deltas.len() % 2 == 0 {
+    let median = if deltas.len().is_multiple_of(2) {
         let mid = deltas.len() / 2;
         (deltas[mid - 1] + deltas[mid]) / 2.0
     } else {
@@ -142,72 +505,383 @@ pub async fn calculate_block_averages(
         min,
         max,
         _sample_size: deltas.len(),
+        confidence,
     })
 }
 
-/// Calculate all blockchain stats
-pub async fn calculate_chain_stats(client: &Client, block_count: usize) -> Result<ChainStats> {
-    let block_time = calculate_block_averages(client, block_count).await?;
+/// Calculate block time averages
+pub async fn calculate_block_averages<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    block_count: usize,
+) -> Result<BlockAverages> {
+    if block_count < 2 {
+        return Err(anyhow!("At least 2 blocks required"));
+    }
 
-    let height = client.get_height().await?;
+    let height = provider.get_height().await?;
     let start = height.saturating_sub(block_count);
+    let mut timestamps = Vec::with_capacity(block_count);
+
+    info!("Reading block times for blocks {}..{}", start, height);
+    for_each_block(provider, start..height, |_, block| {
+        timestamps.push(block.timestamp as f64);
+        Ok(())
+    })
+    .await?;
+
+    block_averages(&timestamps)
+}
+
+/// Calculate block time and difficulty stats for the past `block_count` blocks from their headers
+pub async fn calculate_header_stats<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    block_count: usize,
+) -> Result<HeaderStats> {
+    if block_count < 2 {
+        return Err(anyhow!("At least 2 blocks required"));
+    }
+
+    let height = provider.get_height().await?;
+    let start = height.saturating_sub(block_count);
+    let mut timestamps = Vec::with_capacity(block_count);
+    let mut block_diffs = Vec::with_capacity(block_count);
+    let mut tx_diffs = Vec::with_capacity(block_count);
+
+    info!("Reading headers of blocks {}..{}", start, height);
+    for_each_block(provider, start..height, |_, block| {
+        let header = BlockHeader::from(block);
+        timestamps.push(header.timestamp as f64);
+        block_diffs.push(normalize_difficulty(&header.block_pow_difficulty));
+        tx_diffs.push(normalize_difficulty(&header.tx_pow_difficulty));
+        Ok(())
+    })
+    .await?;
+
+    Ok(HeaderStats {
+        start_height: start,
+        block_time: block_averages(&timestamps)?,
+        avg_block_difficulty: block_diffs.iter().sum::<f64>() / block_diffs.len() as f64,
+        avg_tx_difficulty: tx_diffs.iter().sum::<f64>() / tx_diffs.len() as f64,
+        block_time_series: timestamps.windows(2).map(|w| w[1] - w[0]).collect(),
+        block_difficulty_series: block_diffs,
+        tx_difficulty_series: tx_diffs,
+    })
+}
+
+/// Calculate all blockchain stats for the past `block_count` blocks
+pub async fn calculate_chain_stats<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    block_count: usize,
+) -> Result<ChainStats> {
+    let height = provider.get_height().await?;
+    calculate_chain_stats_range(provider, height.saturating_sub(block_count)..height).await
+}
+
+/// Calculate all blockchain stats for the blocks at `heights`
+pub async fn calculate_chain_stats_range<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+) -> Result<ChainStats> {
+    if heights.len() < 2 {
+        return Err(anyhow!("At least 2 blocks required"));
+    }
+    let Range { start, end } = heights;
 
     let mut total_txs = 0usize;
     let mut total_io = 0usize;
     let mut total_size = 0usize;
+    let mut issued = 0u64;
+    let mut pattern_mix = PatternMix::default();
 
-    let mut miner_count: HashMap<[u8; 32], usize> = HashMap::new();
-    let mut address_count: HashMap<[u8; 32], usize> = HashMap::new();
+    let mut miner_count = AddressCounter::new();
+    let mut address_count = AddressCounter::new();
 
     let mut block_diffs = Vec::new();
     let mut tx_diffs = Vec::new();
-
+    let mut block_times = Vec::new();
+    let mut block_tps = Vec::new();
+    // Transactions of every block after the first, paired with `block_times`
+    let mut block_txs = Vec::new();
+    let mut block_sizes = Vec::new();
+    let mut utilizations = Vec::new();
+
+    let mut timestamps = Vec::with_capacity(end - start);
     let mut first_ts = None;
     let mut last_ts = None;
 
-    for h in start..height {
-        let block = client
-            .get_block_by_height(h)
-            .await?
-            .ok_or_else(|| anyhow!("Missing block {}", h))?;
+    info!("Scanning blocks {}..{} for chain stats", start, end);
+    for_each_block(provider, start..end, |_, block| {
+        timestamps.push(block.timestamp as f64);
         first_ts.get_or_insert(block.timestamp);
+        if let Some(previous) = last_ts {
+            let interval = block.timestamp.saturating_sub(previous) as f64;
+            block_times.push(interval);
+            block_tps.push(block.transactions.len() as f64 / interval.max(1.0));
+            block_txs.push(block.transactions.len() as f64);
+        }
         last_ts = Some(block.timestamp);
 
         total_txs += block.transactions.len();
         for tx in &block.transactions {
             total_io += tx.inputs.len() + tx.outputs.len();
+            pattern_mix.add(tx);
             for i in &tx.inputs {
-                *address_count.entry(*i.output_owner.dump_buf()).or_default() += 1;
+                address_count.add(*i.output_owner.dump_buf())?;
             }
             for o in &tx.outputs {
-                *address_count.entry(*o.receiver.dump_buf()).or_default() += 1;
+                address_count.add(*o.receiver.dump_buf())?;
             }
         }
 
-        if let Some(coinbase) = block.transactions.iter().filter(|tx| tx.inputs.len() == 0).collect::<Vec<&Transaction>>().first() {
-            let out = coinbase.outputs[1];
-            *miner_count.entry(*out.receiver.dump_buf()).or_default() += 1;
+        if let Some(miner) = block_miner(&block) {
+            miner_count.add(miner)?;
+        }
+        if let Some(coinbase) = coinbase(&block) {
+            issued += coinbase.outputs.iter().map(|o| o.amount).sum::<u64>();
         }
 
-        total_size += encode_to_vec(&block, bincode::config::standard())?.len();
+        let size = encode_to_vec(&block, bincode::config::standard())?.len();
+        total_size += size;
+        block_sizes.push(size as f64);
+        utilizations
+            .push(block.transactions.len() as f64 / MAX_TRANSACTIONS_PER_BLOCK as f64 * 100.0);
         block_diffs.push(normalize_difficulty(&block.meta.block_pow_difficulty));
         tx_diffs.push(normalize_difficulty(&block.meta.tx_pow_difficulty));
-    }
+        Ok(())
+    })
+    .await?;
 
-    let blocks_f = block_count as f64;
+    let block_time = block_averages(&timestamps)?;
+    let blocks_f = (end - start) as f64;
     let duration = (last_ts.unwrap() - first_ts.unwrap()) as f64;
 
+    let circulating_supply = supply_at(end);
+    let blocks_per_year = SECONDS_PER_YEAR / block_time.average;
+    let annual_inflation = issued as f64 / blocks_f * blocks_per_year / circulating_supply as f64;
+
     Ok(ChainStats {
+        start_height: start,
         block_time,
         avg_txs_per_block: total_txs as f64 / blocks_f,
         avg_io_per_block: total_io as f64 / blocks_f,
         avg_block_size_bytes: total_size as f64 / blocks_f,
+        avg_utilization: utilizations.iter().sum::<f64>() / blocks_f,
         tps: total_txs as f64 / duration,
+        tps_confidence: ConfidenceInterval::of_ratio(&block_txs, &block_times),
+        pattern_mix,
+        issued,
+        circulating_supply,
+        annual_inflation,
         avg_block_difficulty: block_diffs.iter().sum::<f64>() / block_diffs.len() as f64,
         avg_tx_difficulty: tx_diffs.iter().sum::<f64>() / tx_diffs.len() as f64,
-        top_miners: top_n(miner_count, 10),
-        top_addresses: top_n(address_count, 10),
+        top_miners: miner_count.top(10)?,
+        top_addresses: address_count.top(10)?,
         block_difficulty_series: block_diffs,
         tx_difficulty_series: tx_diffs,
+        block_time_series: block_times,
+        tps_series: block_tps,
+        block_size_series: block_sizes,
+        utilization_series: utilizations,
     })
 }
+
+/// How `averages` draws the difficulty of every block
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ChartOptions {
+    /// One line sparklines of every series instead of the bars
+    pub sparkline: bool,
+    pub plot: PlotOptions,
+    /// Blocks this many standard deviations from the mean are listed below the bars
+    pub outlier_sigma: f64,
+}
+
+/// Per block difficulty bars with their outliers listed below, or one line sparklines of every series
+fn print_difficulty_chart(
+    start_height: usize,
+    block_diffs: &[f64],
+    tx_diffs: &[f64],
+    block_times: &[f64],
+    series: &[Series],
+    options: ChartOptions,
+) {
+    if options.sparkline {
+        chart::print_sparklines(series);
+        return;
+    }
+    let outliers = find_outliers(
+        start_height,
+        block_diffs,
+        tx_diffs,
+        block_times,
+        options.outlier_sigma,
+    );
+    let block_numbers: Vec<usize> = (start_height..start_height + tx_diffs.len()).collect();
+    plot_difficulties(
+        &block_numbers,
+        block_diffs,
+        tx_diffs,
+        &outliers,
+        options.plot,
+    );
+    print_outliers(&outliers);
+}
+
+fn print_block_time(block_time: &BlockAverages) {
+    println!(
+        "Block Time Avg: {:.2}s (95% CI {:.2}s to {:.2}s), Median: {:.2}s, Std Dev: {:.2}s, Min: {:.2}s, Max: {:.2}s",
+        block_time.average,
+        block_time.confidence.low,
+        block_time.confidence.high,
+        block_time.median,
+        block_time.std_dev,
+        block_time.min,
+        block_time.max
+    );
+    print_confidence_note("Block time", &block_time.confidence);
+}
+
+/// Warn when an average is too rough to be quoted as precise
+fn print_confidence_note(name: &str, confidence: &ConfidenceInterval) {
+    if let Some(note) = confidence.note() {
+        println!("{}", color::warning(format!("{name}: {note}")));
+    }
+}
+
+/// Print the difficulty and block time averages of the past `blocks` blocks from their headers alone
+pub async fn run_header_averages<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    blocks: usize,
+    plots: &PlotFiles,
+    chart: ChartOptions,
+    out: &Output,
+) -> Result<()> {
+    let stats = calculate_header_stats(provider, blocks).await?;
+    let series = header_chart_series(&stats);
+    plots.write(&series)?;
+    if out.format == OutputFormat::Json {
+        return out.json(&stats);
+    }
+
+    print_difficulty_chart(
+        stats.start_height,
+        &stats.block_difficulty_series,
+        &stats.tx_difficulty_series,
+        &stats.block_time_series,
+        &series,
+        chart,
+    );
+    println!(
+        "\nAvg Block Difficulty: {:.2}, Avg TX Difficulty: {:.2}",
+        stats.avg_block_difficulty, stats.avg_tx_difficulty
+    );
+    print_block_time(&stats.block_time);
+    Ok(())
+}
+
+/// Print the averages of the past `blocks` blocks, with their top miners and addresses
+pub async fn run_averages<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    blocks: usize,
+    plots: &PlotFiles,
+    chart: ChartOptions,
+    out: &Output,
+    fiat: Option<&Quote>,
+) -> Result<()> {
+    let stats = calculate_chain_stats(provider, blocks).await?;
+    let series = chart_series(&stats);
+    plots.write(&series)?;
+    if out.format == OutputFormat::Json {
+        return out.json(&price::with_fiat(
+            &stats,
+            fiat,
+            &[("issued", stats.issued)],
+        )?);
+    }
+
+    print_difficulty_chart(
+        stats.start_height,
+        &stats.block_difficulty_series,
+        &stats.tx_difficulty_series,
+        &stats.block_time_series,
+        &series,
+        chart,
+    );
+
+    // Optional: print top miners & addresses
+    println!("\nTop 10 Miners:");
+    for (addr, count) in &stats.top_miners {
+        println!(
+            "{} -> {} blocks",
+            color::miner(Public::new_from_buf(addr).dump_base36()),
+            count
+        );
+    }
+
+    println!("\nTop 10 Addresses:");
+    for (addr, count) in &stats.top_addresses {
+        println!(
+            "{} -> {} appearances",
+            color::hash(Public::new_from_buf(addr).dump_base36()),
+            count
+        );
+    }
+
+    println!(
+        "\nAvg TXs/block: {:.2}, Avg IO/block: {:.2}, Avg block size: {:.2} bytes, TPS: {:.2}",
+        stats.avg_txs_per_block, stats.avg_io_per_block, stats.avg_block_size_bytes, stats.tps
+    );
+    println!(
+        "TPS 95% CI: {:.4} to {:.4}",
+        stats.tps_confidence.low, stats.tps_confidence.high
+    );
+    print_confidence_note("TPS", &stats.tps_confidence);
+    println!(
+        "Avg utilization: {:.2}% of {} transactions per block",
+        stats.avg_utilization, MAX_TRANSACTIONS_PER_BLOCK
+    );
+    println!("Transaction mix: {}", stats.pattern_mix.summary());
+
+    println!(
+        "Avg Block Difficulty: {:.2}, Avg TX Difficulty: {:.2}",
+        stats.avg_block_difficulty, stats.avg_tx_difficulty
+    );
+
+    print_block_time(&stats.block_time);
+
+    println!(
+        "Issued: {} SNAP{}, Annualized inflation: {:.4}%",
+        color::amount(format_snap(stats.issued)),
+        price::annotate(fiat, stats.issued),
+        stats.annual_inflation * 100.0
+    );
+    Ok(())
+}
+
+/// Print the coins issued over the past `blocks` blocks as an annualized inflation rate
+pub async fn run_inflation<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    blocks: usize,
+    out: &Output,
+) -> Result<()> {
+    let stats = calculate_chain_stats(provider, blocks).await?;
+    let stats = Inflation::new(&stats, blocks);
+    match out.format {
+        OutputFormat::Text | OutputFormat::Debug => {
+            println!(
+                "Issued over {} blocks: {} SNAP",
+                stats.blocks,
+                color::amount(format_snap(stats.issued))
+            );
+            println!(
+                "Circulating supply: {} SNAP",
+                color::amount(format_snap(stats.circulating_supply))
+            );
+            println!(
+                "Annualized inflation: {:.4}%",
+                stats.annual_inflation * 100.0
+            );
+        }
+        OutputFormat::Json => out.json(&stats)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,109 @@
+// batch.rs
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+use serde_json::Value;
+use std::{fs, path::Path};
+
+use crate::{
+    error::ErrorKind,
+    node::NodeClient,
+    output::Output,
+    query::{self, AddressSections},
+};
+
+/// One line of a batch file
+#[derive(Debug)]
+pub enum Query {
+    Block(String),
+    Tx(String),
+    Addr(String),
+}
+
+impl Query {
+    /// Parse `block <height|hash>`, `tx <id>` or `addr <address>`
+    pub fn parse(line: &str) -> Result<Query> {
+        let mut words = line.split_whitespace();
+        let (Some(kind), Some(id), None) = (words.next(), words.next(), words.next()) else {
+            return Err(anyhow!("Expected `<block|tx|addr> <id>`, got `{line}`"));
+        };
+        let id = id.to_string();
+        match kind {
+            "block" => Ok(Query::Block(id)),
+            "tx" => Ok(Query::Tx(id)),
+            "addr" => Ok(Query::Addr(id)),
+            other => Err(anyhow!(
+                "Unknown query type {other}, expected block, tx or addr"
+            )),
+        }
+    }
+
+    async fn run(&self, client: &NodeClient) -> Result<Value> {
+        Ok(match self {
+            Query::Block(id) => serde_json::to_value(query::block(client, id).await?)?,
+            Query::Tx(id) => serde_json::to_value(query::transaction(client, id).await?)?,
+            Query::Addr(address) => {
+                serde_json::to_value(query::address(client, address, AddressSections::ALL).await?)?
+            }
+        })
+    }
+}
+
+/// Result of one query, a failed query doesn't stop the rest of the batch
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    pub query: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ErrorKind>,
+}
+
+/// Read queries from a file, one per line. Blank lines and `#` comments are ignored.
+pub fn read_queries(path: &Path) -> Result<Vec<(String, Result<Query>)>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Could not read batch file {}", path.display()))?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| (line.to_string(), Query::parse(line)))
+        .collect())
+}
+
+/// Run every query over the same connection, printing a JSON array or one JSON object per line
+pub async fn run_batch(client: &NodeClient, path: &Path, jsonl: bool, out: &Output) -> Result<()> {
+    let mut results = Vec::new();
+    for (line, query) in read_queries(path)? {
+        let outcome = match query {
+            Ok(query) => query.run(client).await,
+            Err(e) => Err(e),
+        };
+        let result = match outcome {
+            Ok(value) => BatchResult {
+                query: line,
+                result: Some(value),
+                error: None,
+                kind: None,
+            },
+            Err(e) => BatchResult {
+                query: line,
+                result: None,
+                error: Some(format!("{:#}", e)),
+                kind: Some(ErrorKind::of(&e)),
+            },
+        };
+
+        if jsonl {
+            out.json_line(&result)?;
+        } else {
+            results.push(result);
+        }
+    }
+
+    if !jsonl {
+        out.json(&results)?;
+    }
+    Ok(())
+}
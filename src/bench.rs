@@ -0,0 +1,114 @@
+// bench.rs
+use anyhow::Result;
+use comfy_table::Table;
+use serde::Serialize;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+use tracing::info;
+
+use crate::{
+    load::{self, Load, Query, Sample, run_load},
+    output::{Output, OutputFormat},
+    source::Source,
+    tables::{align_right, new_table},
+};
+
+/// Concurrency levels measured when `--concurrency` isn't given
+pub const DEFAULT_LEVELS: &[usize] = &[1, 4, 16];
+
+/// Sustained throughput of a node at one concurrency level
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchLevel {
+    pub concurrency: usize,
+    pub blocks_per_second: f64,
+    pub transactions_per_second: f64,
+    pub block_latency_ms: f64,
+    pub transaction_latency_ms: f64,
+    /// Failed or empty responses over both measurements
+    pub errors: usize,
+}
+
+/// Measure block and transaction retrieval for `duration` each at every concurrency level
+pub async fn bench_node(
+    node: SocketAddr,
+    sample: Sample,
+    levels: &[usize],
+    duration: Duration,
+) -> Result<Vec<BenchLevel>> {
+    let sample = Arc::new(sample);
+    let mut results = Vec::new();
+    for &concurrency in levels {
+        info!("Measuring {} concurrent connections", concurrency);
+        let blocks = run_load(
+            node,
+            sample.clone(),
+            &[Query::Blocks],
+            concurrency,
+            duration,
+        )
+        .await?;
+        let txs = run_load(node, sample.clone(), &[Query::Txs], concurrency, duration).await?;
+        let latency = |load: &Load, query| {
+            load.queries
+                .get(&query)
+                .map_or(0.0, |stats| stats.average_latency().as_secs_f64() * 1000.0)
+        };
+        let errors = |load: &Load| {
+            load.queries
+                .values()
+                .map(|stats| stats.errors)
+                .sum::<usize>()
+        };
+        results.push(BenchLevel {
+            concurrency,
+            blocks_per_second: blocks.per_second(Query::Blocks),
+            transactions_per_second: txs.per_second(Query::Txs),
+            block_latency_ms: latency(&blocks, Query::Blocks),
+            transaction_latency_ms: latency(&txs, Query::Txs),
+            errors: errors(&blocks) + errors(&txs),
+        });
+    }
+    Ok(results)
+}
+
+pub fn bench_table(levels: &[BenchLevel]) -> Table {
+    let mut table = new_table(&[
+        "Concurrency",
+        "Blocks/s",
+        "TXs/s",
+        "Block latency (ms)",
+        "TX latency (ms)",
+        "Errors",
+    ]);
+    for level in levels {
+        table.add_row(vec![
+            level.concurrency.to_string(),
+            format!("{:.1}", level.blocks_per_second),
+            format!("{:.1}", level.transactions_per_second),
+            format!("{:.1}", level.block_latency_ms),
+            format!("{:.1}", level.transaction_latency_ms),
+            level.errors.to_string(),
+        ]);
+    }
+    align_right(&mut table, &[0, 1, 2, 3, 4, 5]);
+    table
+}
+
+/// Measure the node of `source` at each concurrency level with queries drawn from the past `sample` blocks
+pub async fn run_bench(
+    source: &Source,
+    concurrency: &[usize],
+    duration: Duration,
+    sample: usize,
+    out: &Output,
+) -> Result<()> {
+    let node = source.client()?.node();
+    load::warn_rate_limited();
+    let sample = Sample::take(source.provider(), sample).await?;
+    let results = bench_node(node, sample, concurrency, duration).await?;
+    match out.format {
+        OutputFormat::Text => println!("{}", bench_table(&results)),
+        OutputFormat::Debug => println!("{:#?}", results),
+        OutputFormat::Json => out.json(&results)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+// blocks.rs
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use snap_coin::{
+    api::requests::{Request, Response},
+    blockchain_data_provider::BlockchainDataProvider,
+    core::block::Block,
+};
+use std::ops::Range;
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+use tracing::debug;
+
+use crate::{fixture::FixtureProvider, limits, node::NodeClient};
+
+/// Blocks requested per batch by `for_each_block`
+pub const BATCH_SIZE: usize = 200;
+
+/// Chain data sources that can fetch a run of consecutive blocks at once
+#[async_trait]
+pub trait BlockRangeProvider: BlockchainDataProvider + Sync {
+    /// Fetch the blocks at `heights` in order, failing if any of them is missing.
+    /// The default asks for one block at a time.
+    async fn get_blocks_range(&self, heights: Range<usize>) -> Result<Vec<Block>> {
+        let mut blocks = Vec::with_capacity(heights.len());
+        for h in heights {
+            debug!("get_block_by_height {}", h);
+            let block = self
+                .get_block_by_height(h)
+                .await?
+                .ok_or_else(|| anyhow!("Missing block {}", h))?;
+            blocks.push(block);
+        }
+        Ok(blocks)
+    }
+}
+
+#[async_trait]
+impl BlockRangeProvider for FixtureProvider {}
+
+/// The node protocol has no range request, but it answers requests on a connection in order.
+/// The requests of a batch are pipelined on a dedicated connection, so a batch costs a couple of
+/// round trips instead of two per block.
+#[async_trait]
+impl BlockRangeProvider for NodeClient {
+    async fn get_blocks_range(&self, heights: Range<usize>) -> Result<Vec<Block>> {
+        debug!("get_blocks_range {}..{}", heights.start, heights.end);
+        let mut stream = TcpStream::connect(self.node()).await?;
+        stream.set_nodelay(true)?;
+
+        let hash_requests = heights.clone().map(|height| Request::BlockHash {
+            height: height as u64,
+        });
+        let mut hashes = Vec::with_capacity(heights.len());
+        for (height, response) in heights
+            .clone()
+            .zip(pipeline(&mut stream, hash_requests).await?)
+        {
+            match response {
+                Response::BlockHash { hash: Some(hash) } => hashes.push(hash),
+                Response::BlockHash { hash: None } => {
+                    return Err(anyhow!("Missing block {}", height));
+                }
+                _ => return Err(anyhow!("Unexpected response to block hash request")),
+            }
+        }
+
+        let block_requests = hashes
+            .into_iter()
+            .map(|block_hash| Request::Block { block_hash });
+        let mut blocks = Vec::with_capacity(heights.len());
+        for (height, response) in heights.zip(pipeline(&mut stream, block_requests).await?) {
+            match response {
+                Response::Block { block: Some(block) } => blocks.push(block),
+                Response::Block { block: None } => return Err(anyhow!("Missing block {}", height)),
+                _ => return Err(anyhow!("Unexpected response to block request")),
+            }
+        }
+        Ok(blocks)
+    }
+}
+
+/// Send the requests and read one response per request in the same order, keeping at most
+/// `limits::max_in_flight()` requests unanswered at a time
+async fn pipeline(
+    stream: &mut TcpStream,
+    requests: impl Iterator<Item = Request>,
+) -> Result<Vec<Response>> {
+    let requests = requests
+        .map(|request| request.encode())
+        .collect::<Result<Vec<_>, _>>()?;
+    let window = limits::max_in_flight();
+
+    let mut sent = 0;
+    let mut responses = Vec::with_capacity(requests.len());
+    while responses.len() < requests.len() {
+        if sent < requests.len() && sent - responses.len() < window {
+            // Fill the window in one write
+            let end = (responses.len() + window).min(requests.len());
+            limits::acquire((end - sent) as u32).await;
+            stream.write_all(&requests[sent..end].concat()).await?;
+            sent = end;
+        }
+        responses.push(Response::decode_from_stream(stream).await?);
+    }
+    Ok(responses)
+}
+
+/// Call `f` with every block at `heights` in order, fetching them `BATCH_SIZE` at a time
+pub async fn for_each_block<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+    mut f: impl FnMut(usize, Block) -> Result<()>,
+) -> Result<()> {
+    let mut start = heights.start;
+    while start < heights.end {
+        let end = (start + BATCH_SIZE).min(heights.end);
+        for (height, block) in (start..end).zip(provider.get_blocks_range(start..end).await?) {
+            f(height, block)?;
+        }
+        start = end;
+    }
+    Ok(())
+}
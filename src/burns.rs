@@ -0,0 +1,135 @@
+// burns.rs
+use anyhow::Result;
+use serde::Serialize;
+use snap_coin::{crypto::keys::Public, to_snap};
+use tracing::{debug, info};
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    chart::{self, PlotFiles, Series},
+    color,
+    config::Config,
+    format_snap,
+    output::{Output, OutputFormat},
+};
+
+#[derive(Debug, Serialize)]
+pub struct BurnPoint {
+    pub height: usize,
+    pub burned: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BurnStats {
+    pub burn_addresses: Vec<Public>,
+    /// Everything ever sent to a burn address, these outputs can never be spent
+    pub total_burned: u64,
+    /// Burned in the scanned window only
+    pub window_burned: u64,
+    pub series: Vec<BurnPoint>,
+}
+
+/// Scan the past `block_count` blocks for outputs paying provably unspendable addresses
+pub async fn calculate_burns<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    burn_addresses: &[Public],
+    block_count: usize,
+) -> Result<BurnStats> {
+    // Burn outputs are never spent, so their unspent outputs are the all time burned supply
+    let mut total_burned = 0u64;
+    for address in burn_addresses {
+        debug!(
+            "get_available_transaction_outputs {}",
+            address.dump_base36()
+        );
+        total_burned += provider
+            .get_available_transaction_outputs(*address)
+            .await?
+            .iter()
+            .map(|(_, output, _)| output.amount)
+            .sum::<u64>();
+    }
+
+    let height = provider.get_height().await?;
+    let start = height.saturating_sub(block_count);
+    let mut series = Vec::with_capacity(block_count);
+
+    info!("Scanning blocks {}..{} for burns", start, height);
+    for_each_block(provider, start..height, |h, block| {
+        let burned = block
+            .transactions
+            .iter()
+            .flat_map(|tx| &tx.outputs)
+            .filter(|o| burn_addresses.contains(&o.receiver))
+            .map(|o| o.amount)
+            .sum();
+        series.push(BurnPoint { height: h, burned });
+        Ok(())
+    })
+    .await?;
+
+    Ok(BurnStats {
+        burn_addresses: burn_addresses.to_vec(),
+        total_burned,
+        window_burned: series.iter().map(|p| p.burned).sum(),
+        series,
+    })
+}
+
+pub fn chart_series(stats: &BurnStats) -> Series {
+    Series {
+        name: "Burned (SNAP)".to_string(),
+        points: stats
+            .series
+            .iter()
+            .map(|p| (p.height, to_snap(p.burned)))
+            .collect(),
+    }
+}
+
+pub fn print_burns(stats: &BurnStats, sparkline: bool) {
+    println!("Burn addresses:");
+    for address in &stats.burn_addresses {
+        println!("  {}", color::hash(address.dump_base36()));
+    }
+
+    if sparkline {
+        println!();
+        chart::print_sparklines(&[chart_series(stats)]);
+    } else {
+        println!("\n{:>8} | {:>20}", "Block #", "Burned (SNAP)");
+        println!("{:-<8}-+-{:-<20}", "", "");
+        for point in stats.series.iter().filter(|p| p.burned > 0) {
+            println!(
+                "{:>8} | {}",
+                point.height,
+                color::amount(format!("{:>20}", format_snap(point.burned)))
+            );
+        }
+    }
+
+    println!(
+        "\nBurned in window: {} SNAP, Total burned supply: {} SNAP",
+        color::amount(format_snap(stats.window_burned)),
+        color::amount(format_snap(stats.total_burned))
+    );
+}
+
+/// Print the coins sent to the configured burn addresses over the past `blocks` blocks
+pub async fn run_burns<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    config: &Config,
+    blocks: usize,
+    sparkline: bool,
+    plots: &PlotFiles,
+    out: &Output,
+) -> Result<()> {
+    let burn_addresses = config.burn_addresses()?;
+    let stats = calculate_burns(provider, &burn_addresses, blocks).await?;
+    plots.write(&[chart_series(&stats)])?;
+    match out.format {
+        OutputFormat::Text | OutputFormat::Debug => print_burns(&stats, sparkline),
+        OutputFormat::Json => out.json(&stats)?,
+    }
+    Ok(())
+}
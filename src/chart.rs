@@ -0,0 +1,268 @@
+// chart.rs
+use anyhow::{Result, anyhow};
+use plotters::{coord::Shift, prelude::*};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// One panel of a chart, plotted against block height
+pub struct Series {
+    pub name: String,
+    pub points: Vec<(usize, f64)>,
+}
+
+impl Series {
+    /// Build a series from values belonging to consecutive heights starting at `start`
+    pub fn from_heights(name: &str, start: usize, values: &[f64]) -> Self {
+        Series {
+            name: name.to_string(),
+            points: values
+                .iter()
+                .enumerate()
+                .map(|(i, v)| (start + i, *v))
+                .collect(),
+        }
+    }
+}
+
+/// Render values as a single line of block glyphs, averaging neighbours when wider than `width`
+pub fn sparkline(values: &[f64], width: usize) -> String {
+    let glyphs = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let bucket = values.len().div_ceil(width.max(1)).max(1);
+    let values: Vec<f64> = values
+        .chunks(bucket)
+        .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+        .collect();
+
+    let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    values
+        .iter()
+        .map(|v| {
+            if range <= 0.0 {
+                return glyphs[0];
+            }
+            let level = ((v - min) / range * (glyphs.len() - 1) as f64).round() as usize;
+            glyphs[level.min(glyphs.len() - 1)]
+        })
+        .collect()
+}
+
+/// Print one sparkline per series, labelled and followed by the min / max of the series
+pub fn print_sparklines(series: &[Series]) {
+    let term_width = match term_size::dimensions() {
+        Some((w, _)) => w,
+        None => 80,
+    };
+    let label_width = series.iter().map(|s| s.name.len()).max().unwrap_or(0);
+
+    for s in series {
+        let values: Vec<f64> = s.points.iter().map(|p| p.1).collect();
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let range = format!("{:.2} .. {:.2}", min, max);
+        let width = term_width.saturating_sub(label_width + range.len() + 4);
+
+        println!(
+            "{:<label_width$}  {}  {}",
+            s.name,
+            sparkline(&values, width),
+            range
+        );
+    }
+}
+
+/// Horizontal bars of percentages, one line per label, the longest bar `width` glyphs wide
+pub fn bar_lines(bars: &[(String, f64)], width: usize) -> Vec<String> {
+    // Eighths of a glyph, so close shares still show different bars
+    let glyphs = ['▏', '▎', '▍', '▌', '▋', '▊', '▉', '█'];
+    let label_width = bars
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let max = bars.iter().map(|bar| bar.1).fold(0.0, f64::max);
+    bars.iter()
+        .map(|(label, value)| {
+            let eighths = if max > 0.0 {
+                (value / max * (width * 8) as f64).round() as usize
+            } else {
+                0
+            };
+            let mut bar = "█".repeat(eighths / 8);
+            if eighths % 8 > 0 {
+                bar.push(glyphs[eighths % 8 - 1]);
+            }
+            format!("{:<label_width$}  {:<width$}  {:.2}%", label, bar, value)
+        })
+        .collect()
+}
+
+/// Print percentages as horizontal bars fitted to the terminal
+pub fn print_bars(bars: &[(String, f64)]) {
+    let term_width = match term_size::dimensions() {
+        Some((w, _)) => w,
+        None => 80,
+    };
+    let label_width = bars
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    // Room for the label, the percentage and the gaps between them
+    let width = term_width.saturating_sub(label_width + 12).max(10);
+    for line in bar_lines(bars, width) {
+        println!("{}", line);
+    }
+}
+
+/// Render percentages as a labelled pie chart, the backend is picked by file extension (png or svg)
+pub fn render_pie(path: &Path, title: &str, slices: &[(String, f64)]) -> Result<()> {
+    let size = (800, 640);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("svg") => draw_pie(
+            SVGBackend::new(path, size).into_drawing_area(),
+            title,
+            slices,
+        ),
+        Some("png") => draw_pie(
+            BitMapBackend::new(path, size).into_drawing_area(),
+            title,
+            slices,
+        ),
+        _ => Err(anyhow!(
+            "Unsupported chart file {}. Expected a .png or .svg extension",
+            path.display()
+        )),
+    }
+}
+
+fn draw_pie<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    title: &str,
+    slices: &[(String, f64)],
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let root = root.titled(title, ("sans-serif", 24))?;
+    let (width, height) = root.dim_in_pixel();
+    let center = (width as i32 / 2, height as i32 / 2);
+    let radius = width.min(height) as f64 * 0.35;
+
+    let sizes: Vec<f64> = slices.iter().map(|slice| slice.1).collect();
+    let labels: Vec<String> = slices
+        .iter()
+        .map(|(label, share)| format!("{} ({:.1}%)", label, share))
+        .collect();
+    let colors: Vec<RGBColor> = (0..slices.len())
+        .map(|i| {
+            let (r, g, b) = Palette99::COLORS[i % Palette99::COLORS.len()];
+            RGBColor(r, g, b)
+        })
+        .collect();
+    let mut pie = Pie::new(&center, &radius, &sizes, &colors, &labels);
+    // The biggest slice starts at twelve o'clock
+    pie.start_angle(-90.0);
+    pie.label_style(("sans-serif", 16).into_font().color(&BLACK));
+    root.draw(&pie)?;
+
+    root.present()?;
+    Ok(())
+}
+
+/// Render every series as its own stacked panel, the backend is picked by file extension (png or svg)
+pub fn render_chart(path: &Path, series: &[Series]) -> Result<()> {
+    let size = (1024, 320 * series.len().max(1) as u32);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("svg") => draw_panels(SVGBackend::new(path, size).into_drawing_area(), series),
+        Some("png") => draw_panels(BitMapBackend::new(path, size).into_drawing_area(), series),
+        _ => Err(anyhow!(
+            "Unsupported chart file {}. Expected a .png or .svg extension",
+            path.display()
+        )),
+    }
+}
+
+fn draw_panels<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, series: &[Series]) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let panels = root.split_evenly((series.len().max(1), 1));
+
+    for (panel, series) in panels.iter().zip(series) {
+        let min_x = series.points.iter().map(|p| p.0).min().unwrap_or(0);
+        let max_x = series.points.iter().map(|p| p.0).max().unwrap_or(0);
+        let max_y = series.points.iter().map(|p| p.1).fold(0.0, f64::max);
+
+        let mut chart = ChartBuilder::on(panel)
+            .caption(&series.name, ("sans-serif", 20))
+            .margin(10)
+            .x_label_area_size(30)
+            .y_label_area_size(70)
+            .build_cartesian_2d(min_x..max_x + 1, 0.0..max_y * 1.05 + f64::EPSILON)?;
+
+        chart.configure_mesh().x_desc("Block #").draw()?;
+        chart.draw_series(LineSeries::new(series.points.iter().copied(), &BLUE))?;
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+/// The `--plot-out` image and `--plot-data` CSV a command was asked for
+#[derive(Debug, Clone, Default)]
+pub struct PlotFiles {
+    pub image: Option<PathBuf>,
+    pub data: Option<PathBuf>,
+}
+
+impl PlotFiles {
+    /// Write the requested files of `series`
+    pub fn write(&self, series: &[Series]) -> Result<()> {
+        if let Some(path) = &self.image {
+            render_chart(path, series)?;
+        }
+        if let Some(path) = &self.data {
+            write_plot_data(path, series)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write series as CSV with a header row, one row per height and an empty cell where a series has no value
+pub fn write_plot_data(path: &Path, series: &[Series]) -> Result<()> {
+    let mut rows: BTreeMap<usize, Vec<Option<f64>>> = BTreeMap::new();
+    for (column, s) in series.iter().enumerate() {
+        for (x, y) in &s.points {
+            rows.entry(*x).or_insert_with(|| vec![None; series.len()])[column] = Some(*y);
+        }
+    }
+
+    let mut out = String::from("block");
+    for series in series {
+        out.push(',');
+        out.push_str(&series.name);
+    }
+    out.push('\n');
+
+    for (x, values) in rows {
+        out.push_str(&x.to_string());
+        for value in values {
+            out.push(',');
+            if let Some(value) = value {
+                out.push_str(&value.to_string());
+            }
+        }
+        out.push('\n');
+    }
+
+    fs::write(path, out)?;
+    Ok(())
+}
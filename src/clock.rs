@@ -0,0 +1,290 @@
+// clock.rs
+use anyhow::{Result, anyhow};
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::blockchain_data_provider::BlockchainDataProvider;
+use std::{
+    net::SocketAddr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::{net::UdpSocket, task::JoinSet};
+use tracing::warn;
+
+use crate::{
+    color,
+    config::Config,
+    error::{ErrorKind, error},
+    format_timestamp,
+    output::{Output, OutputFormat},
+    probe,
+    tables::{align_right, new_table},
+};
+
+/// NTP server asked for the time by default
+pub const DEFAULT_NTP_SERVER: &str = "pool.ntp.org:123";
+
+/// Seconds from the NTP era (1900) to the unix epoch
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |now| now.as_secs_f64())
+}
+
+/// Read an NTP timestamp, seconds and a binary fraction since 1900, as unix seconds
+fn ntp_to_unix(bytes: &[u8]) -> f64 {
+    let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as f64;
+    seconds - NTP_UNIX_OFFSET as f64 + fraction / 2f64.powi(32)
+}
+
+/// Seconds the local clock is behind `server`'s, negative when it's ahead, by a single SNTP query
+pub async fn ntp_offset(server: &str, timeout: Duration) -> Result<f64> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect(server).await.map_err(|e| {
+        error(
+            ErrorKind::Dns,
+            format!("Could not resolve NTP server {server}: {e}"),
+        )
+    })?;
+    // Version 4, client mode, everything else left for the server
+    let mut request = [0u8; 48];
+    request[0] = 0b00_100_011;
+    let sent = unix_now();
+    socket.send(&request).await?;
+    let mut response = [0u8; 48];
+    let received = tokio::time::timeout(timeout, socket.recv(&mut response))
+        .await
+        .map_err(|_| {
+            error(
+                ErrorKind::Timeout,
+                format!("NTP server {server} did not answer within {timeout:?}"),
+            )
+        })??;
+    let arrived = unix_now();
+    if received < 48 || response[0] & 0b111 != 4 {
+        return Err(anyhow!("NTP server {server} sent an invalid response"));
+    }
+    let server_received = ntp_to_unix(&response[32..40]);
+    let server_sent = ntp_to_unix(&response[40..48]);
+    Ok(((server_received - sent) + (server_sent - arrived)) / 2.0)
+}
+
+/// The tip one node reports
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeTip {
+    /// Alias or address the node was given as
+    pub name: String,
+    pub address: SocketAddr,
+    pub height: usize,
+    pub tip_timestamp: u64,
+}
+
+/// Height and tip timestamp of `node`
+pub async fn node_tip(name: String, node: String) -> Result<NodeTip> {
+    let (probe, client) = probe::probe(name.clone(), node).await?;
+    if probe.height == 0 {
+        return Err(anyhow!("{name} has no blocks"));
+    }
+    let tip = client
+        .get_block_by_height(probe.height - 1)
+        .await?
+        .ok_or_else(|| error(ErrorKind::NotFound, format!("{name} has no tip block")))?;
+    Ok(NodeTip {
+        name,
+        address: probe.address,
+        height: probe.height,
+        tip_timestamp: tip.timestamp,
+    })
+}
+
+/// Tips of every `(name, node)` concurrently, nodes slower than `timeout` failing
+pub async fn node_tips(
+    nodes: impl IntoIterator<Item = (String, String)>,
+    timeout: Duration,
+) -> Vec<(String, Result<NodeTip>)> {
+    let mut tips = JoinSet::new();
+    for (name, node) in nodes {
+        tips.spawn(async move {
+            let result = tokio::time::timeout(timeout, node_tip(name.clone(), node))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(error(
+                        ErrorKind::Timeout,
+                        format!("No answer within {:?}", timeout),
+                    ))
+                });
+            (name, result)
+        });
+    }
+    let mut results = Vec::new();
+    while let Some(result) = tips.join_next().await {
+        if let Ok(result) = result {
+            results.push(result);
+        }
+    }
+    results
+}
+
+#[derive(Debug, Serialize)]
+pub struct NodeTime {
+    #[serde(flatten)]
+    pub tip: NodeTip,
+    /// Seconds since the tip was timestamped, by the corrected clock. Negative for tips in the future.
+    pub tip_age: f64,
+    /// Seconds the tip timestamp is ahead of the median tip timestamp
+    pub offset_from_median: i64,
+    /// Whether the tip is more than the tolerance in the future
+    pub ahead: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimeCheck {
+    /// Seconds the local clock is behind NTP, `None` when NTP could not be asked
+    pub local_offset: Option<f64>,
+    /// Unix time corrected by `local_offset`
+    pub now: f64,
+    pub tolerance: u64,
+    pub nodes: Vec<NodeTime>,
+    pub median_tip_timestamp: u64,
+    /// Newest tip timestamp less the oldest, in seconds
+    pub spread: u64,
+    /// Nodes whose tip is more than the tolerance in the future
+    pub ahead: usize,
+    /// More than half the nodes have tips in the future
+    pub skewed_majority: bool,
+}
+
+/// Compare the tips of the nodes with each other and with `now`, corrected by `local_offset`
+pub fn time_check(
+    mut tips: Vec<NodeTip>,
+    now: f64,
+    local_offset: Option<f64>,
+    tolerance: u64,
+) -> Result<TimeCheck> {
+    if tips.is_empty() {
+        return Err(error(ErrorKind::Connect, "None of the nodes answered"));
+    }
+    tips.sort_by(|a, b| a.name.cmp(&b.name));
+    let now = now + local_offset.unwrap_or(0.0);
+    let mut timestamps: Vec<u64> = tips.iter().map(|tip| tip.tip_timestamp).collect();
+    timestamps.sort_unstable();
+    let median_tip_timestamp = timestamps[(timestamps.len() - 1) / 2];
+    let spread = timestamps[timestamps.len() - 1] - timestamps[0];
+    let nodes: Vec<NodeTime> = tips
+        .into_iter()
+        .map(|tip| {
+            let tip_age = now - tip.tip_timestamp as f64;
+            NodeTime {
+                tip_age,
+                offset_from_median: tip.tip_timestamp as i64 - median_tip_timestamp as i64,
+                ahead: -tip_age > tolerance as f64,
+                tip,
+            }
+        })
+        .collect();
+    let ahead = nodes.iter().filter(|node| node.ahead).count();
+    Ok(TimeCheck {
+        local_offset,
+        now,
+        tolerance,
+        median_tip_timestamp,
+        spread,
+        ahead,
+        skewed_majority: ahead * 2 > nodes.len(),
+        nodes,
+    })
+}
+
+pub fn time_table(check: &TimeCheck) -> Table {
+    let mut table = new_table(&["Node", "Height", "Tip Time", "Tip Age", "From Median"]);
+    for node in &check.nodes {
+        let age = format!("{:.0}s", node.tip_age);
+        table.add_row(vec![
+            node.tip.name.clone(),
+            node.tip.height.to_string(),
+            format_timestamp(node.tip.tip_timestamp),
+            if node.ahead { color::warning(age) } else { age },
+            format!("{:+}s", node.offset_from_median),
+        ]);
+    }
+    align_right(&mut table, &[1, 3, 4]);
+    table
+}
+
+pub fn print_time_check(check: &TimeCheck) {
+    println!("{}", time_table(check));
+    match check.local_offset {
+        Some(offset) => println!("Local clock is {:+.3}s off NTP", -offset),
+        None => println!(
+            "{}",
+            color::warning("NTP could not be asked, tip ages are by the local clock")
+        ),
+    }
+    println!(
+        "Tip timestamps spread over {}s, {} of {} nodes have tips more than {}s in the future",
+        check.spread,
+        check.ahead,
+        check.nodes.len(),
+        check.tolerance
+    );
+    if check.skewed_majority {
+        println!(
+            "{}",
+            color::warning(
+                "Most nodes follow a chain timestamped in the future, a sign of timestamp manipulation"
+            )
+        );
+    }
+}
+
+/// Compare the tips of `nodes`, or of the configured nodes, with each other and with the time
+/// from the `ntp` server, the local clock is trusted without one
+pub async fn run_check_time(
+    config: &Config,
+    nodes: Option<&str>,
+    ntp: Option<&str>,
+    tolerance: Duration,
+    timeout: Duration,
+    out: &Output,
+) -> Result<()> {
+    let nodes: Vec<(String, String)> = match nodes {
+        Some(nodes) => config
+            .resolve_nodes(nodes)
+            .into_iter()
+            .map(|node| (node.to_string(), node.to_string()))
+            .collect(),
+        None => config.nodes.clone().into_iter().collect(),
+    };
+    if nodes.len() < 2 {
+        return Err(anyhow!(
+            "check time needs at least 2 nodes from --nodes or the [nodes] config section"
+        ));
+    }
+    let local_offset = match ntp {
+        Some(ntp) => match ntp_offset(ntp, timeout).await {
+            Ok(offset) => Some(offset),
+            Err(e) => {
+                warn!("Could not ask {} for the time: {:#}", ntp, e);
+                None
+            }
+        },
+        None => None,
+    };
+    let mut tips = Vec::new();
+    for (name, result) in node_tips(nodes, timeout).await {
+        match result {
+            Ok(tip) => tips.push(tip),
+            Err(e) => warn!("Skipping {}: {:#}", name, e),
+        }
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs_f64();
+    let check = time_check(tips, now, local_offset, tolerance.as_secs())?;
+    match out.format {
+        OutputFormat::Text => print_time_check(&check),
+        OutputFormat::Debug => println!("{:#?}", check),
+        OutputFormat::Json => out.json(&check)?,
+    }
+    Ok(())
+}
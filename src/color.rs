@@ -0,0 +1,136 @@
+// color.rs
+use anstyle::{AnsiColor, Color, Style};
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{
+    env,
+    fmt::Display,
+    io::{IsTerminal, stdout},
+    sync::OnceLock,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when printing to a terminal and NO_COLOR is not set
+    Auto,
+    Always,
+    Never,
+}
+
+/// Color names per semantic element, read from the `[theme]` config section
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    pub amount: String,
+    pub hash: String,
+    pub warning: String,
+    pub miner: String,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        ThemeConfig {
+            amount: "green".to_string(),
+            hash: "cyan".to_string(),
+            warning: "bold yellow".to_string(),
+            miner: "magenta".to_string(),
+        }
+    }
+}
+
+struct Theme {
+    amount: Style,
+    hash: Style,
+    warning: Style,
+    miner: Style,
+}
+
+static THEME: OnceLock<Option<Theme>> = OnceLock::new();
+
+/// Decide once whether output is colored, and with which theme
+pub fn init(choice: ColorChoice, config: &ThemeConfig) -> Result<()> {
+    let enabled = match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+        }
+    };
+
+    let theme = if enabled {
+        Some(Theme {
+            amount: parse_style(&config.amount)?,
+            hash: parse_style(&config.hash)?,
+            warning: parse_style(&config.warning)?,
+            miner: parse_style(&config.miner)?,
+        })
+    } else {
+        None
+    };
+    let _ = THEME.set(theme);
+    Ok(())
+}
+
+/// Parse a theme entry such as `green`, `bold red` or `bright-blue`
+fn parse_style(spec: &str) -> Result<Style> {
+    let mut style = Style::new();
+    for word in spec.split_whitespace() {
+        style = match word.to_lowercase().as_str() {
+            "bold" => style.bold(),
+            "dim" => style.dimmed(),
+            "italic" => style.italic(),
+            "underline" => style.underline(),
+            color => style.fg_color(Some(Color::Ansi(parse_color(color)?))),
+        };
+    }
+    Ok(style)
+}
+
+fn parse_color(name: &str) -> Result<AnsiColor> {
+    Ok(match name {
+        "black" => AnsiColor::Black,
+        "red" => AnsiColor::Red,
+        "green" => AnsiColor::Green,
+        "yellow" => AnsiColor::Yellow,
+        "blue" => AnsiColor::Blue,
+        "magenta" => AnsiColor::Magenta,
+        "cyan" => AnsiColor::Cyan,
+        "white" => AnsiColor::White,
+        "bright-black" => AnsiColor::BrightBlack,
+        "bright-red" => AnsiColor::BrightRed,
+        "bright-green" => AnsiColor::BrightGreen,
+        "bright-yellow" => AnsiColor::BrightYellow,
+        "bright-blue" => AnsiColor::BrightBlue,
+        "bright-magenta" => AnsiColor::BrightMagenta,
+        "bright-cyan" => AnsiColor::BrightCyan,
+        "bright-white" => AnsiColor::BrightWhite,
+        other => return Err(anyhow!("Unknown theme color {other}")),
+    })
+}
+
+fn paint(pick: impl Fn(&Theme) -> Style, text: impl Display) -> String {
+    match THEME.get() {
+        Some(Some(theme)) => {
+            let style = pick(theme);
+            format!("{}{}{}", style.render(), text, style.render_reset())
+        }
+        _ => text.to_string(),
+    }
+}
+
+pub fn amount(text: impl Display) -> String {
+    paint(|t| t.amount, text)
+}
+
+pub fn hash(text: impl Display) -> String {
+    paint(|t| t.hash, text)
+}
+
+pub fn warning(text: impl Display) -> String {
+    paint(|t| t.warning, text)
+}
+
+pub fn miner(text: impl Display) -> String {
+    paint(|t| t.miner, text)
+}
@@ -0,0 +1,132 @@
+// compare.rs
+use anyhow::Result;
+use comfy_table::Table;
+use serde::Serialize;
+use std::ops::Range;
+
+use crate::{
+    averages::{ChainStats, calculate_chain_stats_range},
+    blocks::BlockRangeProvider,
+    output::{Output, OutputFormat},
+    tables::{align_right, new_table},
+};
+
+/// One statistic measured over both ranges
+#[derive(Debug, Serialize)]
+pub struct ComparedValue {
+    pub name: &'static str,
+    pub a: f64,
+    pub b: f64,
+    /// Change from `a` to `b` in percent, `None` when `a` is zero
+    pub delta_percent: Option<f64>,
+}
+
+/// Side by side statistics of two block ranges, as printed by `averages --compare`
+#[derive(Debug, Serialize)]
+pub struct StatsComparison {
+    pub a: Range<usize>,
+    pub b: Range<usize>,
+    pub values: Vec<ComparedValue>,
+}
+
+/// Share of the blocks in the window mined by the `n` most frequent miners
+fn miner_share(stats: &ChainStats, n: usize) -> f64 {
+    let blocks = stats.block_difficulty_series.len();
+    let mined: usize = stats
+        .top_miners
+        .iter()
+        .take(n)
+        .map(|(_, count)| count)
+        .sum();
+    mined as f64 / blocks.max(1) as f64
+}
+
+fn stats_range(stats: &ChainStats) -> Range<usize> {
+    stats.start_height..stats.start_height + stats.block_difficulty_series.len()
+}
+
+pub fn compare_stats(a: &ChainStats, b: &ChainStats) -> StatsComparison {
+    let value = |name, a: f64, b: f64| ComparedValue {
+        name,
+        a,
+        b,
+        delta_percent: (a != 0.0).then(|| (b - a) / a.abs() * 100.0),
+    };
+    StatsComparison {
+        a: stats_range(a),
+        b: stats_range(b),
+        values: vec![
+            value(
+                "Block time avg (s)",
+                a.block_time.average,
+                b.block_time.average,
+            ),
+            value(
+                "Block time median (s)",
+                a.block_time.median,
+                b.block_time.median,
+            ),
+            value(
+                "Block time std dev (s)",
+                a.block_time.std_dev,
+                b.block_time.std_dev,
+            ),
+            value("TPS", a.tps, b.tps),
+            value("TXs/block", a.avg_txs_per_block, b.avg_txs_per_block),
+            value(
+                "Block size (bytes)",
+                a.avg_block_size_bytes,
+                b.avg_block_size_bytes,
+            ),
+            value(
+                "Block difficulty",
+                a.avg_block_difficulty,
+                b.avg_block_difficulty,
+            ),
+            value("TX difficulty", a.avg_tx_difficulty, b.avg_tx_difficulty),
+            value("Top miner share", miner_share(a, 1), miner_share(b, 1)),
+            value(
+                "Top 10 miners share",
+                miner_share(a, 10),
+                miner_share(b, 10),
+            ),
+        ],
+    }
+}
+
+pub fn comparison_table(comparison: &StatsComparison) -> Table {
+    let a = format!("{}..{}", comparison.a.start, comparison.a.end);
+    let b = format!("{}..{}", comparison.b.start, comparison.b.end);
+    let mut table = new_table(&["", &a, &b, "Change"]);
+    for value in &comparison.values {
+        table.add_row(vec![
+            value.name.to_string(),
+            format!("{:.2}", value.a),
+            format!("{:.2}", value.b),
+            value
+                .delta_percent
+                .map(|delta| format!("{:+.2}%", delta))
+                .unwrap_or_else(|| "-".to_string()),
+        ]);
+    }
+    align_right(&mut table, &[1, 2, 3]);
+    table
+}
+
+/// Compare the chain statistics of two block ranges side by side
+pub async fn run_compare<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    a: Range<usize>,
+    b: Range<usize>,
+    out: &Output,
+) -> Result<()> {
+    let a = calculate_chain_stats_range(provider, a).await?;
+    let b = calculate_chain_stats_range(provider, b).await?;
+    let comparison = compare_stats(&a, &b);
+    match out.format {
+        OutputFormat::Text => println!("{}", comparison_table(&comparison)),
+        OutputFormat::Debug => println!("{:#?}", comparison),
+        OutputFormat::Json => out.json(&comparison)?,
+    }
+    Ok(())
+}
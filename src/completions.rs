@@ -0,0 +1,31 @@
+// completions.rs
+use clap::{
+    Command,
+    builder::{PossibleValue, PossibleValuesParser},
+};
+use clap_complete::Shell;
+use std::io::stdout;
+
+use snap_coin_utils::config::Config;
+
+/// Print a completion script for `shell`.
+/// Node aliases and address book labels from the config are baked in as candidates,
+/// so the script has to be regenerated after editing them.
+pub fn print_completions(shell: Shell, mut command: Command, config: &Config) {
+    if !config.nodes.is_empty() {
+        let aliases = candidates(config.nodes.keys());
+        command = command.mut_arg("node", |arg| arg.value_parser(aliases));
+    }
+    if !config.labels.is_empty() {
+        let labels = candidates(config.labels.keys());
+        command = command.mut_subcommand("addr", |addr| {
+            addr.mut_arg("address", |arg| arg.value_parser(labels))
+        });
+    }
+
+    clap_complete::generate(shell, &mut command, env!("CARGO_BIN_NAME"), &mut stdout());
+}
+
+fn candidates<'a>(names: impl Iterator<Item = &'a String>) -> PossibleValuesParser {
+    PossibleValuesParser::new(names.map(|name| PossibleValue::new(name.clone())))
+}
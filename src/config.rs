@@ -0,0 +1,151 @@
+// config.rs
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use snap_coin::crypto::keys::Public;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    color::ThemeConfig,
+    explorer::ExplorerConfig,
+    labels::{self, LabelBook},
+    pool::split_nodes,
+    price::PriceConfig,
+};
+
+/// User configuration, read from `config.toml` in the platform config directory
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub burns: BurnsConfig,
+    pub theme: ThemeConfig,
+    /// Short names for nodes, `alias = "host:port"`
+    pub nodes: BTreeMap<String, String>,
+    /// Address book, `label = "<base36 address>"`
+    pub labels: BTreeMap<String, String>,
+    /// Price API for `--fiat`
+    pub price: PriceConfig,
+    /// Explorer pages for `--url`
+    pub explorer: ExplorerConfig,
+    /// Labels imported with `alias import`, from `labels.csv` next to the config file
+    #[serde(skip)]
+    pub book: LabelBook,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct BurnsConfig {
+    /// Extra provably unspendable addresses (base36), on top of the all zero key
+    pub addresses: Vec<String>,
+}
+
+impl Config {
+    /// Load the config from an explicit path, or the default location if it exists
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let book = match labels::path_for(path) {
+            Some(book) => LabelBook::load(&book)?,
+            None => LabelBook::default(),
+        };
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match default_path() {
+                Some(path) if path.exists() => path,
+                _ => {
+                    return Ok(Config {
+                        book,
+                        ..Config::default()
+                    });
+                }
+            },
+        };
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Could not read config {}", path.display()))?;
+        let config: Config = toml::from_str(&contents)
+            .with_context(|| format!("Could not parse config {}", path.display()))?;
+        Ok(Config { book, ..config })
+    }
+
+    /// The node address behind an alias, anything else is passed through as is
+    pub fn resolve_node<'a>(&'a self, node: &'a str) -> &'a str {
+        self.nodes.get(node).map(String::as_str).unwrap_or(node)
+    }
+
+    /// Every node of a comma separated list with aliases resolved, an alias may stand for a list itself
+    pub fn resolve_nodes<'a>(&'a self, nodes: &'a str) -> Vec<&'a str> {
+        split_nodes(nodes)
+            .flat_map(|node| split_nodes(self.resolve_node(node)))
+            .collect()
+    }
+
+    /// The address behind an address book label, or an imported one, anything else is passed
+    /// through as is
+    pub fn resolve_address<'a>(&'a self, address: &'a str) -> &'a str {
+        self.labels
+            .get(address)
+            .map(String::as_str)
+            .or_else(|| self.book.address_of(address))
+            .unwrap_or(address)
+    }
+
+    /// The address book label of an address (base36), if it has one. Labels in the config win
+    /// over imported ones.
+    pub fn label_of(&self, address: &str) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(_, labeled)| labeled.as_str() == address)
+            .map(|(label, _)| label.as_str())
+            .or_else(|| self.book.get(address).map(|entry| entry.label.as_str()))
+    }
+
+    /// The label of an address followed by its imported category, e.g. `Exchange A [exchange]`
+    pub fn display_label(&self, address: &str) -> Option<String> {
+        let label = self.label_of(address)?;
+        match self
+            .book
+            .get(address)
+            .and_then(|entry| entry.category.as_ref())
+        {
+            Some(category) => Some(format!("{} [{}]", label, category)),
+            None => Some(label.to_string()),
+        }
+    }
+
+    /// The burn addresses of the config and the imported labels in the `burn` category
+    pub fn burn_addresses(&self) -> Result<Vec<Public>> {
+        let mut addresses = self.burns.burn_addresses()?;
+        for address in self.book.burn_addresses() {
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+        Ok(addresses)
+    }
+}
+
+impl BurnsConfig {
+    /// All addresses treated as burns, always including the all zero key
+    pub fn burn_addresses(&self) -> Result<Vec<Public>> {
+        let mut addresses = vec![Public::new_from_buf(&[0u8; 32])];
+        for address in &self.addresses {
+            let public = Public::new_from_base36(address)
+                .ok_or_else(|| anyhow!("Burn address {address} in config is not valid base36"))?;
+            if !addresses.contains(&public) {
+                addresses.push(public);
+            }
+        }
+        Ok(addresses)
+    }
+}
+
+/// `$XDG_CONFIG_HOME/snap-coin-utils/config.toml`, falling back to `~/.config` and `%APPDATA%`
+pub fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .or_else(|| env::var_os("APPDATA").map(PathBuf::from))?;
+    Some(base.join("snap-coin-utils").join("config.toml"))
+}
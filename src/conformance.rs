@@ -0,0 +1,197 @@
+// conformance.rs
+use anyhow::{Result, anyhow};
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::economics::TARGET_TIME;
+use std::ops::Range;
+use tracing::info;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    color,
+    output::{Output, OutputFormat},
+    tables::{align_right, new_table},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConformanceStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl ConformanceStatus {
+    /// Status of a deviation from the target in percent, failing beyond `fail` and warning
+    /// beyond `warn`
+    pub fn of(deviation: f64, warn: f64, fail: f64) -> Self {
+        if deviation.abs() > fail {
+            ConformanceStatus::Fail
+        } else if deviation.abs() > warn {
+            ConformanceStatus::Warn
+        } else {
+            ConformanceStatus::Pass
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ConformanceStatus::Pass => "pass",
+            ConformanceStatus::Warn => "warn",
+            ConformanceStatus::Fail => "fail",
+        }
+    }
+}
+
+/// Observed block time of a span of blocks against the target
+#[derive(Debug, Serialize)]
+pub struct ConformancePeriod {
+    pub start_height: usize,
+    pub end_height: usize,
+    pub average_block_time: f64,
+    /// Percentage the average is above the target, negative below it
+    pub deviation: f64,
+    pub status: ConformanceStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Conformance {
+    pub target_block_time: u64,
+    pub period: usize,
+    /// Deviations beyond these percentages warn and fail
+    pub warn: f64,
+    pub fail: f64,
+    pub periods: Vec<ConformancePeriod>,
+    /// All the blocks together
+    pub overall: ConformancePeriod,
+}
+
+/// Compare the block times of the blocks from `start_height` to the target in periods of `period`
+/// blocks. `timestamps` starts with the block before `start_height`, so every block's interval is
+/// known.
+pub fn conformance(
+    start_height: usize,
+    timestamps: &[u64],
+    period: usize,
+    warn: f64,
+    fail: f64,
+) -> Result<Conformance> {
+    if timestamps.len() < 2 || period == 0 {
+        return Err(anyhow!(
+            "At least 2 blocks and periods of a block are required"
+        ));
+    }
+    let measure = |start: usize, end: usize| {
+        // Seconds from the block before the span to its last block
+        let secs = timestamps[end - start_height].saturating_sub(timestamps[start - start_height]);
+        let average_block_time = secs as f64 / (end - start) as f64;
+        let deviation = (average_block_time / TARGET_TIME as f64 - 1.0) * 100.0;
+        ConformancePeriod {
+            start_height: start,
+            end_height: end,
+            average_block_time,
+            deviation,
+            status: ConformanceStatus::of(deviation, warn, fail),
+        }
+    };
+    let end_height = start_height + timestamps.len() - 1;
+    let periods = (start_height..end_height)
+        .step_by(period)
+        .map(|start| measure(start, (start + period).min(end_height)))
+        .collect();
+    Ok(Conformance {
+        target_block_time: TARGET_TIME,
+        period,
+        warn,
+        fail,
+        periods,
+        overall: measure(start_height, end_height),
+    })
+}
+
+/// Timestamps of the blocks at `heights` and the block before them
+pub async fn block_timestamps<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+) -> Result<Vec<u64>> {
+    if heights.start == 0 {
+        return Err(anyhow!("The genesis block has no block before it to time"));
+    }
+    info!(
+        "Scanning blocks {}..{} for block times",
+        heights.start - 1,
+        heights.end
+    );
+    let mut timestamps = Vec::with_capacity(heights.len() + 1);
+    for_each_block(provider, heights.start - 1..heights.end, |_, block| {
+        timestamps.push(block.timestamp);
+        Ok(())
+    })
+    .await?;
+    Ok(timestamps)
+}
+
+fn status_cell(status: ConformanceStatus) -> String {
+    match status {
+        ConformanceStatus::Pass => status.name().to_string(),
+        _ => color::warning(status.name()),
+    }
+}
+
+pub fn conformance_table(conformance: &Conformance) -> Table {
+    let mut table = new_table(&["Blocks", "Avg Block Time", "Deviation", "Status"]);
+    for period in &conformance.periods {
+        table.add_row(vec![
+            format!("{}..{}", period.start_height, period.end_height),
+            format!("{:.2}s", period.average_block_time),
+            format!("{:+.1}%", period.deviation),
+            status_cell(period.status),
+        ]);
+    }
+    align_right(&mut table, &[1, 2]);
+    table
+}
+
+pub fn print_conformance(conformance: &Conformance) {
+    println!("{}", conformance_table(conformance));
+    let overall = &conformance.overall;
+    println!(
+        "Blocks {}..{}: {:.2}s average against the {}s target, {:+.1}%, {}",
+        overall.start_height,
+        overall.end_height,
+        overall.average_block_time,
+        conformance.target_block_time,
+        overall.deviation,
+        status_cell(overall.status)
+    );
+}
+
+/// Compare the past `blocks` blocks to the target block time, failing with `check` when they do
+pub async fn run_conformance<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    blocks: usize,
+    period: usize,
+    warn: f64,
+    fail: f64,
+    check: bool,
+    out: &Output,
+) -> Result<()> {
+    let height = provider.get_height().await?;
+    let start = height.saturating_sub(blocks).max(1);
+    let timestamps = block_timestamps(provider, start..height).await?;
+    let report = conformance(start, &timestamps, period, warn, fail)?;
+    match out.format {
+        OutputFormat::Text => print_conformance(&report),
+        OutputFormat::Debug => println!("{:#?}", report),
+        OutputFormat::Json => out.json(&report)?,
+    }
+    if check && report.overall.status == ConformanceStatus::Fail {
+        return Err(anyhow!(
+            "Average block time {:.2}s is {:+.1}% off the {}s target",
+            report.overall.average_block_time,
+            report.overall.deviation,
+            report.target_block_time
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,221 @@
+// connection.rs
+use anyhow::Result;
+use serde::Serialize;
+use snap_coin::{
+    api::requests::RequestResponseError, blockchain_data_provider::BlockchainDataProviderError,
+};
+use std::{
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+use tokio::sync::{Mutex, broadcast};
+use tracing::{info, warn};
+
+use crate::{error::ErrorKind, node::NodeClient, systemd::Watchdog};
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Connection state changes of a `ReconnectingClient`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum ConnectionEvent {
+    Connected {
+        node: SocketAddr,
+    },
+    Disconnected {
+        node: SocketAddr,
+        error: String,
+    },
+    /// A reconnect attempt failed, the next one follows after `delay`, right away when another node of the pool is left to try
+    Retrying {
+        node: SocketAddr,
+        attempt: u32,
+        delay: Duration,
+        error: String,
+    },
+}
+
+/// One long lived connection for daemon style commands, reopened with exponential backoff
+/// when it drops. Node addresses are resolved once, reconnects reuse them and move on to the
+/// next node of the pool first.
+pub struct ReconnectingClient {
+    nodes: Vec<SocketAddr>,
+    /// Index in `nodes` of the node last connected to
+    current: AtomicUsize,
+    client: Mutex<Option<Arc<NodeClient>>>,
+    events: broadcast::Sender<ConnectionEvent>,
+}
+
+impl ReconnectingClient {
+    /// Wrap an already open connection
+    pub fn new(client: NodeClient) -> Self {
+        ReconnectingClient::with_pool(client, &[])
+    }
+
+    /// Wrap an already open connection to one of `pool`, failing over to the others
+    pub fn with_pool(client: NodeClient, pool: &[SocketAddr]) -> Self {
+        let mut nodes = pool.to_vec();
+        let current = match nodes.iter().position(|node| *node == client.node()) {
+            Some(current) => current,
+            None => {
+                nodes.insert(0, client.node());
+                0
+            }
+        };
+        let (events, _) = broadcast::channel(16);
+        ReconnectingClient {
+            nodes,
+            current: AtomicUsize::new(current),
+            client: Mutex::new(Some(Arc::new(client))),
+            events,
+        }
+    }
+
+    /// Receive connection state changes from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ConnectionEvent> {
+        self.events.subscribe()
+    }
+
+    /// Report drops and reconnects of a daemon style command in the log
+    pub fn log_events(&self) {
+        let mut events = self.subscribe();
+        tokio::spawn(async move {
+            while let Ok(event) = events.recv().await {
+                match event {
+                    ConnectionEvent::Connected { node } => info!("Reconnected to {}", node),
+                    ConnectionEvent::Disconnected { node, error } => {
+                        warn!("Lost connection to {}: {}", node, error)
+                    }
+                    ConnectionEvent::Retrying {
+                        node,
+                        attempt,
+                        delay,
+                        error,
+                    } => warn!(
+                        "Reconnect {} to {} failed ({}), retrying in {}s",
+                        attempt,
+                        node,
+                        error,
+                        delay.as_secs()
+                    ),
+                }
+            }
+        });
+    }
+
+    fn emit(&self, event: ConnectionEvent) {
+        // Nobody listening is fine
+        let _ = self.events.send(event);
+    }
+
+    /// The current connection, reconnecting until it succeeds when the last one dropped
+    pub async fn client(&self) -> Arc<NodeClient> {
+        let mut current = self.client.lock().await;
+        if let Some(client) = current.as_ref() {
+            return client.clone();
+        }
+
+        let mut delay = INITIAL_BACKOFF;
+        let mut attempt = 1;
+        let mut index = self.current.load(Ordering::Relaxed);
+        loop {
+            index = (index + 1) % self.nodes.len();
+            let node = self.nodes[index];
+            match NodeClient::connect(node).await {
+                Ok(client) => {
+                    self.emit(ConnectionEvent::Connected { node });
+                    self.current.store(index, Ordering::Relaxed);
+                    let client = Arc::new(client);
+                    *current = Some(client.clone());
+                    return client;
+                }
+                Err(e) => {
+                    // Back off once every node of the pool has been tried
+                    let round_done = attempt % self.nodes.len() as u32 == 0;
+                    self.emit(ConnectionEvent::Retrying {
+                        node,
+                        attempt,
+                        delay: if round_done { delay } else { Duration::ZERO },
+                        error: e.to_string(),
+                    });
+                    if round_done {
+                        tokio::time::sleep(delay).await;
+                        delay = (delay * 2).min(MAX_BACKOFF);
+                    }
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    /// Run `f` on the connection, dropping it when `f` fails because the connection was lost
+    pub async fn run<T>(&self, f: impl AsyncFnOnce(&NodeClient) -> Result<T>) -> Result<T> {
+        let client = self.client().await;
+        let result = f(&client).await;
+        if let Err(e) = &result
+            && is_connection_lost(e)
+        {
+            let mut current = self.client.lock().await;
+            // Only the first failure on a connection reports it, a newer one may be up already
+            if current.as_ref().is_some_and(|c| Arc::ptr_eq(c, &client)) {
+                *current = None;
+                self.emit(ConnectionEvent::Disconnected {
+                    node: client.node(),
+                    error: format!("{:#}", e),
+                });
+            }
+        }
+        result
+    }
+
+    /// Run `poll` every `interval` until dropped, handing what it returns to `handle`. Both get
+    /// `state`, what the daemon carries from one poll to the next. A failed poll is logged as
+    /// `what` failing and retried on the next tick, an error from `handle` ends the loop. The
+    /// systemd watchdog is pinged between polls.
+    pub async fn poll_every<S, T>(
+        &self,
+        interval: Duration,
+        what: &str,
+        state: &mut S,
+        mut poll: impl AsyncFnMut(&NodeClient, &mut S) -> Result<T>,
+        mut handle: impl FnMut(T, &mut S) -> Result<()>,
+    ) -> Result<()> {
+        let mut ticks = tokio::time::interval(interval);
+        let mut watchdog = Watchdog::from_env();
+        loop {
+            tokio::select! {
+                _ = ticks.tick() => {}
+                _ = watchdog.ping() => continue,
+            }
+            match self.run(async |client| poll(client, state).await).await {
+                Ok(value) => handle(value, state)?,
+                Err(e) => warn!("{} failed: {:#}", what, e),
+            }
+        }
+    }
+}
+
+/// Whether an error means the connection to the node is gone
+pub fn is_connection_lost(error: &anyhow::Error) -> bool {
+    let stream_failed = error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<BlockchainDataProviderError>(),
+            Some(BlockchainDataProviderError::RequestResponseError(
+                RequestResponseError::Stream
+            ))
+        ) || matches!(
+            cause.downcast_ref::<RequestResponseError>(),
+            Some(RequestResponseError::Stream)
+        )
+    });
+    stream_failed
+        || matches!(
+            ErrorKind::of(error),
+            ErrorKind::Connect | ErrorKind::Timeout
+        )
+}
@@ -0,0 +1,227 @@
+// convert.rs
+use anyhow::{Result, anyhow};
+use num_bigint::BigUint;
+use num_rational::BigRational;
+use serde::Serialize;
+
+use crate::{
+    color, format_hr, format_snap, normalize_difficulty,
+    output::{Output, OutputFormat},
+    parse_snap,
+};
+
+/// Units `convert` reads, as value suffixes
+const UNITS: [&str; 6] = ["snap", "base", "nano", "target", "difficulty", "diff"];
+
+/// A value in both of its units
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Conversion {
+    /// A SNAP amount and its base units (nano)
+    Amount { snap: String, base: u64 },
+    /// A difficulty target as 64 hex digits, and its normalized difficulty as `difficulty` prints it
+    Difficulty { target: String, difficulty: f64 },
+}
+
+/// Convert a value with its unit as a suffix: `1.5snap`, `150000000base`, `<hex>target` or `256diff`
+pub fn convert(value: &str) -> Result<Conversion> {
+    let value = value.trim().to_ascii_lowercase();
+    let (number, unit) = UNITS
+        .iter()
+        .find_map(|unit| Some((value.strip_suffix(unit)?.trim(), *unit)))
+        .ok_or_else(|| {
+            anyhow!(
+                "Unknown unit in {value}, expected e.g. 1.5snap, 150000000base, <hex>target or 256diff"
+            )
+        })?;
+    match unit {
+        "snap" => Ok(amount(parse_snap(number)?)),
+        "base" | "nano" => {
+            Ok(amount(number.parse().map_err(|_| {
+                anyhow!("Invalid amount {number}, expected whole base units")
+            })?))
+        }
+        "target" => Ok(difficulty(parse_target(number)?)),
+        _ => Ok(difficulty(target_of(parse_difficulty(number)?))),
+    }
+}
+
+/// Parse a target of up to 64 hex digits, an optional `0x` prefix aside
+pub fn parse_target(value: &str) -> Result<BigUint> {
+    let value = value.trim();
+    let hex = value.strip_prefix("0x").unwrap_or(value);
+    BigUint::parse_bytes(hex.as_bytes(), 16)
+        .filter(|target| (1..=256).contains(&target.bits()))
+        .ok_or_else(|| anyhow!("Invalid target {value}, expected up to 64 hex digits above 0"))
+}
+
+/// Parse a normalized difficulty, 1 being the easiest target
+pub fn parse_difficulty(value: &str) -> Result<f64> {
+    parse_hr(value)
+        .filter(|difficulty| *difficulty >= 1.0)
+        .ok_or_else(|| anyhow!("Invalid difficulty {value}, expected a number of 1 or more"))
+}
+
+/// Parse a hashrate such as `5M`, `5MH` or `2.5 KH/s`
+pub fn parse_hashrate(value: &str) -> Result<f64> {
+    let trimmed = value.trim();
+    let number = match trimmed.len().checked_sub(1) {
+        Some(i)
+            if trimmed
+                .get(i..)
+                .is_some_and(|unit| unit.eq_ignore_ascii_case("h")) =>
+        {
+            &trimmed[..i]
+        }
+        _ => trimmed,
+    };
+    parse_hr(number).ok_or_else(|| anyhow!("Invalid hashrate {value}, expected e.g. 2.5K or 5MH/s"))
+}
+
+/// Parse a number as `format_hr` prints it, such as `1.5` or `2.25M`, an `H/s` unit aside
+pub fn parse_hr(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let value = match value.len().checked_sub(3) {
+        Some(i)
+            if value
+                .get(i..)
+                .is_some_and(|unit| unit.eq_ignore_ascii_case("h/s")) =>
+        {
+            value[..i].trim_end()
+        }
+        _ => value,
+    };
+    let (i, unit) = value.char_indices().last()?;
+    let (number, scale) = match unit.to_ascii_uppercase() {
+        'K' => (&value[..i], 1e3),
+        'M' => (&value[..i], 1e6),
+        'G' => (&value[..i], 1e9),
+        'T' => (&value[..i], 1e12),
+        'P' => (&value[..i], 1e15),
+        _ => (value, 1.0),
+    };
+    let number = number.trim().parse::<f64>().ok()? * scale;
+    (number.is_finite() && number > 0.0).then_some(number)
+}
+
+/// A difficulty as a target, a normalized difficulty and the hashrate finding blocks at it every `block_time`
+#[derive(Debug, PartialEq, Serialize)]
+pub struct DifficultyConversion {
+    /// 64 hex digits
+    pub target: String,
+    /// Expected hashes per block, the easiest target divided by the target
+    pub difficulty: f64,
+    pub block_time_secs: f64,
+    /// Hashes per second
+    pub hashrate: f64,
+}
+
+/// What a difficulty is known by
+#[derive(Debug, Clone, Copy)]
+pub enum DifficultySpec<'a> {
+    Target(&'a str),
+    Difficulty(f64),
+    Hashrate(f64),
+}
+
+/// Every representation of a difficulty given by one of them, a hashrate at `block_time_secs` per block
+pub fn convert_difficulty(
+    spec: DifficultySpec,
+    block_time_secs: f64,
+) -> Result<DifficultyConversion> {
+    if !(block_time_secs.is_finite() && block_time_secs > 0.0) {
+        return Err(anyhow!("Block time must be above 0 seconds"));
+    }
+    let target = match spec {
+        DifficultySpec::Target(target) => parse_target(target)?,
+        DifficultySpec::Difficulty(difficulty) => target_of(difficulty),
+        DifficultySpec::Hashrate(hashrate) => target_of(hashrate * block_time_secs),
+    };
+    let (target, difficulty) = describe_target(&target);
+    Ok(DifficultyConversion {
+        target,
+        difficulty,
+        block_time_secs,
+        hashrate: difficulty / block_time_secs,
+    })
+}
+
+fn amount(base: u64) -> Conversion {
+    Conversion::Amount {
+        snap: format_snap(base),
+        base,
+    }
+}
+
+fn difficulty(target: BigUint) -> Conversion {
+    let (target, difficulty) = describe_target(&target);
+    Conversion::Difficulty { target, difficulty }
+}
+
+/// A target as 64 hex digits and its normalized difficulty
+fn describe_target(target: &BigUint) -> (String, f64) {
+    let bytes = target.to_bytes_be();
+    let mut buf = [0; 32];
+    buf[32 - bytes.len()..].copy_from_slice(&bytes);
+    (format!("{target:064x}"), normalize_difficulty(&buf))
+}
+
+/// The target of a normalized difficulty, the easiest target at least that hard
+pub fn target_of(difficulty: f64) -> BigUint {
+    let max_target = BigUint::from_bytes_be(&[255; 32]);
+    // An f64 is an exact fraction, so the division is exact up to rounding down
+    let Some(difficulty) = BigRational::from_float(difficulty) else {
+        return max_target;
+    };
+    (max_target.clone() * difficulty.denom().magnitude() / difficulty.numer().magnitude())
+        .clamp(BigUint::from(1u8), max_target)
+}
+
+/// Print a value converted to its other unit
+pub fn run_convert(value: &str, out: &Output) -> Result<()> {
+    let conversion = convert(value)?;
+    match out.format {
+        OutputFormat::Text => match &conversion {
+            Conversion::Amount { snap, base } => {
+                println!("{} SNAP = {} base units", color::amount(snap), base)
+            }
+            Conversion::Difficulty { target, difficulty } => {
+                println!("Target: {}\nDifficulty: {}", target, difficulty)
+            }
+        },
+        OutputFormat::Debug => println!("{:#?}", conversion),
+        OutputFormat::Json => out.json(&conversion)?,
+    }
+    Ok(())
+}
+
+/// Print the target, difficulty and hashrate of whichever one of them is given
+pub fn run_difficulty_convert(
+    target: Option<&str>,
+    difficulty: Option<&str>,
+    hashrate: Option<&str>,
+    block_time: f64,
+    out: &Output,
+) -> Result<()> {
+    let spec = match (target, difficulty, hashrate) {
+        (Some(target), _, _) => DifficultySpec::Target(target),
+        (_, Some(difficulty), _) => DifficultySpec::Difficulty(parse_difficulty(difficulty)?),
+        (_, _, Some(hashrate)) => DifficultySpec::Hashrate(parse_hashrate(hashrate)?),
+        _ => unreachable!(),
+    };
+    let conversion = convert_difficulty(spec, block_time)?;
+    match out.format {
+        OutputFormat::Text => {
+            println!("Target: {}", conversion.target);
+            println!("Difficulty: {}", format_hr(conversion.difficulty));
+            println!(
+                "Hashrate: {}H/s at {}s per block",
+                format_hr(conversion.hashrate),
+                conversion.block_time_secs
+            );
+        }
+        OutputFormat::Debug => println!("{:#?}", conversion),
+        OutputFormat::Json => out.json(&conversion)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,176 @@
+// counts.rs
+use anyhow::{Context, Result};
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fs::{self, File},
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use tracing::debug;
+
+use crate::limits;
+
+/// Numbers spill files so counters running in the same process don't collide
+static NEXT_RUN: AtomicUsize = AtomicUsize::new(0);
+
+/// Frequency of addresses over a scan. With `--spill-dir` the in memory map is written out as a
+/// sorted run whenever it holds more than the threshold, and the runs are merged at the end,
+/// so memory stays bounded no matter how many distinct addresses a scan sees.
+pub struct AddressCounter {
+    counts: HashMap<[u8; 32], usize>,
+    spill: Option<limits::SpillConfig>,
+    runs: Vec<PathBuf>,
+}
+
+impl Default for AddressCounter {
+    fn default() -> Self {
+        AddressCounter::new()
+    }
+}
+
+impl AddressCounter {
+    /// A counter spilling as configured by `limits::set_spill`
+    pub fn new() -> Self {
+        AddressCounter::with_spill(limits::spill_config())
+    }
+
+    pub fn with_spill(spill: Option<limits::SpillConfig>) -> Self {
+        AddressCounter {
+            counts: HashMap::new(),
+            spill,
+            runs: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, address: [u8; 32]) -> Result<()> {
+        *self.counts.entry(address).or_default() += 1;
+        if let Some(spill) = &self.spill
+            && self.counts.len() > spill.threshold
+        {
+            self.spill_run()?;
+        }
+        Ok(())
+    }
+
+    fn spill_run(&mut self) -> Result<()> {
+        let Some(spill) = &self.spill else {
+            return Ok(());
+        };
+        fs::create_dir_all(&spill.dir)
+            .with_context(|| format!("Could not create {}", spill.dir.display()))?;
+        let path = spill.dir.join(format!(
+            "snap-coin-utils-{}-{}.counts",
+            std::process::id(),
+            NEXT_RUN.fetch_add(1, Ordering::Relaxed)
+        ));
+        debug!(
+            "Spilling {} address counts to {}",
+            self.counts.len(),
+            path.display()
+        );
+
+        let mut entries: Vec<_> = self.counts.drain().collect();
+        entries.sort_unstable_by_key(|(address, _)| *address);
+        let file =
+            File::create(&path).with_context(|| format!("Could not write {}", path.display()))?;
+        self.runs.push(path);
+        let mut writer = BufWriter::new(file);
+        for (address, count) in entries {
+            writer.write_all(&address)?;
+            writer.write_all(&(count as u64).to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// The `n` most frequent addresses, most frequent first
+    pub fn top(mut self, n: usize) -> Result<Vec<([u8; 32], usize)>> {
+        if self.runs.is_empty() {
+            return Ok(top_n(std::mem::take(&mut self.counts), n));
+        }
+
+        self.spill_run()?;
+        let mut readers = self
+            .runs
+            .iter()
+            .map(|path| Ok(BufReader::new(File::open(path)?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        // Merge the sorted runs, adding up the counts of an address found in several of them
+        let mut heads = BinaryHeap::new();
+        for (run, reader) in readers.iter_mut().enumerate() {
+            if let Some((address, count)) = read_entry(reader)? {
+                heads.push(Reverse((address, run, count)));
+            }
+        }
+        let mut top = BinaryHeap::new();
+        let mut current: Option<([u8; 32], usize)> = None;
+        while let Some(Reverse((address, run, count))) = heads.pop() {
+            if let Some(next) = read_entry(&mut readers[run])? {
+                heads.push(Reverse((next.0, run, next.1)));
+            }
+            match &mut current {
+                Some((current_address, total)) if *current_address == address => *total += count,
+                _ => {
+                    if let Some((address, total)) = current.take() {
+                        keep_top(&mut top, address, total, n);
+                    }
+                    current = Some((address, count));
+                }
+            }
+        }
+        if let Some((address, total)) = current {
+            keep_top(&mut top, address, total, n);
+        }
+
+        let mut top: Vec<_> = top
+            .into_iter()
+            .map(|Reverse((count, address))| (address, count))
+            .collect();
+        top.sort_by_key(|(_, count)| Reverse(*count));
+        Ok(top)
+    }
+}
+
+impl Drop for AddressCounter {
+    fn drop(&mut self) {
+        for path in &self.runs {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// Keep the `n` largest counts in a min heap
+fn keep_top(
+    top: &mut BinaryHeap<Reverse<(usize, [u8; 32])>>,
+    address: [u8; 32],
+    count: usize,
+    n: usize,
+) {
+    top.push(Reverse((count, address)));
+    if top.len() > n {
+        top.pop();
+    }
+}
+
+fn read_entry(reader: &mut impl Read) -> Result<Option<([u8; 32], usize)>> {
+    let mut address = [0u8; 32];
+    match reader.read_exact(&mut address) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut count = [0u8; 8];
+    reader.read_exact(&mut count)?;
+    Ok(Some((address, u64::from_le_bytes(count) as usize)))
+}
+
+/// Return top N items from a frequency map
+pub fn top_n(map: HashMap<[u8; 32], usize>, n: usize) -> Vec<([u8; 32], usize)> {
+    let mut v: Vec<_> = map.into_iter().collect();
+    v.sort_by_key(|a| Reverse(a.1));
+    v.truncate(n);
+    v
+}
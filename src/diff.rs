@@ -0,0 +1,192 @@
+// diff.rs
+use anyhow::Result;
+use bincode::encode_to_vec;
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::{blockchain_data_provider::BlockchainDataProvider, core::block::Block};
+
+use crate::{
+    color, format_biguint_hr,
+    node::NodeClient,
+    output::{Output, OutputFormat},
+    query,
+    tables::new_table,
+};
+
+/// One field of the two compared values, rendered for display
+#[derive(Debug, Serialize)]
+pub struct FieldDiff {
+    pub field: &'static str,
+    pub a: String,
+    pub b: String,
+}
+
+impl FieldDiff {
+    fn new(field: &'static str, a: impl ToString, b: impl ToString) -> Self {
+        FieldDiff {
+            field,
+            a: a.to_string(),
+            b: b.to_string(),
+        }
+    }
+
+    pub fn differs(&self) -> bool {
+        self.a != self.b
+    }
+}
+
+/// Structural differences between two blocks, as printed by `diff block`
+#[derive(Debug, Serialize)]
+pub struct BlockDiff {
+    pub fields: Vec<FieldDiff>,
+    /// Transactions (base36 ids) only included in the first block
+    pub only_in_a: Vec<String>,
+    /// Transactions (base36 ids) only included in the second block
+    pub only_in_b: Vec<String>,
+    /// Number of transactions included in both blocks
+    pub shared_transactions: usize,
+}
+
+impl BlockDiff {
+    pub fn is_identical(&self) -> bool {
+        self.only_in_a.is_empty()
+            && self.only_in_b.is_empty()
+            && !self.fields.iter().any(FieldDiff::differs)
+    }
+}
+
+/// Receiver of the block reward, the second output of the coinbase transaction
+fn miner(block: &Block) -> String {
+    block
+        .transactions
+        .iter()
+        .find(|tx| tx.inputs.is_empty())
+        .and_then(|coinbase| coinbase.outputs.get(1))
+        .map(|output| output.receiver.dump_base36())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn transaction_ids(block: &Block) -> Vec<String> {
+    block
+        .transactions
+        .iter()
+        .map(|tx| {
+            tx.transaction_id
+                .map(|id| id.dump_base36())
+                .unwrap_or_else(|| "-".to_string())
+        })
+        .collect()
+}
+
+pub fn diff_blocks(a: &Block, b: &Block) -> Result<BlockDiff> {
+    let hash = |block: &Block| {
+        block
+            .meta
+            .hash
+            .map(|hash| hash.dump_base36())
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let size = |block: &Block| -> Result<usize> {
+        Ok(encode_to_vec(block, bincode::config::standard())?.len())
+    };
+    let fields = vec![
+        FieldDiff::new("Hash", hash(a), hash(b)),
+        FieldDiff::new(
+            "Previous",
+            a.meta.previous_block.dump_base36(),
+            b.meta.previous_block.dump_base36(),
+        ),
+        FieldDiff::new("Timestamp", a.timestamp, b.timestamp),
+        FieldDiff::new("Nonce", a.nonce, b.nonce),
+        FieldDiff::new(
+            "Block Difficulty",
+            format_biguint_hr(&a.meta.block_pow_difficulty),
+            format_biguint_hr(&b.meta.block_pow_difficulty),
+        ),
+        FieldDiff::new(
+            "TX Difficulty",
+            format_biguint_hr(&a.meta.tx_pow_difficulty),
+            format_biguint_hr(&b.meta.tx_pow_difficulty),
+        ),
+        FieldDiff::new("Miner", miner(a), miner(b)),
+        FieldDiff::new("Transactions", a.transactions.len(), b.transactions.len()),
+        FieldDiff::new("Size (bytes)", size(a)?, size(b)?),
+    ];
+
+    let (ids_a, ids_b) = (transaction_ids(a), transaction_ids(b));
+    let only_in_a: Vec<String> = ids_a
+        .iter()
+        .filter(|id| !ids_b.contains(id))
+        .cloned()
+        .collect();
+    let only_in_b: Vec<String> = ids_b
+        .iter()
+        .filter(|id| !ids_a.contains(id))
+        .cloned()
+        .collect();
+    Ok(BlockDiff {
+        fields,
+        shared_transactions: ids_a.len() - only_in_a.len(),
+        only_in_a,
+        only_in_b,
+    })
+}
+
+/// Field by field table, differing rows are highlighted
+pub fn fields_table(fields: &[FieldDiff]) -> Table {
+    let mut table = new_table(&["", "A", "B"]);
+    for field in fields {
+        let mark = |value: &str| {
+            if field.differs() {
+                color::warning(value)
+            } else {
+                value.to_string()
+            }
+        };
+        table.add_row(vec![
+            field.field.to_string(),
+            mark(&field.a),
+            mark(&field.b),
+        ]);
+    }
+    table
+}
+
+pub fn print_block_diff(diff: &BlockDiff) {
+    println!("{}", fields_table(&diff.fields));
+    println!("Shared transactions: {}", diff.shared_transactions);
+    for (label, ids) in [("A", &diff.only_in_a), ("B", &diff.only_in_b)] {
+        if ids.is_empty() {
+            continue;
+        }
+        println!("Only in {}:", label);
+        for id in ids {
+            println!("  {}", color::hash(id));
+        }
+    }
+    if diff.is_identical() {
+        println!("Blocks are identical");
+    }
+}
+
+/// Print the differences between blocks `a` and `b`, looking `b` up on the `other` node when given
+pub async fn run_block_diff<P: BlockchainDataProvider + ?Sized>(
+    provider: &P,
+    other: Option<&NodeClient>,
+    a: &str,
+    b: &str,
+    out: &Output,
+) -> Result<()> {
+    let block_a = query::block(provider, a).await?;
+    let block_b = match other {
+        Some(client) => query::block(client, b).await?,
+        None => query::block(provider, b).await?,
+    };
+    let diff = diff_blocks(&block_a, &block_b)?;
+    match out.format {
+        OutputFormat::Text => print_block_diff(&diff),
+        OutputFormat::Debug => println!("{:#?}", diff),
+        OutputFormat::Json => out.json(&diff)?,
+    }
+    Ok(())
+}
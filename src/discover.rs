@@ -0,0 +1,116 @@
+// discover.rs
+use anyhow::{Result, anyhow};
+use comfy_table::Table;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket},
+    time::Duration,
+};
+
+use crate::{
+    output::{Output, OutputFormat},
+    probe::{self, NodeProbe},
+    tables::{align_right, new_table},
+};
+
+/// API port of a node started with the usual settings
+pub const DEFAULT_PORTS: &[u16] = &[3003];
+
+/// This machine and, when it has a LAN address, every host of its /24
+pub fn local_hosts() -> Vec<IpAddr> {
+    let mut hosts = vec![IpAddr::V4(Ipv4Addr::LOCALHOST)];
+    if let Some(ip) = lan_address() {
+        let [a, b, c, _] = ip.octets();
+        hosts.extend((1..255).map(|d| IpAddr::V4(Ipv4Addr::new(a, b, c, d))));
+    }
+    hosts
+}
+
+/// The address outgoing traffic would use, connecting a UDP socket sends nothing
+fn lan_address() -> Option<Ipv4Addr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    socket.connect((Ipv4Addr::new(192, 0, 2, 1), 9)).ok()?;
+    match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(ip) if !ip.is_loopback() && !ip.is_unspecified() => Some(ip),
+        _ => None,
+    }
+}
+
+/// Parse a host to scan, an IP address or an IPv4 network such as `192.168.1.0/24`
+pub fn parse_hosts(value: &str) -> Result<Vec<IpAddr>> {
+    let Some((network, prefix)) = value.split_once('/') else {
+        let ip = value
+            .parse()
+            .map_err(|_| anyhow!("Invalid host {value}, expected an IP address or network"))?;
+        return Ok(vec![ip]);
+    };
+    let network: Ipv4Addr = network
+        .parse()
+        .map_err(|_| anyhow!("Invalid network {value}, expected e.g. 192.168.1.0/24"))?;
+    let prefix: u32 = prefix
+        .parse()
+        .ok()
+        .filter(|prefix| (16..=32).contains(prefix))
+        .ok_or_else(|| anyhow!("Invalid prefix in {value}, expected /16 to /32"))?;
+    let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+    let start = u32::from(network) & mask;
+    let end = start | !mask;
+    // Leave out the network and broadcast addresses of networks that have them
+    let hosts = if prefix < 31 {
+        start + 1..end
+    } else {
+        start..end + 1
+    };
+    Ok(hosts.map(|ip| IpAddr::V4(Ipv4Addr::from(ip))).collect())
+}
+
+/// Ask every port of every host for its height at once, returning the nodes that answered, best first
+pub async fn discover(hosts: &[IpAddr], ports: &[u16], timeout: Duration) -> Vec<NodeProbe> {
+    let candidates = hosts.iter().flat_map(|host| {
+        ports.iter().map(move |port| {
+            let address = SocketAddr::new(*host, *port).to_string();
+            (address.clone(), address)
+        })
+    });
+    let mut nodes: Vec<_> = probe::probe_all(candidates, timeout)
+        .await
+        .into_iter()
+        .filter_map(|(_, result)| result.ok().map(|(probe, _)| probe))
+        .collect();
+    nodes.sort_by(|a, b| b.height.cmp(&a.height).then(a.latency.cmp(&b.latency)));
+    nodes
+}
+
+pub fn discovered_table(nodes: &[NodeProbe]) -> Table {
+    let mut table = new_table(&["Node", "Height", "Latency (ms)"]);
+    for node in nodes {
+        table.add_row(vec![
+            node.address.to_string(),
+            node.height.to_string(),
+            format!("{:.1}", node.latency.as_secs_f64() * 1000.0),
+        ]);
+    }
+    align_right(&mut table, &[1, 2]);
+    table
+}
+
+/// Probe `hosts`, this machine and its /24 when none are given, and list the nodes that answered
+pub async fn run_discover(
+    hosts: &[Vec<IpAddr>],
+    ports: &[u16],
+    timeout: Duration,
+    out: &Output,
+) -> Result<()> {
+    let hosts = if hosts.is_empty() {
+        local_hosts()
+    } else {
+        hosts.concat()
+    };
+    let nodes = discover(&hosts, ports, timeout).await;
+    match out.format {
+        OutputFormat::Text if nodes.is_empty() => println!("No nodes found"),
+        OutputFormat::Text => println!("{}", discovered_table(&nodes)),
+        OutputFormat::Debug => println!("{:#?}", nodes),
+        OutputFormat::Json => out.json(&nodes)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,111 @@
+// error.rs
+use serde::Serialize;
+use serde_json::json;
+use snap_coin::blockchain_data_provider::BlockchainDataProviderError;
+use std::{fmt, io};
+
+use crate::color;
+
+/// Failure categories with stable exit codes, so scripts can branch on the kind of failure
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorKind {
+    /// Anything not covered below
+    Other,
+    /// The node address could not be resolved
+    Dns,
+    /// The node could not be reached
+    Connect,
+    /// The node did not answer in time
+    Timeout,
+    /// The requested block, transaction or address data does not exist
+    NotFound,
+    /// A block, transaction or address identifier could not be parsed
+    InvalidIdentifier,
+    /// The node answered with an error or an unexpected response
+    NodeError,
+}
+
+impl ErrorKind {
+    /// Process exit code, 2 is left for usage errors reported by clap
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Dns => 3,
+            ErrorKind::Connect => 4,
+            ErrorKind::Timeout => 5,
+            ErrorKind::NotFound => 6,
+            ErrorKind::InvalidIdentifier => 7,
+            ErrorKind::NodeError => 8,
+        }
+    }
+
+    /// Categorize an error, looking through its chain for a known cause
+    pub fn of(error: &anyhow::Error) -> ErrorKind {
+        for cause in error.chain() {
+            if let Some(error) = cause.downcast_ref::<CliError>() {
+                return error.kind;
+            }
+            if cause.is::<BlockchainDataProviderError>() {
+                return ErrorKind::NodeError;
+            }
+            if let Some(error) = cause.downcast_ref::<io::Error>() {
+                return match error.kind() {
+                    io::ErrorKind::TimedOut => ErrorKind::Timeout,
+                    io::ErrorKind::ConnectionRefused
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::NotConnected
+                    | io::ErrorKind::HostUnreachable
+                    | io::ErrorKind::NetworkUnreachable => ErrorKind::Connect,
+                    _ => ErrorKind::Other,
+                };
+            }
+        }
+        ErrorKind::Other
+    }
+}
+
+/// An error tagged with its category
+#[derive(Debug)]
+pub struct CliError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// Build a categorized error, used like `anyhow!`
+pub fn error(kind: ErrorKind, message: impl Into<String>) -> anyhow::Error {
+    CliError {
+        kind,
+        message: message.into(),
+    }
+    .into()
+}
+
+/// Report a failed run on stderr, as a JSON object when JSON output was requested
+pub fn report(error: &anyhow::Error, json: bool) -> u8 {
+    let kind = ErrorKind::of(error);
+    if json {
+        eprintln!(
+            "{}",
+            json!({
+                "error": {
+                    "kind": kind,
+                    "code": kind.exit_code(),
+                    "message": format!("{:#}", error),
+                }
+            })
+        );
+    } else {
+        eprintln!("{} {:#}", color::warning("Error:"), error);
+    }
+    kind.exit_code()
+}
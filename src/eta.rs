@@ -0,0 +1,109 @@
+// eta.rs
+use anyhow::Result;
+use serde::Serialize;
+use snap_coin::{
+    blockchain_data_provider::BlockchainDataProvider, core::difficulty::calculate_block_difficulty,
+    economics::TARGET_TIME,
+};
+
+use crate::{
+    format_hr, normalize_difficulty,
+    output::{Output, OutputFormat},
+};
+
+/// Chances of having found a block the ETA reports the time for
+pub const CONFIDENCES: [f64; 3] = [0.5, 0.9, 0.99];
+
+/// Time within which a block is found with `probability`
+#[derive(Debug, Serialize)]
+pub struct EtaQuantile {
+    pub probability: f64,
+    pub secs: f64,
+}
+
+/// When a solo miner should expect to find a block at the current difficulty
+#[derive(Debug, Serialize)]
+pub struct MineEta {
+    pub hashrate: f64,
+    /// Expected hashes per block, for a block with only the reward transaction
+    pub block_difficulty: f64,
+    /// Hashrate of the whole network, finding blocks at the target block time
+    pub network_hashrate: f64,
+    /// Share of the network hashrate, counting the miner in it
+    pub network_share: f64,
+    /// Mean time between blocks of this miner
+    pub expected_secs: f64,
+    pub quantiles: Vec<EtaQuantile>,
+    pub blocks_per_day: f64,
+}
+
+/// Blocks are found by a Poisson process, so the wait is exponential with the expected time as mean:
+/// the chance of a block within `t` is `1 - e^(-t / expected)`.
+pub fn mine_eta(block_difficulty: &[u8; 32], hashrate: f64) -> MineEta {
+    // A block always holds the reward transaction, and every transaction eases the block a little
+    let difficulty = normalize_difficulty(&calculate_block_difficulty(block_difficulty, 1));
+    let network_hashrate = normalize_difficulty(block_difficulty) / TARGET_TIME as f64;
+    let expected_secs = difficulty / hashrate;
+    MineEta {
+        hashrate,
+        block_difficulty: difficulty,
+        network_hashrate,
+        network_share: hashrate / (network_hashrate + hashrate),
+        expected_secs,
+        quantiles: CONFIDENCES
+            .iter()
+            .map(|&probability| EtaQuantile {
+                probability,
+                secs: -(1.0 - probability).ln() * expected_secs,
+            })
+            .collect(),
+        blocks_per_day: 86_400.0 / expected_secs,
+    }
+}
+
+/// Format seconds as their two biggest units, e.g. `3d 4h`, `2h 0m` or `12m 5s`
+pub fn format_secs(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    match secs {
+        86_400.. => format!("{}d {}h", secs / 86_400, secs % 86_400 / 3600),
+        3600.. => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+        60.. => format!("{}m {}s", secs / 60, secs % 60),
+        _ => format!("{secs}s"),
+    }
+}
+
+pub fn print_mine_eta(eta: &MineEta) {
+    println!(
+        "{}H/s against {}H/s of the network ({:.4}%)",
+        format_hr(eta.hashrate),
+        format_hr(eta.network_hashrate),
+        eta.network_share * 100.0
+    );
+    println!(
+        "Expected time to a block: {} ({} blocks a day)",
+        format_secs(eta.expected_secs),
+        format_hr(eta.blocks_per_day)
+    );
+    for quantile in &eta.quantiles {
+        println!(
+            "{}% chance within {}",
+            quantile.probability * 100.0,
+            format_secs(quantile.secs)
+        );
+    }
+}
+
+/// Print how long `hashrate` takes to mine a block at the current difficulty
+pub async fn run_mine_eta<P: BlockchainDataProvider + ?Sized>(
+    provider: &P,
+    hashrate: f64,
+    out: &Output,
+) -> Result<()> {
+    let eta = mine_eta(&provider.get_block_difficulty().await?, hashrate);
+    match out.format {
+        OutputFormat::Text => print_mine_eta(&eta),
+        OutputFormat::Debug => println!("{:#?}", eta),
+        OutputFormat::Json => out.json(&eta)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,118 @@
+// explorer.rs
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use serde_json::json;
+use snap_coin::blockchain_data_provider::BlockchainDataProvider;
+use tracing::info;
+
+use crate::{
+    error::{ErrorKind, error},
+    output::{Output, OutputFormat},
+    query::{self, parse_address, parse_transaction_id},
+};
+
+/// Web explorer pages linked by `--url`, the `[explorer]` config section
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ExplorerConfig {
+    /// Block page, `{height}` and `{hash}` are replaced, e.g. `https://explorer.example.com/block/{height}`
+    pub block: Option<String>,
+    /// Transaction page, `{id}` is replaced by the base36 transaction id
+    pub tx: Option<String>,
+    /// Address page, `{address}` is replaced by the base36 address
+    pub address: Option<String>,
+}
+
+/// `template` with every `{name}` of `values` replaced
+pub fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    values
+        .iter()
+        .fold(template.to_string(), |url, (name, value)| {
+            url.replace(&format!("{{{}}}", name), value)
+        })
+}
+
+fn template<'a>(template: &'a Option<String>, key: &str) -> Result<&'a str> {
+    template.as_deref().ok_or_else(|| {
+        anyhow!(
+            "--url needs an explorer page, set `{}` in the [explorer] section of the config",
+            key
+        )
+    })
+}
+
+/// The page of the block at a height or with a hash, looked up to fill in both
+pub async fn block_url<P: BlockchainDataProvider + ?Sized>(
+    config: &ExplorerConfig,
+    provider: &P,
+    id: &str,
+) -> Result<String> {
+    let template = template(&config.block, "block")?;
+    let block = query::block(provider, id).await?;
+    let hash = block
+        .meta
+        .hash
+        .ok_or_else(|| anyhow!("Block {} has no hash", id))?;
+    let height = match id.parse::<usize>() {
+        Ok(height) => height,
+        Err(_) => provider.get_height_by_hash(hash).await?.ok_or_else(|| {
+            error(
+                ErrorKind::NotFound,
+                format!("Block {} is not on the chain", id),
+            )
+        })?,
+    };
+    Ok(fill(
+        template,
+        &[
+            ("height", &height.to_string()),
+            ("hash", &hash.dump_base36()),
+        ],
+    ))
+}
+
+/// The page of a transaction, its id is only checked for being base36
+pub fn tx_url(config: &ExplorerConfig, id: &str) -> Result<String> {
+    let template = template(&config.tx, "tx")?;
+    let id = parse_transaction_id(id)?;
+    Ok(fill(template, &[("id", &id.dump_base36())]))
+}
+
+/// The page of an address (base36)
+pub fn address_url(config: &ExplorerConfig, address: &str) -> Result<String> {
+    let template = template(&config.address, "address")?;
+    let address = parse_address(address)?;
+    Ok(fill(template, &[("address", &address.dump_base36())]))
+}
+
+/// Open `url` in the default browser without waiting for it
+pub fn open(url: &str) -> Result<()> {
+    info!("Opening {}", url);
+    open::that_detached(url).with_context(|| format!("Could not open {} in a browser", url))
+}
+
+/// What `--url` and `--open` ask for instead of the object itself
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Link {
+    pub url: bool,
+    pub open: bool,
+}
+
+impl Link {
+    /// Whether the permalink is printed instead of the object
+    pub fn wanted(self) -> bool {
+        self.url || self.open
+    }
+
+    /// Print a permalink, and open it in the browser with `--open`
+    pub fn show(self, out: &Output, url: &str) -> Result<()> {
+        match out.format {
+            OutputFormat::Json => out.json(&json!({ "url": url }))?,
+            OutputFormat::Text | OutputFormat::Debug => println!("{}", url),
+        }
+        if self.open {
+            open(url)?;
+        }
+        Ok(())
+    }
+}
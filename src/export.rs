@@ -0,0 +1,129 @@
+// export.rs
+use anyhow::{Context, Result};
+use std::{
+    fs,
+    io::{self, Write},
+    ops::Range,
+    path::Path,
+};
+use tracing::info;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    headers::BlockHeader,
+    ids::to_hex,
+    normalize_difficulty,
+    utxos::{self, UtxoSet},
+};
+
+/// Rows between two progress messages of a long export
+const PROGRESS_ROWS: usize = 10_000;
+
+/// Write the height, timestamp and normalized block and transaction difficulty of every block at
+/// `heights` to `out` as CSV, returning the rows written. Blocks are fetched in pipelined batches
+/// and written as they arrive, so the whole chain never sits in memory.
+pub async fn export_difficulty<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+    out: &mut impl Write,
+) -> Result<usize> {
+    info!(
+        "Exporting the difficulties of blocks {}..{}",
+        heights.start, heights.end
+    );
+    writeln!(
+        out,
+        "height,timestamp,block_difficulty,transaction_difficulty"
+    )?;
+    let total = heights.len();
+    let mut rows = 0;
+    for_each_block(provider, heights, |height, block| {
+        let header = BlockHeader::from(block);
+        writeln!(
+            out,
+            "{},{},{},{}",
+            height,
+            header.timestamp,
+            normalize_difficulty(&header.block_pow_difficulty),
+            normalize_difficulty(&header.tx_pow_difficulty)
+        )?;
+        rows += 1;
+        if rows % PROGRESS_ROWS == 0 {
+            info!("Exported {} of {} blocks", rows, total);
+        }
+        Ok(())
+    })
+    .await?;
+    out.flush()?;
+    Ok(rows)
+}
+
+/// Write the UTXO set left after the blocks at `heights` to `out` as CSV, one outpoint per row
+/// sorted by transaction id bytes and output index, so exports of the same height are identical.
+/// Returns the rows written.
+pub async fn export_utxos<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+    out: &mut impl Write,
+) -> Result<usize> {
+    let mut set = UtxoSet::default();
+    set.replay(provider, heights, |_, _, _| {}).await?;
+    writeln!(out, "transaction_id,output_index,owner,amount,height")?;
+    let utxos = set.sorted();
+    for ((tx_id, index), utxo) in &utxos {
+        writeln!(
+            out,
+            "{},{},{},{},{}",
+            tx_id.dump_base36(),
+            index,
+            utxo.output.receiver.dump_base36(),
+            utxo.output.amount,
+            utxo.height
+        )?;
+    }
+    out.flush()?;
+    info!("UTXO set commitment {}", to_hex(&set.commitment()));
+    Ok(utxos.len())
+}
+
+/// Open `path` for an export, `-` is stdout
+fn create(path: &Path) -> Result<Box<dyn Write>> {
+    if path.as_os_str() == "-" {
+        return Ok(Box::new(io::stdout().lock()));
+    }
+    let file =
+        fs::File::create(path).with_context(|| format!("Could not create {}", path.display()))?;
+    Ok(Box::new(io::BufWriter::new(file)))
+}
+
+/// Export the difficulties of `range`, the whole chain by default, to `path`
+pub async fn run_difficulty_export<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    range: Option<Range<usize>>,
+    path: &Path,
+) -> Result<()> {
+    let heights = match range {
+        Some(range) => range,
+        None => 0..provider.get_height().await?,
+    };
+    let rows = export_difficulty(provider, heights, &mut create(path)?).await?;
+    info!("Exported {} blocks to {}", rows, path.display());
+    Ok(())
+}
+
+/// Export the UTXO set left below `at_height`, the chain height by default, to `path`
+pub async fn run_utxo_export<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    at_height: Option<usize>,
+    path: &Path,
+) -> Result<()> {
+    let at_height = utxos::resolve_height(provider, at_height).await?;
+    let rows = export_utxos(provider, 0..at_height, &mut create(path)?).await?;
+    info!(
+        "Exported {} UTXOs at height {} to {}",
+        rows,
+        at_height,
+        path.display()
+    );
+    Ok(())
+}
@@ -0,0 +1,284 @@
+// fees.rs
+use anyhow::Result;
+use bincode::encode_to_vec;
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::core::transaction::{Transaction, TransactionOutput};
+use std::ops::Range;
+use tracing::info;
+
+use crate::{
+    blocks::{BATCH_SIZE, BlockRangeProvider},
+    color,
+    node::NodeClient,
+    output::{Output, OutputFormat},
+    price::{self, Quote},
+    query::resolve_inputs,
+    source::Source,
+    tables::{align_right, new_table},
+};
+
+/// What a transaction moves and what it pays, in nano
+#[derive(Debug, Serialize)]
+pub struct TransactionValue {
+    /// Sum of the spent outputs, `None` for coinbases or when one of them wasn't found
+    pub input_value: Option<u64>,
+    pub output_value: u64,
+    /// Paid to addresses other than the spenders, so change isn't counted
+    pub transferred: u64,
+    /// Inputs left unclaimed by the outputs
+    pub fee: Option<u64>,
+    /// Encoded size in bytes
+    pub size: usize,
+    /// Nano of fee per byte
+    pub fee_rate: Option<f64>,
+}
+
+/// Encoded size of a transaction, as blocks are sized in `averages`
+pub fn transaction_size(tx: &Transaction) -> Result<usize> {
+    Ok(encode_to_vec(tx, bincode::config::standard())?.len())
+}
+
+/// Value and fee of `tx`, given the outputs its inputs spend in input order
+pub fn transaction_value(
+    tx: &Transaction,
+    spent: &[Option<TransactionOutput>],
+) -> Result<TransactionValue> {
+    let output_value = tx.outputs.iter().map(|output| output.amount).sum();
+    let transferred = tx
+        .outputs
+        .iter()
+        .filter(|output| {
+            !tx.inputs
+                .iter()
+                .any(|input| input.output_owner == output.receiver)
+        })
+        .map(|output| output.amount)
+        .sum();
+    let input_value = if tx.inputs.is_empty() {
+        None
+    } else {
+        spent
+            .iter()
+            .map(|output| output.as_ref().map(|output| output.amount))
+            .sum::<Option<u64>>()
+    };
+    let fee = input_value.map(|inputs| inputs.saturating_sub(output_value));
+    let size = transaction_size(tx)?;
+    Ok(TransactionValue {
+        input_value,
+        output_value,
+        transferred,
+        fee,
+        size,
+        fee_rate: fee.map(|fee| fee as f64 / size.max(1) as f64),
+    })
+}
+
+/// Percentiles of the fee rates `fees` reports
+pub const FEE_PERCENTILES: [f64; 5] = [10.0, 25.0, 50.0, 75.0, 90.0];
+
+#[derive(Debug, Serialize)]
+pub struct FeePercentile {
+    pub percent: f64,
+    pub fee_rate: f64,
+}
+
+/// Spread of the fee rates (nano per byte) of some confirmed transactions
+#[derive(Debug, Serialize)]
+pub struct FeeRates {
+    pub transactions: usize,
+    /// Transactions left out because an output they spend wasn't found
+    pub unresolved: usize,
+    /// Lowest fee rate that made it in, `None` without any transaction
+    pub min: Option<f64>,
+    pub median: Option<f64>,
+    pub max: Option<f64>,
+    pub percentiles: Vec<FeePercentile>,
+}
+
+impl FeeRates {
+    pub fn new(rates: &[Option<f64>]) -> Self {
+        let mut sorted: Vec<f64> = rates.iter().flatten().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        FeeRates {
+            transactions: rates.len(),
+            unresolved: rates.len() - sorted.len(),
+            min: sorted.first().copied(),
+            median: percentile(&sorted, 50.0),
+            max: sorted.last().copied(),
+            percentiles: FEE_PERCENTILES
+                .iter()
+                .filter_map(|&percent| {
+                    percentile(&sorted, percent).map(|fee_rate| FeePercentile { percent, fee_rate })
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Nearest rank percentile of sorted fee rates, `None` without any
+pub fn percentile(sorted: &[f64], percent: f64) -> Option<f64> {
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted.get(rank.clamp(1, sorted.len().max(1)) - 1).copied()
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlockFeeRates {
+    pub height: usize,
+    #[serde(flatten)]
+    pub rates: FeeRates,
+}
+
+/// Fee rates of the transactions confirmed in a window, block by block and over all of it
+#[derive(Debug, Serialize)]
+pub struct FeeMarket {
+    pub start_height: usize,
+    pub end_height: usize,
+    pub window: FeeRates,
+    pub blocks: Vec<BlockFeeRates>,
+}
+
+/// Sum up the fee rates of every block starting at `start_height`, coinbases left out
+pub fn fee_market(start_height: usize, blocks: &[Vec<Option<f64>>]) -> FeeMarket {
+    FeeMarket {
+        start_height,
+        end_height: start_height + blocks.len(),
+        window: FeeRates::new(&blocks.concat()),
+        blocks: blocks
+            .iter()
+            .enumerate()
+            .map(|(offset, rates)| BlockFeeRates {
+                height: start_height + offset,
+                rates: FeeRates::new(rates),
+            })
+            .collect(),
+    }
+}
+
+/// Fee rates of the transactions of every block at `heights`, coinbases left out.
+/// Fees aren't recorded in transactions, so every input's spent output is looked up.
+pub async fn block_fee_rates(
+    client: &NodeClient,
+    heights: Range<usize>,
+) -> Result<Vec<Vec<Option<f64>>>> {
+    info!(
+        "Scanning blocks {}..{} for fee rates",
+        heights.start, heights.end
+    );
+    let mut rates = Vec::with_capacity(heights.len());
+    let mut start = heights.start;
+    while start < heights.end {
+        let end = (start + BATCH_SIZE).min(heights.end);
+        for block in client.get_blocks_range(start..end).await? {
+            let mut block_rates = Vec::new();
+            for tx in block.transactions.iter().filter(|tx| !tx.inputs.is_empty()) {
+                let spent = resolve_inputs(client, tx).await?;
+                block_rates.push(transaction_value(tx, &spent)?.fee_rate);
+            }
+            rates.push(block_rates);
+        }
+        start = end;
+    }
+    Ok(rates)
+}
+
+pub fn fee_market_table(market: &FeeMarket) -> Table {
+    let rate = |rate: Option<f64>| {
+        rate.map(|rate| format!("{:.2}", rate))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let mut table = new_table(&["Height", "TXs", "Min", "Median", "P90", "Max"]);
+    for block in &market.blocks {
+        let rates = &block.rates;
+        table.add_row(vec![
+            block.height.to_string(),
+            rates.transactions.to_string(),
+            rate(rates.min),
+            rate(rates.median),
+            rate(percentile_of(rates, 90.0)),
+            rate(rates.max),
+        ]);
+    }
+    align_right(&mut table, &[0, 1, 2, 3, 4, 5]);
+    table
+}
+
+fn percentile_of(rates: &FeeRates, percent: f64) -> Option<f64> {
+    rates
+        .percentiles
+        .iter()
+        .find(|percentile| percentile.percent == percent)
+        .map(|percentile| percentile.fee_rate)
+}
+
+/// The fee market as a table and a summary, with the median fee of a kilobyte valued at `fiat`'s
+/// price when given
+pub fn print_fee_market(market: &FeeMarket, fiat: Option<&Quote>) {
+    println!(
+        "Fee rates (nano/byte) of blocks {}..{}:",
+        market.start_height, market.end_height
+    );
+    println!("{}", fee_market_table(market));
+    let window = &market.window;
+    let percentiles: Vec<String> = window
+        .percentiles
+        .iter()
+        .map(|percentile| format!("P{}: {:.2}", percentile.percent, percentile.fee_rate))
+        .collect();
+    println!(
+        "{} transactions, median fee rate: {}",
+        window.transactions,
+        window
+            .median
+            .map(|median| match fiat {
+                Some(quote) => format!(
+                    "{:.2} nano/byte, {} per kB",
+                    median,
+                    quote.format_value(quote.value(1) * median * 1000.0)
+                ),
+                None => format!("{:.2} nano/byte", median),
+            })
+            .unwrap_or_else(|| "none".to_string())
+    );
+    if !percentiles.is_empty() {
+        println!("{}", percentiles.join(", "));
+    }
+    if window.unresolved > 0 {
+        println!(
+            "{}",
+            color::warning(format!(
+                "{} transactions left out, an output they spend wasn't found",
+                window.unresolved
+            ))
+        );
+    }
+}
+
+/// Print the fee rates paid in the past `blocks` blocks
+pub async fn run_fees(
+    source: &Source,
+    blocks: usize,
+    out: &Output,
+    fiat: Option<&Quote>,
+) -> Result<()> {
+    let height = source.provider().get_height().await?;
+    let start = height.saturating_sub(blocks);
+    let rates = block_fee_rates(source.client()?, start..height).await?;
+    let market = fee_market(start, &rates);
+    match out.format {
+        OutputFormat::Text => print_fee_market(&market, fiat),
+        OutputFormat::Debug => println!("{:#?}", market),
+        OutputFormat::Json => {
+            let mut amounts = Vec::new();
+            amounts.extend(
+                market
+                    .window
+                    .median
+                    .map(|median| ("median_fee_per_kb", (median * 1000.0).round() as u64)),
+            );
+            out.json(&price::with_fiat(&market, fiat, &amounts)?)?
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,186 @@
+// finality.rs
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use snap_coin::{blockchain_data_provider::BlockchainDataProvider, economics::TARGET_TIME};
+
+use crate::{
+    error::{ErrorKind, error},
+    format_hr, normalize_difficulty,
+    output::{Output, OutputFormat},
+    query,
+    source::Source,
+};
+
+/// Deepest confirmation count searched for an assurance level before giving up
+pub const MAX_CONFIRMATIONS: u64 = 10_000;
+
+/// Probability that an attacker with `attacker_share` of the hashrate ever catches up from
+/// `confirmations` blocks behind, as in section 11 of the Bitcoin whitepaper
+pub fn reversal_probability(attacker_share: f64, confirmations: u64) -> f64 {
+    let q = attacker_share.clamp(0.0, 1.0);
+    let p = 1.0 - q;
+    if q >= p || confirmations == 0 {
+        return 1.0;
+    }
+    let z = confirmations as f64;
+    let lambda = z * q / p;
+
+    // Poisson terms in log space, they underflow for deep confirmations otherwise
+    let mut log_factorial = 0.0;
+    let mut caught_up = 0.0;
+    for k in 0..=confirmations {
+        if k > 0 {
+            log_factorial += (k as f64).ln();
+        }
+        let poisson = (-lambda + k as f64 * lambda.ln() - log_factorial).exp();
+        caught_up += poisson * (1.0 - (q / p).powf(z - k as f64));
+    }
+    (1.0 - caught_up).clamp(0.0, 1.0)
+}
+
+/// Fewest confirmations bringing the reversal probability down to `1 - assurance`,
+/// `None` when even `MAX_CONFIRMATIONS` don't
+pub fn confirmations_needed(attacker_share: f64, assurance: f64) -> Option<u64> {
+    let risk = 1.0 - assurance;
+    (0..=MAX_CONFIRMATIONS)
+        .find(|&confirmations| reversal_probability(attacker_share, confirmations) <= risk)
+}
+
+#[derive(Debug, Serialize)]
+pub struct Finality {
+    pub height: usize,
+    pub confirmations: u64,
+    /// Fraction of the total hashrate assumed to be the attacker's
+    pub attacker_share: f64,
+    /// Hashes per second of the honest network, estimated from the block difficulty
+    pub network_hashrate: f64,
+    /// Hashes per second the attacker needs to have `attacker_share`
+    pub attacker_hashrate: f64,
+    pub reversal_probability: f64,
+    /// Wanted probability of the block staying on the main chain
+    pub assurance: f64,
+    pub confirmations_needed: Option<u64>,
+    /// Confirmations still to come before `assurance` is reached
+    pub remaining: Option<u64>,
+    /// Expected seconds until then at the target block time
+    pub remaining_secs: Option<u64>,
+}
+
+/// How safe the block at `height` is from being reorganized away
+pub async fn finality<P: BlockchainDataProvider + ?Sized>(
+    provider: &P,
+    height: usize,
+    attacker_share: f64,
+    assurance: f64,
+) -> Result<Finality> {
+    let chain_height = provider.get_height().await?;
+    if height >= chain_height {
+        return Err(error(
+            ErrorKind::NotFound,
+            format!("Block {height} not found, the chain height is {chain_height}"),
+        ));
+    }
+    let confirmations = (chain_height - height) as u64;
+
+    // Hashes expected per block over the target time between blocks
+    let difficulty = provider.get_block_difficulty().await?;
+    let network_hashrate = normalize_difficulty(&difficulty) / TARGET_TIME as f64;
+    let attacker_hashrate = network_hashrate * attacker_share / (1.0 - attacker_share);
+
+    let confirmations_needed = confirmations_needed(attacker_share, assurance);
+    let remaining = confirmations_needed.map(|needed| needed.saturating_sub(confirmations));
+    Ok(Finality {
+        height,
+        confirmations,
+        attacker_share,
+        network_hashrate,
+        attacker_hashrate,
+        reversal_probability: reversal_probability(attacker_share, confirmations),
+        assurance,
+        confirmations_needed,
+        remaining,
+        remaining_secs: remaining.map(|remaining| remaining * TARGET_TIME),
+    })
+}
+
+/// Parse a fraction such as `0.1` or a percentage such as `10%`
+pub fn parse_fraction(value: &str) -> Result<f64> {
+    let value = value.trim();
+    let fraction = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map(|percent| percent / 100.0),
+        None => value.parse::<f64>(),
+    }
+    .map_err(|_| anyhow!("Invalid fraction {value}, expected e.g. 0.1 or 10%"))?;
+    if !(0.0..1.0).contains(&fraction) {
+        return Err(anyhow!("Fraction {value} must be at least 0 and below 1"));
+    }
+    Ok(fraction)
+}
+
+pub fn print_finality(finality: &Finality) {
+    println!(
+        "Block {} has {} confirmations",
+        finality.height, finality.confirmations
+    );
+    println!(
+        "Attacker share: {:.2}% ({}H/s against {}H/s of the network)",
+        finality.attacker_share * 100.0,
+        format_hr(finality.attacker_hashrate),
+        format_hr(finality.network_hashrate)
+    );
+    println!(
+        "Reversal probability: {:.3e}",
+        finality.reversal_probability
+    );
+    let assurance = finality.assurance * 100.0;
+    match (finality.confirmations_needed, finality.remaining_secs) {
+        (Some(needed), _) if needed <= finality.confirmations => println!(
+            "{}% assurance reached, it takes {} confirmations",
+            assurance, needed
+        ),
+        (Some(needed), Some(secs)) => println!(
+            "{}% assurance takes {} confirmations, {} more in about {}m {}s",
+            assurance,
+            needed,
+            needed - finality.confirmations,
+            secs / 60,
+            secs % 60
+        ),
+        _ => println!(
+            "{}% assurance isn't reached within {} confirmations",
+            assurance, MAX_CONFIRMATIONS
+        ),
+    }
+}
+
+/// Print the finality of the block at height `id`, or of the block holding transaction `id`
+pub async fn run_finality(
+    source: &Source,
+    id: &str,
+    attacker: f64,
+    assurance: f64,
+    out: &Output,
+) -> Result<()> {
+    let provider = source.provider();
+    let height = match id.parse::<usize>() {
+        Ok(height) => height,
+        Err(_) => {
+            let tx = query::transaction(source.client()?, id).await?;
+            query::transaction_height(provider, &tx)
+                .await?
+                .ok_or_else(|| {
+                    error(
+                        ErrorKind::NotFound,
+                        format!("Block containing transaction {id} not found"),
+                    )
+                })?
+        }
+    };
+    let finality = finality(provider, height, attacker, assurance).await?;
+    match out.format {
+        OutputFormat::Text => print_finality(&finality),
+        OutputFormat::Debug => println!("{:#?}", finality),
+        OutputFormat::Json => out.json(&finality)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,207 @@
+// fixture.rs
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+use snap_coin::{
+    blockchain_data_provider::{BlockchainDataProvider, BlockchainDataProviderError},
+    core::{
+        block::Block,
+        transaction::{TransactionId, TransactionOutput},
+    },
+    crypto::{Hash, keys::Public},
+    economics::get_block_reward,
+};
+use std::{collections::HashMap, fs, path::Path};
+use tracing::info;
+
+use crate::blocks::{BlockRangeProvider, for_each_block};
+
+/// A recorded run of consecutive blocks plus the node state at recording time
+#[derive(Debug, Serialize, Deserialize, Encode, Decode)]
+pub struct Fixture {
+    /// Height of the first recorded block
+    pub start_height: usize,
+    pub blocks: Vec<Block>,
+    pub block_difficulty: [u8; 32],
+    pub transaction_difficulty: [u8; 32],
+}
+
+impl Fixture {
+    /// Load a fixture, `.bin` files are bincode and anything else JSON
+    pub fn load(path: &Path) -> Result<Fixture> {
+        let bytes =
+            fs::read(path).with_context(|| format!("Could not read fixture {}", path.display()))?;
+        if is_bincode(path) {
+            let (fixture, _) = bincode::decode_from_slice(&bytes, bincode::config::standard())
+                .with_context(|| format!("Could not decode fixture {}", path.display()))?;
+            Ok(fixture)
+        } else {
+            serde_json::from_slice(&bytes)
+                .with_context(|| format!("Could not parse fixture {}", path.display()))
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let bytes = if is_bincode(path) {
+            bincode::encode_to_vec(self, bincode::config::standard())?
+        } else {
+            serde_json::to_vec(self)?
+        };
+        fs::write(path, bytes)
+            .with_context(|| format!("Could not write fixture {}", path.display()))
+    }
+}
+
+fn is_bincode(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "bin")
+}
+
+/// Capture the past `block_count` blocks and current difficulties from a live provider
+pub async fn record<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    block_count: usize,
+) -> Result<Fixture> {
+    let height = provider.get_height().await?;
+    let start = height.saturating_sub(block_count);
+    let mut blocks = Vec::with_capacity(block_count);
+
+    info!("Recording blocks {}..{}", start, height);
+    for_each_block(provider, start..height, |_, block| {
+        blocks.push(block);
+        Ok(())
+    })
+    .await?;
+
+    Ok(Fixture {
+        start_height: start,
+        blocks,
+        block_difficulty: provider.get_block_difficulty().await?,
+        transaction_difficulty: provider.get_transaction_difficulty().await?,
+    })
+}
+
+/// Serves recorded blocks through the provider trait, so stats can run without a node.
+/// Unspent outputs are derived from the recorded blocks only, so they are exact only for fixtures recorded from genesis.
+pub struct FixtureProvider {
+    fixture: Fixture,
+    heights: HashMap<Hash, usize>,
+}
+
+impl FixtureProvider {
+    pub fn new(fixture: Fixture) -> Self {
+        let heights = fixture
+            .blocks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, block)| Some((block.meta.hash?, fixture.start_height + i)))
+            .collect();
+        FixtureProvider { fixture, heights }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(FixtureProvider::new(Fixture::load(path)?))
+    }
+
+    fn block(&self, height: usize) -> Option<&Block> {
+        height
+            .checked_sub(self.fixture.start_height)
+            .and_then(|i| self.fixture.blocks.get(i))
+    }
+}
+
+#[async_trait]
+impl BlockchainDataProvider for FixtureProvider {
+    async fn get_height(&self) -> Result<usize, BlockchainDataProviderError> {
+        Ok(self.fixture.start_height + self.fixture.blocks.len())
+    }
+
+    async fn get_reward(&self) -> Result<u64, BlockchainDataProviderError> {
+        Ok(get_block_reward(self.get_height().await?))
+    }
+
+    async fn get_block_by_height(
+        &self,
+        height: usize,
+    ) -> Result<Option<Block>, BlockchainDataProviderError> {
+        Ok(self.block(height).cloned())
+    }
+
+    async fn get_block_by_hash(
+        &self,
+        hash: Hash,
+    ) -> Result<Option<Block>, BlockchainDataProviderError> {
+        Ok(self
+            .heights
+            .get(&hash)
+            .and_then(|height| self.block(*height))
+            .cloned())
+    }
+
+    async fn get_height_by_hash(
+        &self,
+        hash: Hash,
+    ) -> Result<Option<usize>, BlockchainDataProviderError> {
+        Ok(self.heights.get(&hash).copied())
+    }
+
+    async fn get_block_hash_by_height(
+        &self,
+        height: usize,
+    ) -> Result<Option<Hash>, BlockchainDataProviderError> {
+        Ok(self.block(height).and_then(|block| block.meta.hash))
+    }
+
+    async fn get_transaction_difficulty(&self) -> Result<[u8; 32], BlockchainDataProviderError> {
+        Ok(self.fixture.transaction_difficulty)
+    }
+
+    async fn get_block_difficulty(&self) -> Result<[u8; 32], BlockchainDataProviderError> {
+        Ok(self.fixture.block_difficulty)
+    }
+
+    async fn get_available_transaction_outputs(
+        &self,
+        address: Public,
+    ) -> Result<Vec<(TransactionId, TransactionOutput, usize)>, BlockchainDataProviderError> {
+        let mut outputs = Vec::new();
+        for tx in self
+            .fixture
+            .blocks
+            .iter()
+            .flat_map(|block| &block.transactions)
+        {
+            outputs.retain(
+                |(tx_id, _, index): &(TransactionId, TransactionOutput, usize)| {
+                    !tx.inputs
+                        .iter()
+                        .any(|input| input.transaction_id == *tx_id && input.output_index == *index)
+                },
+            );
+            if let Some(tx_id) = tx.transaction_id {
+                for (index, output) in tx.outputs.iter().enumerate() {
+                    if output.receiver == address {
+                        outputs.push((tx_id, *output, index));
+                    }
+                }
+            }
+        }
+        Ok(outputs)
+    }
+}
+
+/// Record the past `blocks` blocks to the fixture `file`
+pub async fn run_record<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    file: &Path,
+    blocks: usize,
+) -> Result<()> {
+    let fixture = record(provider, blocks).await?;
+    fixture.save(file)?;
+    info!(
+        "Recorded {} blocks to {}",
+        fixture.blocks.len(),
+        file.display()
+    );
+    Ok(())
+}
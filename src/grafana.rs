@@ -0,0 +1,396 @@
+// grafana.rs
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    net::SocketAddr,
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    normalize_difficulty, service,
+    systemd::Watchdog,
+};
+
+/// Largest request body accepted, Grafana's queries are a few hundred bytes
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// Time a client gets to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The series Grafana can query, by the names its `/search` lists
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Height,
+    Difficulty,
+    Tps,
+    BlockTime,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 4] = [
+        Metric::Height,
+        Metric::Difficulty,
+        Metric::Tps,
+        Metric::BlockTime,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Height => "height",
+            Metric::Difficulty => "difficulty",
+            Metric::Tps => "tps",
+            Metric::BlockTime => "block_time",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Metric> {
+        Metric::ALL.into_iter().find(|metric| metric.name() == name)
+    }
+}
+
+/// What a block contributes to the series
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockPoint {
+    pub height: usize,
+    pub timestamp: u64,
+    pub difficulty: f64,
+    pub transactions: usize,
+    /// Seconds since the block before it, unknown for the first cached block
+    pub interval: Option<u64>,
+}
+
+/// The last `window` blocks, topped up from the node before every query instead of rescanning
+pub struct SeriesCache {
+    window: usize,
+    points: VecDeque<BlockPoint>,
+}
+
+impl SeriesCache {
+    pub fn new(window: usize) -> Self {
+        SeriesCache {
+            window,
+            points: VecDeque::new(),
+        }
+    }
+
+    pub fn points(&self) -> &VecDeque<BlockPoint> {
+        &self.points
+    }
+
+    /// Fetch the blocks mined since the last refresh, starting over when the chain got shorter
+    pub async fn refresh<P: BlockRangeProvider + ?Sized>(&mut self, provider: &P) -> Result<()> {
+        let height = provider.get_height().await?;
+        let cached_end = self.points.back().map_or(0, |point| point.height + 1);
+        if height < cached_end {
+            self.points.clear();
+        }
+        let start = height
+            .saturating_sub(self.window)
+            .max(self.points.back().map_or(0, |point| point.height + 1));
+        if start >= height {
+            return Ok(());
+        }
+        debug!("Caching blocks {}..{} for Grafana", start, height);
+        let mut previous = self.points.back().map(|point| point.timestamp);
+        let points = &mut self.points;
+        for_each_block(provider, start..height, |height, block| {
+            points.push_back(BlockPoint {
+                height,
+                timestamp: block.timestamp,
+                difficulty: normalize_difficulty(&block.meta.block_pow_difficulty),
+                transactions: block.transactions.len(),
+                interval: previous.map(|previous| block.timestamp.saturating_sub(previous)),
+            });
+            previous = Some(block.timestamp);
+            Ok(())
+        })
+        .await?;
+        while self.points.len() > self.window {
+            self.points.pop_front();
+        }
+        Ok(())
+    }
+}
+
+/// Datapoints, `[value, unix milliseconds]`, of `metric` over the blocks timestamped from `from`
+/// to `to`, in buckets of `bucket_secs` aligned to the unix epoch. Height is the last block of a
+/// bucket, difficulty and block time the bucket's averages and TPS its transactions over its length.
+pub fn datapoints<'a>(
+    points: impl IntoIterator<Item = &'a BlockPoint>,
+    metric: Metric,
+    from: u64,
+    to: u64,
+    bucket_secs: u64,
+) -> Vec<[f64; 2]> {
+    let bucket_secs = bucket_secs.max(1);
+    let mut buckets: BTreeMap<u64, Vec<&BlockPoint>> = BTreeMap::new();
+    for point in points {
+        if point.timestamp < from || point.timestamp > to {
+            continue;
+        }
+        let start = point.timestamp - point.timestamp % bucket_secs;
+        buckets.entry(start).or_default().push(point);
+    }
+    buckets
+        .into_iter()
+        .filter_map(|(start, bucket)| {
+            let value = match metric {
+                Metric::Height => bucket.iter().map(|point| point.height).max()? as f64,
+                Metric::Difficulty => {
+                    bucket.iter().map(|point| point.difficulty).sum::<f64>() / bucket.len() as f64
+                }
+                Metric::Tps => {
+                    bucket.iter().map(|point| point.transactions).sum::<usize>() as f64
+                        / bucket_secs as f64
+                }
+                Metric::BlockTime => {
+                    let intervals: Vec<u64> =
+                        bucket.iter().filter_map(|point| point.interval).collect();
+                    if intervals.is_empty() {
+                        return None;
+                    }
+                    intervals.iter().sum::<u64>() as f64 / intervals.len() as f64
+                }
+            };
+            Some([value, (start * 1000) as f64])
+        })
+        .collect()
+}
+
+/// Parse an RFC 3339 UTC time, as Grafana sends its time range, e.g. `2025-10-09T14:11:17.866Z`,
+/// into unix seconds
+pub fn parse_rfc3339(value: &str) -> Result<u64> {
+    let invalid = || anyhow!("Invalid time {value}, expected e.g. 2025-10-09T14:11:17Z");
+    let value = value.strip_suffix('Z').ok_or_else(invalid)?;
+    let (date, time) = value.split_once('T').ok_or_else(invalid)?;
+    let numbers = |part: &str, separator: char| -> Result<Vec<i64>> {
+        part.split(separator)
+            .map(|n| n.parse::<i64>().map_err(|_| invalid()))
+            .collect()
+    };
+    let date = numbers(date, '-')?;
+    let time = numbers(time.split('.').next().unwrap_or(time), ':')?;
+    let (&[year, month, day], &[hour, minute, second]) = (&date[..], &time[..]) else {
+        return Err(invalid());
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+
+    // Days since the epoch of a civil date, http://howardhinnant.github.io/date_algorithms.html
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+    u64::try_from(days * 86_400 + hour * 3600 + minute * 60 + second).map_err(|_| invalid())
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryRange {
+    from: String,
+    to: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct QueryTarget {
+    target: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryRequest {
+    range: QueryRange,
+    #[serde(default)]
+    interval_ms: Option<u64>,
+    #[serde(default)]
+    max_data_points: Option<u64>,
+    targets: Vec<QueryTarget>,
+}
+
+#[derive(Debug, Serialize)]
+struct QueryResponse {
+    target: String,
+    datapoints: Vec<[f64; 2]>,
+}
+
+/// Answer one request of the SimpleJSON datasource contract: `/` to test the connection,
+/// `/search` for the metric names, `/query` for their datapoints and empty `/annotations`,
+/// `/tag-keys` and `/tag-values`. Returns the status and JSON body.
+pub async fn handle<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    cache: &mut SeriesCache,
+    method: &str,
+    path: &str,
+    body: &[u8],
+) -> (u16, Value) {
+    let path = path.split('?').next().unwrap_or(path).trim_end_matches('/');
+    match (method, path) {
+        ("GET", "") => (200, json!("OK")),
+        ("POST", "/search") => (200, json!(Metric::ALL.map(Metric::name).to_vec())),
+        ("POST", "/query") => match query(provider, cache, body).await {
+            Ok(response) => (200, response),
+            Err(e) => (400, json!({ "error": format!("{:#}", e) })),
+        },
+        ("POST", "/annotations" | "/tag-keys" | "/tag-values") => (200, json!([])),
+        _ => (404, json!({ "error": format!("No {method} {path}") })),
+    }
+}
+
+async fn query<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    cache: &mut SeriesCache,
+    body: &[u8],
+) -> Result<Value> {
+    let request: QueryRequest = serde_json::from_slice(body)?;
+    let from = parse_rfc3339(&request.range.from)?;
+    let to = parse_rfc3339(&request.range.to)?;
+    // Grafana's interval already fits the range into the panel, the bucket only grows to honour
+    // maxDataPoints
+    let by_points = request
+        .max_data_points
+        .filter(|&points| points > 0)
+        .map_or(1, |points| to.saturating_sub(from).div_ceil(points));
+    let bucket_secs = (request.interval_ms.unwrap_or(0) / 1000).max(by_points);
+    cache.refresh(provider).await?;
+    let responses = request
+        .targets
+        .iter()
+        .map(|target| {
+            let metric = Metric::from_name(&target.target)
+                .ok_or_else(|| anyhow!("Unknown metric {}", target.target))?;
+            Ok(QueryResponse {
+                target: target.target.clone(),
+                datapoints: datapoints(cache.points(), metric, from, to, bucket_secs),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(serde_json::to_value(responses)?)
+}
+
+/// Read one HTTP/1.1 request, returning its method, path and body
+async fn read_request(stream: &mut TcpStream) -> Result<(String, String, Vec<u8>)> {
+    let mut buffer = Vec::new();
+    let header_end = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if buffer.len() > MAX_REQUEST_BYTES {
+            return Err(anyhow!("Request headers too large"));
+        }
+        let mut chunk = [0u8; 4096];
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(anyhow!("Connection closed mid request"));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let content_length = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .map(|(_, value)| value.trim().parse::<usize>())
+        .transpose()?
+        .unwrap_or(0);
+    if content_length > MAX_REQUEST_BYTES {
+        return Err(anyhow!("Request body too large"));
+    }
+    let mut body = buffer.split_off(header_end);
+    while body.len() < content_length {
+        let mut chunk = vec![0u8; content_length - body.len()];
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(anyhow!("Connection closed mid request"));
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+    Ok((method, path, body))
+}
+
+async fn write_response(stream: &mut TcpStream, status: u16, body: &Value) -> Result<()> {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        _ => "Error",
+    };
+    let body = serde_json::to_vec(body)?;
+    // Grafana's browser access mode needs the CORS headers
+    let head = format!(
+        "HTTP/1.1 {status} {reason}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Headers: accept, content-type\r\n\
+         Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+         Connection: close\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Serve the datasource on `listener` until stopped. Requests are answered one at a time, every
+/// query tops up the shared cache of the last `window` blocks first.
+pub async fn serve<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    listener: TcpListener,
+    window: usize,
+) -> Result<()> {
+    info!(
+        "Serving the Grafana datasource on http://{}",
+        listener.local_addr()?
+    );
+    let mut cache = SeriesCache::new(window);
+    let mut watchdog = Watchdog::from_env();
+    loop {
+        let (mut stream, peer) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = watchdog.ping() => continue,
+        };
+        let request = tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await;
+        let (status, body) = match request {
+            Ok(Ok((method, _, _))) if method == "OPTIONS" => (200, Value::Null),
+            Ok(Ok((method, path, body))) => {
+                debug!("{} {} from {}", method, path, peer);
+                handle(provider, &mut cache, &method, &path, &body).await
+            }
+            Ok(Err(e)) => (400, json!({ "error": format!("{:#}", e) })),
+            Err(_) => continue,
+        };
+        if status != 200 {
+            warn!("Answering {} with {}: {}", peer, status, body);
+        }
+        if let Err(e) = write_response(&mut stream, status, &body).await {
+            debug!("Could not answer {}: {:#}", peer, e);
+        }
+    }
+}
+
+/// Serve the Grafana datasource on `listen` until stopped
+pub async fn run_serve<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    listen: SocketAddr,
+    window: usize,
+) -> Result<()> {
+    let listener = TcpListener::bind(listen)
+        .await
+        .with_context(|| format!("Could not listen on {}", listen))?;
+    service::run_daemon(serve(provider, listener, window)).await
+}
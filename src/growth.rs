@@ -0,0 +1,297 @@
+// growth.rs
+use anyhow::{Result, anyhow};
+use bincode::encode_to_vec;
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::{
+    core::block::{Block, MAX_TRANSACTIONS_PER_BLOCK},
+    economics::TARGET_TIME,
+};
+use std::{
+    ops::Range,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::info;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    fees::transaction_size,
+    format_timestamp,
+    output::{Output, OutputFormat},
+    tables::{align_right, new_table},
+};
+
+/// Days ahead `growth` projects the chain size to by default
+pub const DEFAULT_HORIZONS: [u64; 4] = [30, 90, 180, 365];
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Encoded sizes of one block
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BlockSize {
+    pub timestamp: u64,
+    pub bytes: usize,
+    pub transactions: usize,
+    /// Bytes of the block's transactions, the rest is the block's own fields
+    pub transaction_bytes: usize,
+}
+
+impl BlockSize {
+    pub fn of(block: &Block) -> Result<Self> {
+        let mut transaction_bytes = 0;
+        for tx in &block.transactions {
+            transaction_bytes += transaction_size(tx)?;
+        }
+        Ok(BlockSize {
+            timestamp: block.timestamp,
+            bytes: encode_to_vec(block, bincode::config::standard())?.len(),
+            transactions: block.transactions.len(),
+            transaction_bytes,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrowthWindow {
+    pub start_height: usize,
+    pub end_height: usize,
+    pub bytes_per_block: f64,
+}
+
+/// Chain size some days ahead, at the current block sizes and with every block full
+#[derive(Debug, Serialize)]
+pub struct GrowthProjection {
+    pub days: u64,
+    /// Unix time of the projected date
+    pub date: u64,
+    pub current_bytes: f64,
+    pub full_bytes: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Growth {
+    pub height: usize,
+    /// Chain size now in bytes, as given or estimated from the scanned blocks
+    pub size: f64,
+    /// Whether `size` is the scanned blocks' mean size times the height
+    pub size_estimated: bool,
+    pub windows: Vec<GrowthWindow>,
+    pub bytes_per_block: f64,
+    /// Bytes of a block holding `MAX_TRANSACTIONS_PER_BLOCK` transactions of the mean size seen
+    pub full_block_bytes: f64,
+    pub blocks_per_day: f64,
+    pub projections: Vec<GrowthProjection>,
+}
+
+/// Project the chain size from the sizes of the blocks up to `height`, in windows of `window` blocks.
+/// Blocks keep coming at the rate the scanned ones did, at their mean size in the current
+/// scenario and full of transactions of the mean size seen in the full one.
+pub fn growth(
+    height: usize,
+    blocks: &[BlockSize],
+    window: usize,
+    size: Option<u64>,
+    now: u64,
+    horizons: &[u64],
+) -> Result<Growth> {
+    if blocks.len() < 2 || window == 0 {
+        return Err(anyhow!(
+            "At least 2 blocks and windows of a block are required"
+        ));
+    }
+    let start_height = height - blocks.len();
+    let mean = |blocks: &[BlockSize]| {
+        blocks.iter().map(|block| block.bytes).sum::<usize>() as f64 / blocks.len() as f64
+    };
+    let windows = blocks
+        .chunks(window)
+        .enumerate()
+        .map(|(index, chunk)| GrowthWindow {
+            start_height: start_height + index * window,
+            end_height: start_height + index * window + chunk.len(),
+            bytes_per_block: mean(chunk),
+        })
+        .collect();
+    let bytes_per_block = mean(blocks);
+
+    let transactions: usize = blocks.iter().map(|block| block.transactions).sum();
+    let transaction_bytes: usize = blocks.iter().map(|block| block.transaction_bytes).sum();
+    let block_bytes: usize = blocks.iter().map(|block| block.bytes).sum();
+    let overhead = block_bytes.saturating_sub(transaction_bytes) as f64 / blocks.len() as f64;
+    let full_block_bytes = overhead
+        + transaction_bytes as f64 / transactions.max(1) as f64 * MAX_TRANSACTIONS_PER_BLOCK as f64;
+
+    let first = blocks
+        .iter()
+        .map(|block| block.timestamp)
+        .min()
+        .unwrap_or(0);
+    let last = blocks
+        .iter()
+        .map(|block| block.timestamp)
+        .max()
+        .unwrap_or(0);
+    let blocks_per_day = if last > first {
+        (blocks.len() - 1) as f64 / (last - first) as f64 * SECONDS_PER_DAY as f64
+    } else {
+        (SECONDS_PER_DAY / TARGET_TIME) as f64
+    };
+
+    let (size, size_estimated) = match size {
+        Some(size) => (size as f64, false),
+        None => (bytes_per_block * height as f64, true),
+    };
+    let projections = horizons
+        .iter()
+        .map(|&days| {
+            let blocks = blocks_per_day * days as f64;
+            GrowthProjection {
+                days,
+                date: now + days * SECONDS_PER_DAY,
+                current_bytes: size + blocks * bytes_per_block,
+                full_bytes: size + blocks * full_block_bytes,
+            }
+        })
+        .collect();
+    Ok(Growth {
+        height,
+        size,
+        size_estimated,
+        windows,
+        bytes_per_block,
+        full_block_bytes,
+        blocks_per_day,
+        projections,
+    })
+}
+
+/// Sizes of the blocks at `heights`, in height order
+pub async fn block_sizes<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+) -> Result<Vec<BlockSize>> {
+    info!(
+        "Scanning blocks {}..{} for sizes",
+        heights.start, heights.end
+    );
+    let mut sizes = Vec::with_capacity(heights.len());
+    for_each_block(provider, heights, |_, block| {
+        sizes.push(BlockSize::of(&block)?);
+        Ok(())
+    })
+    .await?;
+    Ok(sizes)
+}
+
+/// Format bytes in binary units, e.g. `1.50 GiB`
+pub fn format_bytes(bytes: f64) -> String {
+    let units = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < units.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0} B", value)
+    } else {
+        format!("{:.2} {}", value, units[unit])
+    }
+}
+
+/// Parse a size such as `512`, `20G` or `1.5TiB`, in binary units
+pub fn parse_bytes(value: &str) -> Result<u64> {
+    let trimmed = value.trim();
+    let number_end = trimmed
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(number_end);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid size {value}, expected e.g. 512M or 20G"))?;
+    let unit = unit.trim().to_ascii_uppercase();
+    let power = match unit.trim_end_matches("IB").trim_end_matches('B') {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        "P" => 5,
+        _ => {
+            return Err(anyhow!(
+                "Unknown size unit in {value}, expected K, M, G, T or P"
+            ));
+        }
+    };
+    Ok((number * 1024f64.powi(power)) as u64)
+}
+
+pub fn windows_table(growth: &Growth) -> Table {
+    let mut table = new_table(&["Blocks", "Bytes/Block"]);
+    for window in &growth.windows {
+        table.add_row(vec![
+            format!("{}..{}", window.start_height, window.end_height),
+            format!("{:.1}", window.bytes_per_block),
+        ]);
+    }
+    align_right(&mut table, &[1]);
+    table
+}
+
+pub fn projections_table(growth: &Growth) -> Table {
+    let mut table = new_table(&["In", "Date", "At Current Sizes", "With Full Blocks"]);
+    for projection in &growth.projections {
+        let date = format_timestamp(projection.date);
+        table.add_row(vec![
+            format!("{} days", projection.days),
+            date.split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            format_bytes(projection.current_bytes),
+            format_bytes(projection.full_bytes),
+        ]);
+    }
+    align_right(&mut table, &[2, 3]);
+    table
+}
+
+pub fn print_growth(growth: &Growth) {
+    println!("{}", windows_table(growth));
+    println!(
+        "Chain size at height {}: {}{}",
+        growth.height,
+        format_bytes(growth.size),
+        if growth.size_estimated {
+            " (estimated from the scanned blocks, pass --size for the real one)"
+        } else {
+            ""
+        }
+    );
+    println!(
+        "{:.1} bytes per block, {:.1} bytes per full block, {:.0} blocks per day",
+        growth.bytes_per_block, growth.full_block_bytes, growth.blocks_per_day
+    );
+    println!("{}", projections_table(growth));
+}
+
+/// Project the chain size `days` ahead from the sizes of the past `blocks` blocks
+pub async fn run_growth<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    blocks: usize,
+    window: usize,
+    size: Option<u64>,
+    days: &[u64],
+    out: &Output,
+) -> Result<()> {
+    let height = provider.get_height().await?;
+    let sizes = block_sizes(provider, height.saturating_sub(blocks)..height).await?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let growth = growth(height, &sizes, window, size, now, days)?;
+    match out.format {
+        OutputFormat::Text => print_growth(&growth),
+        OutputFormat::Debug => println!("{:#?}", growth),
+        OutputFormat::Json => out.json(&growth)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,113 @@
+// hashing.rs
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::{Serialize, de::DeserializeOwned};
+use snap_coin::{
+    core::{block::Block, transaction::Transaction},
+    crypto::Hash,
+};
+use std::{
+    fs,
+    io::{self, Read},
+    path::Path,
+};
+
+use crate::{
+    color,
+    ids::{STDIN_ID, parse_hex, to_hex},
+    output::{Output, OutputFormat},
+};
+
+/// What the hashed bytes are
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum HashInput {
+    /// Hash the bytes as they are
+    Raw,
+    /// A block as JSON or bincode, hashed without its hash and transaction inputs and outputs
+    Block,
+    /// A transaction as JSON or bincode, hashed without its id
+    Tx,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HashReport {
+    pub input: HashInput,
+    /// Size of the hashed buffer in bytes
+    pub hashed_bytes: usize,
+    pub base36: String,
+    pub hex: String,
+    /// Hash carried by the block or transaction, when it has one
+    pub stored: Option<String>,
+    /// Whether the carried hash is the computed one
+    pub matches: Option<bool>,
+}
+
+/// The snap-coin `Hash` of `data`, read as `input`
+pub fn hash(input: HashInput, data: &[u8]) -> Result<HashReport> {
+    let (buf, stored) = match input {
+        HashInput::Raw => (data.to_vec(), None),
+        HashInput::Block => {
+            let block: Block = decode(data, "block")?;
+            (block.get_hashing_buf()?, block.meta.hash)
+        }
+        HashInput::Tx => {
+            let tx: Transaction = decode(data, "transaction")?;
+            (tx.get_tx_hashing_buf()?, tx.transaction_id)
+        }
+    };
+    let hash = Hash::new(&buf);
+    Ok(HashReport {
+        input,
+        hashed_bytes: buf.len(),
+        base36: hash.dump_base36(),
+        hex: to_hex(&*hash),
+        stored: stored.map(|stored| stored.dump_base36()),
+        matches: stored.map(|stored| stored == hash),
+    })
+}
+
+/// JSON as printed with `--raw --format json`, or the bincode encoding nodes send
+fn decode<T: DeserializeOwned + bincode::Decode<()>>(data: &[u8], what: &str) -> Result<T> {
+    if data.trim_ascii_start().starts_with(b"{") {
+        return serde_json::from_slice(data).with_context(|| format!("Invalid JSON {what}"));
+    }
+    let (value, _) = bincode::decode_from_slice(data, bincode::config::standard())
+        .with_context(|| format!("Invalid {what}, expected JSON or bincode"))?;
+    Ok(value)
+}
+
+/// Hash a file, stdin when it is `-`, or hex given on the command line
+pub fn run_hash(
+    file: Option<&Path>,
+    hex: Option<&str>,
+    input: HashInput,
+    out: &Output,
+) -> Result<()> {
+    let data = match (file, hex) {
+        (_, Some(hex)) => parse_hex(hex)?,
+        (Some(file), None) if file.as_os_str() == STDIN_ID => {
+            let mut data = Vec::new();
+            io::stdin().read_to_end(&mut data)?;
+            data
+        }
+        (Some(file), None) => {
+            fs::read(file).with_context(|| format!("Could not read {}", file.display()))?
+        }
+        (None, None) => unreachable!(),
+    };
+    let report = hash(input, &data)?;
+    match out.format {
+        OutputFormat::Text => {
+            println!("Base36: {}", color::hash(&report.base36));
+            println!("Hex: {}", report.hex);
+            if let (Some(stored), Some(matches)) = (&report.stored, report.matches) {
+                let verdict = if matches { "matches" } else { "does not match" };
+                println!("Stored: {} ({})", color::hash(stored), verdict);
+            }
+        }
+        OutputFormat::Debug => println!("{:#?}", report),
+        OutputFormat::Json => out.json(&report)?,
+    }
+    Ok(())
+}
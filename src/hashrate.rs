@@ -0,0 +1,175 @@
+// hashrate.rs
+use anyhow::Result;
+use serde::Serialize;
+use snap_coin::{
+    core::{block::Block, difficulty::calculate_block_difficulty},
+    economics::TARGET_TIME,
+};
+use std::ops::Range;
+use tracing::info;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    chart::{self, PlotFiles, Series},
+    format_hr, normalize_difficulty,
+    output::{Output, OutputFormat},
+};
+
+/// Hashes a block was expected to take, its target eased by its transactions as the node checks it
+pub fn block_work(block: &Block) -> f64 {
+    normalize_difficulty(&calculate_block_difficulty(
+        &block.meta.block_pow_difficulty,
+        block.transactions.len(),
+    ))
+}
+
+/// The hashrate estimates at one block
+#[derive(Debug, Serialize)]
+pub struct HashratePoint {
+    pub height: usize,
+    pub timestamp: u64,
+    /// Work of the window's blocks over the time they took, `None` when no time passed
+    pub hashrate: Option<f64>,
+    /// Hashrate finding blocks at the block's difficulty every target block time
+    pub difficulty_hashrate: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HashrateSeries {
+    pub window: usize,
+    pub points: Vec<HashratePoint>,
+}
+
+impl HashrateSeries {
+    /// The estimate at the newest block
+    pub fn latest(&self) -> Option<&HashratePoint> {
+        self.points.last()
+    }
+
+    /// The observed and difficulty implied hashrates as chart series, in H/s
+    pub fn series(&self) -> Vec<Series> {
+        vec![
+            Series {
+                name: format!("Hashrate over {} blocks (H/s)", self.window),
+                points: self
+                    .points
+                    .iter()
+                    .filter_map(|point| point.hashrate.map(|hashrate| (point.height, hashrate)))
+                    .collect(),
+            },
+            Series {
+                name: "Difficulty hashrate (H/s)".to_string(),
+                points: self
+                    .points
+                    .iter()
+                    .map(|point| (point.height, point.difficulty_hashrate))
+                    .collect(),
+            },
+        ]
+    }
+}
+
+/// Rolling hashrate at every block from `from` on: the work of the last `window` blocks, the block
+/// itself included, over the seconds between the block and the one `window` blocks before it.
+/// Blocks with fewer than `window` blocks before them look back as far as they can.
+pub fn rolling_hashrate(
+    works: &[f64],
+    timestamps: &[u64],
+    window: usize,
+    from: usize,
+) -> Vec<Option<f64>> {
+    (from..works.len())
+        .map(|i| {
+            let back = window.min(i);
+            let secs = timestamps[i].saturating_sub(timestamps[i - back]);
+            (secs > 0).then(|| works[i + 1 - back..=i].iter().sum::<f64>() / secs as f64)
+        })
+        .collect()
+}
+
+/// Rolling hashrate estimates of the blocks at `heights` over windows of `window` blocks,
+/// scanning the `window` blocks before them as well
+pub async fn hashrate_series<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+    window: usize,
+) -> Result<HashrateSeries> {
+    let first = heights.start.saturating_sub(window);
+    info!(
+        "Scanning blocks {}..{} for hashrate estimates",
+        first, heights.end
+    );
+    let mut works = Vec::with_capacity(heights.end - first);
+    let mut timestamps = Vec::with_capacity(heights.end - first);
+    let mut difficulty_hashrates = Vec::with_capacity(heights.len());
+    for_each_block(provider, first..heights.end, |height, block| {
+        works.push(block_work(&block));
+        timestamps.push(block.timestamp);
+        if height >= heights.start {
+            difficulty_hashrates
+                .push(normalize_difficulty(&block.meta.block_pow_difficulty) / TARGET_TIME as f64);
+        }
+        Ok(())
+    })
+    .await?;
+
+    let from = heights.start - first;
+    let hashrates = rolling_hashrate(&works, &timestamps, window, from);
+    Ok(HashrateSeries {
+        window,
+        points: heights
+            .zip(&timestamps[from..])
+            .zip(hashrates)
+            .zip(difficulty_hashrates)
+            .map(
+                |(((height, &timestamp), hashrate), difficulty_hashrate)| HashratePoint {
+                    height,
+                    timestamp,
+                    hashrate,
+                    difficulty_hashrate,
+                },
+            )
+            .collect(),
+    })
+}
+
+/// Print the latest hashrate estimate, or the rolling estimates of the past `blocks` blocks with `series`
+pub async fn run_hashrate<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    series: bool,
+    blocks: usize,
+    window: usize,
+    plots: &PlotFiles,
+    out: &Output,
+) -> Result<()> {
+    let height = provider.get_height().await?;
+    let start = height.saturating_sub(if series { blocks } else { 1 });
+    let estimates = hashrate_series(provider, start..height, window).await?;
+    let chart_series = estimates.series();
+    plots.write(&chart_series)?;
+    match out.format {
+        OutputFormat::Text => {
+            if series {
+                chart::print_sparklines(&chart_series);
+            }
+            if let Some(latest) = estimates.latest() {
+                match latest.hashrate {
+                    Some(hashrate) => println!(
+                        "Hashrate: {}H/s over the last {} blocks",
+                        format_hr(hashrate),
+                        window
+                    ),
+                    None => println!("Hashrate: unknown, no time passed in the window"),
+                }
+                println!(
+                    "Difficulty hashrate: {}H/s",
+                    format_hr(latest.difficulty_hashrate)
+                );
+            }
+        }
+        OutputFormat::Debug => println!("{:#?}", estimates),
+        OutputFormat::Json if series => out.json(&estimates)?,
+        OutputFormat::Json => out.json(&estimates.latest())?,
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+// headers.rs
+use serde::Serialize;
+use snap_coin::{core::block::Block, crypto::Hash};
+
+/// The parts of a block needed for time and difficulty statistics, without its transactions.
+/// The node protocol has no header only request, so blocks are still transferred in full, but
+/// converting right away skips walking and re-encoding their transactions.
+#[derive(Debug, Clone, Serialize)]
+pub struct BlockHeader {
+    pub hash: Option<Hash>,
+    pub previous_block: Hash,
+    pub timestamp: u64,
+    pub block_pow_difficulty: [u8; 32],
+    pub tx_pow_difficulty: [u8; 32],
+    pub transaction_count: usize,
+}
+
+impl From<Block> for BlockHeader {
+    fn from(block: Block) -> Self {
+        BlockHeader {
+            hash: block.meta.hash,
+            previous_block: block.meta.previous_block,
+            timestamp: block.timestamp,
+            block_pow_difficulty: block.meta.block_pow_difficulty,
+            tx_pow_difficulty: block.meta.tx_pow_difficulty,
+            transaction_count: block.transactions.len(),
+        }
+    }
+}
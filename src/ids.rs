@@ -0,0 +1,228 @@
+// ids.rs
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+use serde::Serialize;
+use snap_coin::crypto::{
+    Hash, Signature,
+    keys::{Private, Public},
+};
+use std::io::{self, Read, Write};
+use tracing::warn;
+
+use crate::{
+    error::{ErrorKind, error},
+    output::{Output, OutputFormat},
+};
+
+/// Kinds of base36 identifiers, all big endian numbers of a fixed number of bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum IdKind {
+    /// Public key
+    Address,
+    /// Block hash
+    Block,
+    /// Transaction id
+    Tx,
+    /// Private key
+    Secret,
+    /// Input signature
+    Signature,
+}
+
+impl IdKind {
+    /// Length of the raw identifier in bytes
+    pub fn byte_len(self) -> usize {
+        match self {
+            IdKind::Signature => 64,
+            _ => 32,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            IdKind::Address => "address",
+            IdKind::Block => "block",
+            IdKind::Tx => "tx",
+            IdKind::Secret => "secret",
+            IdKind::Signature => "signature",
+        }
+    }
+}
+
+/// An identifier in both encodings
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Identifier {
+    #[serde(rename = "type")]
+    pub kind: IdKind,
+    pub base36: String,
+    /// Raw bytes as lowercase hex, zero padded to the kind's length
+    pub hex: String,
+}
+
+/// Split a `<type>:<value>` tag such as `tx:1a2b` off an identifier, when it has one
+pub fn split_tag(value: &str) -> (Option<IdKind>, &str) {
+    value
+        .split_once(':')
+        .and_then(|(tag, rest)| Some((IdKind::from_str(tag, true).ok()?, rest)))
+        .map_or((None, value), |(kind, rest)| (Some(kind), rest))
+}
+
+/// The raw bytes of a base36 identifier, parsed as snap-coin parses that kind
+pub fn decode(kind: IdKind, base36: &str) -> Result<Identifier> {
+    let base36 = base36.trim();
+    let bytes = match kind {
+        IdKind::Address => Public::new_from_base36(base36).map(|key| key.dump_buf().to_vec()),
+        IdKind::Block | IdKind::Tx => Hash::new_from_base36(base36).map(|hash| hash.to_vec()),
+        IdKind::Secret => Private::new_from_base36(base36).map(|key| key.dump_buf().to_vec()),
+        IdKind::Signature => {
+            Signature::new_from_base36(base36).map(|signature| signature.dump_buf().to_vec())
+        }
+    }
+    .ok_or_else(|| {
+        error(
+            ErrorKind::InvalidIdentifier,
+            format!(
+                "{base36} is not a valid base36 {}, expected at most {} bytes",
+                kind.name(),
+                kind.byte_len()
+            ),
+        )
+    })?;
+    encode(kind, &bytes)
+}
+
+/// The base36 identifier of raw bytes, which must be exactly as long as the kind
+pub fn encode(kind: IdKind, bytes: &[u8]) -> Result<Identifier> {
+    let wrong_length = || {
+        error(
+            ErrorKind::InvalidIdentifier,
+            format!(
+                "A {} is {} bytes, got {}",
+                kind.name(),
+                kind.byte_len(),
+                bytes.len()
+            ),
+        )
+    };
+    let base36 = match kind {
+        IdKind::Signature => {
+            Signature::new_from_buf(&bytes.try_into().map_err(|_| wrong_length())?).dump_base36()
+        }
+        _ => {
+            let buf: [u8; 32] = bytes.try_into().map_err(|_| wrong_length())?;
+            match kind {
+                IdKind::Address => Public::new_from_buf(&buf).dump_base36(),
+                IdKind::Secret => Private::new_from_buf(&buf).dump_base36(),
+                _ => Hash::new_from_buf(buf).dump_base36(),
+            }
+        }
+    };
+    Ok(Identifier {
+        kind,
+        base36,
+        hex: to_hex(bytes),
+    })
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Parse hex digits, an optional `0x` prefix aside
+pub fn parse_hex(hex: &str) -> Result<Vec<u8>> {
+    let hex = hex.trim();
+    let digits = hex.strip_prefix("0x").unwrap_or(hex);
+    if !digits.len().is_multiple_of(2) {
+        return Err(anyhow!("Invalid hex {hex}"));
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(digits.get(i..i + 2).unwrap_or_default(), 16)
+                .map_err(|_| anyhow!("Invalid hex {hex}"))
+        })
+        .collect()
+}
+
+/// Identifier argument meaning "read identifiers from stdin, one per line"
+pub const STDIN_ID: &str = "-";
+
+/// The type of an identifier given with `--type` or as a tag, which must agree when both are
+fn id_kind(kind: Option<IdKind>, tag: Option<IdKind>) -> Result<IdKind> {
+    match (kind, tag) {
+        (Some(kind), Some(tag)) if kind != tag => Err(anyhow!(
+            "The value is tagged {} but --type is {}",
+            tag.name(),
+            kind.name()
+        )),
+        (Some(kind), _) | (None, Some(kind)) => Ok(kind),
+        (None, None) => Err(anyhow!(
+            "Give the identifier type with --type or a tag such as tx:<value>"
+        )),
+    }
+}
+
+/// Identifiers read from stdin stream one JSON value per line
+pub fn stdin_lines(id: &str, out: Output) -> Output {
+    if id == STDIN_ID { out.lines() } else { out }
+}
+
+/// Run `show` for the identifier, or for every line of stdin when it is `-`.
+/// Failures on stdin identifiers are reported and skipped so one bad line doesn't end the stream.
+pub async fn for_each_id(id: &str, mut show: impl AsyncFnMut(&str) -> Result<()>) -> Result<()> {
+    if id != STDIN_ID {
+        return show(id).await;
+    }
+
+    let mut failed = 0;
+    for line in io::stdin().lines() {
+        let line = line?;
+        let id = line.trim();
+        if id.is_empty() {
+            continue;
+        }
+        if let Err(e) = show(id).await {
+            warn!("{}: {}", id, e);
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{failed} identifiers from stdin failed"));
+    }
+    Ok(())
+}
+
+/// Encode hex, or raw bytes from stdin, as a base36 identifier
+pub fn run_encode(value: &str, kind: Option<IdKind>, out: &Output) -> Result<()> {
+    let identifier = if value == STDIN_ID {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        encode(id_kind(kind, None)?, &bytes)?
+    } else {
+        let (tag, hex) = split_tag(value);
+        encode(id_kind(kind, tag)?, &parse_hex(hex)?)?
+    };
+    match out.format {
+        OutputFormat::Text => println!("{}", identifier.base36),
+        OutputFormat::Debug => println!("{:#?}", identifier),
+        OutputFormat::Json => out.json(&identifier)?,
+    }
+    Ok(())
+}
+
+/// Decode a base36 identifier to hex, or to raw bytes on stdout
+pub fn run_decode(value: &str, kind: Option<IdKind>, raw: bool, out: &Output) -> Result<()> {
+    let (tag, base36) = split_tag(value);
+    let identifier = decode(id_kind(kind, tag)?, base36)?;
+    if raw {
+        io::stdout().write_all(&parse_hex(&identifier.hex)?)?;
+        return Ok(());
+    }
+    match out.format {
+        OutputFormat::Text => println!("{}", identifier.hex),
+        OutputFormat::Debug => println!("{:#?}", identifier),
+        OutputFormat::Json => out.json(&identifier)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,435 @@
+// keystore.rs
+use anyhow::{Context, Result, anyhow};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    ChaCha20Poly1305, Nonce,
+    aead::{Aead, KeyInit},
+};
+use comfy_table::Table;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use snap_coin::crypto::keys::{Private, Public};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+use crate::{
+    color, config,
+    error::{ErrorKind, error},
+    ids::{parse_hex, to_hex},
+    mnemonic,
+    output::{Output, OutputFormat},
+    password::{self, NEW_PASSWORD_VAR, PASSWORD_VAR},
+    tables::new_table,
+};
+
+/// Current key derivation and cipher: the password is stretched by argon2id into a
+/// ChaCha20-Poly1305 key. Every encryption draws a fresh salt, so no key encrypts twice and the
+/// nonce can stay fixed, like age's scrypt recipients.
+pub const KDF: &str = "argon2id-chacha20poly1305";
+
+/// Largest key derivation costs a key file or paper wallet may ask for, so a crafted one can't
+/// make decrypting exhaust memory or run for hours
+pub const MAX_MEMORY_KIB: u32 = 1 << 20;
+pub const MAX_ITERATIONS: u32 = 64;
+pub const MAX_PARALLELISM: u32 = 16;
+
+/// A secret key as stored on disk, encrypted with a key derived from its password
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EncryptedKey {
+    /// Base36 address of the key, readable without the password
+    pub address: String,
+    /// Key derivation and cipher, `KDF` for keys written by this version
+    pub kdf: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    /// Hex encoded
+    pub salt: String,
+    /// Hex encoded encrypted secret followed by its tag
+    pub secret: String,
+}
+
+impl EncryptedKey {
+    pub fn encrypt(private: &Private, password: &str) -> Result<EncryptedKey> {
+        if password.is_empty() {
+            return Err(anyhow!("The password can't be empty"));
+        }
+        let salt: [u8; 16] = rand::rng().random();
+        let params = Params::default();
+        let derived: [u8; 32] = derive(
+            password,
+            &salt,
+            params.m_cost(),
+            params.t_cost(),
+            params.p_cost(),
+        )?;
+        let encrypted = ChaCha20Poly1305::new(&derived.into())
+            .encrypt(&Nonce::default(), &private.dump_buf()[..])
+            .map_err(|_| anyhow!("Could not encrypt the key"))?;
+        Ok(EncryptedKey {
+            address: private.to_public().dump_base36(),
+            kdf: KDF.to_string(),
+            memory_kib: params.m_cost(),
+            iterations: params.t_cost(),
+            parallelism: params.p_cost(),
+            salt: to_hex(&salt),
+            secret: to_hex(&encrypted),
+        })
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<Private> {
+        self.check_costs()?;
+        let salt = parse_hex(&self.salt)?;
+        let secret = parse_hex(&self.secret)?;
+        if self.kdf != KDF {
+            return Err(anyhow!("Unsupported key derivation {}", self.kdf));
+        }
+        let derived: [u8; 32] = derive(
+            password,
+            &salt,
+            self.memory_kib,
+            self.iterations,
+            self.parallelism,
+        )?;
+        let decrypted = ChaCha20Poly1305::new(&derived.into())
+            .decrypt(&Nonce::default(), &secret[..])
+            .map_err(|_| anyhow!("Wrong password"))?;
+        let secret: [u8; 32] = decrypted
+            .try_into()
+            .map_err(|_| anyhow!("Encrypted secret is not 32 bytes"))?;
+        Ok(Private::new_from_buf(&secret))
+    }
+
+    fn check_costs(&self) -> Result<()> {
+        for (name, value, max) in [
+            ("memory (KiB)", self.memory_kib, MAX_MEMORY_KIB),
+            ("iterations", self.iterations, MAX_ITERATIONS),
+            ("parallelism", self.parallelism, MAX_PARALLELISM),
+        ] {
+            if value > max {
+                return Err(anyhow!(
+                    "Key derivation {} of {} is above the limit of {}",
+                    name,
+                    value,
+                    max
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// One line form printed on paper wallets, `<kdf>$<memory>$<iterations>$<parallelism>$<salt>$<secret>`.
+    /// It leaves out the address, which the secret gives back once decrypted.
+    pub fn to_compact(&self) -> String {
+        format!(
+            "{}${}${}${}${}${}",
+            self.kdf, self.memory_kib, self.iterations, self.parallelism, self.salt, self.secret
+        )
+    }
+
+    pub fn from_compact(compact: &str) -> Option<EncryptedKey> {
+        let fields = compact.trim().split('$').collect::<Vec<_>>();
+        let [kdf, memory_kib, iterations, parallelism, salt, secret] = fields[..] else {
+            return None;
+        };
+        Some(EncryptedKey {
+            address: String::new(),
+            kdf: kdf.to_string(),
+            memory_kib: memory_kib.parse().ok()?,
+            iterations: iterations.parse().ok()?,
+            parallelism: parallelism.parse().ok()?,
+            salt: salt.to_string(),
+            secret: secret.to_string(),
+        })
+    }
+}
+
+fn derive<const N: usize>(
+    password: &str,
+    salt: &[u8],
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+) -> Result<[u8; N]> {
+    let params = Params::new(memory_kib, iterations, parallelism, Some(N))
+        .map_err(|e| anyhow!("Invalid key derivation parameters: {e}"))?;
+    let mut derived = [0; N];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut derived)
+        .map_err(|e| anyhow!("Could not derive the encryption key: {e}"))?;
+    Ok(derived)
+}
+
+/// A stored key as listed by `keys list`
+#[derive(Debug, Serialize)]
+pub struct StoredKey {
+    pub name: String,
+    pub address: String,
+}
+
+/// A key file `keys list` skipped because it couldn't be read
+#[derive(Debug, Serialize)]
+pub struct UnreadableKey {
+    pub name: String,
+    pub error: String,
+}
+
+/// The stored keys by name, and the files among them that couldn't be read
+#[derive(Debug, Default, Serialize)]
+pub struct KeyList {
+    pub keys: Vec<StoredKey>,
+    pub unreadable: Vec<UnreadableKey>,
+}
+
+/// Directory of encrypted keys, one `<name>.toml` file each
+pub struct Keystore {
+    dir: PathBuf,
+}
+
+/// `keys/` next to the config file
+pub fn default_dir() -> Option<PathBuf> {
+    config::default_path().map(|path| path.with_file_name("keys"))
+}
+
+/// The keys directory given on the command line, or the default one
+pub fn resolve_dir(dir: Option<&Path>) -> Result<PathBuf> {
+    dir.map(Path::to_path_buf)
+        .or_else(default_dir)
+        .ok_or_else(|| anyhow!("No config directory for the keys, pass --dir"))
+}
+
+impl Keystore {
+    pub fn new(dir: &Path) -> Keystore {
+        Keystore {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(error(
+                ErrorKind::InvalidIdentifier,
+                format!("Invalid key name {name}, use letters, digits, - and _"),
+            ));
+        }
+        Ok(self.dir.join(format!("{name}.toml")))
+    }
+
+    pub fn load(&self, name: &str) -> Result<EncryptedKey> {
+        let path = self.path(name)?;
+        if !path.exists() {
+            return Err(error(ErrorKind::NotFound, format!("No key named {name}")));
+        }
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Could not read key {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Could not parse key {}", path.display()))
+    }
+
+    fn save(&self, name: &str, key: &EncryptedKey, replace: bool) -> Result<()> {
+        let path = self.path(name)?;
+        if !replace && path.exists() {
+            return Err(anyhow!("A key named {name} already exists"));
+        }
+        fs::create_dir_all(&self.dir)?;
+        // Written next to the key and renamed over it, so a failed write can't lose the old one
+        let temporary = path.with_extension("toml.tmp");
+        fs::write(&temporary, toml::to_string(key)?)
+            .with_context(|| format!("Could not write key {}", temporary.display()))?;
+        restrict_permissions(&temporary)?;
+        fs::rename(&temporary, &path)
+            .with_context(|| format!("Could not write key {}", path.display()))
+    }
+
+    /// Generate a new key, returning its address
+    pub fn create(&self, name: &str, password: &str) -> Result<Public> {
+        self.import(name, &Private::new_random(), password)
+    }
+
+    pub fn import(&self, name: &str, private: &Private, password: &str) -> Result<Public> {
+        self.save(name, &EncryptedKey::encrypt(private, password)?, false)?;
+        Ok(private.to_public())
+    }
+
+    /// Decrypt a key, for exporting it or signing with it
+    pub fn unlock(&self, name: &str, password: &str) -> Result<Private> {
+        let key = self.load(name)?;
+        let private = key
+            .decrypt(password)
+            .with_context(|| format!("Could not unlock key {name}"))?;
+        if private.to_public().dump_base36() != key.address {
+            return Err(anyhow!(
+                "Key {name} doesn't match its address {}",
+                key.address
+            ));
+        }
+        Ok(private)
+    }
+
+    pub fn change_password(&self, name: &str, old: &str, new: &str) -> Result<()> {
+        let private = self.unlock(name, old)?;
+        self.save(name, &EncryptedKey::encrypt(&private, new)?, true)
+    }
+
+    /// Every stored key by name. A broken key file is reported in `unreadable` rather than
+    /// hiding all the others.
+    pub fn list(&self) -> Result<KeyList> {
+        let mut list = KeyList::default();
+        if !self.dir.exists() {
+            return Ok(list);
+        }
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|extension| extension != "toml") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            match self.load(name) {
+                Ok(key) => list.keys.push(StoredKey {
+                    name: name.to_string(),
+                    address: key.address,
+                }),
+                Err(e) => list.unreadable.push(UnreadableKey {
+                    name: name.to_string(),
+                    error: format!("{e:#}"),
+                }),
+            }
+        }
+        list.keys.sort_by(|a, b| a.name.cmp(&b.name));
+        list.unreadable.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(list)
+    }
+}
+
+/// Only the owner may read key files
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+pub fn keys_table(keys: &[StoredKey]) -> Table {
+    let mut table = new_table(&["Name", "Address"]);
+    for key in keys {
+        table.add_row(vec![key.name.clone(), color::hash(&key.address)]);
+    }
+    table
+}
+
+/// Print a key just stored under `name`
+fn print_stored(out: &Output, name: &str, address: Public) -> Result<()> {
+    let key = StoredKey {
+        name: name.to_string(),
+        address: address.dump_base36(),
+    };
+    match out.format {
+        OutputFormat::Text => println!("{}: {}", key.name, color::hash(&key.address)),
+        OutputFormat::Debug => println!("{:#?}", key),
+        OutputFormat::Json => out.json(&key)?,
+    }
+    Ok(())
+}
+
+/// Generate a new key and store it under `name`
+pub fn run_create(keystore: &Keystore, name: &str, out: &Output) -> Result<()> {
+    let password = password::new_password(PASSWORD_VAR, "Password: ")?;
+    print_stored(out, name, keystore.create(name, &password)?)
+}
+
+/// List the stored keys, warning about the files that couldn't be read
+pub fn run_list(keystore: &Keystore, out: &Output) -> Result<()> {
+    let KeyList { keys, unreadable } = keystore.list()?;
+    for key in &unreadable {
+        warn!("Skipped key {}: {}", key.name, key.error);
+    }
+    match out.format {
+        OutputFormat::Text if keys.is_empty() => println!("No keys stored"),
+        OutputFormat::Text => println!("{}", keys_table(&keys)),
+        OutputFormat::Debug => println!("{:#?}", keys),
+        OutputFormat::Json => out.json(&keys)?,
+    }
+    Ok(())
+}
+
+/// Print the secret of a stored key
+pub fn run_export(keystore: &Keystore, name: &str, out: &Output) -> Result<()> {
+    let private = keystore.unlock(name, &password::password(PASSWORD_VAR, "Password: ")?)?;
+    match out.format {
+        OutputFormat::Text | OutputFormat::Debug => println!("{}", private.dump_base36()),
+        OutputFormat::Json => out.json(&json!({
+            "name": name,
+            "address": private.to_public().dump_base36(),
+            "secret": private.dump_base36(),
+        }))?,
+    }
+    Ok(())
+}
+
+/// Store a base36 secret, or an encrypted paper wallet secret, read from `file` or the terminal
+pub fn run_import(
+    keystore: &Keystore,
+    name: &str,
+    file: Option<&Path>,
+    out: &Output,
+) -> Result<()> {
+    let secret = match file {
+        Some(file) => fs::read_to_string(file)?.trim().to_string(),
+        None => password::read_hidden("Secret key (base36): ")?,
+    };
+    let private = match EncryptedKey::from_compact(&secret) {
+        Some(paper) => paper
+            .decrypt(&password::password(
+                PASSWORD_VAR,
+                "Paper wallet password: ",
+            )?)
+            .context("Could not decrypt the paper wallet secret")?,
+        None => Private::new_from_base36(&secret).ok_or_else(|| {
+            error(
+                ErrorKind::InvalidIdentifier,
+                "Secret key is not valid. Expected base36 key",
+            )
+        })?,
+    };
+    let password = password::new_password(PASSWORD_VAR, "Password: ")?;
+    print_stored(out, name, keystore.import(name, &private, &password)?)
+}
+
+/// Store the key of a mnemonic phrase read from `file` or the terminal
+pub fn run_restore(
+    keystore: &Keystore,
+    name: &str,
+    file: Option<&Path>,
+    out: &Output,
+) -> Result<()> {
+    let phrase = match file {
+        Some(file) => fs::read_to_string(file)?,
+        None => password::read_hidden("Mnemonic phrase: ")?,
+    };
+    let private = mnemonic::private_key(&phrase)?;
+    let password = password::new_password(PASSWORD_VAR, "Password: ")?;
+    print_stored(out, name, keystore.import(name, &private, &password)?)
+}
+
+/// Encrypt a stored key again under a new password
+pub fn run_change_password(keystore: &Keystore, name: &str) -> Result<()> {
+    let old = password::password(PASSWORD_VAR, "Current password: ")?;
+    let new = password::new_password(NEW_PASSWORD_VAR, "New password: ")?;
+    keystore.change_password(name, &old, &new)?;
+    info!("Changed the password of {}", name);
+    Ok(())
+}
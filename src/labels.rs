@@ -0,0 +1,319 @@
+// labels.rs
+use anyhow::{Context, Result, anyhow};
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::crypto::keys::Public;
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config,
+    output::{Output, OutputFormat},
+    tables::new_table,
+};
+
+/// What kind of entity an address belongs to, categories other than the known ones are kept as given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Category {
+    Exchange,
+    Pool,
+    /// Provably unspendable, counted by `burns`
+    Burn,
+    Other(String),
+}
+
+impl Category {
+    /// Parse a category case insensitively, `None` for an empty one
+    pub fn parse(value: &str) -> Option<Category> {
+        let value = value.trim().to_lowercase();
+        match value.as_str() {
+            "" => None,
+            "exchange" => Some(Category::Exchange),
+            "pool" => Some(Category::Pool),
+            "burn" => Some(Category::Burn),
+            _ => Some(Category::Other(value)),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Category::Exchange => "exchange",
+            Category::Pool => "pool",
+            Category::Burn => "burn",
+            Category::Other(name) => name,
+        }
+    }
+}
+
+impl Serialize for Category {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BookEntry {
+    /// Base36 address
+    pub address: String,
+    pub label: String,
+    pub category: Option<Category>,
+}
+
+/// Imported address labels, a CSV file of `address,label,category` rows
+#[derive(Debug, Default)]
+pub struct LabelBook {
+    /// By address
+    entries: BTreeMap<String, BookEntry>,
+}
+
+/// Labels imported by an `alias import`
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ImportSummary {
+    pub added: usize,
+    /// Addresses that had a label already and got the imported one
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+/// `labels.csv` next to the config file at `config`, or the default config location
+pub fn path_for(config: Option<&Path>) -> Option<PathBuf> {
+    config
+        .map(Path::to_path_buf)
+        .or_else(config::default_path)
+        .map(|path| path.with_file_name("labels.csv"))
+}
+
+/// The labels file given on the command line, or the one next to the config file
+pub fn resolve_path(file: Option<&Path>, config: Option<&Path>) -> Result<PathBuf> {
+    file.map(Path::to_path_buf)
+        .or_else(|| path_for(config))
+        .ok_or_else(|| anyhow!("No config directory for the labels file, pass --file"))
+}
+
+/// Split a CSV line into fields, fields in double quotes may hold commas and `""` for a quote
+fn csv_fields(line: &str) -> Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (c, _) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(anyhow!("Unterminated quoted field"));
+    }
+    fields.push(field);
+    Ok(fields.into_iter().map(|f| f.trim().to_string()).collect())
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl LabelBook {
+    /// Read a book, a missing file is an empty book
+    pub fn load(path: &Path) -> Result<LabelBook> {
+        if !path.exists() {
+            return Ok(LabelBook::default());
+        }
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Could not read labels {}", path.display()))?;
+        let mut book = LabelBook::default();
+        for entry in parse_labels(&contents)
+            .with_context(|| format!("Could not parse labels {}", path.display()))?
+        {
+            book.insert(entry);
+        }
+        Ok(book)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create {}", dir.display()))?;
+        }
+        let mut contents = String::from("address,label,category\n");
+        for entry in self.entries.values() {
+            contents.push_str(&format!(
+                "{},{},{}\n",
+                entry.address,
+                csv_field(&entry.label),
+                entry
+                    .category
+                    .as_ref()
+                    .map(|category| csv_field(category.name()))
+                    .unwrap_or_default()
+            ));
+        }
+        fs::write(path, contents).with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    fn insert(&mut self, entry: BookEntry) -> Option<BookEntry> {
+        self.entries.insert(entry.address.clone(), entry)
+    }
+
+    /// Merge imported entries, an imported label replaces the one an address had
+    pub fn import(&mut self, entries: Vec<BookEntry>) -> ImportSummary {
+        let mut summary = ImportSummary::default();
+        for entry in entries {
+            match self.entries.get(&entry.address) {
+                Some(existing) if *existing == entry => summary.unchanged += 1,
+                Some(_) => summary.updated += 1,
+                None => summary.added += 1,
+            }
+            self.insert(entry);
+        }
+        summary
+    }
+
+    pub fn get(&self, address: &str) -> Option<&BookEntry> {
+        self.entries.get(address)
+    }
+
+    /// The address of a label, labels are matched exactly
+    pub fn address_of(&self, label: &str) -> Option<&str> {
+        self.entries
+            .values()
+            .find(|entry| entry.label == label)
+            .map(|entry| entry.address.as_str())
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &BookEntry> {
+        self.entries.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Addresses in the `burn` category
+    pub fn burn_addresses(&self) -> Vec<Public> {
+        self.entries
+            .values()
+            .filter(|entry| entry.category == Some(Category::Burn))
+            .filter_map(|entry| Public::new_from_base36(&entry.address))
+            .collect()
+    }
+}
+
+/// Parse `address,label,category` rows, the category may be left out. Header rows starting with
+/// `address`, blank lines and `#` comments are skipped.
+pub fn parse_labels(contents: &str) -> Result<Vec<BookEntry>> {
+    let mut entries = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = csv_fields(line).with_context(|| format!("Line {}", number + 1))?;
+        if fields[0].eq_ignore_ascii_case("address") {
+            continue;
+        }
+        let (address, label, category) = match fields.as_slice() {
+            [address, label] => (address, label, None),
+            [address, label, category] => (address, label, Category::parse(category)),
+            _ => {
+                return Err(anyhow!(
+                    "Line {} has {} fields, expected address,label,category",
+                    number + 1,
+                    fields.len()
+                ));
+            }
+        };
+        if Public::new_from_base36(address).is_none() {
+            return Err(anyhow!(
+                "Line {}: {} is not a valid base36 address",
+                number + 1,
+                address
+            ));
+        }
+        if label.is_empty() {
+            return Err(anyhow!("Line {}: the label is empty", number + 1));
+        }
+        entries.push(BookEntry {
+            address: address.clone(),
+            label: label.clone(),
+            category,
+        });
+    }
+    Ok(entries)
+}
+
+pub fn book_table(book: &LabelBook) -> Table {
+    let mut table = new_table(&["Label", "Category", "Address"]);
+    for entry in book.entries() {
+        table.add_row(vec![
+            entry.label.clone(),
+            entry
+                .category
+                .as_ref()
+                .map(|category| category.name().to_string())
+                .unwrap_or_default(),
+            entry.address.clone(),
+        ]);
+    }
+    table
+}
+
+/// Merge the labels of a CSV file into the labels file at `path`
+pub fn run_import(path: &Path, csv: &Path, out: &Output) -> Result<()> {
+    let mut book = LabelBook::load(path)?;
+    let contents =
+        fs::read_to_string(csv).with_context(|| format!("Could not read {}", csv.display()))?;
+    let entries =
+        parse_labels(&contents).with_context(|| format!("Could not parse {}", csv.display()))?;
+    let summary = book.import(entries);
+    book.save(path)?;
+    match out.format {
+        OutputFormat::Text => println!(
+            "{} labels added, {} updated and {} unchanged in {}",
+            summary.added,
+            summary.updated,
+            summary.unchanged,
+            path.display()
+        ),
+        OutputFormat::Debug => println!("{:#?}", summary),
+        OutputFormat::Json => out.json(&summary)?,
+    }
+    Ok(())
+}
+
+/// Print the labels file at `path`
+pub fn run_list(path: &Path, out: &Output) -> Result<()> {
+    let book = LabelBook::load(path)?;
+    match out.format {
+        OutputFormat::Text => println!("{}", book_table(&book)),
+        OutputFormat::Debug => println!("{:#?}", book),
+        OutputFormat::Json => out.json(&book.entries().collect::<Vec<_>>())?,
+    }
+    Ok(())
+}
@@ -0,0 +1,259 @@
+//! Snap Coin chain statistics, lookups and output formatting used by the `snap-coin-utils` CLI,
+//! usable from other Rust projects without shelling out to the binary.
+use anyhow::anyhow;
+use num_bigint::BigUint;
+use num_traits::cast::ToPrimitive;
+use snap_coin::economics::NANO_TO_SNAP;
+use std::{ops::Range, time::Duration};
+
+pub mod accept;
+pub mod addresses;
+pub mod anomalies;
+pub mod audit;
+pub mod averages;
+pub mod batch;
+pub mod bench;
+pub mod blocks;
+pub mod burns;
+pub mod chart;
+pub mod clock;
+pub mod color;
+pub mod compare;
+pub mod config;
+pub mod conformance;
+pub mod connection;
+pub mod convert;
+pub mod counts;
+pub mod diff;
+pub mod discover;
+pub mod error;
+pub mod eta;
+pub mod explorer;
+pub mod export;
+pub mod fees;
+pub mod finality;
+pub mod fixture;
+pub mod grafana;
+pub mod growth;
+pub mod hashing;
+pub mod hashrate;
+pub mod headers;
+pub mod ids;
+pub mod keystore;
+pub mod labels;
+pub mod limits;
+pub mod load;
+pub mod luck;
+pub mod mempool;
+pub mod miners;
+pub mod mnemonic;
+pub mod monitor;
+pub mod node;
+pub mod notify;
+pub mod orphans;
+pub mod output;
+pub mod paper;
+pub mod password;
+pub mod patterns;
+pub mod payouts;
+pub mod pool;
+pub mod portfolio;
+pub mod price;
+pub mod probe;
+pub mod qr;
+pub mod query;
+pub mod rewards;
+pub mod richlist;
+pub mod screen;
+pub mod send;
+pub mod service;
+pub mod snapshot;
+pub mod source;
+pub mod stress;
+pub mod summary;
+pub mod systemd;
+pub mod tables;
+pub mod tail;
+pub mod tips;
+pub mod tps;
+pub mod utxos;
+pub mod wallet;
+
+pub fn normalize_difficulty(target: &[u8; 32]) -> f64 {
+    let target = BigUint::from_bytes_be(target);
+    let max_target = BigUint::from_bytes_be(&[255u8; 32]);
+
+    let max_f = max_target.to_f64().unwrap(); // ~1e77
+    let target_f = target.to_f64().unwrap();
+
+    max_f / target_f
+}
+
+pub fn format_biguint_hr(value: &[u8; 32]) -> String {
+    // Use normalize_difficulty to get f64
+    format_hr(normalize_difficulty(value))
+}
+
+/// Format a number with a K, M, G, T or P unit suffix
+pub fn format_hr(value: f64) -> String {
+    let units = ["", "K", "M", "G", "T", "P"];
+    let thousand = 1000.0;
+    let mut value_f = value;
+
+    // Format with units
+    let mut unit_index = 0;
+    while value_f >= thousand && unit_index < units.len() - 1 {
+        value_f /= thousand;
+        unit_index += 1;
+    }
+
+    // Keep 2 decimal places if not an integer
+    if value_f.fract() == 0.0 {
+        format!("{}{}", value_f as u64, units[unit_index])
+    } else {
+        format!("{:.2}{}", value_f, units[unit_index])
+    }
+}
+
+/// Format a nano amount as SNAP without going through a lossy f64
+pub fn format_snap(nano: u64) -> String {
+    let unit = NANO_TO_SNAP as u64;
+    format!("{}.{:08}", nano / unit, nano % unit)
+}
+
+/// Format a unix timestamp as a local date and time with its UTC offset, e.g. `2025-10-09 14:11:17 +02:00`
+pub fn format_timestamp(unix: u64) -> String {
+    let offset = local_offset(unix);
+    let local = unix as i64 + offset;
+    let (days, secs) = (local.div_euclid(86_400), local.rem_euclid(86_400));
+
+    // Civil date from days since the epoch, http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    let sign = if offset < 0 { '-' } else { '+' };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} {}{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        sign,
+        offset.abs() / 3600,
+        offset.abs() % 3600 / 60
+    )
+}
+
+/// Seconds the local time zone is ahead of UTC at `unix`
+#[cfg(unix)]
+fn local_offset(unix: u64) -> i64 {
+    let time = unix as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: both pointers are valid for the call, localtime_r doesn't keep them
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return 0;
+    }
+    tm.tm_gmtoff
+}
+
+/// Without the C library's time zone database times are shown in UTC
+#[cfg(not(unix))]
+fn local_offset(_unix: u64) -> i64 {
+    0
+}
+
+/// Parse a duration such as `500ms`, `90s`, `15m`, `1h` or `2d`, a bare number is seconds
+pub fn parse_duration(value: &str) -> anyhow::Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| anyhow!("Invalid duration {value}, expected e.g. 30s, 5m or 1h"))?;
+    let seconds = match unit {
+        "ms" => return Ok(Duration::from_millis(number)),
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => {
+            return Err(anyhow!(
+                "Unknown duration unit {unit}, expected ms, s, m, h or d"
+            ));
+        }
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| anyhow!("Duration {value} is too long"))
+}
+
+/// `parse_duration` for the period of something repeating, which can't be 0
+pub fn parse_nonzero_duration(value: &str) -> anyhow::Result<Duration> {
+    let duration = parse_duration(value)?;
+    if duration.is_zero() {
+        return Err(anyhow!("Duration {value} has to be longer than 0"));
+    }
+    Ok(duration)
+}
+
+/// Parse a block range such as `1000..2000`, the end height is exclusive
+pub fn parse_block_range(value: &str) -> anyhow::Result<Range<usize>> {
+    let (start, end) = value
+        .split_once("..")
+        .ok_or_else(|| anyhow!("Invalid block range {value}, expected e.g. 1000..2000"))?;
+    let parse = |height: &str| {
+        height
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| anyhow!("Invalid height {height} in block range {value}"))
+    };
+    let range = parse(start)?..parse(end)?;
+    if range.len() < 2 {
+        return Err(anyhow!("Block range {value} must span at least 2 blocks"));
+    }
+    Ok(range)
+}
+
+/// Parse a SNAP amount such as `12.5` into nano without going through a lossy f64
+pub fn parse_snap(value: &str) -> anyhow::Result<u64> {
+    let value = value.trim();
+    let invalid = || anyhow!("Invalid amount {value}, expected SNAP with up to 8 decimals");
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || fraction.len() > 8
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    let unit = NANO_TO_SNAP as u64;
+    let whole: u64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| invalid())?
+    };
+    let fraction: u64 = format!("{fraction:0<8}").parse().map_err(|_| invalid())?;
+    whole
+        .checked_mul(unit)
+        .and_then(|nano| nano.checked_add(fraction))
+        .ok_or_else(invalid)
+}
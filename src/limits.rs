@@ -0,0 +1,97 @@
+// limits.rs
+use std::{
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// Requests pipelined on one connection when `--max-in-flight` isn't given
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 64;
+
+static MAX_IN_FLIGHT: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_IN_FLIGHT);
+
+/// Bound the number of requests sent to the node without having their response yet, set once from `--max-in-flight`
+pub fn set_max_in_flight(limit: NonZeroUsize) {
+    MAX_IN_FLIGHT.store(limit.get(), Ordering::Relaxed);
+}
+
+pub fn max_in_flight() -> usize {
+    MAX_IN_FLIGHT.load(Ordering::Relaxed)
+}
+
+/// Requests per second sent to a node when `--rate-limit` isn't given
+pub const DEFAULT_RATE_LIMIT: f64 = 100.0;
+
+/// Refills at `rate` tokens per second up to one second worth of burst
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+static RATE_LIMIT: Mutex<Option<TokenBucket>> = Mutex::new(None);
+
+/// Limit requests to `per_second` across the process, 0 removes the limit. Set once from `--rate-limit`.
+pub fn set_rate_limit(per_second: f64) {
+    let bucket = (per_second > 0.0).then(|| TokenBucket {
+        rate: per_second,
+        tokens: per_second.max(1.0),
+        refilled: Instant::now(),
+    });
+    *RATE_LIMIT.lock().unwrap() = bucket;
+}
+
+/// Requests per second allowed by `set_rate_limit`, `None` when unlimited
+pub fn rate_limit() -> Option<f64> {
+    RATE_LIMIT
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|bucket| bucket.rate)
+}
+
+/// Wait until `requests` more requests fit in the rate limit
+pub async fn acquire(requests: u32) {
+    let wait = {
+        let mut limit = RATE_LIMIT.lock().unwrap();
+        let Some(bucket) = limit.as_mut() else {
+            return;
+        };
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * bucket.rate).min(bucket.rate.max(1.0));
+        bucket.refilled = now;
+        // Take the tokens right away, going into debt makes later callers queue up behind us
+        bucket.tokens -= requests as f64;
+        (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / bucket.rate))
+    };
+    if let Some(wait) = wait {
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// Where and when address frequency maps move to disk, from `--spill-dir` and `--spill-threshold`
+#[derive(Debug, Clone)]
+pub struct SpillConfig {
+    pub dir: PathBuf,
+    /// Distinct addresses kept in memory before they are written out
+    pub threshold: usize,
+}
+
+/// Distinct addresses kept in memory when `--spill-threshold` isn't given
+pub const DEFAULT_SPILL_THRESHOLD: usize = 1_000_000;
+
+static SPILL: Mutex<Option<SpillConfig>> = Mutex::new(None);
+
+/// Spill address counts of long scans to disk, `None` keeps them all in memory
+pub fn set_spill(config: Option<SpillConfig>) {
+    *SPILL.lock().unwrap() = config;
+}
+
+pub fn spill_config() -> Option<SpillConfig> {
+    SPILL.lock().unwrap().clone()
+}
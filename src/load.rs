@@ -0,0 +1,222 @@
+// load.rs
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use snap_coin::{
+    api::requests::RequestResponseError,
+    blockchain_data_provider::{BlockchainDataProvider, BlockchainDataProviderError},
+    core::transaction::TransactionId,
+    crypto::keys::Public,
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tokio::task::JoinSet;
+use tracing::warn;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    limits,
+    node::NodeClient,
+};
+
+/// Kind of read request sent when putting load on a node
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Query {
+    /// A block by height
+    Blocks,
+    /// A transaction by id
+    Txs,
+    /// The balance of an address
+    Addrs,
+}
+
+/// Real heights, transaction ids and addresses near the tip for load requests to ask for
+#[derive(Clone, Default)]
+pub struct Sample {
+    pub heights: Vec<usize>,
+    pub transactions: Vec<TransactionId>,
+    /// Receivers of the sampled transactions, each once
+    pub addresses: Vec<Public>,
+}
+
+impl Sample {
+    /// Collect the last `blocks` blocks and the transactions in them
+    pub async fn take<P: BlockRangeProvider + ?Sized>(
+        provider: &P,
+        blocks: usize,
+    ) -> Result<Sample> {
+        let height = provider.get_height().await?;
+        let mut sample = Sample::default();
+        let mut seen = HashSet::new();
+        for_each_block(
+            provider,
+            height.saturating_sub(blocks)..height,
+            |height, block| {
+                sample.heights.push(height);
+                for tx in &block.transactions {
+                    sample.transactions.extend(tx.transaction_id);
+                    for output in &tx.outputs {
+                        if seen.insert(*output.receiver.dump_buf()) {
+                            sample.addresses.push(output.receiver);
+                        }
+                    }
+                }
+                Ok(())
+            },
+        )
+        .await?;
+        Ok(sample)
+    }
+
+    /// Whether the sample has anything to ask for with `query`
+    pub fn covers(&self, query: Query) -> bool {
+        match query {
+            Query::Blocks => !self.heights.is_empty(),
+            Query::Txs => !self.transactions.is_empty(),
+            Query::Addrs => !self.addresses.is_empty(),
+        }
+    }
+}
+
+/// Latency of every successful request of one kind, and the number that failed
+#[derive(Debug, Clone, Default)]
+pub struct QueryStats {
+    pub latencies: Vec<Duration>,
+    pub errors: usize,
+}
+
+impl QueryStats {
+    pub fn requests(&self) -> usize {
+        self.latencies.len() + self.errors
+    }
+
+    pub fn average_latency(&self) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+        self.latencies.iter().sum::<Duration>() / self.latencies.len() as u32
+    }
+}
+
+/// Outcome of a `run_load`, `elapsed` runs until the last request in flight at the deadline finished
+#[derive(Debug, Clone, Default)]
+pub struct Load {
+    pub elapsed: Duration,
+    pub queries: BTreeMap<Query, QueryStats>,
+}
+
+impl Load {
+    /// Successful requests of `query` per second
+    pub fn per_second(&self, query: Query) -> f64 {
+        let done = self
+            .queries
+            .get(&query)
+            .map_or(0, |stats| stats.latencies.len());
+        done as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Load generating commands measure the tool instead of the node while the rate limit is on
+pub fn warn_rate_limited() {
+    if let Some(rate_limit) = limits::rate_limit() {
+        warn!(
+            "Requests are capped at {} per second by --rate-limit, pass --rate-limit 0 to measure the node",
+            rate_limit
+        );
+    }
+}
+
+/// Send requests cycling through `queries` for `duration`, from `concurrency` workers with a
+/// connection each since a connection answers one request at a time
+pub async fn run_load(
+    node: SocketAddr,
+    sample: Arc<Sample>,
+    queries: &[Query],
+    concurrency: usize,
+    duration: Duration,
+) -> Result<Load> {
+    let queries: Arc<[Query]> = queries
+        .iter()
+        .copied()
+        .filter(|query| sample.covers(*query))
+        .collect();
+    if queries.is_empty() {
+        return Ok(Load::default());
+    }
+
+    let mut clients = Vec::with_capacity(concurrency);
+    for _ in 0..concurrency {
+        clients.push(NodeClient::connect(node).await?);
+    }
+    let next = Arc::new(AtomicUsize::new(0));
+    let start = Instant::now();
+    let deadline = start + duration;
+    let mut workers = JoinSet::new();
+    for client in clients {
+        let (sample, queries, next) = (sample.clone(), queries.clone(), next.clone());
+        workers.spawn(async move {
+            let mut stats: BTreeMap<Query, QueryStats> = BTreeMap::new();
+            while Instant::now() < deadline {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let query = queries[i % queries.len()];
+                let item = i / queries.len();
+                let sent = Instant::now();
+                let found = match query {
+                    Query::Blocks => {
+                        let height = sample.heights[item % sample.heights.len()];
+                        client
+                            .get_block_by_height(height)
+                            .await
+                            .map(|b| b.is_some())
+                    }
+                    Query::Txs => {
+                        let id = &sample.transactions[item % sample.transactions.len()];
+                        client.get_transaction(id).await.map(|tx| tx.is_some())
+                    }
+                    Query::Addrs => {
+                        let address = sample.addresses[item % sample.addresses.len()];
+                        client.get_balance(address).await.map(|_| true)
+                    }
+                };
+                let stats = stats.entry(query).or_default();
+                match found {
+                    Ok(true) => stats.latencies.push(sent.elapsed()),
+                    // Everything asked for was seen on the chain, missing data is an error too
+                    Ok(false) => stats.errors += 1,
+                    Err(e) => {
+                        stats.errors += 1;
+                        // A worker whose connection dropped stops instead of failing in a busy loop
+                        if matches!(
+                            e,
+                            BlockchainDataProviderError::RequestResponseError(
+                                RequestResponseError::Stream
+                            )
+                        ) {
+                            break;
+                        }
+                    }
+                }
+            }
+            stats
+        });
+    }
+
+    let mut load = Load::default();
+    while let Some(stats) = workers.join_next().await {
+        for (query, stats) in stats? {
+            let total = load.queries.entry(query).or_default();
+            total.latencies.extend(stats.latencies);
+            total.errors += stats.errors;
+        }
+    }
+    load.elapsed = start.elapsed();
+    Ok(load)
+}
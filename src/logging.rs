@@ -0,0 +1,44 @@
+// logging.rs
+use snap_coin_utils::service::PlatformLogWriter;
+use std::{
+    env,
+    io::{IsTerminal, stderr},
+};
+use tracing_subscriber::EnvFilter;
+
+/// Send diagnostics to stderr. Warnings are always shown, `-v` adds info, `-vv` debug (every RPC call) and `-vvv` trace.
+/// `RUST_LOG` takes precedence over the verbosity flags when set. A detached daemon logs to the
+/// platform log instead, without colors or timestamps the log adds itself.
+pub fn init(verbose: u8, json: bool, platform: bool) {
+    let level = match verbose {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level));
+    if platform {
+        tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(PlatformLogWriter)
+            .with_ansi(false)
+            .with_target(false)
+            .without_time()
+            .init();
+        return;
+    }
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(stderr)
+        .with_ansi(stderr().is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()))
+        .with_target(false);
+
+    if json {
+        builder.json().init();
+    } else if verbose == 0 {
+        // Plain warnings, timestamps only help when following a verbose run
+        builder.without_time().init();
+    } else {
+        builder.init();
+    }
+}
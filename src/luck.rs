@@ -0,0 +1,225 @@
+// luck.rs
+use anyhow::Result;
+use serde::Serialize;
+use snap_coin::economics::TARGET_TIME;
+
+use crate::{
+    blocks::BlockRangeProvider,
+    color, format_hr, miners, normalize_difficulty,
+    output::{Output, OutputFormat},
+    query::parse_address,
+};
+
+/// Where the miner's expected share of the blocks comes from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "source")]
+pub enum ShareEstimate {
+    /// A hashrate against the network hashrate of the current block difficulty
+    Hashrate {
+        hashrate: f64,
+        network_hashrate: f64,
+    },
+    /// The share of blocks mined in the blocks before the window
+    Baseline { blocks: usize, mined: usize },
+}
+
+impl ShareEstimate {
+    pub fn share(&self) -> f64 {
+        match *self {
+            ShareEstimate::Hashrate {
+                hashrate,
+                network_hashrate,
+            } => (hashrate / network_hashrate).min(1.0),
+            ShareEstimate::Baseline { blocks, mined } => mined as f64 / blocks.max(1) as f64,
+        }
+    }
+}
+
+/// How a miner's blocks over a window compare with what their share predicts
+#[derive(Debug, Serialize)]
+pub struct Luck {
+    pub miner: String,
+    pub start_height: usize,
+    pub end_height: usize,
+    pub estimate: ShareEstimate,
+    pub share: f64,
+    pub mined: usize,
+    pub expected: f64,
+    /// Mined over expected blocks as a percentage, `None` when none were expected
+    pub luck: Option<f64>,
+    /// Standard deviation of the blocks mined by chance alone
+    pub std_dev: f64,
+    /// Chance of mining this many blocks or fewer
+    pub probability_at_most: f64,
+    /// Chance of mining this many blocks or more
+    pub probability_at_least: f64,
+    /// Most blocks in a row mined by the miner
+    pub longest_streak: usize,
+    /// Most blocks in a row mined by others
+    pub longest_drought: usize,
+    /// Blocks by others since the miner's last block
+    pub current_drought: usize,
+    /// Mean blocks by others between two of the miner's blocks at its share
+    pub expected_drought: Option<f64>,
+}
+
+/// Compare the blocks `miner` mined in a window starting at `start_height`, whose miners are `miners`,
+/// with the binomial distribution of blocks won at the share of `estimate`
+pub fn luck(
+    miner: &[u8; 32],
+    address: &str,
+    start_height: usize,
+    miners: &[Option<[u8; 32]>],
+    estimate: ShareEstimate,
+) -> Luck {
+    let share = estimate.share();
+    let won: Vec<bool> = miners.iter().map(|m| m.as_ref() == Some(miner)).collect();
+    let n = won.len();
+    let mined = won.iter().filter(|won| **won).count();
+    let expected = n as f64 * share;
+
+    // Lengths of the runs of blocks won and lost
+    let (mut longest_streak, mut longest_drought) = (0, 0);
+    for run in won.chunk_by(|a, b| a == b) {
+        let longest = if run[0] {
+            &mut longest_streak
+        } else {
+            &mut longest_drought
+        };
+        *longest = (*longest).max(run.len());
+    }
+
+    let at_most = binomial_cdf(n, share, mined);
+    Luck {
+        miner: address.to_string(),
+        start_height,
+        end_height: start_height + n,
+        estimate,
+        share,
+        mined,
+        expected,
+        luck: (expected > 0.0).then(|| mined as f64 / expected * 100.0),
+        std_dev: (expected * (1.0 - share)).sqrt(),
+        probability_at_most: at_most,
+        probability_at_least: (1.0 - at_most + binomial_pmf(n, share, mined)).min(1.0),
+        longest_streak,
+        longest_drought,
+        current_drought: won.iter().rev().take_while(|won| !**won).count(),
+        expected_drought: (share > 0.0).then(|| 1.0 / share - 1.0),
+    }
+}
+
+/// Chance of exactly `k` wins in `n` tries at `p` each
+pub fn binomial_pmf(n: usize, p: f64, k: usize) -> f64 {
+    binomial_terms(n, p).nth(k).unwrap_or(0.0)
+}
+
+/// Chance of `k` wins or fewer in `n` tries at `p` each
+pub fn binomial_cdf(n: usize, p: f64, k: usize) -> f64 {
+    binomial_terms(n, p).take(k + 1).sum::<f64>().min(1.0)
+}
+
+/// Chances of 0 to `n` wins, computed from logarithms so long windows don't underflow
+fn binomial_terms(n: usize, p: f64) -> impl Iterator<Item = f64> {
+    let (ln_p, ln_q) = (p.ln(), (1.0 - p).ln());
+    // ln of n choose i, updated from one term to the next
+    let mut ln_choose = 0.0;
+    (0..=n).map(move |i| {
+        if i > 0 {
+            ln_choose += ((n - i + 1) as f64).ln() - (i as f64).ln();
+        }
+        // 0 * ln(0) is 0 here, so certain wins and losses come out as 1
+        let wins = if i == 0 { 0.0 } else { i as f64 * ln_p };
+        let losses = if i == n { 0.0 } else { (n - i) as f64 * ln_q };
+        (ln_choose + wins + losses).exp()
+    })
+}
+
+pub fn print_luck(luck: &Luck) {
+    println!(
+        "Miner {} over blocks {}..{}",
+        color::hash(&luck.miner),
+        luck.start_height,
+        luck.end_height
+    );
+    match luck.estimate {
+        ShareEstimate::Hashrate {
+            hashrate,
+            network_hashrate,
+        } => println!(
+            "Share: {:.2}% ({}H/s against {}H/s of the network)",
+            luck.share * 100.0,
+            format_hr(hashrate),
+            format_hr(network_hashrate)
+        ),
+        ShareEstimate::Baseline { blocks, mined } => println!(
+            "Share: {:.2}% ({} of the {} blocks before)",
+            luck.share * 100.0,
+            mined,
+            blocks
+        ),
+    }
+    println!(
+        "Mined {} blocks, {:.2} ± {:.2} expected",
+        luck.mined, luck.expected, luck.std_dev
+    );
+    match luck.luck {
+        Some(percent) => println!("Luck: {:.1}%", percent),
+        None => println!("Luck: none expected"),
+    }
+    println!(
+        "Chance of {} blocks or fewer: {:.2}%, or more: {:.2}%",
+        luck.mined,
+        luck.probability_at_most * 100.0,
+        luck.probability_at_least * 100.0
+    );
+    println!(
+        "Longest streak: {} blocks, longest drought: {} blocks, current drought: {} blocks",
+        luck.longest_streak, luck.longest_drought, luck.current_drought
+    );
+    if let Some(drought) = luck.expected_drought {
+        println!("Expected drought: {:.1} blocks", drought);
+    }
+}
+
+/// Print the luck of `address` over the past `blocks` blocks, against its `hashrate` or its share
+/// of the `baseline` blocks before them, four times `blocks` by default
+pub async fn run_luck<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    address: &str,
+    blocks: usize,
+    hashrate: Option<f64>,
+    baseline: Option<usize>,
+    out: &Output,
+) -> Result<()> {
+    let public = parse_address(address)?;
+    let height = provider.get_height().await?;
+    let start = height.saturating_sub(blocks);
+    let (estimate, first) = match hashrate {
+        Some(hashrate) => {
+            let difficulty = provider.get_block_difficulty().await?;
+            let estimate = ShareEstimate::Hashrate {
+                hashrate,
+                network_hashrate: normalize_difficulty(&difficulty) / TARGET_TIME as f64,
+            };
+            (Some(estimate), start)
+        }
+        None => (None, start.saturating_sub(baseline.unwrap_or(4 * blocks))),
+    };
+    let block_miners = miners::block_miners(provider, first..height).await?;
+    let (before, window) = block_miners.split_at(start - first);
+    let estimate = estimate.unwrap_or(ShareEstimate::Baseline {
+        blocks: before.len(),
+        mined: before
+            .iter()
+            .filter(|m| m.as_ref() == Some(public.dump_buf()))
+            .count(),
+    });
+    let luck = luck(public.dump_buf(), address, start, window, estimate);
+    match out.format {
+        OutputFormat::Text => print_luck(&luck),
+        OutputFormat::Debug => println!("{:#?}", luck),
+        OutputFormat::Json => out.json(&luck)?,
+    }
+    Ok(())
+}
@@ -1,49 +1,56 @@
 use anyhow::anyhow;
-use clap::{Parser, Subcommand};
-use num_bigint::BigUint;
-use num_traits::cast::ToPrimitive;
-use snap_coin::{
-    api::client::Client,
-    blockchain_data_provider::BlockchainDataProvider,
-    core::transaction::TransactionId,
-    crypto::{Hash, keys::Public},
-    to_snap,
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use snap_coin::economics::TARGET_TIME;
+use std::{
+    ffi::OsString,
+    net::{IpAddr, SocketAddr},
+    num::NonZeroUsize,
+    ops::Range,
+    path::PathBuf,
+    process::ExitCode,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use tokio::net::lookup_host;
+use tracing::{error, warn};
 
-mod averages;
-
-pub fn normalize_difficulty(target: &[u8; 32]) -> f64 {
-    let target = BigUint::from_bytes_be(target);
-    let max_target = BigUint::from_bytes_be(&[255u8; 32]);
-
-    let max_f = max_target.to_f64().unwrap(); // ~1e77
-    let target_f = target.to_f64().unwrap();
-
-    max_f / target_f
-}
-
-pub fn format_biguint_hr(value: &[u8; 32]) -> String {
-    let units = ["", "K", "M", "G", "T", "P"];
-    let thousand = 1000.0;
-
-    // Use normalize_difficulty to get f64
-    let mut value_f = normalize_difficulty(value);
-
-    // Format with units
-    let mut unit_index = 0;
-    while value_f >= thousand && unit_index < units.len() - 1 {
-        value_f /= thousand;
-        unit_index += 1;
-    }
+use snap_coin_utils::{
+    accept, addresses, anomalies, audit,
+    averages::{self, ChartOptions, PlotOptions},
+    batch, bench, burns,
+    chart::PlotFiles,
+    clock,
+    color::{self, ColorChoice},
+    compare, config, conformance, convert, diff, discover,
+    error::{self, ErrorKind, error},
+    eta,
+    explorer::Link,
+    export, fees, finality,
+    fixture::{self, FixtureProvider},
+    grafana, growth,
+    hashing::{self, HashInput},
+    hashrate,
+    ids::{self, IdKind},
+    keystore::{self, Keystore},
+    labels, limits, load, luck, mempool, miners, mnemonic,
+    monitor::{self, MonitorOptions},
+    node::NodeClient,
+    orphans,
+    output::{Output, OutputFormat},
+    paper, parse_block_range, parse_duration, parse_nonzero_duration, parse_snap, payouts,
+    pool::NodePool,
+    portfolio, price, probe,
+    query::{self, AddressSections, AddressView, COINBASE_MATURITY, UtxoSort},
+    rewards, richlist, screen, send, service, snapshot,
+    source::Source,
+    stress, tail, tips, tps, utxos, wallet,
+};
 
-    // Keep 2 decimal places if not an integer
-    if value_f.fract() == 0.0 {
-        format!("{}{}", value_f as u64, units[unit_index])
-    } else {
-        format!("{:.2}{}", value_f, units[unit_index])
-    }
-}
+mod completions;
+mod logging;
+mod out_file;
+mod pager;
+mod plugin;
+mod script;
 
 #[derive(Parser)]
 #[command(
@@ -52,8 +59,74 @@ pub fn format_biguint_hr(value: &[u8; 32]) -> String {
     about = "Read snap coin blockchain and node data from the command line"
 )]
 struct Cli {
-    /// Node address to connect too
-    node: String,
+    /// Node address to connect too, an alias from the `[nodes]` config section, a DNS seed or a
+    /// comma separated list of them. Later nodes are fallbacks when the first can't be reached.
+    node: Option<String>,
+
+    /// Output format
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+
+    /// Print only the parts of the JSON output matching a jq style path (e.g. `.transactions[].outputs[].amount`)
+    #[arg(long, global = true)]
+    select: Option<String>,
+
+    /// Render the JSON output through a minijinja template (file path or inline template string)
+    #[arg(long, global = true)]
+    template: Option<String>,
+
+    /// When to color output
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Write output to a file instead of stdout (.gz and .zst are compressed)
+    #[arg(long, global = true)]
+    out: Option<PathBuf>,
+
+    /// Never send long output through $PAGER
+    #[arg(long, global = true)]
+    no_pager: bool,
+
+    /// Log more diagnostics to stderr (-v info, -vv every RPC call, -vvv trace)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log diagnostics as JSON lines
+    #[arg(long, global = true)]
+    log_json: bool,
+
+    /// Probe every node given, or of the `[nodes]` config section without one, and use the highest, then fastest one
+    #[arg(long)]
+    auto_select: bool,
+
+    /// Read chain data from a fixture saved with `record` instead of connecting to a node
+    #[arg(long, global = true)]
+    fixture: Option<PathBuf>,
+
+    /// Most requests sent to the node before their responses arrive, lower it for weak public nodes
+    #[arg(long, global = true, default_value_t = NonZeroUsize::new(limits::DEFAULT_MAX_IN_FLIGHT).unwrap())]
+    max_in_flight: NonZeroUsize,
+
+    /// Requests per second sent to the node, 0 for no limit
+    #[arg(long, global = true, default_value_t = limits::DEFAULT_RATE_LIMIT)]
+    rate_limit: f64,
+
+    /// Move address counts of long scans to files in this directory instead of keeping them all in memory
+    #[arg(long, global = true)]
+    spill_dir: Option<PathBuf>,
+
+    /// Distinct addresses kept in memory before spilling them to --spill-dir
+    #[arg(long, global = true, default_value_t = limits::DEFAULT_SPILL_THRESHOLD)]
+    spill_threshold: usize,
+
+    /// Config file to use instead of the default location
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Show balances, block rewards and fees in a fiat currency too (e.g. `usd`), priced by the
+    /// API of the `[price]` config section
+    #[arg(long, global = true)]
+    fiat: Option<String>,
 
     /// Sub commands
     #[command(subcommand)]
@@ -64,158 +137,1838 @@ struct Cli {
 enum Commands {
     /// Get block by height or hash
     Block {
-        /// Block height (number) or block hash (base36)
+        /// Block height (number) or block hash (base36), `-` reads them from stdin
         id: String,
+
+        /// Only print the summary, without the transactions
+        #[arg(long)]
+        summary: bool,
+
+        /// Print the block structure as the node sends it instead
+        #[arg(long, conflicts_with = "summary")]
+        raw: bool,
+
+        /// Print the block's page on the web explorer of the `[explorer]` config instead
+        #[arg(long)]
+        url: bool,
+
+        /// Open the block's page on the web explorer in the browser, and print it
+        #[arg(long)]
+        open: bool,
     },
 
     /// Get transaction by hash (base36)
     Tx {
-        /// Transaction hash (base36)
+        /// Transaction hash (base36), `-` reads them from stdin
         id: String,
+
+        /// Print the transaction structure as the node sends it instead
+        #[arg(long)]
+        raw: bool,
+
+        /// Print the transaction's page on the web explorer of the `[explorer]` config instead
+        #[arg(long)]
+        url: bool,
+
+        /// Open the transaction's page on the web explorer in the browser, and print it
+        #[arg(long)]
+        open: bool,
     },
 
     /// Get address (base36) info
     Addr {
-        /// Address (base36)
+        /// Address (base36) or address book label, `-` reads them from stdin
         address: String,
+
+        /// Most UTXOs to list
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// UTXOs to skip before listing
+        #[arg(long, default_value_t = 0)]
+        offset: usize,
+
+        /// Order the UTXOs are listed in, as the node returns them by default
+        #[arg(long, value_enum)]
+        sort: Option<UtxoSort>,
+
+        /// Only print the balance, UTXO count, totals received and sent and when the address was active
+        #[arg(long, conflicts_with_all = ["limit", "offset", "sort"])]
+        summary: bool,
+
+        /// Only fetch and print the balance
+        #[arg(long, conflicts_with_all = ["summary", "utxos", "history", "limit", "offset", "sort"])]
+        balance_only: bool,
+
+        /// Print the UTXOs, along with the other sections asked for
+        #[arg(long, conflicts_with = "summary")]
+        utxos: bool,
+
+        /// Print the transaction history, along with the other sections asked for
+        #[arg(long, conflicts_with = "summary")]
+        history: bool,
+
+        /// Split the balance into spendable outputs and block rewards below the maturity depth
+        #[arg(long, conflicts_with_all = ["summary", "balance_only", "utxos", "history", "limit", "offset", "sort"])]
+        maturity: bool,
+
+        /// Confirmations a block reward needs to be spendable, none in the snap-coin protocol
+        #[arg(long, default_value_t = COINBASE_MATURITY, requires = "maturity")]
+        depth: usize,
+
+        /// Print the address's page on the web explorer of the `[explorer]` config instead
+        #[arg(long)]
+        url: bool,
+
+        /// Open the address's page on the web explorer in the browser, and print it
+        #[arg(long)]
+        open: bool,
     },
 
     /// Get current blockchain height
     Height,
 
     /// Get current difficulty
-    Difficulty,
+    Difficulty {
+        #[command(subcommand)]
+        command: Option<DifficultyCommand>,
+    },
+
+    /// Project the chain size at future dates from the size of recent blocks, for sizing disks
+    Growth {
+        /// Number of blocks to scan
+        #[arg(long, default_value_t = 10_000)]
+        blocks: usize,
+
+        /// Blocks in every window the bytes per block are averaged over
+        #[arg(long, default_value_t = NonZeroUsize::new(1000).unwrap())]
+        window: NonZeroUsize,
+
+        /// Chain size now, e.g. 20G from the node's data directory, estimated from the scanned blocks otherwise
+        #[arg(long, value_parser = growth::parse_bytes)]
+        size: Option<u64>,
+
+        /// Days ahead to project the size to
+        #[arg(long, value_delimiter = ',', default_values_t = growth::DEFAULT_HORIZONS)]
+        days: Vec<u64>,
+    },
+
+    /// Compare the observed block time to the protocol's target in periods of blocks
+    Conformance {
+        /// Number of blocks to check
+        #[arg(long, default_value_t = 1000)]
+        blocks: usize,
+
+        /// Blocks in every period
+        #[arg(long, default_value_t = NonZeroUsize::new(100).unwrap())]
+        period: NonZeroUsize,
+
+        /// Warn when the average block time is off the target by more than this many percent
+        #[arg(long, default_value_t = 10.0)]
+        warn: f64,
+
+        /// Fail when the average block time is off the target by more than this many percent
+        #[arg(long, default_value_t = 25.0)]
+        fail: f64,
+
+        /// Exit with an error when the blocks as a whole fail, for monitoring
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Flag unusual block intervals, difficulty jumps, timestamp reversals and miner share jumps
+    Anomalies {
+        /// Number of blocks to scan
+        #[arg(long, default_value_t = 1000)]
+        blocks: usize,
+
+        /// Flag block intervals this many standard deviations from the mean
+        #[arg(long, default_value_t = 3.0)]
+        sigma: f64,
+
+        /// Flag difficulty rising or falling by more than this factor in one block
+        #[arg(long, default_value_t = 2.0)]
+        difficulty_jump: f64,
+
+        /// Blocks in every window miner shares are compared over
+        #[arg(long, default_value_t = NonZeroUsize::new(100).unwrap())]
+        share_window: NonZeroUsize,
+
+        /// Flag miners gaining more than this many percentage points of the blocks between windows
+        #[arg(long, default_value_t = 25.0)]
+        share_jump: f64,
+    },
+
+    /// Flag transactions paying or spending from the addresses of a watchlist, with the direction
+    /// and amount, for deposit screening
+    Screen {
+        /// File with one base36 address per line, `#` comments allowed
+        #[arg(long)]
+        list: PathBuf,
+
+        /// Number of past blocks to screen
+        #[arg(long, default_value_t = 1000)]
+        blocks: usize,
+
+        /// Keep screening new blocks and the mempool until stopped, one line per hit
+        #[arg(long)]
+        watch: bool,
+
+        /// Time between polls of the node with --watch
+        #[arg(long, value_parser = parse_nonzero_duration, default_value = "10s")]
+        interval: Duration,
+
+        /// Pop a desktop notification for every new payment to a watchlist address
+        #[arg(long, requires = "watch")]
+        notify: bool,
+    },
+
+    /// Stream chain activity as it's confirmed, one line at a time, runs until stopped
+    Tail {
+        #[command(subcommand)]
+        command: TailCommand,
+    },
+
+    /// Distinct active and never seen before addresses of the past blocks in buckets of time
+    Addresses {
+        /// Number of blocks to count addresses in, the blocks before them back to genesis are
+        /// scanned to tell new addresses apart
+        #[arg(long, default_value_t = 30_240)]
+        blocks: usize,
+
+        /// Length of every bucket, e.g. 1h, 1d or 7d
+        #[arg(long, value_parser = parse_duration, default_value = "1d")]
+        bucket: Duration,
+
+        /// Show one line sparklines instead of the table
+        #[arg(long)]
+        sparkline: bool,
+
+        /// Also render the series to a .png or .svg file
+        #[arg(long)]
+        plot_out: Option<PathBuf>,
+
+        /// Also write the series to a CSV file (gnuplot friendly)
+        #[arg(long)]
+        plot_data: Option<PathBuf>,
+    },
+
+    /// Transactions per second of the past blocks in buckets of time, to show usage cycles
+    Tps {
+        /// Number of blocks to scan
+        #[arg(long, default_value_t = 1000)]
+        blocks: usize,
+
+        /// Length of every bucket, e.g. 15m, 1h or 1d
+        #[arg(long, value_parser = parse_duration, default_value = "1h")]
+        bucket: Duration,
+
+        /// Show a one line sparkline instead of the table
+        #[arg(long)]
+        sparkline: bool,
+
+        /// Also render the series to a .png or .svg file
+        #[arg(long)]
+        plot_out: Option<PathBuf>,
+
+        /// Also write the series to a CSV file (gnuplot friendly)
+        #[arg(long)]
+        plot_data: Option<PathBuf>,
+    },
+
+    /// Size, value and dust of the UTXO set, rebuilt by replaying the chain, and how fast it grows
+    UtxoStats {
+        /// Number of recent blocks to measure the growth over
+        #[arg(long, default_value_t = 1000)]
+        blocks: usize,
+
+        /// Outputs below this many SNAP count as dust
+        #[arg(long, value_parser = parse_snap, default_value = "0.00001")]
+        dust: u64,
+    },
+
+    /// Hash of the UTXO set at a height, to check that other parties derived the same set
+    UtxoCommitment {
+        /// Hash the set left after the blocks below this height, the chain height by default
+        #[arg(long)]
+        at_height: Option<usize>,
+    },
+
+    /// Largest balances, rebuilt from the UTXO set by replaying the chain
+    Richlist {
+        /// Number of addresses to list
+        #[arg(long, default_value_t = 100)]
+        top: usize,
+
+        /// Also save the list with the current height to a JSON file for a later `--diff`
+        #[arg(long)]
+        snapshot: Option<PathBuf>,
+
+        /// Report the largest balance changes, new entrants and dropouts since this snapshot
+        #[arg(long)]
+        diff: Option<PathBuf>,
+    },
+
+    /// Fee rate percentiles of the transactions confirmed in the past blocks
+    Fees {
+        /// Number of blocks to scan
+        #[arg(long, default_value_t = 100)]
+        blocks: usize,
+    },
+
+    /// Estimate the network hashrate from block difficulties and the time blocks took
+    Hashrate {
+        /// Print a rolling estimate for every block instead of the latest one
+        #[arg(long)]
+        series: bool,
+
+        /// Blocks in the series, ending at the tip
+        #[arg(long, default_value_t = 1000, requires = "series")]
+        blocks: usize,
+
+        /// Blocks every estimate looks back over
+        #[arg(long, default_value_t = NonZeroUsize::new(144).unwrap())]
+        window: NonZeroUsize,
+
+        /// Also render the series to a .png or .svg file
+        #[arg(long, requires = "series")]
+        plot_out: Option<PathBuf>,
+
+        /// Also write the series to a CSV file (gnuplot friendly)
+        #[arg(long, requires = "series")]
+        plot_data: Option<PathBuf>,
+    },
 
     /// Get Current Mempool
-    Mempool,
+    Mempool {
+        /// Also save the mempool with the current height to a JSON file for `diff mempool`
+        #[arg(long)]
+        snapshot: Option<PathBuf>,
+    },
 
     /// Calculate basic average info for the past X blocks
-    Averages { blocks: usize },
+    Averages {
+        #[arg(required_unless_present = "compare")]
+        blocks: Option<usize>,
+
+        /// Compare the stats of two block ranges side by side instead, e.g. `--compare 1000..2000 2000..3000`
+        #[arg(long, num_args = 2, value_names = ["A", "B"], value_parser = parse_block_range, conflicts_with_all = ["plot_out", "plot_data", "sparkline"])]
+        compare: Option<Vec<Range<usize>>>,
+
+        /// Also render difficulty, block time and TPS charts to a .png or .svg file
+        #[arg(long)]
+        plot_out: Option<PathBuf>,
+
+        /// Also write the plotted series to a CSV file (gnuplot friendly)
+        #[arg(long)]
+        plot_data: Option<PathBuf>,
+
+        /// Show one line sparklines instead of the per block bar chart
+        #[arg(long)]
+        sparkline: bool,
+
+        /// Plot difficulties on a log scale
+        #[arg(long)]
+        log_scale: bool,
+
+        /// Scale block and TX difficulty against the same maximum
+        #[arg(long)]
+        shared_scale: bool,
+
+        /// Aggregate blocks so the chart has at most this many rows
+        #[arg(long)]
+        max_rows: Option<usize>,
+
+        /// Mark and list blocks whose interval or difficulty is this many standard deviations from the mean
+        #[arg(long, default_value_t = 3.0)]
+        outlier_sigma: f64,
+
+        /// Only compute block time and difficulty stats, skipping transaction processing
+        #[arg(long, conflicts_with = "compare")]
+        headers_only: bool,
+    },
+
+    /// Print the full block reward emission schedule
+    Rewards,
+
+    /// Find nodes on this machine and the local network by probing their API port
+    Discover {
+        /// Hosts or IPv4 networks to probe, e.g. 192.168.1.0/24, this machine and its /24 by default
+        #[arg(long, value_delimiter = ',', value_parser = discover::parse_hosts)]
+        hosts: Vec<Vec<IpAddr>>,
+
+        /// API ports to try on every host
+        #[arg(long, value_delimiter = ',', default_values_t = discover::DEFAULT_PORTS.to_vec())]
+        ports: Vec<u16>,
+
+        /// Hosts slower than this to answer are left out
+        #[arg(long, value_parser = parse_duration, default_value = "1s")]
+        timeout: Duration,
+    },
+
+    /// Calculate coin issuance over the past X blocks as an annualized inflation rate
+    Inflation {
+        /// Number of blocks to sample
+        #[arg(long)]
+        blocks: usize,
+    },
+
+    /// Show each miner's share of the past blocks, or how it moves over time with `--trend`
+    Miners {
+        /// Number of blocks to scan
+        blocks: usize,
+
+        /// Show the share of every miner in sliding windows instead of over all blocks
+        #[arg(long)]
+        trend: bool,
+
+        /// Show the mean interval between every miner's blocks and offset from their parents' timestamps
+        #[arg(long, conflicts_with = "trend")]
+        timing: bool,
+
+        /// Draw the shares as horizontal bars, miners named by their address book labels
+        #[arg(long, conflicts_with_all = ["trend", "timing"])]
+        chart: bool,
+
+        /// Also render the shares as a pie chart to a .png or .svg file
+        #[arg(long, requires = "chart")]
+        pie_out: Option<PathBuf>,
+
+        /// Blocks in every window of `--trend`
+        #[arg(long, default_value_t = NonZeroUsize::new(144).unwrap(), requires = "trend")]
+        window: NonZeroUsize,
+
+        /// Blocks between the starts of two windows of `--trend`
+        #[arg(long, default_value_t = NonZeroUsize::new(24).unwrap(), requires = "trend")]
+        step: NonZeroUsize,
+
+        /// Miners shown on their own, the others are summed up
+        #[arg(long, default_value_t = 5)]
+        top: usize,
+
+        /// Also render the trend lines to a .png or .svg file
+        #[arg(long, requires = "trend")]
+        plot_out: Option<PathBuf>,
+
+        /// Also write the trend lines to a CSV file (gnuplot friendly)
+        #[arg(long, requires = "trend")]
+        plot_data: Option<PathBuf>,
+    },
+
+    /// Report coins sent to provably unspendable addresses
+    Burns {
+        /// Number of blocks to scan for the per block series
+        #[arg(long)]
+        blocks: usize,
+
+        /// Also render the burn series to a .png or .svg file
+        #[arg(long)]
+        plot_out: Option<PathBuf>,
+
+        /// Also write the burn series to a CSV file (gnuplot friendly)
+        #[arg(long)]
+        plot_data: Option<PathBuf>,
+
+        /// Show a one line sparkline instead of the per block table
+        #[arg(long)]
+        sparkline: bool,
+    },
+
+    /// Print a shell completion script, including node aliases and address labels from the config
+    Completions { shell: Shell },
+
+    /// Run queries from a file (`block <id>`, `tx <id>`, `addr <address>` per line) over one connection
+    Batch {
+        /// File with one query per line, blank lines and `#` comments are skipped
+        file: PathBuf,
+
+        /// Print one JSON object per line as results arrive instead of a single array
+        #[arg(long)]
+        jsonl: bool,
+    },
+
+    /// Periodically write a JSON report of height, difficulty, mempool and recent chain stats
+    Snapshot {
+        /// Time between snapshots, e.g. 30m or 1h
+        #[arg(long, value_parser = parse_nonzero_duration)]
+        every: Duration,
+
+        /// Directory the timestamped reports are written to
+        #[arg(long)]
+        out_dir: PathBuf,
+
+        /// Number of recent blocks included in the chain stats
+        #[arg(long, default_value_t = 100)]
+        blocks: usize,
+    },
+
+    /// Follow the chain and mempool until stopped, printing new blocks, reorgs, mempool changes and
+    /// alerts for watched addresses as JSON lines. Resumes from its state file after a restart.
+    Monitor {
+        /// JSON file the last seen blocks, watched addresses and mempool are kept in
+        #[arg(long)]
+        state: PathBuf,
+
+        /// Address to alert on, kept in the state file for later runs
+        #[arg(long)]
+        address: Vec<String>,
+
+        /// Time between polls of the node
+        #[arg(long, value_parser = parse_nonzero_duration, default_value = "10s")]
+        interval: Duration,
+
+        /// Report transactions moving at least this many SNAP as `whale` events, kept in the state
+        /// file for later runs, 0 turns it off
+        #[arg(long, value_parser = parse_snap)]
+        min_amount: Option<u64>,
+
+        /// Pop a desktop notification for blocks mined by and payments to the watched addresses,
+        /// and for whale transactions
+        #[arg(long)]
+        notify: bool,
+
+        /// Register the monitor, with the other arguments given, as a Windows service started at
+        /// boot and logging to the Event Log
+        #[arg(long, conflicts_with = "daemon")]
+        install_service: bool,
+
+        /// Keep running in the background, detached from the terminal, logging to syslog (unix)
+        #[arg(long)]
+        daemon: bool,
+
+        /// File to keep the pid of the running monitor in, removed when it stops
+        #[arg(long)]
+        pid_file: Option<PathBuf>,
+
+        /// Started by --daemon or the service manager: log to the platform log
+        #[arg(long, hide = true)]
+        detached: bool,
+    },
+
+    /// Serve chain data over HTTP to other tools, runs until stopped
+    Serve {
+        #[command(subcommand)]
+        command: ServeCommand,
+    },
+
+    /// Measure sustained block and transaction retrieval rates at several concurrency levels
+    BenchNode {
+        /// Concurrent connections to measure with, one run per level
+        #[arg(long, value_delimiter = ',', default_values_t = bench::DEFAULT_LEVELS.iter().map(|level| NonZeroUsize::new(*level).unwrap()))]
+        concurrency: Vec<NonZeroUsize>,
+
+        /// Length of every measurement, blocks and transactions are measured separately
+        #[arg(long, value_parser = parse_duration, default_value = "10s")]
+        duration: Duration,
+
+        /// Number of recent blocks, and their transactions, requested over and over
+        #[arg(long, default_value_t = 100)]
+        sample: usize,
+    },
+
+    /// Send a mix of read requests for a while and report latency percentiles and error rates
+    Stress {
+        /// How long to keep the load up
+        #[arg(long, value_parser = parse_duration, default_value = "60s")]
+        duration: Duration,
+
+        /// Concurrent connections sending requests
+        #[arg(long, default_value_t = NonZeroUsize::new(32).unwrap())]
+        concurrency: NonZeroUsize,
+
+        /// Requests to cycle through, repeat one to send it more often
+        #[arg(long, value_enum, value_delimiter = ',', default_values = ["blocks", "txs", "addrs"])]
+        mix: Vec<load::Query>,
+
+        /// Number of recent blocks, their transactions and receivers, requested over and over
+        #[arg(long, default_value_t = 100)]
+        sample: usize,
+    },
+
+    /// Find orphaned blocks by recording the blocks the node announces and checking them later
+    Orphans {
+        #[command(subcommand)]
+        command: OrphansCommand,
+    },
+
+    /// List the active chain tip and the ends of the side branches seen in an `orphans watch` log
+    Tips {
+        /// Log written by `orphans watch`, without it only the active tip is known
+        #[arg(long)]
+        log: Option<PathBuf>,
+    },
+
+    /// Estimate how likely a block or transaction is to be reversed by an attacker, and how many
+    /// confirmations make it safe
+    Finality {
+        /// Block height (number) or transaction hash (base36)
+        id: String,
+
+        /// Assumed share of the total hashrate controlled by the attacker, e.g. 0.1 or 10%
+        #[arg(long, value_parser = finality::parse_fraction, default_value = "10%")]
+        attacker: f64,
+
+        /// Wanted probability of staying on the main chain, e.g. 0.999 or 99.9%
+        #[arg(long, value_parser = finality::parse_fraction, default_value = "99.9%")]
+        assurance: f64,
+    },
+
+    /// Compare the blocks a miner found with what their hashrate share predicts
+    Luck {
+        /// Miner address (base36) or address book label
+        #[arg(long)]
+        miner: String,
+
+        /// Number of latest blocks to judge
+        #[arg(long, default_value_t = 1000)]
+        blocks: usize,
+
+        /// The miner's hashrate, e.g. 5MH, against the network hashrate of the current block difficulty
+        #[arg(long, value_parser = convert::parse_hashrate)]
+        hashrate: Option<f64>,
+
+        /// Without --hashrate, the share is the miner's share of this many blocks before the judged ones,
+        /// four times as many by default
+        #[arg(long, conflicts_with = "hashrate")]
+        baseline: Option<usize>,
+    },
+
+    /// Check a pool paid an address its share of the block rewards the pool earned
+    VerifyPayouts {
+        /// Pool address (base36) or address book label, receiving the block rewards
+        #[arg(long)]
+        miner: String,
+
+        /// Address (base36) or address book label the pool pays
+        #[arg(long)]
+        payout_address: String,
+
+        /// Number of latest blocks to scan
+        #[arg(long, default_value_t = 1000)]
+        blocks: usize,
+
+        /// Share of the rewards owed to the payout address, e.g. 0.05 or 5%
+        #[arg(long, value_parser = finality::parse_fraction, default_value = "100%")]
+        share: f64,
+
+        /// Pool fee kept before shares are paid, e.g. 0.02 or 2%
+        #[arg(long, value_parser = finality::parse_fraction, default_value = "0%")]
+        fee: f64,
+    },
+
+    /// Expected time for a solo miner to find a block at the current block difficulty
+    MineEta {
+        /// Hashes per second, e.g. 5MH or 2.5 KH/s
+        #[arg(long, value_parser = convert::parse_hashrate)]
+        hashrate: f64,
+    },
+
+    /// Follow a set of addresses together, kept in a watch-only wallet file
+    Wallet {
+        /// Wallet file to use instead of `wallet.toml` next to the config file
+        #[arg(long)]
+        file: Option<PathBuf>,
+
+        #[command(subcommand)]
+        command: WalletCommand,
+    },
+
+    /// Read-only treasury dashboard: balance of every listed address, its change over the last
+    /// day and week and the totals, valued with --fiat
+    Portfolio {
+        /// CSV of `address,label` rows, as `alias import` reads
+        list: PathBuf,
+    },
+
+    /// Manage address labels imported from CSV, shown with their category in reports
+    Alias {
+        /// Label file to use instead of `labels.csv` next to the config file
+        #[arg(long)]
+        file: Option<PathBuf>,
+
+        #[command(subcommand)]
+        command: AliasCommand,
+    },
+
+    /// Manage secret keys, stored encrypted with a password
+    Keys {
+        /// Key directory to use instead of `keys/` next to the config file
+        #[arg(long)]
+        dir: Option<PathBuf>,
+
+        #[command(subcommand)]
+        command: KeysCommand,
+    },
+
+    /// Send SNAP from a key of `keys`, mining the transaction's proof of work on this machine
+    Send {
+        /// Address (base36) or address book label to pay
+        to: String,
+
+        /// Amount in SNAP
+        amount: String,
+
+        /// Name of the key to send from
+        #[arg(long)]
+        from: String,
+
+        /// Key directory to use instead of `keys/` next to the config file
+        #[arg(long)]
+        keys_dir: Option<PathBuf>,
+
+        /// Print the chosen inputs, change, size and proof of work estimate without signing or sending
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Submit a transaction `send` made to the node again when it dropped out of the mempool
+    Rebroadcast {
+        /// Transaction ID (base36), looked up in `sent.jsonl` next to the config file
+        id: String,
+
+        /// JSON transaction to submit instead, as printed by `tx --raw --format json`
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+
+    /// Estimate sizes and proof of work times ahead of sending
+    Estimate {
+        #[command(subcommand)]
+        command: EstimateCommand,
+    },
+
+    /// Check whether the node would accept a signed transaction without sending it, and why not
+    TestAccept {
+        /// JSON transaction as printed by `tx --raw --format json`: inline, a file, or `-` for stdin
+        tx: String,
+    },
+
+    /// Convert between SNAP and base units, or a difficulty target and its normalized difficulty
+    Convert {
+        /// Value with its unit: `1.5snap`, `150000000base`, `<hex>target` or `256diff`
+        value: String,
+    },
+
+    /// Compute the snap-coin hash of a file, stdin or hex bytes, or of a block or transaction
+    Hash {
+        /// File to hash, `-` reads stdin
+        #[arg(required_unless_present = "hex")]
+        file: Option<PathBuf>,
+
+        /// Hash these hex bytes instead of a file
+        #[arg(long, conflicts_with = "file")]
+        hex: Option<String>,
+
+        /// Read the data as a block or transaction and hash it as the protocol does
+        #[arg(long = "as", value_enum, default_value_t = HashInput::Raw)]
+        input: HashInput,
+    },
+
+    /// Print the base36 identifier of hex or raw bytes
+    Encode {
+        /// Hex bytes, optionally tagged with their type as in `tx:<hex>`, or `-` for raw bytes on stdin
+        value: String,
+
+        /// Identifier type, unless the value is tagged with it
+        #[arg(long = "type", value_enum)]
+        kind: Option<IdKind>,
+    },
+
+    /// Print the hex or raw bytes of a base36 address, hash, transaction id, secret or signature
+    Decode {
+        /// Base36 identifier, optionally tagged with its type as in `address:<base36>`
+        value: String,
+
+        /// Identifier type, unless the value is tagged with it
+        #[arg(long = "type", value_enum)]
+        kind: Option<IdKind>,
+
+        /// Write the raw bytes to stdout instead of hex
+        #[arg(long)]
+        raw: bool,
+    },
+
+    /// Generate a key pair offline and print its address and secret
+    Keygen {
+        /// Also print a 24 word BIP39 phrase the key can be restored from with `keys restore`
+        #[arg(long)]
+        mnemonic: bool,
+    },
+
+    /// Generate a key pair offline and print a printable HTML sheet with its address and secret as QR codes
+    PaperWallet {
+        /// Encrypt the secret with a password (read from SNAP_COIN_PASSWORD or asked for)
+        #[arg(long)]
+        encrypt: bool,
+    },
+
+    /// Show the differences between two blocks or other chain data
+    Diff {
+        #[command(subcommand)]
+        command: DiffCommand,
+    },
+
+    /// Run custom analyses written in Rhai
+    Script {
+        #[command(subcommand)]
+        command: ScriptCommand,
+    },
+
+    /// Any other command runs `snap-coin-stats-<command>` from PATH
+    #[command(external_subcommand)]
+    External(Vec<OsString>),
+
+    /// Cross-check several nodes, e.g. `check time --nodes a,b,c`
+    Check {
+        #[command(subcommand)]
+        command: CheckCommand,
+    },
+
+    /// Check the chain against the protocol's rules
+    Audit {
+        #[command(subcommand)]
+        command: AuditCommand,
+    },
+
+    /// Dump chain data over whole ranges of the chain for analysis elsewhere
+    Export {
+        #[command(subcommand)]
+        command: ExportCommand,
+    },
+
+    /// Save the past X blocks to a fixture file usable with --fixture (`.bin` for bincode, JSON otherwise)
+    Record {
+        file: PathBuf,
+
+        /// Number of blocks to record
+        #[arg(long)]
+        blocks: usize,
+    },
+}
+
+impl Commands {
+    /// Daemon style commands never finish their output, so it can't go through a pager
+    fn runs_until_stopped(&self) -> bool {
+        matches!(
+            self,
+            Commands::Snapshot { .. }
+                | Commands::Monitor { .. }
+                | Commands::Serve { .. }
+                | Commands::Screen { watch: true, .. }
+                | Commands::Tail { .. }
+                | Commands::Orphans {
+                    command: OrphansCommand::Watch { .. }
+                }
+        )
+    }
+}
+
+#[derive(Subcommand)]
+enum DiffCommand {
+    /// Compare two blocks by height or hash, e.g. competing blocks at the same height during a fork
+    Block {
+        a: String,
+        b: String,
+
+        /// Look up B on this node (address or alias) instead, to compare what two nodes see
+        #[arg(long)]
+        other_node: Option<String>,
+    },
+
+    /// Compare two `mempool --snapshot` files, with a node removed transactions are split into confirmed and dropped
+    Mempool { a: PathBuf, b: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum CheckCommand {
+    /// Compare the tip timestamps of several nodes with each other and with the time from NTP
+    Time {
+        /// Nodes or aliases to compare, the node given before the command or the configured nodes by default
+        #[arg(long)]
+        nodes: Option<String>,
+
+        /// NTP server to take the time from
+        #[arg(long, default_value = clock::DEFAULT_NTP_SERVER)]
+        ntp: String,
+
+        /// Don't ask NTP, trust the local clock
+        #[arg(long, conflicts_with = "ntp")]
+        no_ntp: bool,
+
+        /// Tips timestamped further in the future than this are flagged
+        #[arg(long, value_parser = parse_duration, default_value = "1m")]
+        tolerance: Duration,
+
+        /// Nodes and NTP servers slower than this to answer are left out
+        #[arg(long, value_parser = parse_duration, default_value = "5s")]
+        timeout: Duration,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuditCommand {
+    /// Recompute every block's difficulty adjustment from the blocks before it and flag the blocks
+    /// carrying other difficulties
+    Retargets {
+        /// Number of past blocks to check
+        #[arg(long, default_value_t = 1000, conflicts_with = "range")]
+        blocks: usize,
+
+        /// Heights to check instead, e.g. `1000..2000`
+        #[arg(long, value_parser = parse_block_range)]
+        range: Option<Range<usize>>,
+
+        /// Exit with an error when any block deviates, for monitoring
+        #[arg(long)]
+        check: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ExportCommand {
+    /// Height, timestamp, block difficulty and transaction difficulty of every block as CSV
+    Difficulty {
+        /// CSV file to write, `-` for stdout
+        #[arg(long)]
+        file: PathBuf,
+
+        /// Heights to export, e.g. `1000..2000`, the whole chain by default
+        #[arg(long, value_parser = parse_block_range)]
+        range: Option<Range<usize>>,
+    },
+
+    /// Every unspent output (outpoint, owner, amount, creation height) as CSV, sorted by outpoint
+    Utxos {
+        /// Export the set left after the blocks below this height, the chain height by default
+        #[arg(long)]
+        at_height: Option<usize>,
+
+        /// CSV file to write, `-` for stdout
+        #[arg(long)]
+        file: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum DifficultyCommand {
+    /// Convert between a target, a normalized difficulty and the hashrate mining blocks at it, offline
+    #[command(group(clap::ArgGroup::new("value").required(true)))]
+    Convert {
+        /// Target as up to 64 hex digits
+        #[arg(long, group = "value")]
+        target: Option<String>,
+
+        /// Normalized difficulty as `difficulty` prints it, e.g. 256 or 1.5M
+        #[arg(long, group = "value")]
+        difficulty: Option<String>,
+
+        /// Hashes per second, e.g. 2.5K
+        #[arg(long, group = "value")]
+        hashrate: Option<String>,
+
+        /// Seconds between blocks the hashrate is for, the protocol's target block time by default
+        #[arg(long, default_value_t = TARGET_TIME as f64)]
+        block_time: f64,
+    },
+}
+
+#[derive(Subcommand)]
+enum EstimateCommand {
+    /// Size, minimum fee and proof of work time of a transaction at the node's transaction difficulty
+    Tx {
+        #[arg(long, default_value_t = 1)]
+        inputs: usize,
+
+        #[arg(long, default_value_t = 2)]
+        outputs: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum AliasCommand {
+    /// Merge `address,label,category` rows into the labels, replacing the labels of addresses
+    /// already labeled. Categories exchange, pool and burn are known, burns are counted by `burns`.
+    Import { csv: PathBuf },
+
+    /// List the imported labels
+    List,
+}
+
+#[derive(Subcommand)]
+enum WalletCommand {
+    /// Watch an address, or relabel one already watched
+    Add {
+        /// Address (base36) or address book label
+        address: String,
+
+        /// Name to show the address by
+        #[arg(long)]
+        label: Option<String>,
+    },
+
+    /// Balance of every watched address and their total
+    Balance,
+
+    /// UTXOs of all watched addresses, biggest first
+    Utxos,
+
+    /// Transactions of all watched addresses, oldest first
+    History,
+}
+
+#[derive(Subcommand)]
+enum KeysCommand {
+    /// Generate a new key (the password is read from SNAP_COIN_PASSWORD or asked for)
+    Create { name: String },
+
+    /// Names and addresses of the stored keys, no password needed
+    List,
+
+    /// Print the base36 secret of a key
+    Export { name: String },
+
+    /// Store a base36 secret key or an encrypted paper wallet secret, read from a raw key file or asked for
+    Import {
+        name: String,
+
+        /// File holding the base36 secret
+        file: Option<PathBuf>,
+    },
+
+    /// Store the key of a BIP39 mnemonic phrase, read from a file or asked for
+    Restore {
+        name: String,
+
+        /// File holding the phrase
+        file: Option<PathBuf>,
+    },
+
+    /// Encrypt a key with a new password (read from SNAP_COIN_NEW_PASSWORD or asked for)
+    ChangePassword { name: String },
+}
+
+#[derive(Subcommand)]
+enum OrphansCommand {
+    /// Record every block the node announces to a log, runs until stopped
+    Watch {
+        /// JSON lines log, appended to when it exists
+        #[arg(long)]
+        file: PathBuf,
+    },
+
+    /// Report blocks of a watch log that are no longer on the node's main chain
+    Report {
+        file: PathBuf,
+
+        /// Period the orphan rate is computed over, e.g. 1h or 1d
+        #[arg(long, value_parser = parse_duration, default_value = "1d")]
+        bucket: Duration,
+    },
+}
+
+#[derive(Subcommand)]
+enum TailCommand {
+    /// Confirmed transactions moving at least `--min-amount`, as compact lines: transaction,
+    /// senders→receivers by label, amount and block
+    Txs {
+        /// Only transactions paying at least this many SNAP to others than their senders
+        #[arg(long, value_parser = parse_snap, default_value = "0")]
+        min_amount: u64,
+
+        /// Number of past blocks to print the transactions of before following new ones
+        #[arg(long, default_value_t = 0)]
+        blocks: usize,
+
+        /// Time between polls of the node
+        #[arg(long, value_parser = parse_nonzero_duration, default_value = "10s")]
+        interval: Duration,
+    },
+}
+
+#[derive(Subcommand)]
+enum ServeCommand {
+    /// Answer Grafana's SimpleJSON datasource (`/search` and `/query`) with the height, difficulty,
+    /// tps and block_time series
+    Grafana {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:3030")]
+        listen: SocketAddr,
+
+        /// Number of recent blocks kept in memory to answer queries from
+        #[arg(long, default_value_t = 20160)]
+        blocks: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum ScriptCommand {
+    /// Run a script with `get_height()`, `get_block(id)`, `get_tx(id)` and `iter_blocks(start, end)` available
+    Run {
+        file: PathBuf,
+
+        /// Passed to the script as the `ARGS` array
+        args: Vec<String>,
+    },
+}
+
+/// Resolve every address behind the node passed on the command line
+async fn node_pool(config: &config::Config, node: Option<&str>) -> Result<NodePool, anyhow::Error> {
+    let node = node.ok_or_else(|| anyhow!("A node address is required for this command"))?;
+    NodePool::resolve(config.resolve_nodes(node)).await
+}
+
+/// Connect to the best of the given nodes, or the configured ones, telling the user which one was picked
+async fn auto_select(
+    config: &config::Config,
+    node: Option<&str>,
+) -> Result<NodeClient, anyhow::Error> {
+    let candidates: Vec<(String, String)> = match node {
+        Some(node) => node_pool(config, Some(node))
+            .await?
+            .addresses()
+            .iter()
+            .map(|address| (address.to_string(), address.to_string()))
+            .collect(),
+        None => config.nodes.clone().into_iter().collect(),
+    };
+    if candidates.is_empty() {
+        return Err(anyhow!(
+            "--auto-select needs a node list or nodes in the [nodes] config section"
+        ));
+    }
+    let mut probes = Vec::new();
+    let mut clients = Vec::new();
+    for (name, result) in probe::probe_all(candidates, probe::PROBE_TIMEOUT).await {
+        match result {
+            Ok((probe, client)) => {
+                probes.push(probe);
+                clients.push(client);
+            }
+            Err(e) => warn!("Skipping {}: {:#}", name, e),
+        }
+    }
+    let best = probe::pick_fastest(&probes)
+        .ok_or_else(|| error(ErrorKind::Connect, "None of the configured nodes answered"))?;
+    let chosen = &probes[best];
+    eprintln!(
+        "Using {} ({}), height {}, {} ms",
+        chosen.name,
+        chosen.address,
+        chosen.height,
+        chosen.latency.as_millis()
+    );
+    Ok(clients.swap_remove(best))
+}
+
+/// Global options handed to external subcommands, the node is passed already resolved
+async fn plugin_env(
+    args: &Cli,
+    config: &config::Config,
+) -> Result<Vec<(&'static str, String)>, anyhow::Error> {
+    let value_name = |value: Option<clap::builder::PossibleValue>| {
+        value.map(|v| v.get_name().to_string()).unwrap_or_default()
+    };
+    let mut env = vec![
+        (
+            "SNAP_COIN_FORMAT",
+            value_name(args.format.to_possible_value()),
+        ),
+        (
+            "SNAP_COIN_COLOR",
+            value_name(args.color.to_possible_value()),
+        ),
+        ("SNAP_COIN_VERBOSE", args.verbose.to_string()),
+        ("SNAP_COIN_MAX_IN_FLIGHT", args.max_in_flight.to_string()),
+        ("SNAP_COIN_RATE_LIMIT", args.rate_limit.to_string()),
+    ];
+    if args.node.is_some() {
+        let pool = node_pool(config, args.node.as_deref()).await?;
+        let addresses: Vec<_> = pool.addresses().iter().map(ToString::to_string).collect();
+        env.push(("SNAP_COIN_NODE", addresses[0].clone()));
+        env.push(("SNAP_COIN_NODES", addresses.join(",")));
+    }
+    if let Some(path) = args.config.clone().or_else(config::default_path) {
+        env.push(("SNAP_COIN_CONFIG", path.display().to_string()));
+    }
+    if let Some(path) = &args.fixture {
+        env.push(("SNAP_COIN_FIXTURE", path.display().to_string()));
+    }
+    if args.no_pager {
+        env.push(("SNAP_COIN_NO_PAGER", "1".to_string()));
+    }
+    Ok(env)
 }
 
 #[tokio::main]
-async fn main() -> Result<(), anyhow::Error> {
+async fn main() -> ExitCode {
     let args = Cli::parse();
-    let mut nodes = match lookup_host(args.node.clone()).await {
-        Ok(node) => node,
-        Err(..) => {
-            return Err(anyhow!("Could not resolve {}", args.node));
+    let detached = matches!(args.command, Commands::Monitor { detached: true, .. });
+    let platform_log = detached.then(service::open_platform_log);
+    logging::init(
+        args.verbose,
+        args.log_json,
+        matches!(platform_log, Some(Ok(()))),
+    );
+    if let Some(Err(e)) = platform_log {
+        warn!("{:#}", e);
+    }
+    let json =
+        args.format == OutputFormat::Json || args.select.is_some() || args.template.is_some();
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) if detached => {
+            error!("{:#}", e);
+            ExitCode::FAILURE
         }
+        Err(e) => ExitCode::from(error::report(&e, json)),
+    }
+}
+
+async fn run(args: Cli) -> Result<(), anyhow::Error> {
+    let config = config::Config::load(args.config.as_deref())?;
+    if let Commands::Completions { shell } = args.command {
+        completions::print_completions(shell, Cli::command(), &config);
+        return Ok(());
+    }
+    if let Commands::External(plugin_args) = &args.command {
+        let status = plugin::run(plugin_args, &plugin_env(&args, &config).await?)?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    limits::set_max_in_flight(args.max_in_flight);
+    limits::set_rate_limit(args.rate_limit);
+    limits::set_spill(args.spill_dir.clone().map(|dir| limits::SpillConfig {
+        dir,
+        threshold: args.spill_threshold,
+    }));
+    let _out_file = out_file::open(args.out.as_deref())?;
+    color::init(args.color, &config.theme)?;
+    let _pager = pager::start(args.no_pager || args.command.runs_until_stopped());
+    let out = Output::new(
+        args.format,
+        args.select.as_deref(),
+        args.template.as_deref(),
+    )?;
+
+    let fiat = match &args.fiat {
+        Some(currency) => {
+            let cache = price::cache_path_for(args.config.as_deref())
+                .ok_or_else(|| anyhow!("No config directory to cache prices in, pass --config"))?;
+            let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+            Some(price::quote(&config.price, &cache, currency, now).await?)
+        }
+        None => None,
     };
-    let client = Client::connect(nodes.next().unwrap()).await?;
-    match args.command {
-        Commands::Block { id } => {
-            let height: Option<usize> = id.parse().ok();
-            let hash = Hash::new_from_base36(&id);
-
-            if let Some(height) = height {
-                println!("{:#?}", client.get_block_by_height(height).await?);
-            } else if let Some(hash) = hash {
-                println!("{:#?}", client.get_block_by_hash(hash).await?);
-            } else {
-                return Err(anyhow!(
-                    "Block identifier {id} is not valid. Expected base36 hash or height."
-                ));
+    let fiat = fiat.as_ref();
+
+    // Commands computed purely from protocol constants don't need a node
+    if let Commands::Rewards = args.command {
+        return rewards::run_rewards(&out, fiat);
+    }
+
+    if let Commands::Convert { value } = &args.command {
+        return convert::run_convert(value, &out);
+    }
+
+    if let Commands::Difficulty {
+        command:
+            Some(DifficultyCommand::Convert {
+                target,
+                difficulty,
+                hashrate,
+                block_time,
+            }),
+    } = &args.command
+    {
+        return convert::run_difficulty_convert(
+            target.as_deref(),
+            difficulty.as_deref(),
+            hashrate.as_deref(),
+            *block_time,
+            &out,
+        );
+    }
+
+    if let Commands::Hash { file, hex, input } = &args.command {
+        return hashing::run_hash(file.as_deref(), hex.as_deref(), *input, &out);
+    }
+
+    if let Commands::Encode { value, kind } = &args.command {
+        return ids::run_encode(value, *kind, &out);
+    }
+
+    if let Commands::Decode { value, kind, raw } = &args.command {
+        return ids::run_decode(value, *kind, *raw, &out);
+    }
+
+    // Keys are kept on this machine, so their commands never touch a node
+    if let Commands::Keys { dir, command } = &args.command {
+        let keystore = Keystore::new(&keystore::resolve_dir(dir.as_deref())?);
+        return match command {
+            KeysCommand::Create { name } => keystore::run_create(&keystore, name, &out),
+            KeysCommand::List => keystore::run_list(&keystore, &out),
+            KeysCommand::Export { name } => keystore::run_export(&keystore, name, &out),
+            KeysCommand::Import { name, file } => {
+                keystore::run_import(&keystore, name, file.as_deref(), &out)
+            }
+            KeysCommand::Restore { name, file } => {
+                keystore::run_restore(&keystore, name, file.as_deref(), &out)
             }
+            KeysCommand::ChangePassword { name } => keystore::run_change_password(&keystore, name),
+        };
+    }
+
+    if let Commands::Keygen { mnemonic } = args.command {
+        return mnemonic::run_keygen(mnemonic, &out);
+    }
+
+    if let Commands::PaperWallet { encrypt } = args.command {
+        return paper::run_paper_wallet(encrypt, &out);
+    }
+
+    // Adding an address only edits the wallet file, so it never touches a node
+    if let Commands::Wallet {
+        file,
+        command: WalletCommand::Add { address, label },
+    } = &args.command
+    {
+        let path = wallet::resolve_path(file.as_deref())?;
+        return wallet::run_add(&path, address, label.clone(), &config);
+    }
+
+    if let Commands::Alias { file, command } = &args.command {
+        let path = labels::resolve_path(file.as_deref(), args.config.as_deref())?;
+        return match command {
+            AliasCommand::Import { csv } => labels::run_import(&path, csv, &out),
+            AliasCommand::List => labels::run_list(&path, &out),
+        };
+    }
+
+    // Installing and detaching the monitor start it again rather than connect here
+    if let Commands::Monitor {
+        state,
+        install_service,
+        daemon,
+        pid_file,
+        ..
+    } = &args.command
+    {
+        if *install_service {
+            return monitor::run_install_service(state);
+        }
+        if *daemon {
+            return monitor::run_in_background(pid_file.as_deref());
         }
-        Commands::Tx { id } => {
-            let tx_id = TransactionId::new_from_base36(&id);
-            if let Some(tx_id) = tx_id {
-                println!("{:#?}", client.get_transaction(&tx_id).await?);
+    }
+
+    // Discovery probes hosts itself instead of going through the node argument
+    if let Commands::Discover {
+        hosts,
+        ports,
+        timeout,
+    } = &args.command
+    {
+        return discover::run_discover(hosts, ports, *timeout, &out).await;
+    }
+
+    if let Commands::Check {
+        command:
+            CheckCommand::Time {
+                nodes,
+                ntp,
+                no_ntp,
+                tolerance,
+                timeout,
+            },
+    } = &args.command
+    {
+        return clock::run_check_time(
+            &config,
+            nodes.as_deref().or(args.node.as_deref()),
+            (!no_ntp).then_some(ntp.as_str()),
+            *tolerance,
+            *timeout,
+            &out,
+        )
+        .await;
+    }
+
+    // Mempool snapshots are compared offline, a node is only used to tell confirmed from dropped
+    if let Commands::Diff {
+        command: DiffCommand::Mempool { a, b },
+    } = &args.command
+    {
+        let client = match &args.node {
+            Some(node) => Some(node_pool(&config, Some(node)).await?.connect().await?),
+            None => None,
+        };
+        return mempool::run_diff(a, b, client.as_ref(), &out).await;
+    }
+
+    let (source, pool) = match &args.fixture {
+        Some(path) => (Source::Fixture(FixtureProvider::load(path)?), None),
+        None if args.auto_select => (
+            Source::Node(auto_select(&config, args.node.as_deref()).await?),
+            None,
+        ),
+        None => {
+            let pool = node_pool(&config, args.node.as_deref()).await?;
+            (Source::Node(pool.connect().await?), Some(pool))
+        }
+    };
+    let provider = source.provider();
+    // Daemon style commands fail over to the other nodes given
+    let failover = pool.as_ref().map(NodePool::addresses).unwrap_or_default();
+    match args.command {
+        Commands::Block {
+            id,
+            summary,
+            raw,
+            url,
+            open,
+        } => {
+            let link = Link { url, open };
+            query::run_block(provider, &config, &id, summary, raw, link, out).await?;
+        }
+        Commands::Tx { id, raw, url, open } => {
+            query::run_tx(&source, &config, &id, raw, Link { url, open }, out).await?;
+        }
+        Commands::Addr {
+            address,
+            limit,
+            offset,
+            sort,
+            summary,
+            balance_only,
+            utxos,
+            history,
+            maturity,
+            depth,
+            url,
+            open,
+        } => {
+            let view = if maturity {
+                AddressView::Maturity { depth }
+            } else if summary {
+                AddressView::Summary
             } else {
-                return Err(anyhow!(
-                    "Transaction identifier {id} is not valid. Expected base36 transaction id"
-                ));
-            }
+                AddressView::Sections {
+                    sections: AddressSections::from_flags(balance_only, utxos, history),
+                    sort,
+                    offset,
+                    limit,
+                }
+            };
+            let link = Link { url, open };
+            query::run_addr(&source, &config, &address, view, link, out, fiat).await?;
+        }
+        Commands::Height => query::run_height(provider, &out).await?,
+        Commands::Difficulty { command: None } => query::run_difficulty(provider, &out).await?,
+        Commands::Growth {
+            blocks,
+            window,
+            size,
+            days,
+        } => growth::run_growth(provider, blocks, window.get(), size, &days, &out).await?,
+        Commands::Conformance {
+            blocks,
+            period,
+            warn,
+            fail,
+            check,
+        } => {
+            conformance::run_conformance(provider, blocks, period.get(), warn, fail, check, &out)
+                .await?
+        }
+        Commands::Anomalies {
+            blocks,
+            sigma,
+            difficulty_jump,
+            share_window,
+            share_jump,
+        } => {
+            let options = anomalies::AnomalyOptions {
+                sigma,
+                difficulty_jump,
+                share_window: share_window.get(),
+                share_jump,
+            };
+            anomalies::run_anomalies(provider, blocks, &options, &out).await?;
         }
-        Commands::Addr { address } => {
-            let public = Public::new_from_base36(&address);
-            if let Some(public) = public {
-                println!(
-                    "Balance: {:#?} SNAP",
-                    to_snap(client.get_balance(public).await?)
-                );
-                let utxos = client.get_available_transaction_outputs(public).await?;
-                println!("Available UTXOS:\n{:#?}", utxos);
-                // println!("{}", to_snap(utxos.iter().fold(0, |acc, utxo| acc + utxo.1.amount)));
-                println!(
-                    "Transaction history (blocks):\n{:?}",
-                    client.get_transactions_of_address(public).await?
-                );
+        Commands::Screen {
+            list,
+            blocks,
+            watch: false,
+            ..
+        } => screen::run_screen(&source, &config, &list, blocks, &out).await?,
+        Commands::Screen {
+            list,
+            blocks,
+            watch: true,
+            interval,
+            notify,
+        } => {
+            let connection = source.into_reconnecting(failover)?;
+            screen::run_watch(&connection, &config, &list, blocks, interval, notify, &out).await?;
+        }
+        Commands::Tail {
+            command:
+                TailCommand::Txs {
+                    min_amount,
+                    blocks,
+                    interval,
+                },
+        } => {
+            let connection = source.into_reconnecting(failover)?;
+            tail::run_tail(&connection, &config, blocks, min_amount, interval, &out).await?;
+        }
+        Commands::Addresses {
+            blocks,
+            bucket,
+            sparkline,
+            plot_out,
+            plot_data,
+        } => {
+            let plots = PlotFiles {
+                image: plot_out,
+                data: plot_data,
+            };
+            addresses::run_addresses(provider, blocks, bucket, sparkline, &plots, &out).await?;
+        }
+        Commands::Tps {
+            blocks,
+            bucket,
+            sparkline,
+            plot_out,
+            plot_data,
+        } => {
+            let plots = PlotFiles {
+                image: plot_out,
+                data: plot_data,
+            };
+            tps::run_tps(provider, blocks, bucket, sparkline, &plots, &out).await?;
+        }
+        Commands::UtxoStats { blocks, dust } => {
+            utxos::run_utxo_stats(provider, blocks, dust, &out).await?
+        }
+        Commands::UtxoCommitment { at_height } => {
+            utxos::run_utxo_commitment(provider, at_height, &out).await?
+        }
+        Commands::Richlist {
+            top,
+            snapshot,
+            diff,
+        } => {
+            richlist::run_richlist(
+                provider,
+                &config,
+                top,
+                snapshot.as_deref(),
+                diff.as_deref(),
+                &out,
+            )
+            .await?
+        }
+        Commands::Fees { blocks } => fees::run_fees(&source, blocks, &out, fiat).await?,
+        Commands::Hashrate {
+            series,
+            blocks,
+            window,
+            plot_out,
+            plot_data,
+        } => {
+            let plots = PlotFiles {
+                image: plot_out,
+                data: plot_data,
+            };
+            hashrate::run_hashrate(provider, series, blocks, window.get(), &plots, &out).await?;
+        }
+        Commands::Mempool { snapshot } => {
+            mempool::run_mempool(source.client()?, snapshot.as_deref(), &out).await?
+        }
+        Commands::Averages {
+            compare: Some(ranges),
+            ..
+        } => compare::run_compare(provider, ranges[0].clone(), ranges[1].clone(), &out).await?,
+        Commands::Averages {
+            blocks: Some(blocks),
+            headers_only,
+            plot_out,
+            plot_data,
+            sparkline,
+            log_scale,
+            shared_scale,
+            max_rows,
+            outlier_sigma,
+            ..
+        } => {
+            let plots = PlotFiles {
+                image: plot_out,
+                data: plot_data,
+            };
+            let chart = ChartOptions {
+                sparkline,
+                plot: PlotOptions {
+                    log_scale,
+                    shared_scale,
+                    max_rows,
+                },
+                outlier_sigma,
+            };
+            if headers_only {
+                averages::run_header_averages(provider, blocks, &plots, chart, &out).await?;
             } else {
-                return Err(anyhow!(
-                    "Public address {address} is not valid. Expected base36 address"
-                ));
+                averages::run_averages(provider, blocks, &plots, chart, &out, fiat).await?;
             }
         }
-        Commands::Height => println!("Height: {}", client.get_height().await?),
-        Commands::Difficulty => {
-            println!(
-                "Block Difficulty: {}",
-                format_biguint_hr(&client.get_block_difficulty().await?)
-            );
-            println!(
-                "Transaction Difficulty: {}",
-                format_biguint_hr(&client.get_transaction_difficulty().await?)
-            );
-        }
-        Commands::Mempool => {
-            println!("Mempool:\n{:#?}", client.get_mempool().await?);
-        }
-        Commands::Averages { blocks } => {
-            let stats = averages::calculate_chain_stats(&client, blocks).await?;
-            let height = client.get_height().await?;
-
-            // Plot block times
-            let block_numbers: Vec<usize> =
-                (height - stats.tx_difficulty_series.len()..height).collect();
-            averages::plot_difficulties(
-                &block_numbers,
-                &stats.block_difficulty_series,
-                &stats.tx_difficulty_series,
-            );
-
-            // Optional: print top miners & addresses
-            println!("\nTop 10 Miners:");
-            for (addr, count) in &stats.top_miners {
-                println!(
-                    "{} -> {} blocks",
-                    Public::new_from_buf(addr).dump_base36(),
-                    count
-                );
+        Commands::Inflation { blocks } => averages::run_inflation(provider, blocks, &out).await?,
+        Commands::Burns {
+            blocks,
+            plot_out,
+            plot_data,
+            sparkline,
+        } => {
+            let plots = PlotFiles {
+                image: plot_out,
+                data: plot_data,
+            };
+            burns::run_burns(provider, &config, blocks, sparkline, &plots, &out).await?;
+        }
+        Commands::Miners {
+            blocks,
+            timing: true,
+            top,
+            ..
+        } => miners::run_timings(provider, blocks, top, &out).await?,
+        Commands::Miners {
+            blocks,
+            chart: true,
+            top,
+            pie_out,
+            ..
+        } => miners::run_chart(provider, &config, blocks, top, pie_out.as_deref(), &out).await?,
+        Commands::Miners {
+            blocks,
+            trend: false,
+            top,
+            ..
+        } => miners::run_shares(provider, &config, blocks, top, &out).await?,
+        Commands::Miners {
+            blocks,
+            trend: true,
+            timing: _,
+            chart: _,
+            pie_out: _,
+            window,
+            step,
+            top,
+            plot_out,
+            plot_data,
+        } => {
+            let plots = PlotFiles {
+                image: plot_out,
+                data: plot_data,
+            };
+            miners::run_trend(
+                provider,
+                blocks,
+                window.get(),
+                step.get(),
+                top,
+                &plots,
+                &out,
+            )
+            .await?;
+        }
+        Commands::Batch { file, jsonl } => {
+            batch::run_batch(source.client()?, &file, jsonl, &out).await?;
+        }
+        Commands::Audit {
+            command:
+                AuditCommand::Retargets {
+                    blocks,
+                    range,
+                    check,
+                },
+        } => audit::run_retarget_audit(provider, blocks, range, check, &out).await?,
+        Commands::Export {
+            command: ExportCommand::Difficulty { file, range },
+        } => export::run_difficulty_export(provider, range, &file).await?,
+        Commands::Export {
+            command: ExportCommand::Utxos { at_height, file },
+        } => export::run_utxo_export(provider, at_height, &file).await?,
+        Commands::Record { file, blocks } => fixture::run_record(provider, &file, blocks).await?,
+        Commands::BenchNode {
+            concurrency,
+            duration,
+            sample,
+        } => {
+            let levels: Vec<usize> = concurrency.iter().map(|level| level.get()).collect();
+            bench::run_bench(&source, &levels, duration, sample, &out).await?;
+        }
+        Commands::Stress {
+            duration,
+            concurrency,
+            mix,
+            sample,
+        } => stress::run_stress(&source, duration, concurrency.get(), &mix, sample, &out).await?,
+        Commands::Snapshot {
+            every,
+            out_dir,
+            blocks,
+        } => {
+            let connection = source.into_reconnecting(failover)?;
+            snapshot::run_snapshots(&connection, every, &out_dir, blocks).await?;
+        }
+        Commands::Monitor {
+            state,
+            address,
+            interval,
+            min_amount,
+            notify,
+            pid_file,
+            detached,
+            ..
+        } => {
+            let connection = source.into_reconnecting(failover)?;
+            let options = MonitorOptions {
+                state,
+                addresses: address,
+                interval,
+                min_amount,
+                notify,
+                pid_file,
+                detached,
+            };
+            monitor::run_monitor(&connection, &config, options).await?;
+        }
+        Commands::Serve {
+            command: ServeCommand::Grafana { listen, blocks },
+        } => grafana::run_serve(provider, listen, blocks).await?,
+        Commands::Orphans {
+            command: OrphansCommand::Watch { file },
+        } => {
+            let connection = source.into_reconnecting(failover)?;
+            orphans::run_watch(&connection, &file).await?;
+        }
+        Commands::Orphans {
+            command: OrphansCommand::Report { file, bucket },
+        } => orphans::run_report(provider, &file, bucket, &out).await?,
+        Commands::Tips { log } => tips::run_tips(provider, log.as_deref(), &out).await?,
+        Commands::Finality {
+            id,
+            attacker,
+            assurance,
+        } => finality::run_finality(&source, &id, attacker, assurance, &out).await?,
+        Commands::Luck {
+            miner,
+            blocks,
+            hashrate,
+            baseline,
+        } => {
+            let address = config.resolve_address(&miner);
+            luck::run_luck(provider, address, blocks, hashrate, baseline, &out).await?;
+        }
+        Commands::VerifyPayouts {
+            miner,
+            payout_address,
+            blocks,
+            share,
+            fee,
+        } => {
+            payouts::run_verify_payouts(
+                provider,
+                config.resolve_address(&miner),
+                config.resolve_address(&payout_address),
+                blocks,
+                share,
+                fee,
+                &out,
+            )
+            .await?
+        }
+        Commands::MineEta { hashrate } => eta::run_mine_eta(provider, hashrate, &out).await?,
+        Commands::Send {
+            to,
+            amount,
+            from,
+            keys_dir,
+            dry_run,
+        } => {
+            let keystore = Keystore::new(&keystore::resolve_dir(keys_dir.as_deref())?);
+            let to = config.resolve_address(&to);
+            send::run_send(
+                source.client()?,
+                &keystore,
+                &from,
+                to,
+                &amount,
+                dry_run,
+                &out,
+            )
+            .await?;
+        }
+        Commands::Rebroadcast { id, file } => {
+            send::run_rebroadcast(source.client()?, &id, file.as_deref(), &out).await?
+        }
+        Commands::Estimate {
+            command: EstimateCommand::Tx { inputs, outputs },
+        } => send::run_estimate(source.client()?, inputs, outputs, &out).await?,
+        Commands::TestAccept { tx } => accept::run_test_accept(source.client()?, &tx, &out).await?,
+        Commands::Portfolio { list } => {
+            portfolio::run_portfolio(source.client()?, &list, &out, fiat).await?
+        }
+        Commands::Wallet { file, command } => {
+            let path = wallet::resolve_path(file.as_deref())?;
+            let client = source.client()?;
+            match command {
+                WalletCommand::Balance => {
+                    wallet::run_balance(client, &path, &config, &out, fiat).await?
+                }
+                WalletCommand::Utxos => wallet::run_utxos(client, &path, &config, &out).await?,
+                WalletCommand::History => wallet::run_history(client, &path, &config, &out).await?,
+                WalletCommand::Add { .. } => unreachable!(),
             }
-
-            println!("\nTop 10 Addresses:");
-            for (addr, count) in &stats.top_addresses {
-                println!(
-                    "{} -> {} appearances",
-                    Public::new_from_buf(addr).dump_base36(),
-                    count
-                );
+        }
+        Commands::Diff {
+            command: DiffCommand::Block { a, b, other_node },
+        } => {
+            let other = match other_node {
+                Some(node) => Some(node_pool(&config, Some(&node)).await?.connect().await?),
+                // With several nodes given, B is looked up on another one of them
+                None => match (&pool, &source) {
+                    (Some(pool), Source::Node(client)) if pool.len() > 1 => {
+                        Some(pool.connect_except(client.node()).await?)
+                    }
+                    _ => None,
+                },
+            };
+            diff::run_block_diff(provider, other.as_ref(), &a, &b, &out).await?;
+        }
+        Commands::Script {
+            command: ScriptCommand::Run { file, args },
+        } => {
+            if let Some(result) = script::run_script(source, &file, args).await? {
+                out.json(&result)?;
             }
-
-            println!(
-                "\nAvg TXs/block: {:.2}, Avg IO/block: {:.2}, Avg block size: {:.2} bytes, TPS: {:.2}",
-                stats.avg_txs_per_block,
-                stats.avg_io_per_block,
-                stats.avg_block_size_bytes,
-                stats.tps
-            );
-
-            println!(
-                "Avg Block Difficulty: {:.2}, Avg TX Difficulty: {:.2}",
-                stats.avg_block_difficulty, stats.avg_tx_difficulty
-            );
-
-            println!(
-                "Block Time Avg: {:.2}s, Median: {:.2}s, Std Dev: {:.2}s, Min: {:.2}s, Max: {:.2}s",
-                stats.block_time.average,
-                stats.block_time.median,
-                stats.block_time.std_dev,
-                stats.block_time.min,
-                stats.block_time.max
-            );
         }
+        Commands::Averages { blocks: None, .. }
+        | Commands::Diff {
+            command: DiffCommand::Mempool { .. },
+        }
+        | Commands::Rewards
+        | Commands::Discover { .. }
+        | Commands::Check { .. }
+        | Commands::Alias { .. }
+        | Commands::Keys { .. }
+        | Commands::Convert { .. }
+        | Commands::Difficulty { command: Some(_) }
+        | Commands::Hash { .. }
+        | Commands::Encode { .. }
+        | Commands::Decode { .. }
+        | Commands::Keygen { .. }
+        | Commands::PaperWallet { .. }
+        | Commands::Completions { .. }
+        | Commands::External(_) => unreachable!(),
     }
 
     Ok(())
@@ -0,0 +1,160 @@
+// mempool.rs
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use snap_coin::{
+    blockchain_data_provider::BlockchainDataProvider,
+    core::transaction::{Transaction, TransactionId},
+};
+use std::{
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, info};
+
+use crate::{
+    color,
+    node::NodeClient,
+    output::{Output, OutputFormat},
+    tables,
+};
+
+/// Mempool contents at one point in time, written by `mempool --snapshot`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MempoolSnapshot {
+    /// Unix time the snapshot was taken
+    pub timestamp: u64,
+    pub height: usize,
+    pub transactions: Vec<Transaction>,
+}
+
+impl MempoolSnapshot {
+    pub async fn take(client: &NodeClient) -> Result<MempoolSnapshot> {
+        Ok(MempoolSnapshot {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            height: client.get_height().await?,
+            transactions: client.get_mempool().await?,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<MempoolSnapshot> {
+        let bytes = fs::read(path)
+            .with_context(|| format!("Could not read mempool snapshot {}", path.display()))?;
+        serde_json::from_slice(&bytes)
+            .with_context(|| format!("Could not parse mempool snapshot {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    fn ids(&self) -> Vec<String> {
+        self.transactions
+            .iter()
+            .filter_map(|tx| tx.transaction_id)
+            .map(|id| id.dump_base36())
+            .collect()
+    }
+}
+
+/// Transactions (base36 ids) that entered or left the mempool between two snapshots
+#[derive(Debug, Serialize)]
+pub struct MempoolDiff {
+    pub from_height: usize,
+    pub to_height: usize,
+    pub added: Vec<String>,
+    /// Left the mempool by being included in a block
+    pub confirmed: Vec<String>,
+    /// Left the mempool without being confirmed, or not checked against a node
+    pub removed: Vec<String>,
+    /// Present in both snapshots
+    pub unchanged: usize,
+}
+
+/// Compare two snapshots, transactions that left the pool are all reported as `removed`
+pub fn diff_mempools(a: &MempoolSnapshot, b: &MempoolSnapshot) -> MempoolDiff {
+    let (ids_a, ids_b) = (a.ids(), b.ids());
+    let removed: Vec<String> = ids_a
+        .iter()
+        .filter(|id| !ids_b.contains(id))
+        .cloned()
+        .collect();
+    let added: Vec<String> = ids_b
+        .iter()
+        .filter(|id| !ids_a.contains(id))
+        .cloned()
+        .collect();
+    MempoolDiff {
+        from_height: a.height,
+        to_height: b.height,
+        unchanged: ids_a.len() - removed.len(),
+        added,
+        confirmed: Vec::new(),
+        removed,
+    }
+}
+
+/// Move removed transactions the node knows as part of the chain over to `confirmed`
+pub async fn classify_confirmed(diff: &mut MempoolDiff, client: &NodeClient) -> Result<()> {
+    let mut removed = Vec::new();
+    for id in diff.removed.drain(..) {
+        debug!("get_transaction {}", id);
+        let confirmed = match TransactionId::new_from_base36(&id) {
+            Some(tx_id) => client.get_transaction(&tx_id).await?.is_some(),
+            None => false,
+        };
+        if confirmed {
+            diff.confirmed.push(id);
+        } else {
+            removed.push(id);
+        }
+    }
+    diff.removed = removed;
+    Ok(())
+}
+
+pub fn print_mempool_diff(diff: &MempoolDiff) {
+    println!("Heights: {} -> {}", diff.from_height, diff.to_height);
+    println!("Unchanged: {}", diff.unchanged);
+    for (label, ids) in [
+        ("Added", &diff.added),
+        ("Confirmed", &diff.confirmed),
+        ("Removed", &diff.removed),
+    ] {
+        println!("{} ({}):", label, ids.len());
+        for id in ids {
+            println!("  {}", color::hash(id));
+        }
+    }
+}
+
+/// Print the current mempool, saving it for `diff mempool` when `snapshot` is given
+pub async fn run_mempool(client: &NodeClient, snapshot: Option<&Path>, out: &Output) -> Result<()> {
+    let pool = MempoolSnapshot::take(client).await?;
+    if let Some(path) = snapshot {
+        pool.save(path)?;
+        info!("Saved mempool snapshot to {}", path.display());
+    }
+    let mempool = pool.transactions;
+    match out.format {
+        OutputFormat::Text => println!("Mempool:\n{}", tables::transactions_table(&mempool)),
+        OutputFormat::Debug => println!("Mempool:\n{:#?}", mempool),
+        OutputFormat::Json => out.json(&mempool)?,
+    }
+    Ok(())
+}
+
+/// Compare two mempool snapshots offline, `client` tells confirmed from dropped transactions
+pub async fn run_diff(a: &Path, b: &Path, client: Option<&NodeClient>, out: &Output) -> Result<()> {
+    let mut diff = diff_mempools(&MempoolSnapshot::load(a)?, &MempoolSnapshot::load(b)?);
+    if let Some(client) = client {
+        classify_confirmed(&mut diff, client).await?;
+    }
+    match out.format {
+        OutputFormat::Text => print_mempool_diff(&diff),
+        OutputFormat::Debug => println!("{:#?}", diff),
+        OutputFormat::Json => out.json(&diff)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,560 @@
+// miners.rs
+use anyhow::{Result, anyhow};
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::{
+    core::{block::Block, transaction::Transaction},
+    crypto::keys::Public,
+    economics::TARGET_TIME,
+};
+use std::{collections::HashMap, ops::Range, path::Path};
+use tracing::info;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    chart::{self, PlotFiles, Series},
+    color,
+    config::Config,
+    output::{Output, OutputFormat},
+    tables::{align_right, new_table},
+};
+
+/// The reward paying transaction of a block, the only one without inputs
+pub fn coinbase(block: &Block) -> Option<&Transaction> {
+    block.transactions.iter().find(|tx| tx.inputs.is_empty())
+}
+
+/// Who mined a block, the receiver of the second coinbase output
+pub fn block_miner(block: &Block) -> Option<[u8; 32]> {
+    coinbase(block)
+        .and_then(|tx| tx.outputs.get(1))
+        .map(|output| *output.receiver.dump_buf())
+}
+
+/// The miner of every block at `heights`, in height order
+pub async fn block_miners<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+) -> Result<Vec<Option<[u8; 32]>>> {
+    info!(
+        "Scanning blocks {}..{} for miners",
+        heights.start, heights.end
+    );
+    let mut miners = Vec::with_capacity(heights.len());
+    for_each_block(provider, heights, |_, block| {
+        miners.push(block_miner(&block));
+        Ok(())
+    })
+    .await?;
+    Ok(miners)
+}
+
+#[derive(Debug, Serialize)]
+pub struct MinerShare {
+    /// Base36 address, `None` for the sum of the miners not shown individually
+    pub miner: Option<String>,
+    pub blocks: usize,
+    /// Percentage of the blocks with a miner
+    pub share: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MinerShares {
+    pub start_height: usize,
+    pub end_height: usize,
+    pub miners: Vec<MinerShare>,
+}
+
+fn count_blocks(miners: &[Option<[u8; 32]>]) -> HashMap<[u8; 32], usize> {
+    let mut counts = HashMap::new();
+    for miner in miners.iter().flatten() {
+        *counts.entry(*miner).or_default() += 1;
+    }
+    counts
+}
+
+/// Miners ordered by blocks mined, ties broken by address so the order is stable
+fn ranked(counts: &HashMap<[u8; 32], usize>) -> Vec<([u8; 32], usize)> {
+    let mut ranked: Vec<_> = counts.iter().map(|(miner, n)| (*miner, *n)).collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    ranked
+}
+
+fn base36(miner: &[u8; 32]) -> String {
+    Public::new_from_buf(miner).dump_base36()
+}
+
+/// Share of the `top` biggest miners over blocks starting at `start_height`, the rest summed up
+pub fn miner_shares(start_height: usize, miners: &[Option<[u8; 32]>], top: usize) -> MinerShares {
+    let counts = count_blocks(miners);
+    let total: usize = counts.values().sum();
+    let share = |blocks: usize| blocks as f64 / total.max(1) as f64 * 100.0;
+    let ranked = ranked(&counts);
+    let mut shares: Vec<_> = ranked
+        .iter()
+        .take(top)
+        .map(|(miner, blocks)| MinerShare {
+            miner: Some(base36(miner)),
+            blocks: *blocks,
+            share: share(*blocks),
+        })
+        .collect();
+    let other: usize = ranked.iter().skip(top).map(|(_, blocks)| blocks).sum();
+    if other > 0 {
+        shares.push(MinerShare {
+            miner: None,
+            blocks: other,
+            share: share(other),
+        });
+    }
+    MinerShares {
+        start_height,
+        end_height: start_height + miners.len(),
+        miners: shares,
+    }
+}
+
+/// Shares with the label of every miner that has one, the label column is left out when none do
+pub fn shares_table(shares: &MinerShares, label: impl Fn(&str) -> Option<String>) -> Table {
+    let labels: Vec<Option<String>> = shares
+        .miners
+        .iter()
+        .map(|share| share.miner.as_deref().and_then(&label))
+        .collect();
+    let labeled = labels.iter().any(Option::is_some);
+    let mut table = if labeled {
+        new_table(&["Miner", "Label", "Blocks", "Share"])
+    } else {
+        new_table(&["Miner", "Blocks", "Share"])
+    };
+    for (share, label) in shares.miners.iter().zip(labels) {
+        let mut row = vec![
+            share
+                .miner
+                .as_ref()
+                .map(color::miner)
+                .unwrap_or_else(|| "Other".to_string()),
+        ];
+        if labeled {
+            row.push(label.unwrap_or_default());
+        }
+        row.push(share.blocks.to_string());
+        row.push(format!("{:.2}%", share.share));
+        table.add_row(row);
+    }
+    if labeled {
+        align_right(&mut table, &[2, 3]);
+    } else {
+        align_right(&mut table, &[1, 2]);
+    }
+    table
+}
+
+/// Every share named for a chart, by `label` where it has one and by a shortened address otherwise
+pub fn share_slices<L: Into<String>>(
+    shares: &MinerShares,
+    label: impl Fn(&str) -> Option<L>,
+) -> Vec<(String, f64)> {
+    shares
+        .miners
+        .iter()
+        .map(|share| {
+            let name = match &share.miner {
+                Some(miner) => label(miner)
+                    .map(Into::into)
+                    .unwrap_or_else(|| format!("{}...", &miner[..miner.len().min(12)])),
+                None => "Other".to_string(),
+            };
+            (name, share.share)
+        })
+        .collect()
+}
+
+/// The miner and timestamp of a block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinedBlock {
+    pub miner: Option<[u8; 32]>,
+    pub timestamp: u64,
+}
+
+/// The miner and timestamp of every block at `heights`, in height order, along with the timestamp
+/// of the block before them, `None` when the scan starts at genesis
+pub async fn mined_blocks<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+) -> Result<(Option<u64>, Vec<MinedBlock>)> {
+    info!(
+        "Scanning blocks {}..{} for miners and timestamps",
+        heights.start, heights.end
+    );
+    let parent = heights.start.checked_sub(1);
+    let mut parent_timestamp = None;
+    let mut blocks = Vec::with_capacity(heights.len());
+    for_each_block(
+        provider,
+        parent.unwrap_or(0)..heights.end,
+        |height, block| {
+            if Some(height) == parent {
+                parent_timestamp = Some(block.timestamp);
+            } else {
+                blocks.push(MinedBlock {
+                    miner: block_miner(&block),
+                    timestamp: block.timestamp,
+                });
+            }
+            Ok(())
+        },
+    )
+    .await?;
+    Ok((parent_timestamp, blocks))
+}
+
+/// How one miner timestamps its blocks
+#[derive(Debug, Serialize)]
+pub struct MinerTiming {
+    /// Base36 address, `None` for the miners not shown individually
+    pub miner: Option<String>,
+    pub blocks: usize,
+    /// Mean seconds between two consecutive blocks of the miner, `None` below two blocks
+    pub mean_interval: Option<f64>,
+    /// Mean seconds from the parent's timestamp to the block's, `None` without a parent
+    pub mean_offset: Option<f64>,
+    /// Blocks timestamped before their parent
+    pub backdated: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MinerTimings {
+    pub start_height: usize,
+    pub end_height: usize,
+    /// Mean offset of all blocks, what every miner's is compared with
+    pub network_offset: Option<f64>,
+    pub miners: Vec<MinerTiming>,
+}
+
+#[derive(Default)]
+struct TimingSums {
+    blocks: usize,
+    last: Option<u64>,
+    intervals: (f64, usize),
+    offsets: (f64, usize),
+    backdated: usize,
+}
+
+impl TimingSums {
+    fn add(&mut self, block: &MinedBlock, parent: Option<u64>) {
+        self.blocks += 1;
+        if let Some(last) = self.last.replace(block.timestamp) {
+            self.intervals.0 += block.timestamp as f64 - last as f64;
+            self.intervals.1 += 1;
+        }
+        if let Some(parent) = parent {
+            self.offsets.0 += block.timestamp as f64 - parent as f64;
+            self.offsets.1 += 1;
+            if block.timestamp < parent {
+                self.backdated += 1;
+            }
+        }
+    }
+
+    fn timing(&self, miner: Option<String>) -> MinerTiming {
+        let mean = |(sum, n): (f64, usize)| (n > 0).then(|| sum / n as f64);
+        MinerTiming {
+            miner,
+            blocks: self.blocks,
+            mean_interval: mean(self.intervals),
+            mean_offset: mean(self.offsets),
+            backdated: self.backdated,
+        }
+    }
+}
+
+/// Block intervals and timestamp offsets of the `top` biggest miners of `blocks`, starting at
+/// `start_height` after a parent timestamped `parent_timestamp`. A miner whose offsets stray from
+/// the network's is likely running a skewed clock or gaming the timestamps.
+pub fn miner_timings(
+    start_height: usize,
+    parent_timestamp: Option<u64>,
+    blocks: &[MinedBlock],
+    top: usize,
+) -> MinerTimings {
+    let miners: Vec<Option<[u8; 32]>> = blocks.iter().map(|block| block.miner).collect();
+    let tracked: Vec<[u8; 32]> = ranked(&count_blocks(&miners))
+        .into_iter()
+        .take(top)
+        .map(|(miner, _)| miner)
+        .collect();
+
+    let mut sums: HashMap<[u8; 32], TimingSums> = HashMap::new();
+    let mut other = TimingSums::default();
+    let mut network = TimingSums::default();
+    let mut parent = parent_timestamp;
+    for block in blocks {
+        network.add(block, parent);
+        match block.miner {
+            Some(miner) if tracked.contains(&miner) => {
+                sums.entry(miner).or_default().add(block, parent)
+            }
+            _ => other.add(block, parent),
+        }
+        parent = Some(block.timestamp);
+    }
+
+    let mut timings: Vec<_> = tracked
+        .iter()
+        .map(|miner| sums[miner].timing(Some(base36(miner))))
+        .collect();
+    if other.blocks > 0 {
+        let mut other = other.timing(None);
+        // Intervals only mean something between blocks of one miner
+        other.mean_interval = None;
+        timings.push(other);
+    }
+    MinerTimings {
+        start_height,
+        end_height: start_height + blocks.len(),
+        network_offset: network.timing(None).mean_offset,
+        miners: timings,
+    }
+}
+
+pub fn timings_table(timings: &MinerTimings) -> Table {
+    let secs = |secs: Option<f64>| {
+        secs.map(|secs| format!("{:.1}s", secs))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    let mut table = new_table(&[
+        "Miner",
+        "Blocks",
+        "Mean Interval",
+        "Mean Offset",
+        "Backdated",
+    ]);
+    for timing in &timings.miners {
+        table.add_row(vec![
+            timing
+                .miner
+                .as_ref()
+                .map(color::miner)
+                .unwrap_or_else(|| "Other".to_string()),
+            timing.blocks.to_string(),
+            secs(timing.mean_interval),
+            secs(timing.mean_offset),
+            timing.backdated.to_string(),
+        ]);
+    }
+    align_right(&mut table, &[1, 2, 3, 4]);
+    table
+}
+
+#[derive(Debug, Serialize)]
+pub struct MinerTrendLine {
+    /// Base36 address, `None` for the sum of the miners not shown individually
+    pub miner: Option<String>,
+    /// Percentage of the blocks of every window
+    pub shares: Vec<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MinerTrend {
+    pub window: usize,
+    pub step: usize,
+    /// Last height of every window
+    pub window_ends: Vec<usize>,
+    pub miners: Vec<MinerTrendLine>,
+}
+
+/// Share of the `top` biggest miners of the whole scan in windows of `window` blocks, `step` blocks apart
+pub fn miner_trend(
+    start_height: usize,
+    miners: &[Option<[u8; 32]>],
+    window: usize,
+    step: usize,
+    top: usize,
+) -> Result<MinerTrend> {
+    if window == 0 || step == 0 {
+        return Err(anyhow!("Window and step must be at least one block"));
+    }
+    if window > miners.len() {
+        return Err(anyhow!(
+            "A window of {} blocks doesn't fit in the {} scanned blocks",
+            window,
+            miners.len()
+        ));
+    }
+    let tracked: Vec<[u8; 32]> = ranked(&count_blocks(miners))
+        .into_iter()
+        .take(top)
+        .map(|(miner, _)| miner)
+        .collect();
+
+    let mut window_ends = Vec::new();
+    let mut lines: Vec<Vec<f64>> = vec![Vec::new(); tracked.len() + 1];
+    for offset in (0..=miners.len() - window).step_by(step) {
+        let counts = count_blocks(&miners[offset..offset + window]);
+        let total: usize = counts.values().sum();
+        let share = |blocks: usize| blocks as f64 / total.max(1) as f64 * 100.0;
+        let mut other = total;
+        for (line, miner) in lines.iter_mut().zip(&tracked) {
+            let blocks = counts.get(miner).copied().unwrap_or(0);
+            other -= blocks;
+            line.push(share(blocks));
+        }
+        lines[tracked.len()].push(share(other));
+        window_ends.push(start_height + offset + window - 1);
+    }
+
+    let mut miners: Vec<_> = tracked
+        .iter()
+        .zip(lines.iter())
+        .map(|(miner, shares)| MinerTrendLine {
+            miner: Some(base36(miner)),
+            shares: shares.clone(),
+        })
+        .collect();
+    let other = lines.pop().unwrap_or_default();
+    if other.iter().any(|share| *share > 0.0) {
+        miners.push(MinerTrendLine {
+            miner: None,
+            shares: other,
+        });
+    }
+    Ok(MinerTrend {
+        window,
+        step,
+        window_ends,
+        miners,
+    })
+}
+
+/// One series per trend line, against the last height of every window
+pub fn trend_series(trend: &MinerTrend) -> Vec<Series> {
+    trend
+        .miners
+        .iter()
+        .map(|line| Series {
+            name: match &line.miner {
+                Some(miner) => format!("{}... (%)", &miner[..miner.len().min(12)]),
+                None => "Other (%)".to_string(),
+            },
+            points: trend
+                .window_ends
+                .iter()
+                .copied()
+                .zip(line.shares.iter().copied())
+                .collect(),
+        })
+        .collect()
+}
+
+/// Print how far the `top` miners of the past `blocks` blocks timestamp their blocks from their parents
+pub async fn run_timings<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    blocks: usize,
+    top: usize,
+    out: &Output,
+) -> Result<()> {
+    let height = provider.get_height().await?;
+    let start = height.saturating_sub(blocks);
+    let (parent, mined) = mined_blocks(provider, start..height).await?;
+    let timings = miner_timings(start, parent, &mined, top);
+    match out.format {
+        OutputFormat::Text => {
+            println!("{}", timings_table(&timings));
+            if let Some(offset) = timings.network_offset {
+                println!(
+                    "Network mean offset: {:.1}s, {}s targeted",
+                    offset, TARGET_TIME
+                );
+            }
+        }
+        OutputFormat::Debug => println!("{:#?}", timings),
+        OutputFormat::Json => out.json(&timings)?,
+    }
+    Ok(())
+}
+
+/// Print the shares of the `top` miners as bars, and render them to a pie chart at `pie_out`
+pub async fn run_chart<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    config: &Config,
+    blocks: usize,
+    top: usize,
+    pie_out: Option<&Path>,
+    out: &Output,
+) -> Result<()> {
+    let height = provider.get_height().await?;
+    let start = height.saturating_sub(blocks);
+    let block_miners = block_miners(provider, start..height).await?;
+    let shares = miner_shares(start, &block_miners, top);
+    let slices = share_slices(&shares, |miner| config.display_label(miner));
+    let title = format!(
+        "Share of blocks {}..{}",
+        shares.start_height, shares.end_height
+    );
+    if let Some(path) = pie_out {
+        chart::render_pie(path, &title, &slices)?;
+    }
+    match out.format {
+        OutputFormat::Text => {
+            println!("{}:", title);
+            chart::print_bars(&slices);
+        }
+        OutputFormat::Debug => println!("{:#?}", shares),
+        OutputFormat::Json => out.json(&shares)?,
+    }
+    Ok(())
+}
+
+/// Print the `top` miners of the past `blocks` blocks and their shares
+pub async fn run_shares<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    config: &Config,
+    blocks: usize,
+    top: usize,
+    out: &Output,
+) -> Result<()> {
+    let height = provider.get_height().await?;
+    let start = height.saturating_sub(blocks);
+    let block_miners = block_miners(provider, start..height).await?;
+    let shares = miner_shares(start, &block_miners, top);
+    match out.format {
+        OutputFormat::Text => println!(
+            "{}",
+            shares_table(&shares, |miner| config.display_label(miner))
+        ),
+        OutputFormat::Debug => println!("{:#?}", shares),
+        OutputFormat::Json => out.json(&shares)?,
+    }
+    Ok(())
+}
+
+/// Print the shares of the `top` miners in windows of `window` blocks every `step` blocks
+pub async fn run_trend<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    blocks: usize,
+    window: usize,
+    step: usize,
+    top: usize,
+    plots: &PlotFiles,
+    out: &Output,
+) -> Result<()> {
+    let height = provider.get_height().await?;
+    let start = height.saturating_sub(blocks);
+    let block_miners = block_miners(provider, start..height).await?;
+    let trend = miner_trend(start, &block_miners, window, step, top)?;
+    let series = trend_series(&trend);
+    plots.write(&series)?;
+    match out.format {
+        OutputFormat::Text => {
+            println!(
+                "Share of blocks per {} block window, every {} blocks:",
+                trend.window, trend.step
+            );
+            chart::print_sparklines(&series);
+        }
+        OutputFormat::Debug => println!("{:#?}", trend),
+        OutputFormat::Json => out.json(&trend)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+// mnemonic.rs
+use anyhow::Result;
+use bip39::Mnemonic;
+use hmac::{Hmac, Mac};
+use rand::Rng;
+use serde_json::json;
+use sha2::Sha512;
+use snap_coin::crypto::keys::Private;
+
+use crate::{
+    color,
+    error::{ErrorKind, error},
+    output::{Output, OutputFormat},
+};
+
+/// Words of a generated phrase, carrying 256 bits of entropy
+pub const WORDS: usize = 24;
+
+/// A new random BIP39 phrase of `WORDS` English words
+pub fn generate() -> String {
+    let entropy: [u8; 32] = rand::rng().random();
+    // 32 bytes is a valid entropy length
+    Mnemonic::from_entropy(&entropy).unwrap().to_string()
+}
+
+/// The key of a BIP39 phrase: the SLIP-10 ed25519 master key of its seed, without a passphrase.
+/// Any valid BIP39 phrase works, whatever its length.
+pub fn private_key(phrase: &str) -> Result<Private> {
+    let mnemonic = Mnemonic::parse(phrase.trim()).map_err(|e| {
+        error(
+            ErrorKind::InvalidIdentifier,
+            format!("Mnemonic phrase is not valid: {e}"),
+        )
+    })?;
+    Ok(Private::new_from_buf(&master_key(&mnemonic.to_seed(""))))
+}
+
+/// SLIP-10 ed25519 master key of a seed, the left half of HMAC-SHA512 keyed with "ed25519 seed"
+pub fn master_key(seed: &[u8]) -> [u8; 32] {
+    // HMAC takes keys of any length
+    let mut mac = Hmac::<Sha512>::new_from_slice(b"ed25519 seed").unwrap();
+    mac.update(seed);
+    mac.finalize().into_bytes()[..32].try_into().unwrap()
+}
+
+/// Print a new random key pair, with the phrase it comes from when `mnemonic` is set
+pub fn run_keygen(mnemonic: bool, out: &Output) -> Result<()> {
+    let (private, phrase) = match mnemonic {
+        true => {
+            let phrase = generate();
+            (private_key(&phrase)?, Some(phrase))
+        }
+        false => (Private::new_random(), None),
+    };
+    let key = json!({
+        "address": private.to_public().dump_base36(),
+        "secret": private.dump_base36(),
+        "mnemonic": phrase,
+    });
+    match out.format {
+        OutputFormat::Text => {
+            println!(
+                "Address: {}",
+                color::hash(private.to_public().dump_base36())
+            );
+            println!("Secret: {}", private.dump_base36());
+            if let Some(phrase) = phrase {
+                println!("Mnemonic: {}", phrase);
+            }
+        }
+        OutputFormat::Debug => println!("{:#?}", key),
+        OutputFormat::Json => out.json(&key)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,594 @@
+// monitor.rs
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use snap_coin::{
+    core::transaction::{Transaction, TransactionId},
+    crypto::{Hash, keys::Public},
+};
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    fs,
+    io::{Write, stdout},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{Level, warn};
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    config::Config,
+    connection::ReconnectingClient,
+    miners::block_miner,
+    notify,
+    query::parse_address,
+    service,
+};
+
+/// Blocks kept in the state file to find where a reorg forked off, deeper reorgs are still
+/// reported but without the height they forked at
+pub const RECENT_BLOCKS: usize = 100;
+
+/// A block of the chain as the monitor last saw it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SeenTip {
+    pub height: usize,
+    pub hash: Hash,
+}
+
+/// What the monitor remembers across restarts
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MonitorState {
+    /// The last blocks seen, oldest first, the last one is the tip
+    pub recent: Vec<SeenTip>,
+    /// Base36 addresses alerted on
+    pub addresses: Vec<String>,
+    /// Transactions in the mempool at the last poll, so a restart doesn't report them again
+    pub mempool: HashSet<TransactionId>,
+    /// Nano a transaction has to move to raise a `whale` event, `None` for no whale events
+    #[serde(default)]
+    pub min_amount: Option<u64>,
+}
+
+impl MonitorState {
+    /// Read the state file, a missing one is a fresh state
+    pub fn load(path: &Path) -> Result<MonitorState> {
+        if !path.exists() {
+            return Ok(MonitorState::default());
+        }
+        let bytes = fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+        serde_json::from_slice(&bytes)
+            .with_context(|| format!("Could not parse monitor state {}", path.display()))
+    }
+
+    /// Write the state through a temporary file, so a process killed mid write leaves the previous state
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let partial = path.with_extension("tmp");
+        fs::write(&partial, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Could not write {}", partial.display()))?;
+        fs::rename(&partial, path)
+            .with_context(|| format!("Could not write {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn tip(&self) -> Option<SeenTip> {
+        self.recent.last().copied()
+    }
+
+    /// Add base36 addresses to the watched ones, returning how many were new
+    pub fn watch(&mut self, addresses: &[String]) -> usize {
+        let mut added = 0;
+        for address in addresses {
+            if !self.addresses.contains(address) {
+                self.addresses.push(address.clone());
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// The watched addresses as keys
+    pub fn watched(&self) -> Result<Vec<Public>> {
+        self.addresses
+            .iter()
+            .map(|address| {
+                Public::new_from_base36(address)
+                    .ok_or_else(|| anyhow!("Watched address {address} is not valid"))
+            })
+            .collect()
+    }
+
+    /// Forget all but the last `RECENT_BLOCKS` blocks
+    fn trim(&mut self) {
+        if self.recent.len() > RECENT_BLOCKS {
+            self.recent.drain(..self.recent.len() - RECENT_BLOCKS);
+        }
+    }
+}
+
+/// A sender or receiver of a whale transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Party {
+    pub address: String,
+    /// Address book label, filled in by `MonitorEvent::label`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Nano paid to a receiver, senders' spent amounts aren't looked up
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amount: Option<u64>,
+}
+
+impl Party {
+    /// The label, or the address without one
+    pub fn name(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.address)
+    }
+}
+
+/// One line of the monitor's output
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum MonitorEvent {
+    /// The monitor (re)started, from the tip of its state file when there is one
+    Started {
+        tip: Option<usize>,
+        addresses: usize,
+    },
+    /// The monitor was stopped, its state saved at `tip`
+    Stopped { tip: Option<usize> },
+    Block {
+        height: usize,
+        hash: String,
+        timestamp: u64,
+        transactions: usize,
+        /// Receiver of the block reward, `None` for a block without a coinbase
+        miner: Option<String>,
+    },
+    /// Blocks the monitor saw left the chain
+    Reorg {
+        /// Last block still on the chain, unknown when every recorded block left it
+        common_height: Option<usize>,
+        /// Recorded blocks no longer on the chain
+        orphaned: usize,
+        old_tip: String,
+    },
+    /// The mempool changed since the last poll
+    Mempool {
+        transactions: usize,
+        added: usize,
+        removed: usize,
+    },
+    /// A transaction paying or spending from a watched address
+    Alert {
+        address: String,
+        transaction: String,
+        /// Block the transaction was confirmed in, `None` while it's in the mempool
+        height: Option<usize>,
+        /// Nano paid to the address
+        received: u64,
+        /// Outputs of the address the transaction spends
+        spent_outputs: usize,
+        /// The transaction is a block reward
+        coinbase: bool,
+    },
+    /// A transaction moving at least the `--min-amount` rule
+    Whale {
+        transaction: String,
+        /// Block the transaction was confirmed in, `None` while it's in the mempool
+        height: Option<usize>,
+        /// Nano paid to others than the senders, change isn't counted
+        amount: u64,
+        senders: Vec<Party>,
+        receivers: Vec<Party>,
+    },
+}
+
+impl MonitorEvent {
+    /// Fill in the address book labels of the parties of a whale event
+    pub fn label(&mut self, label: impl Fn(&str) -> Option<String>) {
+        if let MonitorEvent::Whale {
+            senders, receivers, ..
+        } = self
+        {
+            for party in senders.iter_mut().chain(receivers) {
+                party.label = label(&party.address);
+            }
+        }
+    }
+}
+
+/// Print an event as one JSON line with the unix time it happened, flushed right away so log
+/// collectors see it. A detached monitor sends the line to the platform log instead.
+pub fn log_event<T: Serialize>(event: &T) -> Result<()> {
+    #[derive(Serialize)]
+    struct Line<'a, T> {
+        time: u64,
+        #[serde(flatten)]
+        event: &'a T,
+    }
+    let line = Line {
+        time: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        event,
+    };
+    let line = serde_json::to_string(&line)?;
+    if service::log_to_platform(Level::INFO, &line) {
+        return Ok(());
+    }
+    let mut out = stdout().lock();
+    writeln!(out, "{}", line)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Alerts for the watched addresses `tx` pays or spends from
+pub fn alerts(tx: &Transaction, height: Option<usize>, addresses: &[Public]) -> Vec<MonitorEvent> {
+    let Some(id) = tx.transaction_id else {
+        return Vec::new();
+    };
+    addresses
+        .iter()
+        .filter_map(|address| {
+            let received = tx
+                .outputs
+                .iter()
+                .filter(|output| output.receiver == *address)
+                .map(|output| output.amount)
+                .sum();
+            let spent_outputs = tx
+                .inputs
+                .iter()
+                .filter(|input| input.output_owner == *address)
+                .count();
+            (received > 0 || spent_outputs > 0).then(|| MonitorEvent::Alert {
+                address: address.dump_base36(),
+                transaction: id.dump_base36(),
+                height,
+                received,
+                spent_outputs,
+                coinbase: tx.inputs.is_empty(),
+            })
+        })
+        .collect()
+}
+
+/// Nano `tx` pays to addresses other than the ones it spends from, so change isn't counted
+pub fn moved_amount(tx: &Transaction) -> u64 {
+    tx.outputs
+        .iter()
+        .filter(|output| {
+            !tx.inputs
+                .iter()
+                .any(|input| input.output_owner == output.receiver)
+        })
+        .map(|output| output.amount)
+        .sum()
+}
+
+/// A whale event when `tx` moves at least `min_amount` nano. Block rewards aren't movements and
+/// never count.
+pub fn whale(tx: &Transaction, height: Option<usize>, min_amount: u64) -> Option<MonitorEvent> {
+    let id = tx.transaction_id?;
+    if tx.inputs.is_empty() {
+        return None;
+    }
+    let amount = moved_amount(tx);
+    if amount < min_amount {
+        return None;
+    }
+    let mut senders: Vec<Party> = Vec::new();
+    for input in &tx.inputs {
+        let address = input.output_owner.dump_base36();
+        if !senders.iter().any(|party| party.address == address) {
+            senders.push(Party {
+                address,
+                label: None,
+                amount: None,
+            });
+        }
+    }
+    let mut receivers: Vec<Party> = Vec::new();
+    for output in &tx.outputs {
+        let address = output.receiver.dump_base36();
+        if senders.iter().any(|party| party.address == address) {
+            continue;
+        }
+        match receivers.iter_mut().find(|party| party.address == address) {
+            Some(party) => party.amount = party.amount.map(|paid| paid + output.amount),
+            None => receivers.push(Party {
+                address,
+                label: None,
+                amount: Some(output.amount),
+            }),
+        }
+    }
+    Some(MonitorEvent::Whale {
+        transaction: id.dump_base36(),
+        height,
+        amount,
+        senders,
+        receivers,
+    })
+}
+
+/// Alerts for the watched addresses and the whale event of `tx`
+fn transaction_events(
+    tx: &Transaction,
+    height: Option<usize>,
+    addresses: &[Public],
+    min_amount: Option<u64>,
+) -> Vec<MonitorEvent> {
+    let mut events = alerts(tx, height, addresses);
+    events.extend(min_amount.and_then(|min_amount| whale(tx, height, min_amount)));
+    events
+}
+
+/// Bring `state` up to the provider's tip and return what happened on the way: a reorg when blocks
+/// it recorded left the chain, then every new block and the alerts and whale events of its
+/// transactions. A fresh state starts at the current tip instead of replaying the chain.
+pub async fn poll_chain<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    state: &mut MonitorState,
+) -> Result<Vec<MonitorEvent>> {
+    let height = provider.get_height().await?;
+    let mut events = Vec::new();
+    let Some(old_tip) = state.tip() else {
+        if height > 0
+            && let Some(hash) = provider.get_block_hash_by_height(height - 1).await?
+        {
+            state.recent.push(SeenTip {
+                height: height - 1,
+                hash,
+            });
+        }
+        return Ok(events);
+    };
+
+    // Worked on a copy, so a poll failing half way leaves the state as it was
+    let mut recent = state.recent.clone();
+    // The newest recorded block still on the chain, usually the tip itself
+    let mut common = None;
+    for (index, seen) in recent.iter().enumerate().rev() {
+        if seen.height < height
+            && provider.get_block_hash_by_height(seen.height).await? == Some(seen.hash)
+        {
+            common = Some(index);
+            break;
+        }
+    }
+    let next = match common {
+        Some(index) => recent[index].height + 1,
+        None => recent[0].height,
+    };
+    let orphaned = recent.len() - common.map_or(0, |index| index + 1);
+    if orphaned > 0 {
+        events.push(MonitorEvent::Reorg {
+            common_height: common.map(|index| recent[index].height),
+            orphaned,
+            old_tip: old_tip.hash.dump_base36(),
+        });
+        recent.truncate(common.map_or(0, |index| index + 1));
+    }
+
+    let addresses = state.watched()?;
+    let min_amount = state.min_amount;
+    for_each_block(provider, next..height, |height, block| {
+        let hash = block
+            .meta
+            .hash
+            .ok_or_else(|| anyhow!("Block {height} has no hash"))?;
+        events.push(MonitorEvent::Block {
+            height,
+            hash: hash.dump_base36(),
+            timestamp: block.timestamp,
+            transactions: block.transactions.len(),
+            miner: block_miner(&block).map(|miner| Public::new_from_buf(&miner).dump_base36()),
+        });
+        for tx in &block.transactions {
+            events.extend(transaction_events(tx, Some(height), &addresses, min_amount));
+        }
+        recent.push(SeenTip { height, hash });
+        Ok(())
+    })
+    .await?;
+    state.recent = recent;
+    state.trim();
+    Ok(events)
+}
+
+/// Compare `mempool` with the one recorded in `state`, returning a `Mempool` event when it changed
+/// and alerts and whale events for the transactions that entered it
+pub fn poll_mempool(
+    state: &mut MonitorState,
+    mempool: &[Transaction],
+) -> Result<Vec<MonitorEvent>> {
+    let addresses = state.watched()?;
+    let current: HashSet<TransactionId> =
+        mempool.iter().filter_map(|tx| tx.transaction_id).collect();
+    let mut events = Vec::new();
+    let added: Vec<&Transaction> = mempool
+        .iter()
+        .filter(|tx| {
+            tx.transaction_id
+                .is_some_and(|id| !state.mempool.contains(&id))
+        })
+        .collect();
+    let removed = state.mempool.difference(&current).count();
+    if !added.is_empty() || removed > 0 {
+        events.push(MonitorEvent::Mempool {
+            transactions: current.len(),
+            added: added.len(),
+            removed,
+        });
+    }
+    for tx in added {
+        events.extend(transaction_events(tx, None, &addresses, state.min_amount));
+    }
+    state.mempool = current;
+    Ok(events)
+}
+
+/// One poll of the daemon: the blocks since the recorded tip, then the changes of `mempool`. The
+/// mempool is fetched before the chain poll, so nothing fails after that has moved the state.
+pub async fn poll<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    state: &mut MonitorState,
+    mempool: &[Transaction],
+) -> Result<Vec<MonitorEvent>> {
+    let mut events = poll_chain(provider, state).await?;
+    events.extend(poll_mempool(state, mempool)?);
+    Ok(events)
+}
+
+/// Poll the node every `interval` until dropped, handing the events to `on_event` and saving
+/// `state` to `path` after each poll. The state is only saved once its events are out, so a
+/// restart repeats events rather than missing them.
+pub async fn watch(
+    connection: &ReconnectingClient,
+    state: &mut MonitorState,
+    path: &Path,
+    interval: Duration,
+    mut on_event: impl FnMut(MonitorEvent, &MonitorState) -> Result<()>,
+) -> Result<()> {
+    connection
+        .poll_every(
+            interval,
+            "Monitor poll",
+            state,
+            async |client, state| {
+                let mempool = client.get_mempool().await?;
+                poll(client, state, &mempool).await
+            },
+            |events, state| {
+                for event in events {
+                    on_event(event, state)?;
+                }
+                state.save(path)
+            },
+        )
+        .await
+}
+
+/// How `monitor` runs, as given on its command line
+pub struct MonitorOptions {
+    pub state: PathBuf,
+    pub addresses: Vec<String>,
+    pub interval: Duration,
+    /// Replaces the whale threshold kept in the state, 0 turns whale alerts off
+    pub min_amount: Option<u64>,
+    pub notify: bool,
+    pub pid_file: Option<PathBuf>,
+    /// Started by `--daemon` or `--install-service` rather than by hand
+    pub detached: bool,
+}
+
+/// Install the monitor as a Windows service running the same command line
+pub fn run_install_service(state: &Path) -> Result<()> {
+    if !state.is_absolute() {
+        return Err(anyhow!(
+            "--install-service needs an absolute --state path, services don't start in this directory"
+        ));
+    }
+    let command_line: Vec<OsString> = std::env::args_os().collect();
+    service::install(service::detached_args(
+        &command_line,
+        &["--install-service"],
+        &["--detached"],
+    ))?;
+    println!(
+        "Installed the {} service, start it with `sc start {}`",
+        service::SERVICE_NAME,
+        service::SERVICE_NAME
+    );
+    Ok(())
+}
+
+/// Start the same command line again in the background, unless the pid file names a running monitor
+pub fn run_in_background(pid_file: Option<&Path>) -> Result<()> {
+    if let Some(path) = pid_file
+        && let Some(pid) = service::running_pid(path)?
+    {
+        return Err(anyhow!(
+            "{} says the monitor already runs as pid {}",
+            path.display(),
+            pid
+        ));
+    }
+    let command_line: Vec<OsString> = std::env::args_os().collect();
+    let pid = service::daemonize(&service::detached_args(
+        &command_line,
+        &["--daemon"],
+        &["--detached"],
+    ))?;
+    println!("Monitor running in the background as pid {}", pid);
+    Ok(())
+}
+
+/// Watch the chain and mempool until stopped, logging every event and keeping the state file
+/// current so a restart picks up where this run left off
+pub async fn run_monitor(
+    connection: &ReconnectingClient,
+    config: &Config,
+    options: MonitorOptions,
+) -> Result<()> {
+    notify::check_available(options.notify)?;
+    let _pid_file = options
+        .pid_file
+        .as_deref()
+        .map(service::PidFile::create)
+        .transpose()?;
+    let service = options.detached.then(service::start_service);
+    for address in &options.addresses {
+        parse_address(address)?;
+    }
+    let mut state = MonitorState::load(&options.state)?;
+    let mut changed = state.watch(&options.addresses) > 0;
+    if let Some(min_amount) = options.min_amount {
+        let min_amount = (min_amount > 0).then_some(min_amount);
+        changed |= state.min_amount != min_amount;
+        state.min_amount = min_amount;
+    }
+    if changed {
+        state.save(&options.state)?;
+    }
+    let mut connection_events = connection.subscribe();
+    tokio::spawn(async move {
+        while let Ok(event) = connection_events.recv().await {
+            if let Err(e) = log_event(&event) {
+                warn!("Could not log a connection event: {:#}", e);
+            }
+        }
+    });
+    log_event(&MonitorEvent::Started {
+        tip: state.tip().map(|tip| tip.height),
+        addresses: state.addresses.len(),
+    })?;
+    let label = |address: &str| config.display_label(address);
+    service::run_daemon(watch(
+        connection,
+        &mut state,
+        &options.state,
+        options.interval,
+        |mut event, state| {
+            event.label(label);
+            log_event(&event)?;
+            if options.notify
+                && let Some(notification) =
+                    notify::for_monitor_event(&event, &state.addresses, label)
+            {
+                notify::show(notification);
+            }
+            Ok(())
+        },
+    ))
+    .await?;
+    // Polls are only ever interrupted before they change the state, so this is the last
+    // complete one
+    state.save(&options.state)?;
+    log_event(&MonitorEvent::Stopped {
+        tip: state.tip().map(|tip| tip.height),
+    })?;
+    if let Some(service) = service {
+        service.stopped().await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,171 @@
+// node.rs
+use async_trait::async_trait;
+use snap_coin::{
+    api::{
+        client::Client,
+        requests::{Request, RequestResponseError, Response},
+    },
+    blockchain_data_provider::{BlockchainDataProvider, BlockchainDataProviderError},
+    core::{
+        block::Block,
+        blockchain::BlockchainError,
+        transaction::{Transaction, TransactionId, TransactionOutput},
+    },
+    crypto::{Hash, keys::Public},
+    full_node::node_state::ChainEvent,
+};
+use std::{io, net::SocketAddr};
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+
+use crate::limits;
+
+/// Connection to a node that every request of the tool goes through, so `--rate-limit` covers all of them
+pub struct NodeClient {
+    client: Client,
+}
+
+impl NodeClient {
+    pub async fn connect(node: SocketAddr) -> io::Result<NodeClient> {
+        Ok(NodeClient {
+            client: Client::connect(node).await?,
+        })
+    }
+
+    /// Address of the connected node
+    pub fn node(&self) -> SocketAddr {
+        self.client.node
+    }
+
+    pub async fn get_mempool(&self) -> Result<Vec<Transaction>, BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_mempool().await
+    }
+
+    pub async fn get_transaction(
+        &self,
+        transaction_id: &TransactionId,
+    ) -> Result<Option<Transaction>, BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_transaction(transaction_id).await
+    }
+
+    pub async fn get_balance(&self, address: Public) -> Result<u64, BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_balance(address).await
+    }
+
+    pub async fn get_transactions_of_address(
+        &self,
+        address: Public,
+    ) -> Result<Vec<Hash>, BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_transactions_of_address(address).await
+    }
+
+    /// Transaction difficulty raised by the current mempool pressure, what new transactions are mined against
+    pub async fn get_live_transaction_difficulty(
+        &self,
+    ) -> Result<[u8; 32], BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_live_transaction_difficulty().await
+    }
+
+    /// Hand a mined transaction to the node, the inner result is the node's verdict on it
+    pub async fn submit_transaction(
+        &self,
+        transaction: Transaction,
+    ) -> Result<Result<(), BlockchainError>, BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.submit_transaction(transaction).await
+    }
+
+    /// Subscribe to new blocks and transactions, pushed by the node on a connection of their own
+    pub async fn chain_events(&self) -> io::Result<ChainEvents> {
+        let request = Request::SubscribeToChainEvents
+            .encode()
+            .map_err(io::Error::other)?;
+        let mut stream = TcpStream::connect(self.node()).await?;
+        limits::acquire(1).await;
+        stream.write_all(&request).await?;
+        Ok(ChainEvents { stream })
+    }
+}
+
+/// Events of a `NodeClient::chain_events` subscription, in the order the node saw them
+pub struct ChainEvents {
+    stream: TcpStream,
+}
+
+impl ChainEvents {
+    /// Wait for the next event, a `Stream` error means the subscription ended
+    pub async fn next(&mut self) -> Result<ChainEvent, RequestResponseError> {
+        match Response::decode_from_stream(&mut self.stream).await? {
+            Response::ChainEvent { event } => Ok(event),
+            _ => Err(RequestResponseError::IncorrectResponse),
+        }
+    }
+}
+
+#[async_trait]
+impl BlockchainDataProvider for NodeClient {
+    async fn get_height(&self) -> Result<usize, BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_height().await
+    }
+
+    async fn get_reward(&self) -> Result<u64, BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_reward().await
+    }
+
+    /// Looks up the hash first, so this costs two requests
+    async fn get_block_by_height(
+        &self,
+        height: usize,
+    ) -> Result<Option<Block>, BlockchainDataProviderError> {
+        limits::acquire(2).await;
+        self.client.get_block_by_height(height).await
+    }
+
+    async fn get_block_by_hash(
+        &self,
+        hash: Hash,
+    ) -> Result<Option<Block>, BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_block_by_hash(hash).await
+    }
+
+    async fn get_height_by_hash(
+        &self,
+        hash: Hash,
+    ) -> Result<Option<usize>, BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_height_by_hash(hash).await
+    }
+
+    async fn get_block_hash_by_height(
+        &self,
+        height: usize,
+    ) -> Result<Option<Hash>, BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_block_hash_by_height(height).await
+    }
+
+    async fn get_transaction_difficulty(&self) -> Result<[u8; 32], BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_transaction_difficulty().await
+    }
+
+    async fn get_block_difficulty(&self) -> Result<[u8; 32], BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_block_difficulty().await
+    }
+
+    async fn get_available_transaction_outputs(
+        &self,
+        address: Public,
+    ) -> Result<Vec<(TransactionId, TransactionOutput, usize)>, BlockchainDataProviderError> {
+        limits::acquire(1).await;
+        self.client.get_available_transaction_outputs(address).await
+    }
+}
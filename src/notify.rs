@@ -0,0 +1,142 @@
+// notify.rs
+use anyhow::{Result, anyhow};
+use tracing::{debug, warn};
+
+use crate::{
+    format_snap,
+    monitor::{MonitorEvent, Party},
+    screen::{Direction, ScreenHit},
+};
+
+/// A native desktop notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopNotification {
+    pub summary: String,
+    pub body: String,
+}
+
+/// Whether this build can show desktop notifications, see the `desktop-notifications` feature
+pub const AVAILABLE: bool = cfg!(feature = "desktop-notifications");
+
+/// Fail early when desktop notifications are asked for but left out of the build
+pub fn check_available(notify: bool) -> Result<()> {
+    if notify && !AVAILABLE {
+        return Err(anyhow!(
+            "--notify needs a build with the desktop-notifications feature"
+        ));
+    }
+    Ok(())
+}
+
+/// The notification a monitor event is worth: a block mined by one of `addresses`, a payment
+/// to one of them or a whale transaction. Block rewards are announced by their block, not again
+/// as a payment.
+pub fn for_monitor_event(
+    event: &MonitorEvent,
+    addresses: &[String],
+    label: impl Fn(&str) -> Option<String>,
+) -> Option<DesktopNotification> {
+    let name = |address: &str| label(address).unwrap_or_else(|| address.to_string());
+    match event {
+        MonitorEvent::Block {
+            height,
+            transactions,
+            miner: Some(miner),
+            ..
+        } if addresses.contains(miner) => Some(DesktopNotification {
+            summary: format!("Block {} mined", height),
+            body: format!(
+                "{} found a block with {} transactions",
+                name(miner),
+                transactions
+            ),
+        }),
+        MonitorEvent::Alert {
+            address,
+            height,
+            received,
+            coinbase: false,
+            ..
+        } if *received > 0 => Some(DesktopNotification {
+            summary: match height {
+                Some(height) => format!("Payment confirmed in block {}", height),
+                None => "Incoming payment".to_string(),
+            },
+            body: format!("{} SNAP to {}", format_snap(*received), name(address)),
+        }),
+        MonitorEvent::Whale {
+            height,
+            amount,
+            senders,
+            receivers,
+            ..
+        } => Some(DesktopNotification {
+            summary: match height {
+                Some(height) => format!("Whale transaction in block {}", height),
+                None => "Whale transaction in the mempool".to_string(),
+            },
+            body: format!(
+                "{} SNAP from {} to {}",
+                format_snap(*amount),
+                names(senders),
+                names(receivers)
+            ),
+        }),
+        _ => None,
+    }
+}
+
+/// `a, b` for a few parties, `a, b and 3 more` for many
+fn names(parties: &[Party]) -> String {
+    let shown: Vec<&str> = parties.iter().take(2).map(Party::name).collect();
+    match parties.len() {
+        0..=2 => shown.join(", "),
+        n => format!("{} and {} more", shown.join(", "), n - 2),
+    }
+}
+
+/// The notification of a screening hit paying a watchlist address
+pub fn for_screen_hit(
+    hit: &ScreenHit,
+    label: impl Fn(&str) -> Option<String>,
+) -> Option<DesktopNotification> {
+    if hit.direction != Direction::Incoming {
+        return None;
+    }
+    let place = match hit.height {
+        Some(height) => format!("block {}", height),
+        None => "the mempool".to_string(),
+    };
+    Some(DesktopNotification {
+        summary: "Watchlist address paid".to_string(),
+        body: format!(
+            "{} SNAP to {} in {}",
+            hit.amount
+                .map(format_snap)
+                .unwrap_or_else(|| "?".to_string()),
+            label(&hit.address).unwrap_or_else(|| hit.address.clone()),
+            place
+        ),
+    })
+}
+
+/// Show a notification without waiting for it, a desktop that can't show it only gets a warning
+pub fn show(notification: DesktopNotification) {
+    debug!("Notifying {:?}", notification);
+    #[cfg(feature = "desktop-notifications")]
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = notify_rust::Notification::new()
+            .appname("snap-coin-utils")
+            .summary(&notification.summary)
+            .body(&notification.body)
+            .show()
+        {
+            warn!("Could not show a desktop notification: {}", e);
+        }
+    });
+    #[cfg(not(feature = "desktop-notifications"))]
+    warn!(
+        "Built without desktop notifications, not showing {}",
+        notification.summary
+    );
+}
@@ -0,0 +1,237 @@
+// orphans.rs
+use anyhow::{Context, Result};
+use comfy_table::Table;
+use serde::{Deserialize, Serialize};
+use snap_coin::{
+    blockchain_data_provider::BlockchainDataProvider, crypto::Hash,
+    full_node::node_state::ChainEvent,
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, info, warn};
+
+use crate::{
+    color,
+    connection::{self, ReconnectingClient},
+    node::NodeClient,
+    output::{Output, OutputFormat},
+    service,
+    systemd::Watchdog,
+    tables::{align_right, new_table},
+};
+
+/// A block as announced by the node, one JSON line of the watch log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeenBlock {
+    /// Unix time the announcement arrived
+    pub seen: u64,
+    pub hash: Hash,
+    pub previous: Hash,
+}
+
+/// Append every block the node announces to `path` until the subscription ends.
+/// The node has no request for stale blocks, so a log of announcements is what orphans are found in.
+pub async fn watch_blocks(client: &NodeClient, path: &Path) -> Result<()> {
+    let mut events = client.chain_events().await?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Could not open {}", path.display()))?;
+    info!("Recording announced blocks to {}", path.display());
+    let mut watchdog = Watchdog::from_env();
+    loop {
+        // Reading an event can't be cancelled halfway, so the same read is polled between pings
+        let next = events.next();
+        tokio::pin!(next);
+        let event = loop {
+            tokio::select! {
+                event = &mut next => break event?,
+                _ = watchdog.ping() => {}
+            }
+        };
+        let ChainEvent::Block { block } = event else {
+            continue;
+        };
+        let Some(hash) = block.meta.hash else {
+            continue;
+        };
+        let seen = SeenBlock {
+            seen: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            hash,
+            previous: block.meta.previous_block,
+        };
+        debug!("Block {} announced", seen.hash.dump_base36());
+        writeln!(file, "{}", serde_json::to_string(&seen)?)?;
+        file.flush()?;
+    }
+}
+
+/// Read a watch log, skipping blocks announced more than once
+pub fn load_seen_blocks(path: &Path) -> Result<Vec<SeenBlock>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+    let mut hashes = HashSet::new();
+    let mut blocks = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let block: SeenBlock = serde_json::from_str(line)
+            .with_context(|| format!("Invalid line {} in {}", number + 1, path.display()))?;
+        if hashes.insert(block.hash) {
+            blocks.push(block);
+        }
+    }
+    Ok(blocks)
+}
+
+#[derive(Debug, Serialize)]
+pub struct Orphan {
+    /// Height the block was mined at, unknown when its parent is off the main chain too
+    pub height: Option<usize>,
+    pub hash: Hash,
+    pub seen: u64,
+}
+
+/// Announced and orphaned blocks in one period of the watch log
+#[derive(Debug, Serialize)]
+pub struct OrphanBucket {
+    /// Unix time the period starts at
+    pub start: u64,
+    pub blocks: usize,
+    pub orphans: usize,
+    /// Percentage of the announced blocks that left the main chain
+    pub rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrphanReport {
+    pub blocks: usize,
+    pub orphans: Vec<Orphan>,
+    pub rate: f64,
+    pub buckets: Vec<OrphanBucket>,
+}
+
+/// Check every announced block against the node's main chain, grouping by `bucket` of announcement time
+pub async fn orphan_report<P: BlockchainDataProvider + ?Sized>(
+    provider: &P,
+    seen: &[SeenBlock],
+    bucket: Duration,
+) -> Result<OrphanReport> {
+    let bucket_secs = bucket.as_secs().max(1);
+    let mut orphans = Vec::new();
+    let mut buckets: BTreeMap<u64, (usize, usize)> = BTreeMap::new();
+    for block in seen {
+        let counts = buckets
+            .entry(block.seen / bucket_secs * bucket_secs)
+            .or_default();
+        counts.0 += 1;
+        if provider.get_height_by_hash(block.hash).await?.is_some() {
+            continue;
+        }
+        counts.1 += 1;
+        let height = provider
+            .get_height_by_hash(block.previous)
+            .await?
+            .map(|parent| parent + 1);
+        orphans.push(Orphan {
+            height,
+            hash: block.hash,
+            seen: block.seen,
+        });
+    }
+
+    let rate = |blocks: usize, orphans: usize| orphans as f64 / blocks.max(1) as f64 * 100.0;
+    Ok(OrphanReport {
+        blocks: seen.len(),
+        rate: rate(seen.len(), orphans.len()),
+        orphans,
+        buckets: buckets
+            .into_iter()
+            .map(|(start, (blocks, orphans))| OrphanBucket {
+                start,
+                blocks,
+                orphans,
+                rate: rate(blocks, orphans),
+            })
+            .collect(),
+    })
+}
+
+pub fn buckets_table(report: &OrphanReport) -> Table {
+    let mut table = new_table(&["Since (unix)", "Blocks", "Orphans", "Rate"]);
+    for bucket in &report.buckets {
+        table.add_row(vec![
+            bucket.start.to_string(),
+            bucket.blocks.to_string(),
+            bucket.orphans.to_string(),
+            format!("{:.2}%", bucket.rate),
+        ]);
+    }
+    align_right(&mut table, &[1, 2, 3]);
+    table
+}
+
+pub fn print_orphan_report(report: &OrphanReport) {
+    println!("{}", buckets_table(report));
+    if !report.orphans.is_empty() {
+        println!("\nOrphaned blocks:");
+        for orphan in &report.orphans {
+            let height = orphan
+                .height
+                .map(|height| height.to_string())
+                .unwrap_or_else(|| "?".to_string());
+            println!("{:>8}  {}", height, color::hash(orphan.hash.dump_base36()));
+        }
+    }
+    println!(
+        "\n{} of {} announced blocks orphaned ({:.2}%)",
+        report.orphans.len(),
+        report.blocks,
+        report.rate
+    );
+}
+
+/// Append every block the node sees to `path` until stopped, resubscribing when the connection drops
+pub async fn run_watch(connection: &ReconnectingClient, path: &Path) -> Result<()> {
+    connection.log_events();
+    service::run_daemon(async {
+        loop {
+            // The subscription only ends when the connection drops, the next run reconnects
+            if let Err(e) = connection
+                .run(async |client| watch_blocks(client, path).await)
+                .await
+            {
+                warn!("Watching blocks failed: {:#}", e);
+                if !connection::is_connection_lost(&e) {
+                    return Err(e);
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    })
+    .await
+}
+
+/// Print the orphans among the blocks logged to `file`, counted in buckets of `bucket`
+pub async fn run_report<P: BlockchainDataProvider + ?Sized>(
+    provider: &P,
+    file: &Path,
+    bucket: Duration,
+    out: &Output,
+) -> Result<()> {
+    let seen = load_seen_blocks(file)?;
+    let report = orphan_report(provider, &seen, bucket).await?;
+    match out.format {
+        OutputFormat::Text => print_orphan_report(&report),
+        OutputFormat::Debug => println!("{:#?}", report),
+        OutputFormat::Json => out.json(&report)?,
+    }
+    Ok(())
+}
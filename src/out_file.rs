@@ -0,0 +1,103 @@
+// out_file.rs
+use anyhow::{Context, Result};
+use flate2::{Compression, write::GzEncoder};
+use std::{
+    fs::File,
+    io::{self, Write, stdout},
+    path::Path,
+    thread::JoinHandle,
+};
+
+/// File writer, compressed according to the file extension
+enum Writer {
+    Plain(File),
+    Gzip(GzEncoder<File>),
+    Zstd(zstd::Encoder<'static, File>),
+}
+
+impl Writer {
+    fn create(path: &Path) -> Result<Writer> {
+        let file =
+            File::create(path).with_context(|| format!("Could not create {}", path.display()))?;
+        Ok(match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Writer::Gzip(GzEncoder::new(file, Compression::default())),
+            Some("zst") => Writer::Zstd(zstd::Encoder::new(file, 0)?),
+            _ => Writer::Plain(file),
+        })
+    }
+
+    fn inner(&mut self) -> &mut dyn Write {
+        match self {
+            Writer::Plain(file) => file,
+            Writer::Gzip(encoder) => encoder,
+            Writer::Zstd(encoder) => encoder,
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Writer::Plain(mut file) => file.flush(),
+            Writer::Gzip(encoder) => encoder.finish().map(|_| ()),
+            Writer::Zstd(encoder) => encoder.finish().map(|_| ()),
+        }
+    }
+}
+
+/// Redirects stdout into a file for the lifetime of the guard
+pub struct OutFile {
+    thread: Option<JoinHandle<io::Result<()>>>,
+}
+
+/// Point stdout at `path` so every command can write exports without shell redirection
+pub fn open(path: Option<&Path>) -> Result<OutFile> {
+    let Some(path) = path else {
+        return Ok(OutFile { thread: None });
+    };
+    let writer = Writer::create(path)?;
+    Ok(OutFile {
+        thread: Some(redirect_stdout(writer)?),
+    })
+}
+
+#[cfg(unix)]
+fn redirect_stdout(mut writer: Writer) -> Result<JoinHandle<io::Result<()>>> {
+    use std::os::fd::FromRawFd;
+
+    let mut fds = [0; 2];
+    // SAFETY: fds has room for both ends, the write end replaces stdout and the read end is owned by the copy thread
+    let mut reader = unsafe {
+        if libc::pipe(fds.as_mut_ptr()) < 0
+            || libc::dup2(fds[1], libc::STDOUT_FILENO) < 0
+            || libc::close(fds[1]) < 0
+        {
+            return Err(io::Error::last_os_error().into());
+        }
+        File::from_raw_fd(fds[0])
+    };
+
+    Ok(std::thread::spawn(move || {
+        io::copy(&mut reader, writer.inner())?;
+        writer.finish()
+    }))
+}
+
+#[cfg(not(unix))]
+fn redirect_stdout(_writer: Writer) -> Result<JoinHandle<io::Result<()>>> {
+    Err(anyhow::anyhow!("--out is only supported on unix platforms"))
+}
+
+impl Drop for OutFile {
+    fn drop(&mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = stdout().flush();
+            // Closing stdout ends the copy, then wait for the file to be fully written
+            #[cfg(unix)]
+            unsafe {
+                libc::close(libc::STDOUT_FILENO);
+            }
+            if let Ok(Err(e)) = thread.join() {
+                tracing::error!("Failed to write output file: {}", e);
+            }
+        }
+    }
+}
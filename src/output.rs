@@ -0,0 +1,195 @@
+// output.rs
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{fmt::Debug, fs, path::Path};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable output
+    Text,
+    /// Machine readable JSON
+    Json,
+    /// Raw Rust debug dumps of the underlying data
+    Debug,
+}
+
+/// Where and how command results are printed
+pub struct Output {
+    pub format: OutputFormat,
+    select: Option<Selector>,
+    template: Option<String>,
+    /// Print JSON compactly, one value per line
+    lines: bool,
+}
+
+impl Output {
+    /// Select expressions and templates work on the JSON representation, so they always imply JSON
+    pub fn new(format: OutputFormat, select: Option<&str>, template: Option<&str>) -> Result<Self> {
+        let select = select.map(Selector::parse).transpose()?;
+        let template = template.map(load_template).transpose()?;
+        let format = if select.is_some() || template.is_some() {
+            OutputFormat::Json
+        } else {
+            format
+        };
+        Ok(Output {
+            format,
+            select,
+            template,
+            lines: false,
+        })
+    }
+
+    /// Switch JSON output to one compact value per line, for streaming many results
+    pub fn lines(mut self) -> Self {
+        self.lines = true;
+        self
+    }
+
+    fn to_json_string<T: Serialize>(&self, value: &T) -> Result<String> {
+        Ok(if self.lines {
+            serde_json::to_string(value)?
+        } else {
+            serde_json::to_string_pretty(value)?
+        })
+    }
+
+    /// Print a value in the selected format, text falls back to the debug representation
+    pub fn value<T: Serialize + Debug>(&self, value: &T) -> Result<()> {
+        match self.format {
+            OutputFormat::Text | OutputFormat::Debug => println!("{:#?}", value),
+            OutputFormat::Json => self.json(value)?,
+        }
+        Ok(())
+    }
+
+    /// Print a value as compact single line JSON, used for streaming JSONL output
+    pub fn json_line<T: Serialize>(&self, value: &T) -> Result<()> {
+        if self.select.is_some() || self.template.is_some() {
+            return self.json(value);
+        }
+        println!("{}", serde_json::to_string(value)?);
+        Ok(())
+    }
+
+    pub fn json<T: Serialize>(&self, value: &T) -> Result<()> {
+        let values = match &self.select {
+            Some(select) => select.apply(serde_json::to_value(value)?),
+            None if self.template.is_some() => vec![serde_json::to_value(value)?],
+            None => {
+                println!("{}", self.to_json_string(value)?);
+                return Ok(());
+            }
+        };
+
+        if let Some(template) = &self.template {
+            for value in values {
+                println!("{}", render_template(template, value)?);
+            }
+            return Ok(());
+        }
+
+        // Like `jq -r`, selected strings print raw and scalars print one per line
+        for value in values {
+            match value {
+                Value::String(s) => println!("{}", s),
+                Value::Array(_) | Value::Object(_) => println!("{}", self.to_json_string(&value)?),
+                scalar => println!("{}", scalar),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A template argument is read as a file if one exists at that path, otherwise used as the template itself
+fn load_template(template: &str) -> Result<String> {
+    if Path::new(template).is_file() {
+        return Ok(fs::read_to_string(template)?);
+    }
+    Ok(template.to_string())
+}
+
+/// Render a template against a value, available as `data` and (for objects) through its top level fields
+fn render_template(template: &str, value: Value) -> Result<String> {
+    let mut context = match &value {
+        Value::Object(map) => map.clone(),
+        _ => Map::new(),
+    };
+    context.insert("data".to_string(), value);
+
+    let env = minijinja::Environment::new();
+    Ok(env.render_str(template, minijinja::value::Serde(&context))?)
+}
+
+#[derive(Debug)]
+enum Step {
+    Field(String),
+    Index(usize),
+    Iterate,
+}
+
+/// A jq style path such as `.transactions[].outputs[0].amount`
+#[derive(Debug)]
+pub struct Selector {
+    steps: Vec<Step>,
+}
+
+impl Selector {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let mut steps = Vec::new();
+        let mut rest = expr.trim();
+        if !rest.starts_with(['.', '[']) {
+            return Err(anyhow!("Select expression {expr} must start with '.'"));
+        }
+
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix('[') {
+                let end = after
+                    .find(']')
+                    .ok_or_else(|| anyhow!("Unclosed '[' in select expression {expr}"))?;
+                let inner = after[..end].trim();
+                if inner.is_empty() {
+                    steps.push(Step::Iterate);
+                } else {
+                    let index = inner
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid index [{inner}] in select expression"))?;
+                    steps.push(Step::Index(index));
+                }
+                rest = &after[end + 1..];
+            } else if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                if end > 0 {
+                    steps.push(Step::Field(after[..end].to_string()));
+                }
+                rest = &after[end..];
+            } else {
+                return Err(anyhow!("Unexpected '{rest}' in select expression {expr}"));
+            }
+        }
+
+        Ok(Selector { steps })
+    }
+
+    /// Apply the path, every `[]` fans out into multiple results
+    pub fn apply(&self, value: Value) -> Vec<Value> {
+        let mut current = vec![value];
+        for step in &self.steps {
+            current = current
+                .into_iter()
+                .flat_map(|value| match step {
+                    Step::Field(name) => vec![value.get(name).cloned().unwrap_or(Value::Null)],
+                    Step::Index(i) => vec![value.get(i).cloned().unwrap_or(Value::Null)],
+                    Step::Iterate => match value {
+                        Value::Array(values) => values,
+                        Value::Object(map) => map.into_iter().map(|(_, v)| v).collect(),
+                        _ => vec![],
+                    },
+                })
+                .collect();
+        }
+        current
+    }
+}
@@ -0,0 +1,72 @@
+// pager.rs
+use std::{
+    env,
+    io::{IsTerminal, Write, stdout},
+    process::Child,
+};
+
+/// Keeps the pager alive while output is written, waits for it to exit on drop
+pub struct Pager {
+    child: Option<Child>,
+}
+
+/// Send stdout through `$PAGER` (or `less`) when printing to a terminal.
+/// `less -F` exits straight away when everything fits on one screen, so short output is unaffected.
+pub fn start(disabled: bool) -> Pager {
+    if disabled || !stdout().is_terminal() {
+        return Pager { child: None };
+    }
+    Pager {
+        child: spawn_pager(),
+    }
+}
+
+#[cfg(unix)]
+fn spawn_pager() -> Option<Child> {
+    use std::{
+        os::fd::AsRawFd,
+        process::{Command, Stdio},
+    };
+
+    let pager = env::var("PAGER").unwrap_or_else(|_| "less".to_string());
+    if pager.trim().is_empty() || pager == "cat" {
+        return None;
+    }
+
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(&pager).stdin(Stdio::piped());
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+
+    let mut child = command.spawn().ok()?;
+    let stdin = child.stdin.take()?;
+    // SAFETY: both descriptors are valid, stdout now refers to the pager pipe.
+    // Quitting the pager early should end the process quietly instead of panicking on EPIPE.
+    unsafe {
+        if libc::dup2(stdin.as_raw_fd(), libc::STDOUT_FILENO) < 0 {
+            return None;
+        }
+        libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+    }
+    Some(child)
+}
+
+#[cfg(not(unix))]
+fn spawn_pager() -> Option<Child> {
+    None
+}
+
+impl Drop for Pager {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.child {
+            let _ = stdout().flush();
+            // Close our end of the pipe so the pager sees EOF
+            #[cfg(unix)]
+            unsafe {
+                libc::close(libc::STDOUT_FILENO);
+            }
+            let _ = child.wait();
+        }
+    }
+}
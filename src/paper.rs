@@ -0,0 +1,99 @@
+// paper.rs
+use anyhow::Result;
+use serde::Serialize;
+use snap_coin::crypto::keys::Private;
+
+use crate::{
+    keystore::EncryptedKey,
+    output::{Output, OutputFormat},
+    password::{self, PASSWORD_VAR},
+    qr::QrCode,
+};
+
+/// A freshly generated key pair for cold storage, never seen by a node
+#[derive(Debug, Serialize)]
+pub struct PaperWallet {
+    pub address: String,
+    /// Base36 secret, or the compact encrypted form of `keystore::EncryptedKey` with a password
+    pub secret: String,
+    pub encrypted: bool,
+}
+
+impl PaperWallet {
+    pub fn generate(password: Option<&str>) -> Result<PaperWallet> {
+        let private = Private::new_random();
+        Ok(PaperWallet {
+            address: private.to_public().dump_base36(),
+            secret: match password {
+                Some(password) => EncryptedKey::encrypt(&private, password)?.to_compact(),
+                None => private.dump_base36(),
+            },
+            encrypted: password.is_some(),
+        })
+    }
+
+    /// A printable page with the address and secret as text and QR codes
+    pub fn to_html(&self) -> Result<String> {
+        let address_qr = QrCode::encode(self.address.as_bytes())?.to_svg();
+        let secret_qr = QrCode::encode(self.secret.as_bytes())?.to_svg();
+        let secret_note = if self.encrypted {
+            "Encrypted with a password by argon2id. Restore it with <code>snap-coin-utils keys import</code> and the password."
+        } else {
+            "Anyone holding this secret can spend the funds of the address. Restore it with <code>snap-coin-utils keys import</code>."
+        };
+        Ok(format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Snap Coin paper wallet</title>
+<style>
+body {{ font-family: sans-serif; max-width: 46em; margin: 2em auto; }}
+section {{ display: flex; gap: 2em; align-items: center; border: 1px solid #000; padding: 1.5em; margin-bottom: 2em; page-break-inside: avoid; }}
+svg {{ width: 14em; height: 14em; flex: none; }}
+code {{ word-break: break-all; font-size: 1.1em; }}
+.cut {{ border-top: 1px dashed #000; margin: 2em 0; }}
+</style>
+</head>
+<body>
+<h1>Snap Coin paper wallet</h1>
+<section>
+{address_qr}
+<div>
+<h2>Address</h2>
+<p>Share it to receive funds.</p>
+<code>{address}</code>
+</div>
+</section>
+<div class="cut"></div>
+<section>
+{secret_qr}
+<div>
+<h2>Secret key</h2>
+<p>{secret_note}</p>
+<code>{secret}</code>
+</div>
+</section>
+</body>
+</html>
+"#,
+            address = self.address,
+            secret = self.secret,
+        ))
+    }
+}
+
+/// Print a new paper wallet, its secret encrypted with a password when `encrypt` is set
+pub fn run_paper_wallet(encrypt: bool, out: &Output) -> Result<()> {
+    let password = match encrypt {
+        true => Some(password::new_password(PASSWORD_VAR, "Password: ")?),
+        false => None,
+    };
+    let paper = PaperWallet::generate(password.as_deref())?;
+    match out.format {
+        OutputFormat::Text => print!("{}", paper.to_html()?),
+        OutputFormat::Debug => println!("{:#?}", paper),
+        OutputFormat::Json => out.json(&paper)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,89 @@
+// password.rs
+use anyhow::{Result, anyhow};
+use std::{
+    env,
+    io::{BufRead, IsTerminal, Write, stderr, stdin},
+};
+
+/// Environment variables holding key passwords, for scripts without a terminal
+pub const PASSWORD_VAR: &str = "SNAP_COIN_PASSWORD";
+pub const NEW_PASSWORD_VAR: &str = "SNAP_COIN_NEW_PASSWORD";
+
+/// Password from the `var` environment variable, or else asked for on the terminal
+pub fn password(var: &str, prompt: &str) -> Result<String> {
+    match env::var(var) {
+        Ok(password) => Ok(password),
+        Err(_) => read_hidden(prompt),
+    }
+}
+
+/// Like `password`, but asked twice on a terminal so a typo doesn't lock the key away
+pub fn new_password(var: &str, prompt: &str) -> Result<String> {
+    if let Ok(password) = env::var(var) {
+        return Ok(password);
+    }
+    let password = read_hidden(prompt)?;
+    if stdin().is_terminal() && read_hidden("Repeat it: ")? != password {
+        return Err(anyhow!("The passwords don't match"));
+    }
+    Ok(password)
+}
+
+/// Read a line from stdin, without echoing it when stdin is a terminal
+pub fn read_hidden(prompt: &str) -> Result<String> {
+    let terminal = stdin().is_terminal();
+    if terminal {
+        eprint!("{prompt}");
+        stderr().flush()?;
+    }
+    let _echo = terminal.then(EchoOff::new);
+    let mut line = String::new();
+    stdin().lock().read_line(&mut line)?;
+    if terminal {
+        eprintln!();
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Turns terminal echo off until dropped
+#[cfg(unix)]
+struct EchoOff(Option<libc::termios>);
+
+#[cfg(unix)]
+impl EchoOff {
+    fn new() -> EchoOff {
+        // SAFETY: termios is plain data filled in by tcgetattr before being used
+        unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return EchoOff(None);
+            }
+            let mut hidden = termios;
+            hidden.c_lflag &= !libc::ECHO;
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &hidden);
+            EchoOff(Some(termios))
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for EchoOff {
+    fn drop(&mut self) {
+        if let Some(termios) = &self.0 {
+            // SAFETY: restores the settings read in `new`
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, termios);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct EchoOff;
+
+#[cfg(not(unix))]
+impl EchoOff {
+    fn new() -> EchoOff {
+        EchoOff
+    }
+}
@@ -0,0 +1,134 @@
+// patterns.rs
+use serde::Serialize;
+use snap_coin::core::transaction::Transaction;
+
+/// The shape of a transaction's inputs and outputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransactionPattern {
+    /// Mints the block reward, the only transaction without inputs
+    Coinbase,
+    /// Pays only addresses it spends from
+    SelfTransfer,
+    /// Several inputs merged into a single output
+    Consolidation,
+    /// A single input split over three or more outputs
+    FanOut,
+    /// Up to two outputs, a payment and usually change
+    Payment,
+    /// Several inputs and three or more outputs
+    Other,
+}
+
+impl TransactionPattern {
+    pub const ALL: [TransactionPattern; 6] = [
+        TransactionPattern::Coinbase,
+        TransactionPattern::Payment,
+        TransactionPattern::Consolidation,
+        TransactionPattern::FanOut,
+        TransactionPattern::SelfTransfer,
+        TransactionPattern::Other,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TransactionPattern::Coinbase => "coinbase",
+            TransactionPattern::SelfTransfer => "self-transfer",
+            TransactionPattern::Consolidation => "consolidation",
+            TransactionPattern::FanOut => "fan-out",
+            TransactionPattern::Payment => "payment",
+            TransactionPattern::Other => "other",
+        }
+    }
+}
+
+/// Tag a transaction with its pattern, checked in the order the variants are declared
+pub fn classify(tx: &Transaction) -> TransactionPattern {
+    let inputs = tx.inputs.len();
+    let outputs = tx.outputs.len();
+    if inputs == 0 {
+        TransactionPattern::Coinbase
+    } else if tx.outputs.iter().all(|output| {
+        tx.inputs
+            .iter()
+            .any(|input| input.output_owner == output.receiver)
+    }) {
+        TransactionPattern::SelfTransfer
+    } else if inputs > 1 && outputs == 1 {
+        TransactionPattern::Consolidation
+    } else if inputs == 1 && outputs > 2 {
+        TransactionPattern::FanOut
+    } else if outputs <= 2 {
+        TransactionPattern::Payment
+    } else {
+        TransactionPattern::Other
+    }
+}
+
+/// How many transactions of every pattern a window holds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct PatternMix {
+    pub coinbase: usize,
+    pub payment: usize,
+    pub consolidation: usize,
+    pub fan_out: usize,
+    pub self_transfer: usize,
+    pub other: usize,
+}
+
+impl PatternMix {
+    pub fn add(&mut self, tx: &Transaction) {
+        *self.count_mut(classify(tx)) += 1;
+    }
+
+    pub fn count(&self, pattern: TransactionPattern) -> usize {
+        match pattern {
+            TransactionPattern::Coinbase => self.coinbase,
+            TransactionPattern::SelfTransfer => self.self_transfer,
+            TransactionPattern::Consolidation => self.consolidation,
+            TransactionPattern::FanOut => self.fan_out,
+            TransactionPattern::Payment => self.payment,
+            TransactionPattern::Other => self.other,
+        }
+    }
+
+    fn count_mut(&mut self, pattern: TransactionPattern) -> &mut usize {
+        match pattern {
+            TransactionPattern::Coinbase => &mut self.coinbase,
+            TransactionPattern::SelfTransfer => &mut self.self_transfer,
+            TransactionPattern::Consolidation => &mut self.consolidation,
+            TransactionPattern::FanOut => &mut self.fan_out,
+            TransactionPattern::Payment => &mut self.payment,
+            TransactionPattern::Other => &mut self.other,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        TransactionPattern::ALL
+            .iter()
+            .map(|&pattern| self.count(pattern))
+            .sum()
+    }
+
+    /// Fraction of the transactions with `pattern`, 0 for an empty mix
+    pub fn share(&self, pattern: TransactionPattern) -> f64 {
+        self.count(pattern) as f64 / self.total().max(1) as f64
+    }
+
+    /// The patterns that occur with their counts and percentages, e.g. `payment 12 (60.0%)`
+    pub fn summary(&self) -> String {
+        TransactionPattern::ALL
+            .iter()
+            .filter(|&&pattern| self.count(pattern) > 0)
+            .map(|&pattern| {
+                format!(
+                    "{} {} ({:.1}%)",
+                    pattern.name(),
+                    self.count(pattern),
+                    self.share(pattern) * 100.0
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
@@ -0,0 +1,202 @@
+// payouts.rs
+use anyhow::Result;
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::{core::transaction::TransactionId, crypto::keys::Public};
+use std::ops::Range;
+use tracing::info;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    color, format_snap,
+    miners::coinbase,
+    output::{Output, OutputFormat},
+    query::parse_address,
+    tables::{align_right, new_table},
+};
+
+/// A transaction from the pool paying the payout address
+#[derive(Debug, Serialize)]
+pub struct Payout {
+    pub height: usize,
+    pub transaction_id: Option<TransactionId>,
+    pub amount: u64,
+}
+
+/// What a pool earned over a window against what it paid one of its users
+#[derive(Debug, Serialize)]
+pub struct PayoutReport {
+    pub pool: Public,
+    pub payout_address: Public,
+    pub start_height: usize,
+    pub end_height: usize,
+    /// Blocks whose reward went to the pool
+    pub blocks_mined: usize,
+    /// Block rewards paid to the pool
+    pub rewards: u64,
+    /// Part of the rewards owed to the payout address, after the pool fee
+    pub share: f64,
+    pub fee: f64,
+    pub expected: u64,
+    pub paid: u64,
+    pub payouts: Vec<Payout>,
+    /// Expected minus paid, 0 when the payout address got all it was owed
+    pub shortfall: u64,
+}
+
+/// Sum the block rewards `pool` earned in `heights` and the payments it made to `payout_address`.
+/// The address is owed `share` of the rewards after the pool keeps `fee` of them.
+pub async fn verify_payouts<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+    pool: Public,
+    payout_address: Public,
+    share: f64,
+    fee: f64,
+) -> Result<PayoutReport> {
+    info!(
+        "Scanning blocks {}..{} for pool rewards and payouts",
+        heights.start, heights.end
+    );
+    let mut report = PayoutReport {
+        pool,
+        payout_address,
+        start_height: heights.start,
+        end_height: heights.end,
+        blocks_mined: 0,
+        rewards: 0,
+        share,
+        fee,
+        expected: 0,
+        paid: 0,
+        payouts: Vec::new(),
+        shortfall: 0,
+    };
+    for_each_block(provider, heights, |height, block| {
+        if let Some(coinbase) = coinbase(&block) {
+            let reward = coinbase
+                .outputs
+                .iter()
+                .filter(|output| output.receiver == pool)
+                .map(|output| output.amount)
+                .sum::<u64>();
+            if reward > 0 {
+                report.blocks_mined += 1;
+                report.rewards += reward;
+            }
+        }
+        // Spending the pool's outputs makes it a payment by the pool
+        for tx in &block.transactions {
+            if !tx.inputs.iter().any(|input| input.output_owner == pool) {
+                continue;
+            }
+            let amount = tx
+                .outputs
+                .iter()
+                .filter(|output| output.receiver == payout_address)
+                .map(|output| output.amount)
+                .sum::<u64>();
+            if amount > 0 {
+                report.paid += amount;
+                report.payouts.push(Payout {
+                    height,
+                    transaction_id: tx.transaction_id,
+                    amount,
+                });
+            }
+        }
+        Ok(())
+    })
+    .await?;
+    report.expected = (report.rewards as f64 * (1.0 - fee) * share).floor() as u64;
+    report.shortfall = report.expected.saturating_sub(report.paid);
+    Ok(report)
+}
+
+pub fn payouts_table(payouts: &[Payout]) -> Table {
+    let mut table = new_table(&["Height", "TX ID", "Amount (SNAP)"]);
+    for payout in payouts {
+        table.add_row(vec![
+            payout.height.to_string(),
+            color::hash(
+                payout
+                    .transaction_id
+                    .map(|id| id.dump_base36())
+                    .unwrap_or_default(),
+            ),
+            color::amount(format_snap(payout.amount)),
+        ]);
+    }
+    align_right(&mut table, &[0, 2]);
+    table
+}
+
+pub fn print_payouts(report: &PayoutReport) {
+    println!(
+        "Pool {} over blocks {}..{}",
+        color::hash(report.pool.dump_base36()),
+        report.start_height,
+        report.end_height
+    );
+    println!(
+        "Rewards: {} SNAP from {} blocks",
+        color::amount(format_snap(report.rewards)),
+        report.blocks_mined
+    );
+    println!(
+        "Owed to {}: {} SNAP ({:.2}% share after a {:.2}% fee)",
+        color::hash(report.payout_address.dump_base36()),
+        color::amount(format_snap(report.expected)),
+        report.share * 100.0,
+        report.fee * 100.0
+    );
+    println!(
+        "Paid: {} SNAP in {} transactions",
+        color::amount(format_snap(report.paid)),
+        report.payouts.len()
+    );
+    if !report.payouts.is_empty() {
+        println!("{}", payouts_table(&report.payouts));
+    }
+    if report.shortfall > 0 {
+        println!(
+            "{}",
+            color::warning(format!(
+                "Shortfall: {} SNAP less than owed",
+                format_snap(report.shortfall)
+            ))
+        );
+    } else {
+        println!("No shortfall");
+    }
+}
+
+/// Check the payouts of the `pool` blocks of the past `blocks` blocks to `payout_address`
+pub async fn run_verify_payouts<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    pool: &str,
+    payout_address: &str,
+    blocks: usize,
+    share: f64,
+    fee: f64,
+    out: &Output,
+) -> Result<()> {
+    let pool = parse_address(pool)?;
+    let payout_address = parse_address(payout_address)?;
+    let height = provider.get_height().await?;
+    let report = verify_payouts(
+        provider,
+        height.saturating_sub(blocks)..height,
+        pool,
+        payout_address,
+        share,
+        fee,
+    )
+    .await?;
+    match out.format {
+        OutputFormat::Text => print_payouts(&report),
+        OutputFormat::Debug => println!("{:#?}", report),
+        OutputFormat::Json => out.json(&report)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,38 @@
+// plugin.rs
+use anyhow::{Result, anyhow};
+use std::{
+    ffi::OsString,
+    io,
+    process::{Command, ExitStatus},
+};
+
+/// Prefix of external subcommand executables, `snap-coin-stats foo` runs `snap-coin-stats-foo`
+pub const PLUGIN_PREFIX: &str = "snap-coin-stats-";
+
+/// Run `snap-coin-stats-<name>` from PATH with the remaining arguments.
+/// Global options are passed through `SNAP_COIN_*` environment variables.
+pub fn run(args: &[OsString], env: &[(&str, String)]) -> Result<ExitStatus> {
+    let (name, rest) = args
+        .split_first()
+        .ok_or_else(|| anyhow!("Missing external subcommand name"))?;
+    let mut program = OsString::from(PLUGIN_PREFIX);
+    program.push(name);
+
+    let status = Command::new(&program)
+        .args(rest)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .status();
+    match status {
+        Ok(status) => Ok(status),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Err(anyhow!(
+            "Unknown command {}, and no {} found on PATH",
+            name.to_string_lossy(),
+            program.to_string_lossy()
+        )),
+        Err(e) => Err(anyhow!(
+            "Could not run {}: {}",
+            program.to_string_lossy(),
+            e
+        )),
+    }
+}
@@ -0,0 +1,113 @@
+// pool.rs
+use anyhow::Result;
+use std::net::SocketAddr;
+use tokio::net::lookup_host;
+use tracing::{debug, info, warn};
+
+use crate::{
+    error::{ErrorKind, error},
+    node::NodeClient,
+};
+
+/// Every address behind the node argument. Each entry of a comma separated list is resolved
+/// on its own, and a DNS seed contributes all of its A records.
+#[derive(Debug, Clone)]
+pub struct NodePool {
+    addresses: Vec<SocketAddr>,
+}
+
+impl NodePool {
+    /// Resolve `nodes`, skipping entries that don't resolve as long as one does
+    pub async fn resolve<'a>(nodes: impl IntoIterator<Item = &'a str>) -> Result<NodePool> {
+        let mut addresses = Vec::new();
+        let mut unresolved = Vec::new();
+        for node in nodes {
+            match lookup_host(node).await {
+                Ok(resolved) => {
+                    let resolved: Vec<_> = resolved.collect();
+                    debug!("Resolved {} to {:?}", node, resolved);
+                    for address in resolved {
+                        if !addresses.contains(&address) {
+                            addresses.push(address);
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!("Could not resolve {}: {}", node, e);
+                    unresolved.push(node);
+                }
+            }
+        }
+        if addresses.is_empty() {
+            return Err(error(
+                ErrorKind::Dns,
+                format!("Could not resolve {}", unresolved.join(", ")),
+            ));
+        }
+        Ok(NodePool { addresses })
+    }
+
+    /// Resolved addresses in the order they were given
+    pub fn addresses(&self) -> &[SocketAddr] {
+        &self.addresses
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    /// Connect to the first address that accepts the connection
+    pub async fn connect(&self) -> Result<NodeClient> {
+        connect_first(&self.addresses).await
+    }
+
+    /// Connect to another node of the pool than `node`, for comparing what two nodes see
+    pub async fn connect_except(&self, node: SocketAddr) -> Result<NodeClient> {
+        let others: Vec<_> = self
+            .addresses
+            .iter()
+            .copied()
+            .filter(|address| *address != node)
+            .collect();
+        if others.is_empty() {
+            return Err(error(
+                ErrorKind::Connect,
+                format!("The node pool has no other node than {}", node),
+            ));
+        }
+        connect_first(&others).await
+    }
+}
+
+async fn connect_first(addresses: &[SocketAddr]) -> Result<NodeClient> {
+    let mut last_error = None;
+    for address in addresses {
+        match NodeClient::connect(*address).await {
+            Ok(client) => {
+                info!("Connected to {}", address);
+                return Ok(client);
+            }
+            Err(e) => {
+                if addresses.len() > 1 {
+                    warn!("Could not connect to {}: {}", address, e);
+                }
+                last_error = Some(
+                    anyhow::Error::new(e).context(format!("Could not connect to {}", address)),
+                );
+            }
+        }
+    }
+    Err(last_error.unwrap_or_else(|| error(ErrorKind::Connect, "No node to connect to")))
+}
+
+/// Split a comma separated node argument, dropping empty entries
+pub fn split_nodes(nodes: &str) -> impl Iterator<Item = &str> {
+    nodes
+        .split(',')
+        .map(str::trim)
+        .filter(|node| !node.is_empty())
+}
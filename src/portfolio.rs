@@ -0,0 +1,258 @@
+// portfolio.rs
+use anyhow::{Context, Result};
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::{
+    core::transaction::{Transaction, TransactionId},
+    crypto::keys::Public,
+};
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{debug, info};
+
+use crate::{
+    color,
+    error::{ErrorKind, error},
+    format_snap,
+    labels::{BookEntry, parse_labels},
+    node::NodeClient,
+    output::{Output, OutputFormat},
+    price::{self, Quote},
+    query::parse_address,
+    tables::{align_right, new_table},
+};
+
+pub const DAY: u64 = 24 * 60 * 60;
+pub const WEEK: u64 = 7 * DAY;
+
+/// Read the addresses of a portfolio, `address,label` rows as `alias import` reads them
+pub fn load_list(path: &Path) -> Result<Vec<BookEntry>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Could not read portfolio {}", path.display()))?;
+    let entries = parse_labels(&contents)
+        .with_context(|| format!("Could not parse portfolio {}", path.display()))?;
+    if entries.is_empty() {
+        return Err(error(
+            ErrorKind::NotFound,
+            format!("Portfolio {} lists no addresses", path.display()),
+        ));
+    }
+    Ok(entries)
+}
+
+/// What `tx` moves into `address` minus what it spends from it, in nano. Spent amounts are taken
+/// from the transactions in `funding`, `None` when one of them is missing.
+pub fn net_flow(
+    tx: &Transaction,
+    address: &Public,
+    funding: &HashMap<TransactionId, Transaction>,
+) -> Option<i64> {
+    let received: u64 = tx
+        .outputs
+        .iter()
+        .filter(|output| output.receiver == *address)
+        .map(|output| output.amount)
+        .sum();
+    let mut spent = 0u64;
+    for input in tx
+        .inputs
+        .iter()
+        .filter(|input| input.output_owner == *address)
+    {
+        let output = funding
+            .get(&input.transaction_id)?
+            .outputs
+            .get(input.output_index)?;
+        spent += output.amount;
+    }
+    Some(received as i64 - spent as i64)
+}
+
+/// Balance change over the `period` seconds before `now`, from `(timestamp, net flow)` pairs
+pub fn change_since(flows: &[(u64, i64)], now: u64, period: u64) -> i64 {
+    let since = now.saturating_sub(period);
+    flows
+        .iter()
+        .filter(|(timestamp, _)| *timestamp > since)
+        .map(|(_, flow)| flow)
+        .sum()
+}
+
+#[derive(Debug, Serialize)]
+pub struct Holding {
+    pub address: String,
+    pub label: String,
+    pub balance: u64,
+    /// Balance change over the last 24 hours, in nano
+    pub change_24h: i64,
+    pub change_7d: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Portfolio {
+    /// Unix time the changes are counted back from
+    pub time: u64,
+    pub holdings: Vec<Holding>,
+    pub total: u64,
+    pub change_24h: i64,
+    pub change_7d: i64,
+}
+
+impl Portfolio {
+    pub fn new(time: u64, holdings: Vec<Holding>) -> Portfolio {
+        Portfolio {
+            time,
+            total: holdings.iter().map(|holding| holding.balance).sum(),
+            change_24h: holdings.iter().map(|holding| holding.change_24h).sum(),
+            change_7d: holdings.iter().map(|holding| holding.change_7d).sum(),
+            holdings,
+        }
+    }
+}
+
+/// Net flows of the transactions of `address` newer than `since`, with their timestamps.
+/// Every transaction of the address is fetched for its timestamp, and the ones in the window
+/// for the outputs they spend.
+async fn recent_flows(
+    client: &NodeClient,
+    address: Public,
+    since: u64,
+    cache: &mut HashMap<TransactionId, Transaction>,
+) -> Result<Vec<(u64, i64)>> {
+    debug!("get_transactions_of_address {}", address.dump_base36());
+    let ids = client.get_transactions_of_address(address).await?;
+    let mut flows = Vec::new();
+    for id in ids {
+        let Some(tx) = fetch(client, &id, cache).await? else {
+            continue;
+        };
+        if tx.timestamp <= since {
+            continue;
+        }
+        for input in tx
+            .inputs
+            .iter()
+            .filter(|input| input.output_owner == address)
+        {
+            fetch(client, &input.transaction_id, cache).await?;
+        }
+        let flow = net_flow(&tx, &address, cache).ok_or_else(|| {
+            error(
+                ErrorKind::NotFound,
+                format!(
+                    "An output spent by transaction {} wasn't found",
+                    id.dump_base36()
+                ),
+            )
+        })?;
+        flows.push((tx.timestamp, flow));
+    }
+    Ok(flows)
+}
+
+/// A transaction by ID, from `cache` or else the node
+async fn fetch(
+    client: &NodeClient,
+    id: &TransactionId,
+    cache: &mut HashMap<TransactionId, Transaction>,
+) -> Result<Option<Transaction>> {
+    if let Some(tx) = cache.get(id) {
+        return Ok(Some(tx.clone()));
+    }
+    debug!("get_transaction {}", id.dump_base36());
+    let tx = client.get_transaction(id).await?;
+    if let Some(tx) = &tx {
+        cache.insert(*id, tx.clone());
+    }
+    Ok(tx)
+}
+
+/// Current balance of every listed address and how it changed over the last day and week,
+/// reconstructed from the transactions of the week before `now`
+pub async fn portfolio(client: &NodeClient, entries: &[BookEntry], now: u64) -> Result<Portfolio> {
+    info!("Valuing a portfolio of {} addresses", entries.len());
+    let mut cache = HashMap::new();
+    let mut holdings = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let address = parse_address(&entry.address)?;
+        debug!("get_balance {}", entry.address);
+        let balance = client.get_balance(address).await?;
+        let flows = recent_flows(client, address, now.saturating_sub(WEEK), &mut cache).await?;
+        holdings.push(Holding {
+            address: entry.address.clone(),
+            label: entry.label.clone(),
+            balance,
+            change_24h: change_since(&flows, now, DAY),
+            change_7d: change_since(&flows, now, WEEK),
+        });
+    }
+    Ok(Portfolio::new(now, holdings))
+}
+
+/// A signed amount in SNAP, e.g. `+1.50000000` or `-0.25000000`
+pub fn format_change(nano: i64) -> String {
+    let sign = if nano < 0 { '-' } else { '+' };
+    format!("{}{}", sign, format_snap(nano.unsigned_abs()))
+}
+
+pub fn portfolio_table(portfolio: &Portfolio, fiat: Option<&Quote>) -> Table {
+    let fiat_header = fiat.map(|quote| format!("Value ({})", quote.currency.to_uppercase()));
+    let mut headers = vec!["Label", "Address", "Balance (SNAP)", "24h", "7d"];
+    headers.extend(fiat_header.as_deref());
+    let mut table = new_table(&headers);
+    let row = |label: String, address: String, balance: u64, day: i64, week: i64| {
+        let mut row = vec![
+            label,
+            address,
+            color::amount(format_snap(balance)),
+            format_change(day),
+            format_change(week),
+        ];
+        row.extend(fiat.map(|quote| format!("{:.2}", quote.value(balance))));
+        row
+    };
+    for holding in &portfolio.holdings {
+        table.add_row(row(
+            holding.label.clone(),
+            color::hash(&holding.address),
+            holding.balance,
+            holding.change_24h,
+            holding.change_7d,
+        ));
+    }
+    table.add_row(row(
+        "Total".to_string(),
+        String::new(),
+        portfolio.total,
+        portfolio.change_24h,
+        portfolio.change_7d,
+    ));
+    align_right(&mut table, &[2, 3, 4, 5]);
+    table
+}
+
+/// Print the balances and recent changes of the addresses in the portfolio `list`
+pub async fn run_portfolio(
+    client: &NodeClient,
+    list: &Path,
+    out: &Output,
+    fiat: Option<&Quote>,
+) -> Result<()> {
+    let entries = load_list(list)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let portfolio = portfolio(client, &entries, now).await?;
+    match out.format {
+        OutputFormat::Text => println!("{}", portfolio_table(&portfolio, fiat)),
+        OutputFormat::Debug => println!("{:#?}", portfolio),
+        OutputFormat::Json => out.json(&price::with_fiat(
+            &portfolio,
+            fiat,
+            &[("total", portfolio.total)],
+        )?)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,230 @@
+// price.rs
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use snap_coin::to_snap;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::{debug, info, warn};
+
+use crate::{config, parse_duration};
+
+/// Where fiat prices come from, the `[price]` config section
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PriceConfig {
+    /// HTTP API returning the price of one SNAP as JSON, `{currency}` is replaced by the lower
+    /// case currency, e.g. `https://prices.example.com/v1/snap?vs={currency}`
+    pub url: Option<String>,
+    /// JSON pointer to the price in the response, `{currency}` is replaced as in the URL
+    pub pointer: String,
+    /// How long a fetched price is used before it is fetched again, e.g. `10m`
+    pub ttl: String,
+}
+
+impl Default for PriceConfig {
+    fn default() -> Self {
+        PriceConfig {
+            url: None,
+            pointer: "/price".to_string(),
+            ttl: "10m".to_string(),
+        }
+    }
+}
+
+/// The price of one SNAP in a fiat currency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Quote {
+    /// Lower case currency code, e.g. `usd`
+    pub currency: String,
+    pub price: f64,
+    /// Unix time the price was fetched
+    pub fetched: u64,
+}
+
+impl Quote {
+    /// Fiat value of an amount in nano
+    pub fn value(&self, nano: u64) -> f64 {
+        to_snap(nano) * self.price
+    }
+
+    /// Fiat value of an amount in nano, e.g. `12.34 USD`
+    pub fn format(&self, nano: u64) -> String {
+        self.format_value(self.value(nano))
+    }
+
+    /// A fiat value with its currency, more decimals for values under a cent
+    pub fn format_value(&self, value: f64) -> String {
+        let decimals = if value != 0.0 && value.abs() < 0.01 {
+            6
+        } else {
+            2
+        };
+        format!("{:.*} {}", decimals, value, self.currency.to_uppercase())
+    }
+
+    /// The quote as JSON along with the fiat value of every named amount
+    pub fn json(&self, amounts: &[(&str, u64)]) -> Value {
+        let mut fiat = json!({
+            "currency": self.currency,
+            "price": self.price,
+            "fetched": self.fetched,
+        });
+        for (name, nano) in amounts {
+            fiat[*name] = json!(self.value(*nano));
+        }
+        fiat
+    }
+}
+
+/// ` (12.34 USD)` to follow an amount in nano with a quote, nothing without one
+pub fn annotate(quote: Option<&Quote>, nano: u64) -> String {
+    quote
+        .map(|quote| format!(" ({})", quote.format(nano)))
+        .unwrap_or_default()
+}
+
+/// `value` as JSON with a `fiat` field holding the quote and the fiat value of `amounts`,
+/// or unchanged without a quote
+pub fn with_fiat<T: Serialize>(
+    value: &T,
+    quote: Option<&Quote>,
+    amounts: &[(&str, u64)],
+) -> Result<Value> {
+    let mut value = serde_json::to_value(value)?;
+    if let (Some(quote), Value::Object(object)) = (quote, &mut value) {
+        object.insert("fiat".to_string(), quote.json(amounts));
+    }
+    Ok(value)
+}
+
+/// Prices fetched before, by currency, kept in `prices.json` next to the config file
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PriceCache {
+    pub quotes: BTreeMap<String, Quote>,
+}
+
+/// `prices.json` next to the config file at `config`, or the default config location
+pub fn cache_path_for(config: Option<&Path>) -> Option<PathBuf> {
+    config
+        .map(Path::to_path_buf)
+        .or_else(config::default_path)
+        .map(|path| path.with_file_name("prices.json"))
+}
+
+impl PriceCache {
+    /// Read the cache, a missing file is an empty cache
+    pub fn load(path: &Path) -> Result<PriceCache> {
+        if !path.exists() {
+            return Ok(PriceCache::default());
+        }
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Could not read price cache {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Could not parse price cache {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create {}", dir.display()))?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    /// The cached quote of `currency` if it was fetched less than `ttl` before `now`
+    pub fn fresh(&self, currency: &str, ttl: Duration, now: u64) -> Option<&Quote> {
+        self.quotes
+            .get(currency)
+            .filter(|quote| now.saturating_sub(quote.fetched) < ttl.as_secs())
+    }
+}
+
+/// The URL or pointer of the config with `{currency}` filled in
+fn fill_currency(template: &str, currency: &str) -> String {
+    template.replace("{currency}", currency)
+}
+
+/// The price at `pointer` in a price API response, a number or a numeric string
+pub fn extract_price(response: &str, pointer: &str) -> Result<f64> {
+    let response: Value =
+        serde_json::from_str(response).context("The price API didn't return JSON")?;
+    let price = match response.pointer(pointer) {
+        Some(Value::Number(price)) => price.as_f64(),
+        Some(Value::String(price)) => price.parse().ok(),
+        Some(_) => None,
+        None => return Err(anyhow!("The price API response has nothing at {}", pointer)),
+    };
+    price
+        .filter(|price| price.is_finite() && *price >= 0.0)
+        .ok_or_else(|| anyhow!("The price at {} is not a number", pointer))
+}
+
+async fn fetch_price(url: String) -> Result<String> {
+    tokio::task::spawn_blocking(move || {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .timeout_global(Some(Duration::from_secs(10)))
+            .build()
+            .into();
+        agent
+            .get(&url)
+            .call()
+            .and_then(|mut response| response.body_mut().read_to_string())
+            .with_context(|| format!("Could not fetch the price from {}", url))
+    })
+    .await?
+}
+
+/// The price of SNAP in `currency`, from the cache at `cache_path` while it is younger than the
+/// configured TTL, fetched from the price API otherwise. When the API can't be reached an older
+/// cached price is used with a warning, so offline runs keep giving the same values.
+pub async fn quote(
+    config: &PriceConfig,
+    cache_path: &Path,
+    currency: &str,
+    now: u64,
+) -> Result<Quote> {
+    let currency = currency.trim().to_lowercase();
+    let ttl = parse_duration(&config.ttl).context("Invalid `ttl` in the [price] config")?;
+    let mut cache = PriceCache::load(cache_path)?;
+    if let Some(quote) = cache.fresh(&currency, ttl, now) {
+        debug!("Using the cached {} price from {}", currency, quote.fetched);
+        return Ok(quote.clone());
+    }
+    let url = config.url.as_deref().ok_or_else(|| {
+        anyhow!("--fiat needs a price API, set `url` in the [price] section of the config")
+    })?;
+    let url = fill_currency(url, &currency);
+    info!("Fetching the {} price from {}", currency, url);
+    let fetched = fetch_price(url)
+        .await
+        .and_then(|response| extract_price(&response, &fill_currency(&config.pointer, &currency)));
+    match fetched {
+        Ok(price) => {
+            let quote = Quote {
+                currency: currency.clone(),
+                price,
+                fetched: now,
+            };
+            cache.quotes.insert(currency, quote.clone());
+            cache.save(cache_path)?;
+            Ok(quote)
+        }
+        Err(e) => match cache.quotes.remove(&currency) {
+            Some(quote) => {
+                warn!(
+                    "{:#}, using the cached price from {}s ago",
+                    e,
+                    now.saturating_sub(quote.fetched)
+                );
+                Ok(quote)
+            }
+            None => Err(e),
+        },
+    }
+}
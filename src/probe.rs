@@ -0,0 +1,105 @@
+// probe.rs
+use anyhow::Result;
+use serde::Serialize;
+use snap_coin::blockchain_data_provider::BlockchainDataProvider;
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+use tokio::{net::lookup_host, task::JoinSet};
+use tracing::debug;
+
+use crate::{
+    error::{ErrorKind, error},
+    node::NodeClient,
+};
+
+/// Candidates slower than this to connect and answer are left out by `--auto-select`
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Height and round trip time of one candidate node
+#[derive(Debug, Clone, Serialize)]
+pub struct NodeProbe {
+    /// Alias or address the node was given as
+    pub name: String,
+    pub address: SocketAddr,
+    pub height: usize,
+    /// Time of a `get_height` round trip on an open connection
+    #[serde(rename = "latency_ms", serialize_with = "serialize_millis")]
+    pub latency: Duration,
+}
+
+fn serialize_millis<S: serde::Serializer>(
+    latency: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(latency.as_secs_f64() * 1000.0)
+}
+
+/// Resolve and connect to `node`, measuring a height request. The connection is kept for reuse.
+pub async fn probe(name: String, node: String) -> Result<(NodeProbe, NodeClient)> {
+    let address = lookup_host(&node)
+        .await
+        .ok()
+        .and_then(|mut addresses| addresses.next())
+        .ok_or_else(|| error(ErrorKind::Dns, format!("Could not resolve {}", node)))?;
+    let client = NodeClient::connect(address).await?;
+    let start = Instant::now();
+    let height = client.get_height().await?;
+    let latency = start.elapsed();
+    debug!(
+        "{} ({}) at height {} in {:?}",
+        name, address, height, latency
+    );
+    Ok((
+        NodeProbe {
+            name,
+            address,
+            height,
+            latency,
+        },
+        client,
+    ))
+}
+
+/// Probe every `(name, node)` candidate concurrently, leaving out those slower than `timeout`.
+/// Results are in no particular order.
+pub async fn probe_all(
+    candidates: impl IntoIterator<Item = (String, String)>,
+    timeout: Duration,
+) -> Vec<(String, Result<(NodeProbe, NodeClient)>)> {
+    let mut probes = JoinSet::new();
+    for (name, node) in candidates {
+        probes.spawn(async move {
+            let result = tokio::time::timeout(timeout, probe(name.clone(), node))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(error(
+                        ErrorKind::Timeout,
+                        format!("No answer within {:?}", timeout),
+                    ))
+                });
+            (name, result)
+        });
+    }
+    let mut results = Vec::new();
+    while let Some(result) = probes.join_next().await {
+        if let Ok(result) = result {
+            results.push(result);
+        }
+    }
+    results
+}
+
+/// Index of the best node: the highest chain wins, latency breaks ties between nodes at that height
+pub fn pick_fastest(probes: &[NodeProbe]) -> Option<usize> {
+    probes
+        .iter()
+        .enumerate()
+        .max_by(|(_, a), (_, b)| {
+            a.height
+                .cmp(&b.height)
+                .then_with(|| b.latency.cmp(&a.latency))
+        })
+        .map(|(i, _)| i)
+}
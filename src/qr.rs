@@ -0,0 +1,48 @@
+// qr.rs
+use anyhow::{Result, anyhow};
+use qrcode::{Color, EcLevel, Version};
+
+/// A QR code symbol, `modules[y][x]` is true for dark modules
+pub struct QrCode {
+    pub version: usize,
+    pub size: usize,
+    pub modules: Vec<Vec<bool>>,
+}
+
+impl QrCode {
+    /// Encode data at error correction level M, in the smallest version holding it
+    pub fn encode(data: &[u8]) -> Result<QrCode> {
+        let code = qrcode::QrCode::with_error_correction_level(data, EcLevel::M)
+            .map_err(|e| anyhow!("{} bytes don't fit in a QR code: {}", data.len(), e))?;
+        let Version::Normal(version) = code.version() else {
+            return Err(anyhow!("Expected a full size QR code"));
+        };
+        let size = code.width();
+        let modules = code
+            .to_colors()
+            .chunks(size)
+            .map(|row| row.iter().map(|&color| color == Color::Dark).collect())
+            .collect();
+        Ok(QrCode {
+            version: version as usize,
+            size,
+            modules,
+        })
+    }
+
+    /// The symbol as an SVG image with a quiet zone of 4 modules, one unit per module
+    pub fn to_svg(&self) -> String {
+        let mut path = String::new();
+        for (y, row) in self.modules.iter().enumerate() {
+            for (x, &dark) in row.iter().enumerate() {
+                if dark {
+                    path.push_str(&format!("M{} {}h1v1h-1z", x + 4, y + 4));
+                }
+            }
+        }
+        let side = self.size + 8;
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {side} {side}\" shape-rendering=\"crispEdges\"><rect width=\"{side}\" height=\"{side}\" fill=\"#fff\"/><path d=\"{path}\" fill=\"#000\"/></svg>"
+        )
+    }
+}
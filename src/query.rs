@@ -0,0 +1,831 @@
+// query.rs
+use anyhow::{Result, anyhow};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::json;
+use snap_coin::{
+    blockchain_data_provider::BlockchainDataProvider,
+    core::{
+        block::Block,
+        transaction::{Transaction, TransactionId, TransactionOutput},
+    },
+    crypto::{Hash, keys::Public},
+    economics::{EXPIRATION_TIME, TARGET_TIME},
+    to_snap,
+};
+use std::collections::{HashMap, hash_map::Entry};
+use tracing::debug;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    color,
+    config::Config,
+    error::{ErrorKind, error},
+    explorer::{self, Link},
+    fees::{TransactionValue, transaction_value},
+    format_biguint_hr, format_snap,
+    ids::{for_each_id, stdin_lines},
+    miners::coinbase,
+    node::NodeClient,
+    normalize_difficulty,
+    output::{Output, OutputFormat},
+    price::{self, Quote},
+    source::Source,
+    summary::{self, SummarizedBlock},
+    tables,
+};
+
+/// Balance, spendable outputs and history of one address, as far as they were asked for
+#[derive(Debug, Serialize)]
+pub struct AddressInfo {
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<u64>,
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub utxos: Option<AddressUtxos>,
+    /// Transactions touching the address, oldest first
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub history: Option<Vec<Hash>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressUtxos {
+    /// All of the address' UTXOs, however many `utxos` holds
+    pub utxo_count: usize,
+    pub utxo_value: u64,
+    pub utxos: Vec<(TransactionId, TransactionOutput, usize)>,
+}
+
+/// The parts of an address to fetch, one request each
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AddressSections {
+    pub balance: bool,
+    pub utxos: bool,
+    pub history: bool,
+}
+
+impl AddressSections {
+    pub const ALL: AddressSections = AddressSections {
+        balance: true,
+        utxos: true,
+        history: true,
+    };
+
+    /// The sections `addr` prints: just the balance, the ones asked for, or all of them
+    pub fn from_flags(balance_only: bool, utxos: bool, history: bool) -> AddressSections {
+        if balance_only {
+            AddressSections {
+                balance: true,
+                utxos: false,
+                history: false,
+            }
+        } else if utxos || history {
+            AddressSections {
+                balance: false,
+                utxos,
+                history,
+            }
+        } else {
+            AddressSections::ALL
+        }
+    }
+}
+
+/// What `addr` prints about an address
+#[derive(Clone, Copy, Debug)]
+pub enum AddressView {
+    /// The spendable balance and the block rewards below `depth` confirmations
+    Maturity { depth: usize },
+    /// Totals and activity, see `AddressSummary`
+    Summary,
+    /// The sections asked for, with `limit` UTXOs after `offset` of them in `sort` order
+    Sections {
+        sections: AddressSections,
+        sort: Option<UtxoSort>,
+        offset: usize,
+        limit: Option<usize>,
+    },
+}
+
+/// Orders of an address' UTXO listing
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum UtxoSort {
+    /// Biggest first
+    Value,
+    /// Oldest first
+    Age,
+}
+
+impl AddressInfo {
+    /// Keep `limit` UTXOs after skipping `offset` of them in `sort` order, the node's order without one.
+    /// The history is in chain order, so it dates UTXOs without looking up their transactions.
+    pub fn select_utxos(&mut self, sort: Option<UtxoSort>, offset: usize, limit: Option<usize>) {
+        let Some(AddressUtxos { utxos, .. }) = &mut self.utxos else {
+            return;
+        };
+        match sort {
+            Some(UtxoSort::Value) => {
+                utxos.sort_by(|a, b| b.1.amount.cmp(&a.1.amount).then(a.2.cmp(&b.2)))
+            }
+            Some(UtxoSort::Age) => {
+                let positions: HashMap<Hash, usize> = self
+                    .history
+                    .iter()
+                    .flatten()
+                    .enumerate()
+                    .map(|(position, tx_id)| (*tx_id, position))
+                    .collect();
+                utxos.sort_by_key(|(tx_id, _, index)| {
+                    (positions.get(tx_id).copied().unwrap_or(usize::MAX), *index)
+                });
+            }
+            None => {}
+        }
+        utxos.drain(..offset.min(utxos.len()));
+        if let Some(limit) = limit {
+            utxos.truncate(limit);
+        }
+    }
+}
+
+/// Where a transaction is, as far as the node knows
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "kebab-case")]
+pub enum TransactionStatus {
+    Confirmed {
+        height: usize,
+        block: Hash,
+        confirmations: usize,
+    },
+    Mempool,
+    /// Confirmed, but the block holding it wasn't found
+    Unknown,
+}
+
+/// A transaction and the block it was mined in
+#[derive(Debug, Serialize)]
+pub struct TransactionInfo {
+    #[serde(flatten)]
+    pub transaction: Transaction,
+    #[serde(flatten)]
+    pub status: TransactionStatus,
+    #[serde(flatten)]
+    pub value: TransactionValue,
+    /// The outputs spent by the inputs in input order, `None` where their transaction wasn't found
+    pub spent: Vec<Option<TransactionOutput>>,
+}
+
+/// Look up a block by height (number) or hash (base36)
+pub async fn block<P: BlockchainDataProvider + ?Sized>(provider: &P, id: &str) -> Result<Block> {
+    let block = if let Ok(height) = id.parse::<usize>() {
+        debug!("get_block_by_height {}", height);
+        provider.get_block_by_height(height).await?
+    } else if let Some(hash) = Hash::new_from_base36(id) {
+        debug!("get_block_by_hash {}", id);
+        provider.get_block_by_hash(hash).await?
+    } else {
+        return Err(error(
+            ErrorKind::InvalidIdentifier,
+            format!("Block identifier {id} is not valid. Expected base36 hash or height."),
+        ));
+    };
+    block.ok_or_else(|| error(ErrorKind::NotFound, format!("Block {id} not found")))
+}
+
+pub fn parse_transaction_id(id: &str) -> Result<TransactionId> {
+    TransactionId::new_from_base36(id).ok_or_else(|| {
+        error(
+            ErrorKind::InvalidIdentifier,
+            format!("Transaction identifier {id} is not valid. Expected base36 transaction id"),
+        )
+    })
+}
+
+/// Look up a transaction by its base36 id
+pub async fn transaction(client: &NodeClient, id: &str) -> Result<Transaction> {
+    let tx_id = parse_transaction_id(id)?;
+    debug!("get_transaction {}", id);
+    client
+        .get_transaction(&tx_id)
+        .await?
+        .ok_or_else(|| error(ErrorKind::NotFound, format!("Transaction {id} not found")))
+}
+
+/// Look up a transaction by its base36 id in the chain and then in the mempool
+pub async fn transaction_info(client: &NodeClient, id: &str) -> Result<TransactionInfo> {
+    let tx_id = parse_transaction_id(id)?;
+    debug!("get_transaction {}", id);
+    let info = async |transaction: Transaction, status| {
+        let spent = resolve_inputs(client, &transaction).await?;
+        Ok::<_, anyhow::Error>(TransactionInfo {
+            value: transaction_value(&transaction, &spent)?,
+            transaction,
+            status,
+            spent,
+        })
+    };
+    if let Some(transaction) = client.get_transaction(&tx_id).await? {
+        let status = match transaction_height(client, &transaction).await? {
+            Some(height) => TransactionStatus::Confirmed {
+                height,
+                block: client
+                    .get_block_hash_by_height(height)
+                    .await?
+                    .ok_or_else(|| anyhow!("Missing block {}", height))?,
+                confirmations: client.get_height().await?.saturating_sub(height),
+            },
+            None => TransactionStatus::Unknown,
+        };
+        return info(transaction, status).await;
+    }
+
+    debug!("get_mempool");
+    let transaction = client
+        .get_mempool()
+        .await?
+        .into_iter()
+        .find(|tx| tx.transaction_id == Some(tx_id))
+        .ok_or_else(|| error(ErrorKind::NotFound, format!("Transaction {id} not found")))?;
+    info(transaction, TransactionStatus::Mempool).await
+}
+
+/// The outputs spent by the inputs of `tx`, `None` for those whose transaction wasn't found
+pub async fn resolve_inputs(
+    client: &NodeClient,
+    tx: &Transaction,
+) -> Result<Vec<Option<TransactionOutput>>> {
+    let mut previous: HashMap<TransactionId, Option<Transaction>> = HashMap::new();
+    let mut spent = Vec::with_capacity(tx.inputs.len());
+    for input in &tx.inputs {
+        let transaction = match previous.entry(input.transaction_id) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                debug!("get_transaction {}", input.transaction_id.dump_base36());
+                entry.insert(client.get_transaction(&input.transaction_id).await?)
+            }
+        };
+        spent.push(
+            transaction
+                .as_ref()
+                .and_then(|transaction| transaction.outputs.get(input.output_index))
+                .cloned(),
+        );
+    }
+    Ok(spent)
+}
+
+/// Height of the main chain block holding a confirmed transaction.
+/// The node has no request for it, but a transaction can only be mined before it expires
+/// `EXPIRATION_TIME` seconds after its timestamp, so only the blocks of that window are searched.
+pub async fn transaction_height<P: BlockchainDataProvider + ?Sized>(
+    provider: &P,
+    tx: &Transaction,
+) -> Result<Option<usize>> {
+    // A block late by a target time on the transaction clock still counts
+    let earliest = tx.timestamp.saturating_sub(TARGET_TIME);
+    let latest = tx.timestamp + EXPIRATION_TIME + TARGET_TIME;
+    let height = provider.get_height().await?;
+
+    // Blocks missing from a fixture sit below the ones it recorded
+    let (mut low, mut high) = (0, height);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        debug!("get_block_by_height {}", middle);
+        match provider.get_block_by_height(middle).await? {
+            Some(block) if block.timestamp >= earliest => high = middle,
+            _ => low = middle + 1,
+        }
+    }
+
+    for height in low..height {
+        debug!("get_block_by_height {}", height);
+        let Some(block) = provider.get_block_by_height(height).await? else {
+            break;
+        };
+        if block.timestamp > latest {
+            break;
+        }
+        if block
+            .transactions
+            .iter()
+            .any(|candidate| candidate.transaction_id == tx.transaction_id)
+        {
+            return Ok(Some(height));
+        }
+    }
+    Ok(None)
+}
+
+/// Parse a base36 address, reporting an invalid one as an `InvalidIdentifier` error
+pub fn parse_address(address: &str) -> Result<Public> {
+    Public::new_from_base36(address).ok_or_else(|| {
+        error(
+            ErrorKind::InvalidIdentifier,
+            format!("Public address {address} is not valid. Expected base36 address"),
+        )
+    })
+}
+
+/// Fetch the balance, UTXOs and transaction history of a base36 address, those in `sections`
+pub async fn address(
+    client: &NodeClient,
+    address: &str,
+    sections: AddressSections,
+) -> Result<AddressInfo> {
+    let public = parse_address(address)?;
+    let mut info = AddressInfo {
+        address: address.to_string(),
+        balance: None,
+        utxos: None,
+        history: None,
+    };
+    if sections.balance {
+        debug!("get_balance {}", address);
+        info.balance = Some(client.get_balance(public).await?);
+    }
+    if sections.utxos {
+        debug!("get_available_transaction_outputs {}", address);
+        let utxos = client.get_available_transaction_outputs(public).await?;
+        info.utxos = Some(AddressUtxos {
+            utxo_count: utxos.len(),
+            utxo_value: utxos.iter().map(|(_, output, _)| output.amount).sum(),
+            utxos,
+        });
+    }
+    if sections.history {
+        debug!("get_transactions_of_address {}", address);
+        info.history = Some(client.get_transactions_of_address(public).await?);
+    }
+    Ok(info)
+}
+
+/// Confirmations of each of `utxos`, `None` when the block holding its transaction wasn't found.
+/// Each transaction takes a lookup and a search of its blocks, so only the table dates them.
+pub async fn utxo_confirmations(
+    client: &NodeClient,
+    utxos: &[(TransactionId, TransactionOutput, usize)],
+) -> Result<Vec<Option<usize>>> {
+    let height = client.get_height().await?;
+    let mut mined: HashMap<TransactionId, Option<usize>> = HashMap::new();
+    for (tx_id, _, _) in utxos {
+        if mined.contains_key(tx_id) {
+            continue;
+        }
+        debug!("get_transaction {}", tx_id.dump_base36());
+        let tx_height = match client.get_transaction(tx_id).await? {
+            Some(tx) => transaction_height(client, &tx).await?,
+            None => None,
+        };
+        mined.insert(*tx_id, tx_height);
+    }
+    Ok(utxos
+        .iter()
+        .map(|(tx_id, _, _)| mined[tx_id].map(|mined| height.saturating_sub(mined)))
+        .collect())
+}
+
+/// Confirmations a block reward needs before it can be spent.
+/// snap-coin has no maturity rule, reward outputs are spendable from the next block like any other.
+pub const COINBASE_MATURITY: usize = 0;
+
+/// A block reward output of an address still short of the maturity depth
+#[derive(Debug, Serialize)]
+pub struct ImmatureOutput {
+    pub transaction_id: TransactionId,
+    pub output_index: usize,
+    pub amount: u64,
+    /// Height of the block paying the reward
+    pub height: usize,
+    pub confirmations: usize,
+    /// Height of the first block that can spend it
+    pub unlock_height: usize,
+}
+
+/// An address' UTXOs split into spendable ones and block rewards below the maturity depth
+#[derive(Debug, Serialize)]
+pub struct AddressMaturity {
+    pub address: String,
+    pub height: usize,
+    pub depth: usize,
+    pub spendable: u64,
+    pub immature: u64,
+    /// Soonest to unlock first
+    pub immature_outputs: Vec<ImmatureOutput>,
+}
+
+/// Split the UTXOs of a base36 address by whether the block rewards among them have `depth` confirmations.
+/// Only the last `depth` blocks can hold an immature reward, so only their coinbases are looked at.
+pub async fn address_maturity<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    address: &str,
+    depth: usize,
+) -> Result<AddressMaturity> {
+    let public = parse_address(address)?;
+    debug!("get_available_transaction_outputs {}", address);
+    let utxos = provider.get_available_transaction_outputs(public).await?;
+    let height = provider.get_height().await?;
+
+    // A reward mined at `h` has `height - h` confirmations
+    let mut rewards: HashMap<TransactionId, usize> = HashMap::new();
+    for_each_block(
+        provider,
+        (height + 1).saturating_sub(depth)..height,
+        |h, block| {
+            if let Some(id) = coinbase(&block).and_then(|tx| tx.transaction_id) {
+                rewards.insert(id, h);
+            }
+            Ok(())
+        },
+    )
+    .await?;
+
+    let mut maturity = AddressMaturity {
+        address: address.to_string(),
+        height,
+        depth,
+        spendable: 0,
+        immature: 0,
+        immature_outputs: Vec::new(),
+    };
+    for (transaction_id, output, output_index) in utxos {
+        match rewards.get(&transaction_id) {
+            Some(&mined) => {
+                maturity.immature += output.amount;
+                maturity.immature_outputs.push(ImmatureOutput {
+                    transaction_id,
+                    output_index,
+                    amount: output.amount,
+                    height: mined,
+                    confirmations: height - mined,
+                    unlock_height: mined + depth,
+                });
+            }
+            None => maturity.spendable += output.amount,
+        }
+    }
+    maturity
+        .immature_outputs
+        .sort_by_key(|output| (output.unlock_height, output.output_index));
+    Ok(maturity)
+}
+
+/// Totals of an address without its UTXOs and history
+#[derive(Debug, Serialize)]
+pub struct AddressSummary {
+    pub address: String,
+    pub balance: u64,
+    pub utxo_count: usize,
+    /// Paid to the address over its history, change included
+    pub received: u64,
+    /// Spent from the address, what it received less what's left
+    pub sent: u64,
+    /// Heights of the blocks holding the first and last transaction of the address
+    pub first_seen: Option<usize>,
+    pub last_seen: Option<usize>,
+}
+
+/// Sum up the history of a base36 address, one request per transaction it took part in
+pub async fn address_summary(client: &NodeClient, address: &str) -> Result<AddressSummary> {
+    let public = parse_address(address)?;
+    debug!(
+        "get_balance, get_available_transaction_outputs, get_transactions_of_address {}",
+        address
+    );
+    let balance = client.get_balance(public).await?;
+    let utxo_count = client
+        .get_available_transaction_outputs(public)
+        .await?
+        .len();
+    let history = client.get_transactions_of_address(public).await?;
+    let mut received = 0;
+    let (mut first, mut last) = (None, None);
+    for tx_id in &history {
+        debug!("get_transaction {}", tx_id.dump_base36());
+        let Some(tx) = client.get_transaction(tx_id).await? else {
+            continue;
+        };
+        received += tx
+            .outputs
+            .iter()
+            .filter(|output| output.receiver == public)
+            .map(|output| output.amount)
+            .sum::<u64>();
+        first.get_or_insert_with(|| tx.clone());
+        last = Some(tx);
+    }
+    let seen = async |tx: Option<Transaction>| match tx {
+        Some(tx) => transaction_height(client, &tx).await,
+        None => Ok(None),
+    };
+    Ok(AddressSummary {
+        address: address.to_string(),
+        balance,
+        utxo_count,
+        received,
+        sent: received.saturating_sub(balance),
+        first_seen: seen(first).await?,
+        last_seen: seen(last).await?,
+    })
+}
+
+/// Print a block by height or hash, every block listed on stdin when `id` is `-`
+pub async fn run_block(
+    provider: &(dyn BlockchainDataProvider + Send + Sync),
+    config: &Config,
+    id: &str,
+    summary_only: bool,
+    raw: bool,
+    link: Link,
+    out: Output,
+) -> Result<()> {
+    let out = stdin_lines(id, out);
+    for_each_id(id, async |id| {
+        if link.wanted() {
+            let url = explorer::block_url(&config.explorer, provider, id).await?;
+            return link.show(&out, &url);
+        }
+        let block = block(provider, id).await?;
+        if raw {
+            return out.value(&block);
+        }
+        let summary = summary::block_summary(provider, &block, config).await?;
+        match out.format {
+            OutputFormat::Text => {
+                summary::print_block_summary(&block, &summary);
+                if !summary_only {
+                    println!("Transactions:");
+                    println!("{}", tables::transactions_table(&block.transactions));
+                }
+            }
+            OutputFormat::Debug if summary_only => println!("{:#?}", summary),
+            OutputFormat::Debug => println!("{:#?}", block),
+            OutputFormat::Json if summary_only => out.json(&summary)?,
+            OutputFormat::Json => out.json(&SummarizedBlock { block, summary })?,
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Print a transaction with its status, every transaction listed on stdin when `id` is `-`
+pub async fn run_tx(
+    source: &Source,
+    config: &Config,
+    id: &str,
+    raw: bool,
+    link: Link,
+    out: Output,
+) -> Result<()> {
+    let out = stdin_lines(id, out);
+    for_each_id(id, async |id| {
+        if link.wanted() {
+            return link.show(&out, &explorer::tx_url(&config.explorer, id)?);
+        }
+        let info = transaction_info(source.client()?, id).await?;
+        match out.format {
+            OutputFormat::Text if raw => println!("{:#?}", info.transaction),
+            OutputFormat::Text => summary::print_transaction(&info),
+            OutputFormat::Debug => println!("{:#?}", info),
+            OutputFormat::Json if raw => out.json(&info.transaction)?,
+            OutputFormat::Json => out.json(&info)?,
+        }
+        Ok(())
+    })
+    .await
+}
+
+/// Print an address or label as `view` asks, every address listed on stdin when it is `-`
+pub async fn run_addr(
+    source: &Source,
+    config: &Config,
+    address: &str,
+    view: AddressView,
+    link: Link,
+    out: Output,
+    fiat: Option<&Quote>,
+) -> Result<()> {
+    let out = stdin_lines(address, out);
+    for_each_id(address, async |address| {
+        let address = config.resolve_address(address);
+        if link.wanted() {
+            return link.show(&out, &explorer::address_url(&config.explorer, address)?);
+        }
+        let (sections, sort, offset, limit) = match view {
+            AddressView::Maturity { depth } => {
+                let maturity = address_maturity(source.provider(), address, depth).await?;
+                return show_address_maturity(&out, &maturity, fiat);
+            }
+            AddressView::Summary => {
+                let summary = address_summary(source.client()?, address).await?;
+                return show_address_summary(&out, &summary, fiat);
+            }
+            AddressView::Sections {
+                sections,
+                sort,
+                offset,
+                limit,
+            } => (sections, sort, offset, limit),
+        };
+        // Sorting by age dates the UTXOs by the history
+        let fetch = AddressSections {
+            history: sections.history || sections.utxos && sort == Some(UtxoSort::Age),
+            ..sections
+        };
+        let client = source.client()?;
+        let mut info = self::address(client, address, fetch).await?;
+        info.select_utxos(sort, offset, limit);
+        if !sections.history {
+            info.history = None;
+        }
+        // Dating the UTXOs costs requests per transaction, only the table shows it
+        let confirmations = match (&info.utxos, out.format) {
+            (Some(utxos), OutputFormat::Text) => utxo_confirmations(client, &utxos.utxos).await?,
+            _ => Vec::new(),
+        };
+        show_address(&out, &info, &confirmations, offset, fiat)
+    })
+    .await
+}
+
+fn show_address(
+    out: &Output,
+    info: &AddressInfo,
+    confirmations: &[Option<usize>],
+    offset: usize,
+    fiat: Option<&Quote>,
+) -> Result<()> {
+    match out.format {
+        OutputFormat::Text => {
+            println!("Address: {}", color::hash(&info.address));
+            if let Some(balance) = info.balance {
+                println!(
+                    "Balance: {} SNAP{}",
+                    color::amount(format_snap(balance)),
+                    price::annotate(fiat, balance)
+                );
+            }
+            if let Some(utxos) = &info.utxos {
+                println!(
+                    "Available UTXOS:\n{}",
+                    tables::utxo_table(&utxos.utxos, confirmations)
+                );
+                let shown = if utxos.utxos.is_empty() {
+                    "none".to_string()
+                } else {
+                    format!("{}-{}", offset + 1, offset + utxos.utxos.len())
+                };
+                println!(
+                    "Showing {} of {} UTXOs, {} SNAP{} in total",
+                    shown,
+                    utxos.utxo_count,
+                    color::amount(format_snap(utxos.utxo_value)),
+                    price::annotate(fiat, utxos.utxo_value)
+                );
+            }
+            if let Some(history) = &info.history {
+                println!("Transaction history:");
+                for tx in history {
+                    println!("  {}", color::hash(tx.dump_base36()));
+                }
+            }
+        }
+        OutputFormat::Debug => {
+            if let Some(balance) = info.balance {
+                println!("Balance: {:#?} SNAP", to_snap(balance));
+            }
+            if let Some(utxos) = &info.utxos {
+                println!("Available UTXOS:\n{:#?}", utxos.utxos);
+            }
+            if let Some(history) = &info.history {
+                println!("Transaction history (blocks):\n{:?}", history);
+            }
+        }
+        OutputFormat::Json => {
+            let mut amounts = Vec::new();
+            amounts.extend(info.balance.map(|balance| ("balance", balance)));
+            amounts.extend(
+                info.utxos
+                    .as_ref()
+                    .map(|utxos| ("utxo_value", utxos.utxo_value)),
+            );
+            out.json(&price::with_fiat(info, fiat, &amounts)?)?
+        }
+    }
+    Ok(())
+}
+
+fn show_address_summary(
+    out: &Output,
+    summary: &AddressSummary,
+    fiat: Option<&Quote>,
+) -> Result<()> {
+    match out.format {
+        OutputFormat::Text => {
+            let height = |height: Option<usize>| {
+                height
+                    .map(|height| format!("block {}", height))
+                    .unwrap_or_else(|| "unknown".to_string())
+            };
+            println!("Address: {}", color::hash(&summary.address));
+            println!(
+                "Balance: {} SNAP{}",
+                color::amount(format_snap(summary.balance)),
+                price::annotate(fiat, summary.balance)
+            );
+            println!("UTXOs: {}", summary.utxo_count);
+            println!(
+                "Received: {} SNAP{}",
+                color::amount(format_snap(summary.received)),
+                price::annotate(fiat, summary.received)
+            );
+            println!(
+                "Sent: {} SNAP{}",
+                color::amount(format_snap(summary.sent)),
+                price::annotate(fiat, summary.sent)
+            );
+            println!("First seen: {}", height(summary.first_seen));
+            println!("Last seen: {}", height(summary.last_seen));
+        }
+        OutputFormat::Debug => println!("{:#?}", summary),
+        OutputFormat::Json => out.json(&price::with_fiat(
+            summary,
+            fiat,
+            &[
+                ("balance", summary.balance),
+                ("received", summary.received),
+                ("sent", summary.sent),
+            ],
+        )?)?,
+    }
+    Ok(())
+}
+
+fn show_address_maturity(
+    out: &Output,
+    maturity: &AddressMaturity,
+    fiat: Option<&Quote>,
+) -> Result<()> {
+    match out.format {
+        OutputFormat::Text => {
+            println!("Address: {}", color::hash(&maturity.address));
+            println!(
+                "Spendable: {} SNAP{}",
+                color::amount(format_snap(maturity.spendable)),
+                price::annotate(fiat, maturity.spendable)
+            );
+            println!(
+                "Immature: {} SNAP{} in {} block rewards below {} confirmations",
+                color::amount(format_snap(maturity.immature)),
+                price::annotate(fiat, maturity.immature),
+                maturity.immature_outputs.len(),
+                maturity.depth
+            );
+            if !maturity.immature_outputs.is_empty() {
+                println!("{}", tables::immature_table(&maturity.immature_outputs));
+            }
+        }
+        OutputFormat::Debug => println!("{:#?}", maturity),
+        OutputFormat::Json => out.json(&price::with_fiat(
+            maturity,
+            fiat,
+            &[
+                ("spendable", maturity.spendable),
+                ("immature", maturity.immature),
+            ],
+        )?)?,
+    }
+    Ok(())
+}
+
+/// Print the chain height
+pub async fn run_height<P: BlockRangeProvider + ?Sized>(provider: &P, out: &Output) -> Result<()> {
+    let height = provider.get_height().await?;
+    match out.format {
+        OutputFormat::Text | OutputFormat::Debug => println!("Height: {}", height),
+        OutputFormat::Json => out.json(&json!({ "height": height }))?,
+    }
+    Ok(())
+}
+
+/// Print the current block and transaction difficulties
+pub async fn run_difficulty<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    out: &Output,
+) -> Result<()> {
+    let block_difficulty = provider.get_block_difficulty().await?;
+    let tx_difficulty = provider.get_transaction_difficulty().await?;
+    match out.format {
+        OutputFormat::Text | OutputFormat::Debug => {
+            println!("Block Difficulty: {}", format_biguint_hr(&block_difficulty));
+            println!(
+                "Transaction Difficulty: {}",
+                format_biguint_hr(&tx_difficulty)
+            );
+        }
+        OutputFormat::Json => out.json(&json!({
+            "block_difficulty": normalize_difficulty(&block_difficulty),
+            "transaction_difficulty": normalize_difficulty(&tx_difficulty),
+        }))?,
+    }
+    Ok(())
+}
@@ -0,0 +1,136 @@
+// rewards.rs
+use anyhow::Result;
+use serde::Serialize;
+use snap_coin::economics::{HALVING_INTERVAL, MIN_REWARD, get_block_reward};
+
+use crate::{
+    color, format_snap,
+    output::{Output, OutputFormat},
+    price::{self, Quote},
+};
+
+#[derive(Debug, Serialize)]
+pub struct RewardEra {
+    pub era: usize,
+    pub start_height: usize,
+    /// Last height of the era, `None` once the subsidy bottoms out at `MIN_REWARD`
+    pub end_height: Option<usize>,
+    pub subsidy: u64,
+    /// Total coins issued by the end of this era, `None` for the open ended tail era
+    pub cumulative_supply: Option<u64>,
+}
+
+/// Build the full emission schedule from the protocol halving constants
+pub fn emission_schedule() -> Vec<RewardEra> {
+    let mut eras = Vec::new();
+    let mut cumulative_supply = 0u64;
+    let mut era = 0;
+
+    loop {
+        let start_height = era * HALVING_INTERVAL;
+        let subsidy = get_block_reward(start_height);
+
+        // Once the reward hits the floor it never halves again
+        if subsidy <= MIN_REWARD {
+            eras.push(RewardEra {
+                era,
+                start_height,
+                end_height: None,
+                subsidy,
+                cumulative_supply: None,
+            });
+            return eras;
+        }
+
+        cumulative_supply += subsidy * HALVING_INTERVAL as u64;
+        eras.push(RewardEra {
+            era,
+            start_height,
+            end_height: Some(start_height + HALVING_INTERVAL - 1),
+            subsidy,
+            cumulative_supply: Some(cumulative_supply),
+        });
+        era += 1;
+    }
+}
+
+/// Coins issued by the blocks below `height`, summed era by era rather than block by block like
+/// `economics::total_reward`, so it's cheap at any height
+pub fn supply_at(height: usize) -> u64 {
+    let mut supply = 0u64;
+    let mut start = 0;
+    while start < height {
+        let subsidy = get_block_reward(start);
+        // Once the reward hits the floor it never halves again
+        let end = match subsidy <= MIN_REWARD {
+            true => height,
+            false => (start + HALVING_INTERVAL).min(height),
+        };
+        supply += subsidy * (end - start) as u64;
+        start = end;
+    }
+    supply
+}
+
+/// The schedule as a table, with the subsidy valued at `fiat`'s current price when given
+pub fn print_emission_schedule(eras: &[RewardEra], fiat: Option<&Quote>) {
+    let fiat_header = fiat
+        .map(|quote| {
+            format!(
+                " | {:>18}",
+                format!("Subsidy ({})", quote.currency.to_uppercase())
+            )
+        })
+        .unwrap_or_default();
+    println!(
+        "{:>4} | {:<25} | {:>20} | {:>26}{}",
+        "Era", "Heights", "Subsidy (SNAP)", "Supply at era end (SNAP)", fiat_header
+    );
+    let fiat_rule = if fiat.is_some() {
+        format!("-+-{:-<18}", "")
+    } else {
+        String::new()
+    };
+    println!(
+        "{:-<4}-+-{:-<25}-+-{:-<20}-+-{:-<26}{}",
+        "", "", "", "", fiat_rule
+    );
+
+    for era in eras {
+        let heights = match era.end_height {
+            Some(end) => format!("{} - {}", era.start_height, end),
+            None => format!("{} - ...", era.start_height),
+        };
+        let supply = match era.cumulative_supply {
+            Some(supply) => format_snap(supply),
+            None => "tail emission".to_string(),
+        };
+
+        let fiat_subsidy = fiat
+            .map(|quote| format!(" | {:>18}", format!("{:.2}", quote.value(era.subsidy))))
+            .unwrap_or_default();
+        println!(
+            "{:>4} | {:<25} | {} | {}{}",
+            era.era,
+            heights,
+            color::amount(format!("{:>20}", format_snap(era.subsidy))),
+            color::amount(format!("{:>26}", supply)),
+            fiat_subsidy
+        );
+    }
+}
+
+/// Print the emission schedule, computed purely from protocol constants so no node is needed
+pub fn run_rewards(out: &Output, fiat: Option<&Quote>) -> Result<()> {
+    let schedule = emission_schedule();
+    match out.format {
+        OutputFormat::Text | OutputFormat::Debug => print_emission_schedule(&schedule, fiat),
+        OutputFormat::Json => out.json(
+            &schedule
+                .iter()
+                .map(|era| price::with_fiat(era, fiat, &[("subsidy", era.subsidy)]))
+                .collect::<Result<Vec<_>>>()?,
+        )?,
+    }
+    Ok(())
+}
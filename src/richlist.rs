@@ -0,0 +1,265 @@
+// richlist.rs
+use anyhow::{Context, Result};
+use comfy_table::Table;
+use serde::{Deserialize, Serialize};
+use snap_coin::crypto::keys::Public;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::info;
+
+use crate::{
+    blocks::BlockRangeProvider,
+    color,
+    config::Config,
+    format_snap,
+    output::{Output, OutputFormat},
+    portfolio::format_change,
+    tables::{align_right, new_table},
+    utxos::UtxoSet,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RichEntry {
+    pub address: String,
+    /// Sum of the address's unspent outputs, in nano
+    pub balance: u64,
+}
+
+/// The largest balances at one height, written by `richlist --snapshot`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RichList {
+    /// Unix time the list was taken
+    pub timestamp: u64,
+    pub height: usize,
+    /// Value of the whole UTXO set, in nano
+    pub supply: u64,
+    /// Largest balance first, ties by address
+    pub entries: Vec<RichEntry>,
+}
+
+impl RichList {
+    /// The `top` largest balances of the owners in `set`
+    pub fn from_utxos(set: &UtxoSet, height: usize, timestamp: u64, top: usize) -> RichList {
+        let mut balances: HashMap<[u8; 32], u64> = HashMap::new();
+        for (_, utxo) in set.iter() {
+            *balances
+                .entry(*utxo.output.receiver.dump_buf())
+                .or_default() += utxo.output.amount;
+        }
+        let mut entries: Vec<RichEntry> = balances
+            .into_iter()
+            .map(|(address, balance)| RichEntry {
+                address: Public::new_from_buf(&address).dump_base36(),
+                balance,
+            })
+            .collect();
+        entries.sort_by(|a, b| {
+            b.balance
+                .cmp(&a.balance)
+                .then_with(|| a.address.cmp(&b.address))
+        });
+        entries.truncate(top);
+        RichList {
+            timestamp,
+            height,
+            supply: set.value(),
+            entries,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<RichList> {
+        let bytes = fs::read(path)
+            .with_context(|| format!("Could not read rich list snapshot {}", path.display()))?;
+        serde_json::from_slice(&bytes)
+            .with_context(|| format!("Could not parse rich list snapshot {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Could not write {}", path.display()))
+    }
+}
+
+/// Balance of an address on both lists
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BalanceChange {
+    pub address: String,
+    pub before: u64,
+    pub after: u64,
+    /// `after - before`, in nano
+    pub change: i64,
+}
+
+/// How the rich list changed between two snapshots
+#[derive(Debug, Serialize)]
+pub struct RichListDiff {
+    pub from_height: usize,
+    pub to_height: usize,
+    /// Largest increase first
+    pub increases: Vec<BalanceChange>,
+    /// Largest decrease first
+    pub decreases: Vec<BalanceChange>,
+    /// On the new list only, with their new balance
+    pub entered: Vec<RichEntry>,
+    /// On the old list only, with their old balance
+    pub dropped: Vec<RichEntry>,
+}
+
+/// Compare two rich lists. Addresses on both with an unchanged balance are left out; an address
+/// that dropped out may still hold coins, just less than the new list's smallest entry.
+pub fn diff_richlists(old: &RichList, new: &RichList) -> RichListDiff {
+    let before: HashMap<&str, u64> = old
+        .entries
+        .iter()
+        .map(|entry| (entry.address.as_str(), entry.balance))
+        .collect();
+    let after: HashSet<&str> = new
+        .entries
+        .iter()
+        .map(|entry| entry.address.as_str())
+        .collect();
+    let mut increases = Vec::new();
+    let mut decreases = Vec::new();
+    let mut entered = Vec::new();
+    for entry in &new.entries {
+        let Some(&balance) = before.get(entry.address.as_str()) else {
+            entered.push(entry.clone());
+            continue;
+        };
+        let change = BalanceChange {
+            address: entry.address.clone(),
+            before: balance,
+            after: entry.balance,
+            change: entry.balance as i64 - balance as i64,
+        };
+        match change.change {
+            0 => {}
+            1.. => increases.push(change),
+            _ => decreases.push(change),
+        }
+    }
+    increases.sort_by_key(|change| std::cmp::Reverse(change.change));
+    decreases.sort_by_key(|change| change.change);
+    RichListDiff {
+        from_height: old.height,
+        to_height: new.height,
+        increases,
+        decreases,
+        entered,
+        dropped: old
+            .entries
+            .iter()
+            .filter(|entry| !after.contains(entry.address.as_str()))
+            .cloned()
+            .collect(),
+    }
+}
+
+pub fn richlist_table(list: &RichList, label: impl Fn(&str) -> Option<String>) -> Table {
+    let mut table = new_table(&["Rank", "Address", "Balance (SNAP)", "Share"]);
+    for (rank, entry) in list.entries.iter().enumerate() {
+        table.add_row(vec![
+            (rank + 1).to_string(),
+            label(&entry.address).unwrap_or_else(|| color::hash(&entry.address)),
+            color::amount(format_snap(entry.balance)),
+            format!(
+                "{:.2}%",
+                entry.balance as f64 / list.supply.max(1) as f64 * 100.0
+            ),
+        ]);
+    }
+    align_right(&mut table, &[0, 2, 3]);
+    table
+}
+
+fn changes_table(changes: &[BalanceChange], label: &impl Fn(&str) -> Option<String>) -> Table {
+    let mut table = new_table(&["Address", "Before", "After", "Change"]);
+    for change in changes {
+        table.add_row(vec![
+            label(&change.address).unwrap_or_else(|| color::hash(&change.address)),
+            format_snap(change.before),
+            color::amount(format_snap(change.after)),
+            format_change(change.change),
+        ]);
+    }
+    align_right(&mut table, &[1, 2, 3]);
+    table
+}
+
+fn entries_table(entries: &[RichEntry], label: &impl Fn(&str) -> Option<String>) -> Table {
+    let mut table = new_table(&["Address", "Balance (SNAP)"]);
+    for entry in entries {
+        table.add_row(vec![
+            label(&entry.address).unwrap_or_else(|| color::hash(&entry.address)),
+            color::amount(format_snap(entry.balance)),
+        ]);
+    }
+    align_right(&mut table, &[1]);
+    table
+}
+
+pub fn print_richlist_diff(diff: &RichListDiff, label: impl Fn(&str) -> Option<String>) {
+    println!("Heights: {} -> {}", diff.from_height, diff.to_height);
+    let sections = [
+        ("Largest increases", &diff.increases),
+        ("Largest decreases", &diff.decreases),
+    ];
+    for (title, changes) in sections {
+        println!("{} ({}):", title, changes.len());
+        if !changes.is_empty() {
+            println!("{}", changes_table(changes, &label));
+        }
+    }
+    for (title, entries) in [
+        ("New entrants", &diff.entered),
+        ("Dropped out", &diff.dropped),
+    ] {
+        println!("{} ({}):", title, entries.len());
+        if !entries.is_empty() {
+            println!("{}", entries_table(entries, &label));
+        }
+    }
+}
+
+/// Replay the chain and print the `top` balances, or how they changed since the `diff` snapshot
+pub async fn run_richlist<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    config: &Config,
+    top: usize,
+    snapshot: Option<&Path>,
+    diff: Option<&Path>,
+    out: &Output,
+) -> Result<()> {
+    // Read the old snapshot before the replay so a bad path fails fast
+    let old = diff.map(RichList::load).transpose()?;
+    let height = provider.get_height().await?;
+    let mut set = UtxoSet::default();
+    set.replay(provider, 0..height, |_, _, _| {}).await?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let list = RichList::from_utxos(&set, height, now, top);
+    if let Some(path) = snapshot {
+        list.save(path)?;
+        info!("Saved rich list snapshot to {}", path.display());
+    }
+    let label = |address: &str| config.display_label(address);
+    match old {
+        Some(old) => {
+            let diff = diff_richlists(&old, &list);
+            match out.format {
+                OutputFormat::Text => print_richlist_diff(&diff, label),
+                OutputFormat::Debug => println!("{:#?}", diff),
+                OutputFormat::Json => out.json(&diff)?,
+            }
+        }
+        None => match out.format {
+            OutputFormat::Text => println!("{}", richlist_table(&list, label)),
+            OutputFormat::Debug => println!("{:#?}", list),
+            OutputFormat::Json => out.json(&list)?,
+        },
+    }
+    Ok(())
+}
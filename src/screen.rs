@@ -0,0 +1,433 @@
+// screen.rs
+use anyhow::{Context, Result, anyhow};
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::{
+    core::transaction::{Transaction, TransactionId},
+    crypto::keys::Public,
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    ops::Range,
+    path::Path,
+    time::Duration,
+};
+use tracing::{debug, info};
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    color,
+    config::Config,
+    connection::ReconnectingClient,
+    format_snap,
+    node::NodeClient,
+    notify,
+    output::{Output, OutputFormat},
+    service,
+    source::Source,
+    tables::{align_right, new_table},
+};
+
+/// Addresses screened for
+#[derive(Debug, Default)]
+pub struct Watchlist {
+    addresses: HashSet<[u8; 32]>,
+}
+
+impl Watchlist {
+    pub fn insert(&mut self, address: Public) {
+        self.addresses.insert(*address.dump_buf());
+    }
+
+    pub fn contains(&self, address: &Public) -> bool {
+        self.addresses.contains(address.dump_buf())
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+}
+
+/// Read a watchlist, one base36 address per line. Anything after the address, blank lines and
+/// `#` comments are ignored, so lists can carry notes.
+pub fn load_watchlist(path: &Path) -> Result<Watchlist> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Could not read watchlist {}", path.display()))?;
+    let mut watchlist = Watchlist::default();
+    for (number, line) in contents.lines().enumerate() {
+        let Some(address) = line
+            .split(|c: char| c.is_whitespace() || c == ',' || c == '#')
+            .next()
+            .filter(|address| !address.is_empty())
+        else {
+            continue;
+        };
+        let public = Public::new_from_base36(address).ok_or_else(|| {
+            anyhow!(
+                "Line {} of {}: {} is not a valid base36 address",
+                number + 1,
+                path.display(),
+                address
+            )
+        })?;
+        watchlist.insert(public);
+    }
+    if watchlist.is_empty() {
+        return Err(anyhow!("Watchlist {} has no addresses", path.display()));
+    }
+    Ok(watchlist)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Direction {
+    /// The transaction pays the address
+    Incoming,
+    /// The transaction spends outputs of the address
+    Outgoing,
+}
+
+impl Direction {
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::Incoming => "in",
+            Direction::Outgoing => "out",
+        }
+    }
+}
+
+/// A transaction touching a watchlist address
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScreenHit {
+    /// Block the transaction is in, `None` for the mempool
+    pub height: Option<usize>,
+    pub transaction: String,
+    pub address: String,
+    pub direction: Direction,
+    /// Nano paid to or spent from the address, `None` when some spent output could not be looked up
+    pub amount: Option<u64>,
+}
+
+/// Amounts of outputs, by transaction and output index, for pricing what inputs spend
+pub type KnownOutputs = HashMap<(TransactionId, usize), u64>;
+
+/// The watchlist addresses `tx` pays or spends from, one hit per address and direction. Spent
+/// amounts come from `known`, outputs missing from it leave the amount unknown.
+pub fn screen_transaction(
+    tx: &Transaction,
+    height: Option<usize>,
+    watchlist: &Watchlist,
+    known: &KnownOutputs,
+) -> Vec<ScreenHit> {
+    let Some(id) = tx.transaction_id else {
+        return Vec::new();
+    };
+    // Sorted by address so hits come out in a stable order
+    let mut hits: BTreeMap<(String, u8), ScreenHit> = BTreeMap::new();
+    for output in tx
+        .outputs
+        .iter()
+        .filter(|output| watchlist.contains(&output.receiver))
+    {
+        let address = output.receiver.dump_base36();
+        let hit = hits
+            .entry((address.clone(), 0))
+            .or_insert_with(|| ScreenHit {
+                height,
+                transaction: id.dump_base36(),
+                address,
+                direction: Direction::Incoming,
+                amount: Some(0),
+            });
+        hit.amount = hit.amount.map(|amount| amount + output.amount);
+    }
+    for input in tx
+        .inputs
+        .iter()
+        .filter(|input| watchlist.contains(&input.output_owner))
+    {
+        let address = input.output_owner.dump_base36();
+        let hit = hits
+            .entry((address.clone(), 1))
+            .or_insert_with(|| ScreenHit {
+                height,
+                transaction: id.dump_base36(),
+                address,
+                direction: Direction::Outgoing,
+                amount: Some(0),
+            });
+        let spent = known
+            .get(&(input.transaction_id, input.output_index))
+            .copied();
+        hit.amount = hit.amount.zip(spent).map(|(amount, spent)| amount + spent);
+    }
+    hits.into_values().collect()
+}
+
+/// Record the outputs of `tx` paying watchlist addresses, the only ones hits can spend
+pub fn remember_outputs(tx: &Transaction, watchlist: &Watchlist, known: &mut KnownOutputs) {
+    let Some(id) = tx.transaction_id else {
+        return;
+    };
+    for (index, output) in tx.outputs.iter().enumerate() {
+        if watchlist.contains(&output.receiver) {
+            known.insert((id, index), output.amount);
+        }
+    }
+}
+
+/// Look up the outputs spent by watchlist addresses in `tx` that `known` is missing
+pub async fn resolve_spent(
+    client: &NodeClient,
+    tx: &Transaction,
+    watchlist: &Watchlist,
+    known: &mut KnownOutputs,
+) -> Result<()> {
+    for input in &tx.inputs {
+        let key = (input.transaction_id, input.output_index);
+        if !watchlist.contains(&input.output_owner) || known.contains_key(&key) {
+            continue;
+        }
+        debug!("get_transaction {}", input.transaction_id.dump_base36());
+        if let Some(previous) = client.get_transaction(&input.transaction_id).await? {
+            remember_outputs(&previous, watchlist, known);
+        }
+    }
+    Ok(())
+}
+
+/// Screen the transactions of the blocks at `heights`. With a node client, outputs spent from
+/// before the range are looked up so outgoing amounts are known; without one they stay unknown.
+pub async fn screen_blocks<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    client: Option<&NodeClient>,
+    heights: Range<usize>,
+    watchlist: &Watchlist,
+    known: &mut KnownOutputs,
+) -> Result<Vec<ScreenHit>> {
+    info!(
+        "Screening blocks {}..{} against {} addresses",
+        heights.start,
+        heights.end,
+        watchlist.len()
+    );
+    let mut matched = Vec::new();
+    for_each_block(provider, heights, |height, block| {
+        for tx in block.transactions {
+            remember_outputs(&tx, watchlist, known);
+            let touches = tx
+                .inputs
+                .iter()
+                .any(|input| watchlist.contains(&input.output_owner))
+                || tx
+                    .outputs
+                    .iter()
+                    .any(|output| watchlist.contains(&output.receiver));
+            if touches {
+                matched.push((height, tx));
+            }
+        }
+        Ok(())
+    })
+    .await?;
+
+    let mut hits = Vec::new();
+    for (height, tx) in matched {
+        if let Some(client) = client {
+            resolve_spent(client, &tx, watchlist, known).await?;
+        }
+        hits.extend(screen_transaction(&tx, Some(height), watchlist, known));
+    }
+    Ok(hits)
+}
+
+/// What `screen --watch` carries from one poll to the next
+#[derive(Debug, Default)]
+pub struct WatchState {
+    /// First block of the next poll, `None` before the first one
+    pub next: Option<usize>,
+    known: KnownOutputs,
+    mempool_seen: HashSet<TransactionId>,
+}
+
+impl WatchState {
+    /// Screen the blocks mined since the last poll, the last `blocks` on the first one, and the
+    /// `mempool` transactions the last poll didn't see. A failed poll leaves the state where it
+    /// was, so the next one retries from the same block.
+    pub async fn poll<P: BlockRangeProvider + ?Sized>(
+        &mut self,
+        provider: &P,
+        client: Option<&NodeClient>,
+        mempool: &[Transaction],
+        watchlist: &Watchlist,
+        blocks: usize,
+    ) -> Result<Vec<ScreenHit>> {
+        let height = provider.get_height().await?;
+        let start = self.next.unwrap_or(height.saturating_sub(blocks));
+        let mut hits = if start < height {
+            screen_blocks(provider, client, start..height, watchlist, &mut self.known).await?
+        } else {
+            Vec::new()
+        };
+        for tx in mempool {
+            if tx
+                .transaction_id
+                .is_none_or(|id| self.mempool_seen.contains(&id))
+            {
+                continue;
+            }
+            if let Some(client) = client {
+                resolve_spent(client, tx, watchlist, &mut self.known).await?;
+            }
+            hits.extend(screen_transaction(tx, None, watchlist, &self.known));
+        }
+        self.next = Some(height);
+        self.mempool_seen = mempool.iter().filter_map(|tx| tx.transaction_id).collect();
+        Ok(hits)
+    }
+}
+
+/// Screen new blocks and mempool transactions every `interval` until dropped. Each hit goes to
+/// `on_hit` along with whether it is news, the past blocks of the first poll are history.
+pub async fn watch(
+    connection: &ReconnectingClient,
+    watchlist: &Watchlist,
+    blocks: usize,
+    interval: Duration,
+    mut on_hit: impl FnMut(&ScreenHit, bool) -> Result<()>,
+) -> Result<()> {
+    connection
+        .poll_every(
+            interval,
+            "Screening",
+            &mut WatchState::default(),
+            async |client, state| {
+                let news = state.next.is_some();
+                let mempool = client.get_mempool().await?;
+                let hits = state
+                    .poll(client, Some(client), &mempool, watchlist, blocks)
+                    .await?;
+                Ok((hits, news))
+            },
+            |(hits, news), _| {
+                for hit in &hits {
+                    on_hit(hit, news)?;
+                }
+                Ok(())
+            },
+        )
+        .await
+}
+
+fn amount_cell(hit: &ScreenHit) -> String {
+    hit.amount
+        .map(|amount| color::amount(format_snap(amount)))
+        .unwrap_or_else(|| "?".to_string())
+}
+
+pub fn hits_table(hits: &[ScreenHit], label: impl Fn(&str) -> Option<String>) -> Table {
+    let mut table = new_table(&["Height", "Transaction", "Address", "Dir", "Amount"]);
+    for hit in hits {
+        table.add_row(vec![
+            hit.height
+                .map_or_else(|| "mempool".to_string(), |height| height.to_string()),
+            color::hash(&hit.transaction),
+            label(&hit.address).unwrap_or_else(|| hit.address.clone()),
+            hit.direction.name().to_string(),
+            amount_cell(hit),
+        ]);
+    }
+    align_right(&mut table, &[0, 4]);
+    table
+}
+
+/// A hit as one line for the live mode, e.g. `601 <tx> in <address> 1.00000000`
+pub fn hit_line(hit: &ScreenHit, label: impl Fn(&str) -> Option<String>) -> String {
+    format!(
+        "{} {} {} {} {}",
+        hit.height
+            .map_or_else(|| "mempool".to_string(), |height| height.to_string()),
+        color::hash(&hit.transaction),
+        hit.direction.name(),
+        label(&hit.address).unwrap_or_else(|| hit.address.clone()),
+        amount_cell(hit)
+    )
+}
+
+/// Screen the past `blocks` blocks against the watchlist in `list`
+pub async fn run_screen(
+    source: &Source,
+    config: &Config,
+    list: &Path,
+    blocks: usize,
+    out: &Output,
+) -> Result<()> {
+    let watchlist = load_watchlist(list)?;
+    let provider = source.provider();
+    let height = provider.get_height().await?;
+    let start = height.saturating_sub(blocks);
+    let mut known = KnownOutputs::new();
+    let hits = screen_blocks(
+        provider,
+        source.client().ok(),
+        start..height,
+        &watchlist,
+        &mut known,
+    )
+    .await?;
+    match out.format {
+        OutputFormat::Text if hits.is_empty() => println!(
+            "No transactions of blocks {}..{} touch the watchlist",
+            start, height
+        ),
+        OutputFormat::Text => println!(
+            "{}",
+            hits_table(&hits, |address| config.display_label(address))
+        ),
+        OutputFormat::Debug => println!("{:#?}", hits),
+        OutputFormat::Json => out.json(&hits)?,
+    }
+    Ok(())
+}
+
+/// Screen new blocks and the mempool every `interval` until stopped, a line per hit and a
+/// desktop notification per new payment with `notify`
+pub async fn run_watch(
+    connection: &ReconnectingClient,
+    config: &Config,
+    list: &Path,
+    blocks: usize,
+    interval: Duration,
+    notify: bool,
+    out: &Output,
+) -> Result<()> {
+    notify::check_available(notify)?;
+    let watchlist = load_watchlist(list)?;
+    connection.log_events();
+    let label = |address: &str| config.display_label(address);
+    service::run_daemon(watch(
+        connection,
+        &watchlist,
+        blocks,
+        interval,
+        |hit, news| {
+            match out.format {
+                OutputFormat::Json => println!("{}", serde_json::to_string(hit)?),
+                _ => println!("{}", hit_line(hit, label)),
+            }
+            if notify
+                && news
+                && let Some(notification) = notify::for_screen_hit(hit, label)
+            {
+                notify::show(notification);
+            }
+            Ok(())
+        },
+    ))
+    .await
+}
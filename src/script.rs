@@ -0,0 +1,101 @@
+// script.rs
+use anyhow::{Result, anyhow};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Scope};
+use serde::Serialize;
+use serde_json::Value;
+use snap_coin_utils::{error::ErrorKind, query, source::Source};
+use std::{path::Path, sync::Arc};
+use tokio::runtime::Handle;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+/// Blocks `start..end`, fetched one at a time while a script loops over them
+#[derive(Clone)]
+struct BlockRange {
+    source: Arc<Source>,
+    handle: Handle,
+    start: i64,
+    end: i64,
+}
+
+impl IntoIterator for BlockRange {
+    type Item = ScriptResult<Dynamic>;
+    type IntoIter = Box<dyn Iterator<Item = Self::Item>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new((self.start.max(0)..self.end.max(0)).map(move |height| {
+            let block = self
+                .handle
+                .block_on(query::block(self.source.provider(), &height.to_string()));
+            to_dynamic(block)
+        }))
+    }
+}
+
+/// Missing blocks and transactions are `()` in scripts, other failures abort the script
+fn to_dynamic<T: Serialize>(value: Result<T>) -> ScriptResult<Dynamic> {
+    match value {
+        Ok(value) => rhai::serde::to_dynamic(value),
+        Err(e) if ErrorKind::of(&e) == ErrorKind::NotFound => Ok(Dynamic::UNIT),
+        Err(e) => Err(format!("{:#}", e).into()),
+    }
+}
+
+/// Run a Rhai script with chain access. Available functions:
+/// `get_height()`, `get_block(height_or_hash)`, `get_tx(id)` and `iter_blocks(start, end)`,
+/// script arguments are in the `ARGS` array. Returns the value of the last expression, if any.
+pub async fn run_script(source: Source, path: &Path, args: Vec<String>) -> Result<Option<Value>> {
+    let source = Arc::new(source);
+    let handle = Handle::current();
+    let path = path.to_path_buf();
+
+    // Rhai is synchronous, so the script runs on a blocking thread that drives requests on the runtime
+    tokio::task::spawn_blocking(move || {
+        let mut engine = Engine::new();
+
+        let (s, h) = (source.clone(), handle.clone());
+        engine.register_fn("get_height", move || -> ScriptResult<i64> {
+            let height = h
+                .block_on(s.provider().get_height())
+                .map_err(|e| e.to_string())?;
+            Ok(height as i64)
+        });
+
+        let (s, h) = (source.clone(), handle.clone());
+        engine.register_fn("get_block", move |height: i64| {
+            to_dynamic(h.block_on(query::block(s.provider(), &height.to_string())))
+        });
+
+        let (s, h) = (source.clone(), handle.clone());
+        engine.register_fn("get_block", move |hash: &str| {
+            to_dynamic(h.block_on(query::block(s.provider(), hash)))
+        });
+
+        let (s, h) = (source.clone(), handle.clone());
+        engine.register_fn("get_tx", move |id: &str| {
+            to_dynamic(h.block_on(async { query::transaction(s.client()?, id).await }))
+        });
+
+        engine.register_iterator_result::<BlockRange, Dynamic>();
+        let (s, h) = (source.clone(), handle.clone());
+        engine.register_fn("iter_blocks", move |start: i64, end: i64| BlockRange {
+            source: s.clone(),
+            handle: h.clone(),
+            start,
+            end,
+        });
+
+        let mut scope = Scope::new();
+        let args: Array = args.into_iter().map(Dynamic::from).collect();
+        scope.push_constant("ARGS", args);
+
+        let result = engine
+            .eval_file_with_scope::<Dynamic>(&mut scope, path)
+            .map_err(|e| anyhow!("Script failed: {}", e))?;
+        if result.is_unit() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::to_value(result)?))
+    })
+    .await?
+}
@@ -0,0 +1,555 @@
+// send.rs
+use anyhow::{Context, Result, anyhow};
+use comfy_table::Table;
+use serde::Serialize;
+use serde_json::json;
+use snap_coin::{
+    blockchain_data_provider::BlockchainDataProvider,
+    core::transaction::{
+        MAX_TRANSACTION_IO, Transaction, TransactionId, TransactionInput, TransactionOutput,
+    },
+    crypto::{
+        Hash, Signature,
+        keys::{Private, Public},
+    },
+    economics::EXPIRATION_TIME,
+};
+use std::{
+    cmp::Reverse,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tracing::{info, warn};
+
+use crate::{
+    color, config,
+    error::{ErrorKind, error},
+    fees::transaction_size,
+    format_hr, format_snap,
+    keystore::Keystore,
+    node::NodeClient,
+    normalize_difficulty,
+    output::{Output, OutputFormat},
+    parse_snap,
+    password::{self, PASSWORD_VAR},
+    query::parse_address,
+    tables::{align_right, new_table},
+};
+
+/// Time spent hashing to estimate how long the proof of work takes on this machine
+const HASH_RATE_SAMPLE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SelectedInput {
+    pub transaction_id: TransactionId,
+    pub index: usize,
+    pub amount: u64,
+}
+
+/// A payment planned against the sender's UTXOs, everything `send` does short of signing
+#[derive(Debug, Serialize)]
+pub struct SendPlan {
+    pub from: Public,
+    pub to: Public,
+    pub amount: u64,
+    /// UTXOs spent, biggest first
+    pub inputs: Vec<SelectedInput>,
+    pub input_value: u64,
+    /// Paid back to the sender, 0 when the inputs add up exactly
+    pub change: u64,
+    /// Inputs have to balance the outputs, so transactions carry no fee
+    pub fee: u64,
+    /// Encoded size in bytes once signed and mined
+    pub size: usize,
+    /// Live transaction difficulty, the expected number of hashes of the proof of work
+    pub transaction_difficulty: f64,
+    /// Hashes per second measured on this machine
+    pub hash_rate: f64,
+    pub estimated_pow_secs: f64,
+    #[serde(skip)]
+    live_difficulty: [u8; 32],
+}
+
+/// Spend the biggest UTXOs first until `amount` is covered, so as few inputs as possible are signed
+pub fn select_coins(
+    mut available: Vec<(TransactionId, TransactionOutput, usize)>,
+    amount: u64,
+) -> Result<Vec<SelectedInput>> {
+    available.sort_by_key(|(_, output, _)| Reverse(output.amount));
+    let mut selected = Vec::new();
+    let mut total = 0;
+    for (transaction_id, output, index) in available {
+        if total >= amount {
+            break;
+        }
+        total += output.amount;
+        selected.push(SelectedInput {
+            transaction_id,
+            index,
+            amount: output.amount,
+        });
+    }
+    if total < amount {
+        return Err(anyhow!(
+            "Insufficient funds, {} SNAP available to send {} SNAP",
+            format_snap(total),
+            format_snap(amount)
+        ));
+    }
+    Ok(selected)
+}
+
+/// Select the coins paying `amount` from `from` to `to` and estimate the proof of work
+pub async fn plan(client: &NodeClient, from: Public, to: Public, amount: u64) -> Result<SendPlan> {
+    if amount == 0 {
+        return Err(anyhow!("Amount must be above 0"));
+    }
+    let inputs = select_coins(
+        client.get_available_transaction_outputs(from).await?,
+        amount,
+    )?;
+    let input_value = inputs.iter().map(|input| input.amount).sum::<u64>();
+    let live_difficulty = client.get_live_transaction_difficulty().await?;
+    let mut plan = SendPlan {
+        from,
+        to,
+        amount,
+        inputs,
+        input_value,
+        change: input_value - amount,
+        fee: 0,
+        size: 0,
+        transaction_difficulty: normalize_difficulty(&live_difficulty),
+        hash_rate: 0.0,
+        estimated_pow_secs: 0.0,
+        live_difficulty,
+    };
+    let outputs = plan.outputs().len();
+    if plan.inputs.len() + outputs > MAX_TRANSACTION_IO {
+        return Err(anyhow!(
+            "{} inputs and {} outputs are more than the {} a transaction may have, send a smaller amount first",
+            plan.inputs.len(),
+            outputs,
+            MAX_TRANSACTION_IO
+        ));
+    }
+
+    // Placeholder signatures, id and the widest nonce give the size of the mined transaction
+    let mut sized = plan.unsigned();
+    mined_placeholders(&mut sized);
+    plan.size = transaction_size(&sized)?;
+    plan.hash_rate = hash_rate(&sized)?;
+    plan.estimated_pow_secs = plan.transaction_difficulty / plan.hash_rate;
+    Ok(plan)
+}
+
+/// Placeholder signatures, id and the widest nonce give the size of the mined transaction
+fn mined_placeholders(tx: &mut Transaction) {
+    for input in &mut tx.inputs {
+        input.signature = Some(Signature::new_from_buf(&[0; 64]));
+    }
+    tx.nonce = u64::MAX;
+    tx.transaction_id = Some(Hash::new_from_buf([0; 32]));
+}
+
+/// Size and proof of work cost of a transaction with a given number of inputs and outputs
+#[derive(Debug, Serialize)]
+pub struct TransactionEstimate {
+    pub inputs: usize,
+    pub outputs: usize,
+    /// Encoded size in bytes once signed and mined, at most
+    pub size: usize,
+    /// Always 0, like [`SendPlan::fee`]
+    pub minimum_fee: u64,
+    /// Transaction difficulty, the expected number of hashes of the proof of work
+    pub transaction_difficulty: f64,
+    /// Hashes per second measured on this machine
+    pub hash_rate: f64,
+    pub estimated_pow_secs: f64,
+}
+
+/// Estimate a transaction of `inputs` inputs and `outputs` outputs at the node's transaction difficulty.
+/// Amounts and output indexes are sized at their widest encoding, so real transactions are at most this big.
+pub async fn estimate(
+    client: &NodeClient,
+    inputs: usize,
+    outputs: usize,
+) -> Result<TransactionEstimate> {
+    if inputs == 0 || outputs == 0 {
+        return Err(anyhow!(
+            "A transaction needs at least one input and one output"
+        ));
+    }
+    if inputs + outputs > MAX_TRANSACTION_IO {
+        return Err(anyhow!(
+            "{} inputs and {} outputs are more than the {} a transaction may have",
+            inputs,
+            outputs,
+            MAX_TRANSACTION_IO
+        ));
+    }
+    let owner = Public::new_from_buf(&[0; 32]);
+    let mut tx = Transaction {
+        inputs: (0..inputs)
+            .map(|_| TransactionInput {
+                transaction_id: Hash::new_from_buf([0; 32]),
+                output_index: MAX_TRANSACTION_IO,
+                signature: None,
+                output_owner: owner,
+            })
+            .collect(),
+        outputs: (0..outputs)
+            .map(|_| TransactionOutput {
+                amount: u64::MAX,
+                receiver: owner,
+            })
+            .collect(),
+        transaction_id: None,
+        nonce: 0,
+        timestamp: now(),
+    };
+    mined_placeholders(&mut tx);
+    let transaction_difficulty = normalize_difficulty(&client.get_transaction_difficulty().await?);
+    let hash_rate = hash_rate(&tx)?;
+    Ok(TransactionEstimate {
+        inputs,
+        outputs,
+        size: transaction_size(&tx)?,
+        minimum_fee: 0,
+        transaction_difficulty,
+        hash_rate,
+        estimated_pow_secs: transaction_difficulty / hash_rate,
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Hashes per second of the proof of work hash over the transaction
+fn hash_rate(tx: &Transaction) -> Result<f64> {
+    let buf = tx.get_tx_hashing_buf()?;
+    let start = Instant::now();
+    let mut hashes = 0;
+    while hashes < 3 || start.elapsed() < HASH_RATE_SAMPLE {
+        Hash::new(&buf);
+        hashes += 1;
+    }
+    Ok(hashes as f64 / start.elapsed().as_secs_f64())
+}
+
+impl SendPlan {
+    fn outputs(&self) -> Vec<TransactionOutput> {
+        let mut outputs = vec![TransactionOutput {
+            amount: self.amount,
+            receiver: self.to,
+        }];
+        if self.change > 0 {
+            outputs.push(TransactionOutput {
+                amount: self.change,
+                receiver: self.from,
+            });
+        }
+        outputs
+    }
+
+    fn unsigned(&self) -> Transaction {
+        Transaction {
+            inputs: self
+                .inputs
+                .iter()
+                .map(|input| TransactionInput {
+                    transaction_id: input.transaction_id,
+                    output_index: input.index,
+                    signature: None,
+                    output_owner: self.from,
+                })
+                .collect(),
+            outputs: self.outputs(),
+            transaction_id: None,
+            nonce: 0,
+            timestamp: now(),
+        }
+    }
+
+    /// Sign the planned transaction and mine it at the live difficulty, blocking until the proof of work is found
+    pub fn build(&self, private: &Private) -> Result<Transaction> {
+        if private.to_public() != self.from {
+            return Err(anyhow!("The key doesn't own {}", self.from.dump_base36()));
+        }
+        let mut tx = self.unsigned();
+        let buf = tx.get_input_signing_buf()?;
+        let mut private = *private;
+        for input in &mut tx.inputs {
+            input.signature = Some(Signature::new_signature(&mut private, &buf));
+        }
+        tx.compute_pow(&self.live_difficulty, None)?;
+        Ok(tx)
+    }
+}
+
+pub fn inputs_table(inputs: &[SelectedInput]) -> Table {
+    let mut table = new_table(&["TX ID", "Index", "Amount (SNAP)"]);
+    for input in inputs {
+        table.add_row(vec![
+            color::hash(input.transaction_id.dump_base36()),
+            input.index.to_string(),
+            color::amount(format_snap(input.amount)),
+        ]);
+    }
+    align_right(&mut table, &[1, 2]);
+    table
+}
+
+pub fn print_plan(plan: &SendPlan) {
+    println!(
+        "Send {} SNAP to {}",
+        color::amount(format_snap(plan.amount)),
+        color::hash(plan.to.dump_base36())
+    );
+    println!(
+        "Inputs: {} SNAP from {} UTXOs\n{}",
+        color::amount(format_snap(plan.input_value)),
+        plan.inputs.len(),
+        inputs_table(&plan.inputs)
+    );
+    if plan.change > 0 {
+        println!(
+            "Change: {} SNAP back to {}",
+            color::amount(format_snap(plan.change)),
+            color::hash(plan.from.dump_base36())
+        );
+    } else {
+        println!("Change: none");
+    }
+    println!("Fee: {} SNAP", color::amount(format_snap(plan.fee)));
+    println!("Size: {} bytes", plan.size);
+    println!(
+        "Proof of work: {} hashes at {}H/s, about {:.1}s",
+        format_hr(plan.transaction_difficulty),
+        format_hr(plan.hash_rate),
+        plan.estimated_pow_secs
+    );
+}
+
+pub fn print_estimate(estimate: &TransactionEstimate) {
+    println!(
+        "{} inputs, {} outputs: at most {} bytes",
+        estimate.inputs, estimate.outputs, estimate.size
+    );
+    println!(
+        "Minimum fee: {} SNAP",
+        color::amount(format_snap(estimate.minimum_fee))
+    );
+    println!(
+        "Proof of work: {} hashes at {}H/s, about {:.1}s",
+        format_hr(estimate.transaction_difficulty),
+        format_hr(estimate.hash_rate),
+        estimate.estimated_pow_secs
+    );
+}
+
+/// `sent.jsonl` next to the config file, every transaction `send` submitted
+pub fn default_sent_log() -> Option<PathBuf> {
+    config::default_path().map(|path| path.with_file_name("sent.jsonl"))
+}
+
+/// Append a submitted transaction to the sent log, one JSON line each
+pub fn record_sent(path: &Path, tx: &Transaction) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Could not open {}", path.display()))?;
+    writeln!(file, "{}", serde_json::to_string(tx)?)?;
+    Ok(())
+}
+
+/// A transaction of the sent log, the last one recorded with that id
+pub fn find_sent(path: &Path, id: &TransactionId) -> Result<Option<Transaction>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+    let mut found = None;
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let tx: Transaction = serde_json::from_str(line)
+            .with_context(|| format!("Invalid line {} in {}", number + 1, path.display()))?;
+        if tx.transaction_id.as_ref() == Some(id) {
+            found = Some(tx);
+        }
+    }
+    Ok(found)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "outcome")]
+pub enum Rebroadcast {
+    /// Already in a block, nothing to do
+    Confirmed,
+    /// Still waiting in the node's mempool
+    InMempool,
+    /// Past its expiration time, nodes reject it for good and it has to be sent again
+    Expired,
+    Resubmitted,
+    Rejected {
+        reason: String,
+    },
+}
+
+/// Hand a transaction to the node again unless it's confirmed, waiting or expired
+pub async fn rebroadcast(client: &NodeClient, tx: Transaction) -> Result<Rebroadcast> {
+    let id = tx
+        .transaction_id
+        .ok_or_else(|| anyhow!("The transaction has no id, it was never mined"))?;
+    if client.get_transaction(&id).await?.is_some() {
+        return Ok(Rebroadcast::Confirmed);
+    }
+    let mempool = client.get_mempool().await?;
+    if mempool
+        .iter()
+        .any(|pending| pending.transaction_id == Some(id))
+    {
+        return Ok(Rebroadcast::InMempool);
+    }
+    if tx.timestamp + EXPIRATION_TIME < now() {
+        return Ok(Rebroadcast::Expired);
+    }
+    Ok(match client.submit_transaction(tx).await? {
+        Ok(()) => Rebroadcast::Resubmitted,
+        Err(e) => Rebroadcast::Rejected {
+            reason: e.to_string(),
+        },
+    })
+}
+
+/// Send `amount` from the stored key `from` to `to`, or only print the plan with `dry_run`
+pub async fn run_send(
+    client: &NodeClient,
+    keystore: &Keystore,
+    from: &str,
+    to: &str,
+    amount: &str,
+    dry_run: bool,
+    out: &Output,
+) -> Result<()> {
+    let sender = parse_address(&keystore.load(from)?.address)?;
+    let receiver = parse_address(to)?;
+    let plan = plan(client, sender, receiver, parse_snap(amount)?).await?;
+    if dry_run {
+        match out.format {
+            OutputFormat::Text => print_plan(&plan),
+            OutputFormat::Debug => println!("{:#?}", plan),
+            OutputFormat::Json => out.json(&plan)?,
+        }
+        return Ok(());
+    }
+    let private = keystore.unlock(from, &password::password(PASSWORD_VAR, "Password: ")?)?;
+    info!(
+        "Computing the proof of work, about {:.1}s",
+        plan.estimated_pow_secs
+    );
+    let tx = tokio::task::spawn_blocking(move || plan.build(&private)).await??;
+    let id = tx
+        .transaction_id
+        .unwrap_or_else(|| Hash::new_from_buf([0; 32]));
+    if let Err(e) = client.submit_transaction(tx.clone()).await? {
+        return Err(error(
+            ErrorKind::NodeError,
+            format!("The node rejected the transaction: {e}"),
+        ));
+    }
+    // Kept for `rebroadcast`, the transaction is sent either way
+    if let Some(log) = default_sent_log()
+        && let Err(e) = record_sent(&log, &tx)
+    {
+        warn!(
+            "Could not record the transaction in {}: {:#}",
+            log.display(),
+            e
+        );
+    }
+    match out.format {
+        OutputFormat::Text | OutputFormat::Debug => {
+            println!("Sent {}", color::hash(id.dump_base36()))
+        }
+        OutputFormat::Json => out.json(&json!({ "transaction_id": id }))?,
+    }
+    Ok(())
+}
+
+/// Submit the sent transaction `id` again, read from `file` or the sent log
+pub async fn run_rebroadcast(
+    client: &NodeClient,
+    id: &str,
+    file: Option<&Path>,
+    out: &Output,
+) -> Result<()> {
+    let tx_id = TransactionId::new_from_base36(id).ok_or_else(|| {
+        error(
+            ErrorKind::InvalidIdentifier,
+            format!("Transaction identifier {id} is not valid. Expected base36 transaction id"),
+        )
+    })?;
+    let tx = match file {
+        Some(file) => serde_json::from_str(&fs::read_to_string(file)?)
+            .with_context(|| format!("Could not parse transaction {}", file.display()))?,
+        None => {
+            let log = default_sent_log()
+                .ok_or_else(|| anyhow!("No config directory for the sent log, pass --file"))?;
+            find_sent(&log, &tx_id)?.ok_or_else(|| {
+                error(
+                    ErrorKind::NotFound,
+                    format!("Transaction {id} is not in {}, pass --file", log.display()),
+                )
+            })?
+        }
+    };
+    if tx.transaction_id != Some(tx_id) {
+        return Err(anyhow!("The transaction in the file is not {id}"));
+    }
+    let outcome = rebroadcast(client, tx).await?;
+    match out.format {
+        OutputFormat::Text => match &outcome {
+            Rebroadcast::Confirmed => println!("Already confirmed"),
+            Rebroadcast::InMempool => println!("Already in the mempool"),
+            Rebroadcast::Expired => {
+                println!("Expired, nodes won't accept it anymore, send it again")
+            }
+            Rebroadcast::Resubmitted => println!("Resubmitted"),
+            Rebroadcast::Rejected { reason } => println!("Rejected: {}", reason),
+        },
+        OutputFormat::Debug => println!("{:#?}", outcome),
+        OutputFormat::Json => out.json(&outcome)?,
+    }
+    Ok(())
+}
+
+/// Print the size, minimum fee and proof of work time of a transaction with `inputs` inputs and `outputs` outputs
+pub async fn run_estimate(
+    client: &NodeClient,
+    inputs: usize,
+    outputs: usize,
+    out: &Output,
+) -> Result<()> {
+    let estimate = estimate(client, inputs, outputs).await?;
+    match out.format {
+        OutputFormat::Text => print_estimate(&estimate),
+        OutputFormat::Debug => println!("{:#?}", estimate),
+        OutputFormat::Json => out.json(&estimate)?,
+    }
+    Ok(())
+}
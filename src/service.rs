@@ -0,0 +1,463 @@
+// service.rs
+use anyhow::{Context, Result, anyhow};
+use std::{
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Condvar, Mutex, OnceLock},
+};
+use tokio::sync::Notify;
+use tracing::{Level, Metadata, debug, info, warn};
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::systemd;
+
+/// Name of the Windows service and of the monitor in the platform log
+pub const SERVICE_NAME: &str = "snap-coin-monitor";
+
+/// The command line a detached monitor is started with: `args` without the program name and the
+/// `remove`d flags, followed by `add`
+pub fn detached_args(args: &[OsString], remove: &[&str], add: &[&str]) -> Vec<OsString> {
+    args.iter()
+        .skip(1)
+        .filter(|arg| !remove.iter().any(|flag| arg.as_os_str() == *flag))
+        .cloned()
+        .chain(add.iter().map(OsString::from))
+        .collect()
+}
+
+/// Whether a process with `pid` is running. Only unix can tell, elsewhere every process is
+/// assumed gone so a stale pid file never blocks a start.
+pub fn process_running(pid: u32) -> bool {
+    #[cfg(unix)]
+    {
+        // Signal 0 only checks the process exists, EPERM means it does but belongs to another user
+        // 0 and negative pids would address process groups
+        let Some(pid) = libc::pid_t::try_from(pid).ok().filter(|&pid| pid > 0) else {
+            return false;
+        };
+        // SAFETY: kill with signal 0 sends nothing
+        let sent = unsafe { libc::kill(pid, 0) };
+        sent == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        false
+    }
+}
+
+/// The pid of a running process a pid file names, `None` when there is no file or it's stale
+pub fn running_pid(path: &Path) -> Result<Option<u32>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Could not read {}", path.display())),
+    };
+    Ok(contents
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&pid| process_running(pid)))
+}
+
+/// Our pid in a file for init scripts and `kill $(cat ...)`, removed again when dropped
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Write the pid of this process to `path`, refusing when it names another running process
+    pub fn create(path: &Path) -> Result<PidFile> {
+        if let Some(pid) = running_pid(path)?
+            && pid != std::process::id()
+        {
+            return Err(anyhow!(
+                "{} says the monitor already runs as pid {}",
+                path.display(),
+                pid
+            ));
+        }
+        fs::write(path, format!("{}\n", std::process::id()))
+            .with_context(|| format!("Could not write {}", path.display()))?;
+        Ok(PidFile {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Could not remove {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Start the program again with `args` in a new session, detached from the terminal, and
+/// return its pid. The working directory is kept, so relative paths still work.
+#[cfg(unix)]
+pub fn daemonize(args: &[OsString]) -> Result<u32> {
+    use std::{
+        os::unix::process::CommandExt,
+        process::{Command, Stdio},
+    };
+
+    let program = std::env::current_exe().context("Could not find the running executable")?;
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // SAFETY: setsid is async signal safe, nothing else runs between fork and exec
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    let child = command.spawn().context("Could not start the daemon")?;
+    Ok(child.id())
+}
+
+#[cfg(not(unix))]
+pub fn daemonize(_args: &[OsString]) -> Result<u32> {
+    Err(anyhow!(
+        "--daemon needs a unix system, use --install-service on Windows"
+    ))
+}
+
+/// Where a detached monitor's events and diagnostics go, syslog on unix and the Event Log on
+/// Windows
+enum PlatformLog {
+    #[cfg(unix)]
+    Syslog(std::os::unix::net::UnixDatagram),
+    #[cfg(windows)]
+    EventLog(windows_sys::Win32::Foundation::HANDLE),
+}
+
+// SAFETY: an event source handle may be used from any thread
+#[cfg(windows)]
+unsafe impl Send for PlatformLog {}
+#[cfg(windows)]
+unsafe impl Sync for PlatformLog {}
+
+static PLATFORM_LOG: OnceLock<PlatformLog> = OnceLock::new();
+
+/// Send events and diagnostics to the platform log from now on
+pub fn open_platform_log() -> Result<()> {
+    let log = PlatformLog::open()?;
+    let _ = PLATFORM_LOG.set(log);
+    Ok(())
+}
+
+/// Syslog severity of a level, sent with the daemon facility
+pub fn syslog_priority(level: Level) -> u8 {
+    const DAEMON: u8 = 3 << 3;
+    DAEMON
+        + match level {
+            Level::ERROR => 3,
+            Level::WARN => 4,
+            Level::INFO => 6,
+            _ => 7,
+        }
+}
+
+impl PlatformLog {
+    #[cfg(unix)]
+    fn open() -> Result<PlatformLog> {
+        let socket = std::os::unix::net::UnixDatagram::unbound()?;
+        ["/dev/log", "/var/run/syslog"]
+            .iter()
+            .find_map(|path| socket.connect(path).ok())
+            .ok_or_else(|| anyhow!("No syslog socket at /dev/log or /var/run/syslog"))?;
+        Ok(PlatformLog::Syslog(socket))
+    }
+
+    #[cfg(windows)]
+    fn open() -> Result<PlatformLog> {
+        use windows_sys::Win32::System::EventLog::RegisterEventSourceW;
+
+        let name: Vec<u16> = SERVICE_NAME.encode_utf16().chain([0]).collect();
+        // SAFETY: the name is NUL terminated and outlives the call
+        let handle = unsafe { RegisterEventSourceW(std::ptr::null(), name.as_ptr()) };
+        if handle.is_null() {
+            return Err(io::Error::last_os_error()).context("Could not open the Event Log");
+        }
+        Ok(PlatformLog::EventLog(handle))
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    fn open() -> Result<PlatformLog> {
+        Err(anyhow!("This system has no platform log"))
+    }
+
+    fn send(&self, level: Level, message: &str) {
+        let message = message.trim_end();
+        match self {
+            #[cfg(unix)]
+            PlatformLog::Syslog(socket) => {
+                let line = format!(
+                    "<{}>{}[{}]: {}",
+                    syslog_priority(level),
+                    SERVICE_NAME,
+                    std::process::id(),
+                    message
+                );
+                // Nowhere left to report a failure to
+                let _ = socket.send(line.as_bytes());
+            }
+            #[cfg(windows)]
+            PlatformLog::EventLog(handle) => {
+                use windows_sys::Win32::System::EventLog::{
+                    EVENTLOG_ERROR_TYPE, EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE,
+                    ReportEventW,
+                };
+
+                let kind = match level {
+                    Level::ERROR => EVENTLOG_ERROR_TYPE,
+                    Level::WARN => EVENTLOG_WARNING_TYPE,
+                    _ => EVENTLOG_INFORMATION_TYPE,
+                };
+                let text: Vec<u16> = message.encode_utf16().chain([0]).collect();
+                let strings = [text.as_ptr()];
+                // SAFETY: one NUL terminated string is passed and outlives the call
+                unsafe {
+                    ReportEventW(
+                        *handle,
+                        kind,
+                        0,
+                        0,
+                        std::ptr::null_mut(),
+                        1,
+                        0,
+                        strings.as_ptr(),
+                        std::ptr::null(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Send `message` to the platform log if it was opened, `false` when the caller should print it
+pub fn log_to_platform(level: Level, message: &str) -> bool {
+    match PLATFORM_LOG.get() {
+        Some(log) => {
+            log.send(level, message);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Diagnostics writer for the platform log, one message per formatted event
+pub struct PlatformWriter {
+    level: Level,
+}
+
+impl io::Write for PlatformWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !log_to_platform(self.level, &String::from_utf8_lossy(buf)) {
+            io::stderr().write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Makes a [`PlatformWriter`] for every diagnostic, at its level
+pub struct PlatformLogWriter;
+
+impl<'a> MakeWriter<'a> for PlatformLogWriter {
+    type Writer = PlatformWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        PlatformWriter { level: Level::INFO }
+    }
+
+    fn make_writer_for(&'a self, meta: &Metadata<'_>) -> Self::Writer {
+        PlatformWriter {
+            level: *meta.level(),
+        }
+    }
+}
+
+static STOP: Notify = Notify::const_new();
+static FINISHED: (Mutex<bool>, Condvar) = (Mutex::new(false), Condvar::new());
+
+/// Ask the daemon to shut down, as the Windows service manager does
+pub fn request_stop() {
+    STOP.notify_one();
+}
+
+/// Wait until something asks the daemon to shut down with [`request_stop`]
+pub async fn stop_requested() {
+    STOP.notified().await
+}
+
+/// Run a daemon style command until it fails or is stopped by SIGTERM or Ctrl-C, telling systemd
+/// when it's ready and when it's stopping. The daemon is dropped at its next await point, so it
+/// must not leave files half written across one.
+pub async fn run_daemon(daemon: impl Future<Output = Result<()>>) -> Result<()> {
+    systemd::ready();
+    tokio::select! {
+        result = daemon => result?,
+        _ = systemd::shutdown_signal() => info!("Shutting down"),
+        _ = stop_requested() => info!("Stopped by the service manager"),
+    }
+    systemd::stopping();
+    Ok(())
+}
+
+/// The daemon is done, let the service manager know it stopped
+fn mark_finished() {
+    let (finished, changed) = &FINISHED;
+    *finished.lock().unwrap_or_else(|e| e.into_inner()) = true;
+    changed.notify_all();
+}
+
+#[cfg_attr(not(windows), allow(dead_code))]
+fn wait_finished() {
+    let (finished, changed) = &FINISHED;
+    let mut done = finished.lock().unwrap_or_else(|e| e.into_inner());
+    while !*done {
+        done = changed.wait(done).unwrap_or_else(|e| e.into_inner());
+    }
+}
+
+/// Connection to the Windows service manager while the monitor runs as a service
+pub struct ServiceHandle {
+    dispatcher: Option<tokio::task::JoinHandle<Result<()>>>,
+}
+
+/// Report to the Windows service manager when started as a service, the monitor runs on while
+/// the dispatcher waits on a thread of its own. Nothing to report to elsewhere.
+pub fn start_service() -> ServiceHandle {
+    #[cfg(windows)]
+    let dispatcher = Some(tokio::task::spawn_blocking(windows::dispatch));
+    #[cfg(not(windows))]
+    let dispatcher = None;
+    ServiceHandle { dispatcher }
+}
+
+impl ServiceHandle {
+    /// Tell the service manager the monitor stopped
+    pub async fn stopped(self) -> Result<()> {
+        mark_finished();
+        if let Some(dispatcher) = self.dispatcher {
+            dispatcher.await??;
+        }
+        debug!("Service stopped");
+        Ok(())
+    }
+}
+
+/// Register the monitor as a Windows service started at boot with `args`
+#[cfg(windows)]
+pub fn install(args: Vec<OsString>) -> Result<()> {
+    windows::install(args)
+}
+
+#[cfg(not(windows))]
+pub fn install(_args: Vec<OsString>) -> Result<()> {
+    Err(anyhow!(
+        "--install-service registers a Windows service, elsewhere use --daemon with --pid-file or the systemd unit from the README"
+    ))
+}
+
+#[cfg(windows)]
+mod windows {
+    use anyhow::{Context, Result};
+    use std::{ffi::OsString, time::Duration};
+    use windows_service::{
+        define_windows_service,
+        service::{
+            ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl,
+            ServiceExitCode, ServiceInfo, ServiceStartType, ServiceState, ServiceStatus,
+            ServiceType,
+        },
+        service_control_handler::{self, ServiceControlHandlerResult},
+        service_dispatcher,
+        service_manager::{ServiceManager, ServiceManagerAccess},
+    };
+
+    use super::{SERVICE_NAME, request_stop, wait_finished};
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    /// Hand the thread to the service manager until the service stopped
+    pub fn dispatch() -> Result<()> {
+        service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+            .context("Could not connect to the service manager, is this run as a service?")
+    }
+
+    fn service_main(_args: Vec<OsString>) {
+        if let Err(e) = run() {
+            tracing::error!("Service failed: {}", e);
+            request_stop();
+        }
+    }
+
+    fn status(state: ServiceState, accept: ServiceControlAccept) -> ServiceStatus {
+        ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted: accept,
+            exit_code: ServiceExitCode::Win32(0),
+            checkpoint: 0,
+            wait_hint: Duration::from_secs(10),
+            process_id: None,
+        }
+    }
+
+    fn run() -> windows_service::Result<()> {
+        let handler = |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                request_stop();
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        };
+        let handle = service_control_handler::register(SERVICE_NAME, handler)?;
+        handle.set_service_status(status(
+            ServiceState::Running,
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        ))?;
+        wait_finished();
+        handle.set_service_status(status(ServiceState::Stopped, ServiceControlAccept::empty()))
+    }
+
+    pub fn install(args: Vec<OsString>) -> Result<()> {
+        let manager =
+            ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CREATE_SERVICE)
+                .context("Could not open the service manager, run this as an administrator")?;
+        let info = ServiceInfo {
+            name: SERVICE_NAME.into(),
+            display_name: "Snap Coin monitor".into(),
+            service_type: ServiceType::OWN_PROCESS,
+            start_type: ServiceStartType::AutoStart,
+            error_control: ServiceErrorControl::Normal,
+            executable_path: std::env::current_exe()
+                .context("Could not find the running executable")?,
+            launch_arguments: args,
+            dependencies: Vec::new(),
+            account_name: None,
+            account_password: None,
+        };
+        let service = manager
+            .create_service(&info, ServiceAccess::CHANGE_CONFIG)
+            .with_context(|| format!("Could not create the {} service", SERVICE_NAME))?;
+        service
+            .set_description("Follows the Snap Coin chain and logs blocks, reorgs and alerts")?;
+        Ok(())
+    }
+}
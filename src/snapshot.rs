@@ -0,0 +1,97 @@
+// snapshot.rs
+use anyhow::{Context, Result};
+use serde::Serialize;
+use snap_coin::{blockchain_data_provider::BlockchainDataProvider, core::transaction::Transaction};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tracing::info;
+
+use crate::{
+    averages::{ChainStats, calculate_chain_stats},
+    connection::ReconnectingClient,
+    node::NodeClient,
+    normalize_difficulty, service,
+};
+
+#[derive(Debug, Serialize)]
+pub struct MempoolStats {
+    pub transactions: usize,
+    pub inputs: usize,
+    pub outputs: usize,
+    /// Sum of all output amounts (nano)
+    pub total_amount: u64,
+}
+
+impl MempoolStats {
+    pub fn new(mempool: &[Transaction]) -> Self {
+        MempoolStats {
+            transactions: mempool.len(),
+            inputs: mempool.iter().map(|tx| tx.inputs.len()).sum(),
+            outputs: mempool.iter().map(|tx| tx.outputs.len()).sum(),
+            total_amount: mempool
+                .iter()
+                .flat_map(|tx| &tx.outputs)
+                .map(|o| o.amount)
+                .sum(),
+        }
+    }
+}
+
+/// Point in time report of the chain, written periodically by `snapshot`
+#[derive(Debug, Serialize)]
+pub struct Snapshot {
+    /// Unix time the snapshot was taken
+    pub timestamp: u64,
+    pub height: usize,
+    pub block_difficulty: f64,
+    pub transaction_difficulty: f64,
+    pub mempool: MempoolStats,
+    pub stats: ChainStats,
+}
+
+pub async fn take_snapshot(client: &NodeClient, block_count: usize) -> Result<Snapshot> {
+    Ok(Snapshot {
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        height: client.get_height().await?,
+        block_difficulty: normalize_difficulty(&client.get_block_difficulty().await?),
+        transaction_difficulty: normalize_difficulty(&client.get_transaction_difficulty().await?),
+        mempool: MempoolStats::new(&client.get_mempool().await?),
+        stats: calculate_chain_stats(client, block_count).await?,
+    })
+}
+
+/// Write `snapshot-<timestamp>.json` into `dir`, through a temporary file so readers never see a partial report
+pub fn write_snapshot(snapshot: &Snapshot, dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(dir).with_context(|| format!("Could not create {}", dir.display()))?;
+    let path = dir.join(format!("snapshot-{}.json", snapshot.timestamp));
+    let partial = path.with_extension("json.tmp");
+    fs::write(&partial, serde_json::to_vec_pretty(snapshot)?)
+        .with_context(|| format!("Could not write {}", partial.display()))?;
+    fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+/// Write a snapshot of the past `blocks` blocks to `out_dir` every `every` until stopped
+pub async fn run_snapshots(
+    connection: &ReconnectingClient,
+    every: Duration,
+    out_dir: &Path,
+    blocks: usize,
+) -> Result<()> {
+    connection.log_events();
+    service::run_daemon(connection.poll_every(
+        every,
+        "Snapshot",
+        &mut (),
+        async |client, _| take_snapshot(client, blocks).await,
+        |snapshot, _| {
+            let path = write_snapshot(&snapshot, out_dir)?;
+            info!("Wrote {}", path.display());
+            Ok(())
+        },
+    ))
+    .await
+}
@@ -0,0 +1,43 @@
+// source.rs
+use anyhow::{Result, anyhow};
+use std::net::SocketAddr;
+
+use crate::{
+    blocks::BlockRangeProvider, connection::ReconnectingClient, fixture::FixtureProvider,
+    node::NodeClient,
+};
+
+/// Where a command reads the chain from, a live node or a `--fixture` file
+pub enum Source {
+    Node(NodeClient),
+    Fixture(FixtureProvider),
+}
+
+impl Source {
+    pub fn provider(&self) -> &(dyn BlockRangeProvider + Send + Sync) {
+        match self {
+            Source::Node(client) => client,
+            Source::Fixture(fixture) => fixture,
+        }
+    }
+
+    /// Mempool, balances and transaction lookups are only served by a live node
+    pub fn client(&self) -> Result<&NodeClient> {
+        match self {
+            Source::Node(client) => Ok(client),
+            Source::Fixture(_) => Err(needs_node()),
+        }
+    }
+
+    /// Daemon style commands keep the node connection up, failing over to the rest of `pool`
+    pub fn into_reconnecting(self, pool: &[SocketAddr]) -> Result<ReconnectingClient> {
+        match self {
+            Source::Node(client) => Ok(ReconnectingClient::with_pool(client, pool)),
+            Source::Fixture(_) => Err(needs_node()),
+        }
+    }
+}
+
+fn needs_node() -> anyhow::Error {
+    anyhow!("This command needs a live node and can't run from --fixture")
+}
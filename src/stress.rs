@@ -0,0 +1,123 @@
+// stress.rs
+use anyhow::Result;
+use clap::ValueEnum;
+use comfy_table::Table;
+use serde::Serialize;
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    load::{self, Load, Query, Sample, run_load},
+    output::{Output, OutputFormat},
+    source::Source,
+    tables::{align_right, new_table},
+};
+
+/// Outcome of one kind of request over a stress run
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryReport {
+    pub query: Query,
+    pub requests: usize,
+    pub errors: usize,
+    /// Percentage of requests that failed
+    pub error_rate: f64,
+    /// Requests per second, failed ones included
+    pub throughput: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StressReport {
+    pub concurrency: usize,
+    pub elapsed_secs: f64,
+    pub queries: Vec<QueryReport>,
+}
+
+/// Nearest rank percentile of sorted latencies, zero without any
+pub fn percentile(sorted: &[Duration], percent: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+pub fn stress_report(load: &Load, concurrency: usize) -> StressReport {
+    let elapsed = load.elapsed.as_secs_f64().max(f64::EPSILON);
+    let ms = |latency: Duration| latency.as_secs_f64() * 1000.0;
+    let queries = load
+        .queries
+        .iter()
+        .map(|(query, stats)| {
+            let mut latencies = stats.latencies.clone();
+            latencies.sort_unstable();
+            let requests = stats.requests();
+            QueryReport {
+                query: *query,
+                requests,
+                errors: stats.errors,
+                error_rate: stats.errors as f64 / requests.max(1) as f64 * 100.0,
+                throughput: requests as f64 / elapsed,
+                p50_ms: ms(percentile(&latencies, 50.0)),
+                p90_ms: ms(percentile(&latencies, 90.0)),
+                p99_ms: ms(percentile(&latencies, 99.0)),
+                max_ms: ms(latencies.last().copied().unwrap_or_default()),
+            }
+        })
+        .collect();
+    StressReport {
+        concurrency,
+        elapsed_secs: load.elapsed.as_secs_f64(),
+        queries,
+    }
+}
+
+pub fn stress_table(report: &StressReport) -> Table {
+    let mut table = new_table(&[
+        "Query", "Requests", "Req/s", "Errors", "Error %", "p50 (ms)", "p90 (ms)", "p99 (ms)",
+        "Max (ms)",
+    ]);
+    for query in &report.queries {
+        table.add_row(vec![
+            query
+                .query
+                .to_possible_value()
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default(),
+            query.requests.to_string(),
+            format!("{:.1}", query.throughput),
+            query.errors.to_string(),
+            format!("{:.2}", query.error_rate),
+            format!("{:.1}", query.p50_ms),
+            format!("{:.1}", query.p90_ms),
+            format!("{:.1}", query.p99_ms),
+            format!("{:.1}", query.max_ms),
+        ]);
+    }
+    align_right(&mut table, &[1, 2, 3, 4, 5, 6, 7, 8]);
+    table
+}
+
+/// Load the node of `source` with the `mix` of queries for `duration` and report how it held up
+pub async fn run_stress(
+    source: &Source,
+    duration: Duration,
+    concurrency: usize,
+    mix: &[Query],
+    sample: usize,
+    out: &Output,
+) -> Result<()> {
+    let node = source.client()?.node();
+    load::warn_rate_limited();
+    let sample = Arc::new(Sample::take(source.provider(), sample).await?);
+    let load = run_load(node, sample, mix, concurrency, duration).await?;
+    let report = stress_report(&load, concurrency);
+    match out.format {
+        OutputFormat::Text => println!("{}", stress_table(&report)),
+        OutputFormat::Debug => println!("{:#?}", report),
+        OutputFormat::Json => out.json(&report)?,
+    }
+    Ok(())
+}
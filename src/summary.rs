@@ -0,0 +1,169 @@
+// summary.rs
+use anyhow::Result;
+use bincode::encode_to_vec;
+use serde::Serialize;
+use snap_coin::{
+    blockchain_data_provider::BlockchainDataProvider, core::block::Block, crypto::keys::Public,
+    economics::get_block_reward,
+};
+
+use crate::{
+    color,
+    config::Config,
+    format_biguint_hr, format_snap, format_timestamp,
+    miners::{block_miner, coinbase},
+    normalize_difficulty,
+    query::{TransactionInfo, TransactionStatus},
+    tables,
+};
+
+/// The figures of a block worth knowing before reading its transactions
+#[derive(Debug, Serialize)]
+pub struct BlockSummary {
+    /// Unknown for blocks off the node's main chain
+    pub height: Option<usize>,
+    pub confirmations: Option<usize>,
+    /// Reward for the height without fees, unknown along with the height
+    pub subsidy: Option<u64>,
+    /// Coinbase value above the subsidy
+    pub fees: Option<u64>,
+    /// Base36 address of the receiver of the mining reward
+    pub miner: Option<String>,
+    /// Address book label of the miner
+    pub miner_label: Option<String>,
+    /// Sum of the outputs of every transaction, coinbase included
+    pub output_value: u64,
+    pub transactions: usize,
+    /// Encoded size in bytes
+    pub size: usize,
+    pub block_difficulty: f64,
+    pub transaction_difficulty: f64,
+}
+
+/// A block with its summary, as printed by `block --format json`
+#[derive(Debug, Serialize)]
+pub struct SummarizedBlock {
+    #[serde(flatten)]
+    pub block: Block,
+    pub summary: BlockSummary,
+}
+
+pub async fn block_summary<P: BlockchainDataProvider + ?Sized>(
+    provider: &P,
+    block: &Block,
+    config: &Config,
+) -> Result<BlockSummary> {
+    let height = match block.meta.hash {
+        Some(hash) => provider.get_height_by_hash(hash).await?,
+        None => None,
+    };
+    let confirmations = match height {
+        Some(height) => Some(provider.get_height().await?.saturating_sub(height)),
+        None => None,
+    };
+    let subsidy = height.map(get_block_reward);
+    let coinbase_value = coinbase(block).map(|tx| tx.outputs.iter().map(|o| o.amount).sum::<u64>());
+    let miner = block_miner(block).map(|miner| Public::new_from_buf(&miner).dump_base36());
+    Ok(BlockSummary {
+        height,
+        confirmations,
+        subsidy,
+        fees: subsidy
+            .zip(coinbase_value)
+            .map(|(subsidy, value)| value.saturating_sub(subsidy)),
+        miner_label: miner
+            .as_deref()
+            .and_then(|miner| config.display_label(miner)),
+        miner,
+        output_value: block
+            .transactions
+            .iter()
+            .flat_map(|tx| &tx.outputs)
+            .map(|output| output.amount)
+            .sum(),
+        transactions: block.transactions.len(),
+        size: encode_to_vec(block, bincode::config::standard())?.len(),
+        block_difficulty: normalize_difficulty(&block.meta.block_pow_difficulty),
+        transaction_difficulty: normalize_difficulty(&block.meta.tx_pow_difficulty),
+    })
+}
+
+pub fn print_block_summary(block: &Block, summary: &BlockSummary) {
+    if let Some(hash) = block.meta.hash {
+        println!("Hash: {}", color::hash(hash.dump_base36()));
+    }
+    if let (Some(height), Some(confirmations)) = (summary.height, summary.confirmations) {
+        println!("Height: {}", height);
+        println!("Confirmations: {}", confirmations);
+    }
+    println!(
+        "Previous: {}",
+        color::hash(block.meta.previous_block.dump_base36())
+    );
+    println!("Timestamp: {}", format_timestamp(block.timestamp));
+    match (&summary.miner, &summary.miner_label) {
+        (Some(miner), Some(label)) => println!("Miner: {} ({})", color::miner(miner), label),
+        (Some(miner), None) => println!("Miner: {}", color::miner(miner)),
+        (None, _) => println!("Miner: unknown"),
+    }
+    if let Some(subsidy) = summary.subsidy {
+        println!("Subsidy: {} SNAP", color::amount(format_snap(subsidy)));
+    }
+    if let Some(fees) = summary.fees {
+        println!("Fees: {} SNAP", color::amount(format_snap(fees)));
+    }
+    println!(
+        "Output value: {} SNAP",
+        color::amount(format_snap(summary.output_value))
+    );
+    println!("Transaction count: {}", summary.transactions);
+    println!("Size: {} bytes", summary.size);
+    println!(
+        "Block difficulty: {}",
+        format_biguint_hr(&block.meta.block_pow_difficulty)
+    );
+    println!(
+        "Transaction difficulty: {}",
+        format_biguint_hr(&block.meta.tx_pow_difficulty)
+    );
+}
+
+pub fn print_transaction(info: &TransactionInfo) {
+    let tx = &info.transaction;
+    if let Some(id) = tx.transaction_id {
+        println!("ID: {}", color::hash(id.dump_base36()));
+    }
+    match &info.status {
+        TransactionStatus::Confirmed {
+            height,
+            block,
+            confirmations,
+        } => {
+            println!("Block: {} ({})", height, color::hash(block.dump_base36()));
+            println!("Confirmations: {}", confirmations);
+        }
+        TransactionStatus::Mempool => println!("Block: in mempool"),
+        TransactionStatus::Unknown => println!("Block: unknown"),
+    }
+    println!("Timestamp: {}", format_timestamp(tx.timestamp));
+    let value = &info.value;
+    println!(
+        "Value: {} SNAP ({} SNAP transferred)",
+        color::amount(format_snap(value.output_value)),
+        color::amount(format_snap(value.transferred))
+    );
+    match (value.fee, value.fee_rate) {
+        (Some(fee), Some(rate)) => println!(
+            "Fee: {} SNAP ({:.2} nano/byte)",
+            color::amount(format_snap(fee)),
+            rate
+        ),
+        _ if tx.inputs.is_empty() => println!("Fee: none, coinbase"),
+        _ => println!("Fee: unknown, spent outputs not found"),
+    }
+    println!("Size: {} bytes", value.size);
+    if !tx.inputs.is_empty() {
+        println!("Inputs:\n{}", tables::inputs_table(tx, &info.spent));
+    }
+    println!("Outputs:\n{}", tables::outputs_table(tx));
+}
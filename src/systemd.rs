@@ -0,0 +1,116 @@
+// systemd.rs
+use std::{env, io, time::Duration};
+use tracing::{debug, warn};
+
+/// Send `state`, e.g. `READY=1`, to the socket systemd passes in `NOTIFY_SOCKET`.
+/// Without one the process isn't run as a `Type=notify` service and nothing is sent.
+pub fn notify(state: &str) {
+    let Some(socket) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    debug!("sd_notify {}", state);
+    if let Err(e) = notify_socket(&socket, state) {
+        warn!("Could not notify systemd: {}", e);
+    }
+}
+
+/// Send `state` to a notify socket, a path or an abstract socket name starting with `@`
+#[cfg(target_os = "linux")]
+pub fn notify_socket(socket: &std::ffi::OsStr, state: &str) -> io::Result<()> {
+    use std::os::{
+        linux::net::SocketAddrExt,
+        unix::{
+            ffi::OsStrExt,
+            net::{SocketAddr, UnixDatagram},
+        },
+    };
+
+    let address = match socket.as_bytes().strip_prefix(b"@") {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(socket)?,
+    };
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address)?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn notify_socket(_socket: &std::ffi::OsStr, _state: &str) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "systemd notifications need Linux",
+    ))
+}
+
+/// How often systemd expects `WATCHDOG=1`, half of `WATCHDOG_USEC` to leave room for delays but
+/// at least a millisecond. `None` when the watchdog is off or, by `WATCHDOG_PID`, meant for
+/// another process.
+pub fn watchdog_interval(usec: Option<&str>, pid: Option<&str>, own_pid: u32) -> Option<Duration> {
+    if let Some(pid) = pid
+        && pid.parse::<u32>().ok() != Some(own_pid)
+    {
+        return None;
+    }
+    let usec: u64 = usec?.parse().ok().filter(|&usec| usec > 0)?;
+    Some(Duration::from_micros(usec / 2).max(Duration::from_millis(1)))
+}
+
+/// The systemd watchdog of a daemon's main loop. The loop pings it while waiting for its next
+/// iteration, so a loop stuck on something else gets the process restarted.
+pub struct Watchdog {
+    ticks: Option<tokio::time::Interval>,
+}
+
+impl Watchdog {
+    /// The watchdog `WATCHDOG_USEC` and `WATCHDOG_PID` ask for, off without them
+    pub fn from_env() -> Self {
+        let interval = watchdog_interval(
+            env::var("WATCHDOG_USEC").ok().as_deref(),
+            env::var("WATCHDOG_PID").ok().as_deref(),
+            std::process::id(),
+        );
+        Watchdog {
+            ticks: interval.map(tokio::time::interval),
+        }
+    }
+
+    /// Wait until the next ping is due and send it, forever when the watchdog is off. Meant as a
+    /// `tokio::select!` branch next to what the loop waits for.
+    pub async fn ping(&mut self) {
+        match &mut self.ticks {
+            Some(ticks) => {
+                ticks.tick().await;
+                notify("WATCHDOG=1");
+            }
+            None => std::future::pending().await,
+        }
+    }
+}
+
+/// Tell systemd the daemon is up
+pub fn ready() {
+    notify("READY=1");
+}
+
+/// Tell systemd the daemon is shutting down
+pub fn stopping() {
+    notify("STOPPING=1");
+}
+
+/// Wait for SIGTERM, what systemd stops services with, or Ctrl-C
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+                return;
+            }
+            Err(e) => warn!("Could not listen for SIGTERM: {}", e),
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
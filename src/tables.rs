@@ -0,0 +1,125 @@
+// tables.rs
+use comfy_table::{CellAlignment, ContentArrangement, Table, presets::UTF8_FULL_CONDENSED};
+use snap_coin::core::transaction::{Transaction, TransactionId, TransactionOutput};
+
+use crate::{color, format_snap, query::ImmatureOutput};
+
+/// A table with the shared look of all command output
+pub fn new_table(header: &[&str]) -> Table {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL_CONDENSED)
+        .set_content_arrangement(ContentArrangement::Dynamic)
+        .set_header(header.to_vec());
+    table
+}
+
+/// Right align the given columns, used for amounts and counts
+pub fn align_right(table: &mut Table, columns: &[usize]) {
+    for column in columns {
+        if let Some(column) = table.column_mut(*column) {
+            column.set_cell_alignment(CellAlignment::Right);
+        }
+    }
+}
+
+fn transaction_id(tx: &Transaction) -> String {
+    tx.transaction_id
+        .map(|id| color::hash(id.dump_base36()))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// One row per transaction with its IO counts and total output value
+pub fn transactions_table(transactions: &[Transaction]) -> Table {
+    let mut table = new_table(&["TX ID", "Inputs", "Outputs", "Amount (SNAP)"]);
+    for tx in transactions {
+        let amount: u64 = tx.outputs.iter().map(|o| o.amount).sum();
+        table.add_row(vec![
+            transaction_id(tx),
+            tx.inputs.len().to_string(),
+            tx.outputs.len().to_string(),
+            color::amount(format_snap(amount)),
+        ]);
+    }
+    align_right(&mut table, &[1, 2, 3]);
+    table
+}
+
+/// The outputs spent by a transaction with their owners and amounts, `spent` in input order
+pub fn inputs_table(tx: &Transaction, spent: &[Option<TransactionOutput>]) -> Table {
+    let mut table = new_table(&["TX ID", "Index", "Owner", "Amount (SNAP)"]);
+    for (index, input) in tx.inputs.iter().enumerate() {
+        let spent = spent.get(index).and_then(Option::as_ref);
+        table.add_row(vec![
+            color::hash(input.transaction_id.dump_base36()),
+            input.output_index.to_string(),
+            color::hash(
+                spent
+                    .map(|output| output.receiver)
+                    .unwrap_or(input.output_owner)
+                    .dump_base36(),
+            ),
+            spent
+                .map(|output| color::amount(format_snap(output.amount)))
+                .unwrap_or_else(|| "?".to_string()),
+        ]);
+    }
+    align_right(&mut table, &[1, 3]);
+    table
+}
+
+/// The receivers and amounts of a transaction
+pub fn outputs_table(tx: &Transaction) -> Table {
+    let mut table = new_table(&["Index", "Receiver", "Amount (SNAP)"]);
+    for (index, output) in tx.outputs.iter().enumerate() {
+        table.add_row(vec![
+            index.to_string(),
+            color::hash(output.receiver.dump_base36()),
+            color::amount(format_snap(output.amount)),
+        ]);
+    }
+    align_right(&mut table, &[0, 2]);
+    table
+}
+
+/// Unspent outputs as returned by `get_available_transaction_outputs`, with the confirmations of
+/// each, `?` where its block wasn't found
+pub fn utxo_table(
+    utxos: &[(TransactionId, TransactionOutput, usize)],
+    confirmations: &[Option<usize>],
+) -> Table {
+    let mut table = new_table(&["TX ID", "Index", "Amount (SNAP)", "Confirmations"]);
+    for ((tx_id, output, index), confirmations) in utxos.iter().zip(confirmations) {
+        table.add_row(vec![
+            color::hash(tx_id.dump_base36()),
+            index.to_string(),
+            color::amount(format_snap(output.amount)),
+            confirmations.map_or_else(|| "?".to_string(), |count| count.to_string()),
+        ]);
+    }
+    align_right(&mut table, &[1, 2, 3]);
+    table
+}
+
+pub fn immature_table(outputs: &[ImmatureOutput]) -> Table {
+    let mut table = new_table(&[
+        "TX ID",
+        "Index",
+        "Amount (SNAP)",
+        "Height",
+        "Confirmations",
+        "Unlocks At",
+    ]);
+    for output in outputs {
+        table.add_row(vec![
+            color::hash(output.transaction_id.dump_base36()),
+            output.output_index.to_string(),
+            color::amount(format_snap(output.amount)),
+            output.height.to_string(),
+            output.confirmations.to_string(),
+            output.unlock_height.to_string(),
+        ]);
+    }
+    align_right(&mut table, &[1, 2, 3, 4, 5]);
+    table
+}
@@ -0,0 +1,141 @@
+// tail.rs
+use anyhow::Result;
+use std::{ops::Range, time::Duration};
+use tracing::debug;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    color,
+    config::Config,
+    connection::ReconnectingClient,
+    format_snap,
+    monitor::{MonitorEvent, Party, whale},
+    output::{Output, OutputFormat},
+    service,
+};
+
+/// Whale events of the confirmed transactions in the blocks at `heights` moving at least
+/// `min_amount` nano, in chain order
+pub async fn large_transactions<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+    min_amount: u64,
+) -> Result<Vec<MonitorEvent>> {
+    debug!(
+        "Looking for transactions of {} nano or more in blocks {}..{}",
+        min_amount, heights.start, heights.end
+    );
+    let mut found = Vec::new();
+    for_each_block(provider, heights, |height, block| {
+        found.extend(
+            block
+                .transactions
+                .iter()
+                .filter_map(|tx| whale(tx, Some(height), min_amount)),
+        );
+        Ok(())
+    })
+    .await?;
+    Ok(found)
+}
+
+/// Where `tail txs` is in the chain between polls
+#[derive(Debug, Default)]
+pub struct TxFeed {
+    /// First block of the next poll, `None` before the first one
+    pub next: Option<usize>,
+}
+
+impl TxFeed {
+    /// The large transactions of the blocks mined since the last poll, the last `blocks` on the
+    /// first one. A failed poll leaves the feed where it was.
+    pub async fn poll<P: BlockRangeProvider + ?Sized>(
+        &mut self,
+        provider: &P,
+        blocks: usize,
+        min_amount: u64,
+    ) -> Result<Vec<MonitorEvent>> {
+        let height = provider.get_height().await?;
+        let start = self.next.unwrap_or(height.saturating_sub(blocks));
+        let found = large_transactions(provider, start..height, min_amount).await?;
+        self.next = Some(height);
+        Ok(found)
+    }
+}
+
+/// Poll for large transactions every `interval` until dropped, handing each to `on_event`
+pub async fn watch(
+    connection: &ReconnectingClient,
+    blocks: usize,
+    min_amount: u64,
+    interval: Duration,
+    mut on_event: impl FnMut(MonitorEvent) -> Result<()>,
+) -> Result<()> {
+    connection
+        .poll_every(
+            interval,
+            "Polling transactions",
+            &mut TxFeed::default(),
+            async |client, feed| feed.poll(client, blocks, min_amount).await,
+            |found, _| found.into_iter().try_for_each(&mut on_event),
+        )
+        .await
+}
+
+/// The first party's label or address, `+N` counting the others
+fn parties(parties: &[Party]) -> String {
+    match parties {
+        [] => "-".to_string(),
+        [party] => party.name().to_string(),
+        [party, rest @ ..] => format!("{} +{}", party.name(), rest.len()),
+    }
+}
+
+/// A transaction as one line of the feed, e.g. `<tx> Exchange→<address> +1 2500.00000000 SNAP #601`
+pub fn tx_line(event: &MonitorEvent) -> Option<String> {
+    let MonitorEvent::Whale {
+        transaction,
+        height,
+        amount,
+        senders,
+        receivers,
+    } = event
+    else {
+        return None;
+    };
+    Some(format!(
+        "{} {}→{} {} SNAP #{}",
+        color::hash(transaction),
+        parties(senders),
+        parties(receivers),
+        color::amount(format_snap(*amount)),
+        height.map_or_else(|| "mempool".to_string(), |height| height.to_string())
+    ))
+}
+
+/// Print the transactions of new blocks moving at least `min_amount` until stopped
+pub async fn run_tail(
+    connection: &ReconnectingClient,
+    config: &Config,
+    blocks: usize,
+    min_amount: u64,
+    interval: Duration,
+    out: &Output,
+) -> Result<()> {
+    connection.log_events();
+    service::run_daemon(watch(
+        connection,
+        blocks,
+        min_amount,
+        interval,
+        |mut event| {
+            event.label(|address| config.display_label(address));
+            match out.format {
+                OutputFormat::Json => println!("{}", serde_json::to_string(&event)?),
+                _ => println!("{}", tx_line(&event).unwrap_or_default()),
+            }
+            Ok(())
+        },
+    ))
+    .await
+}
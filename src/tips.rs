@@ -0,0 +1,133 @@
+// tips.rs
+use anyhow::{Result, anyhow};
+use comfy_table::Table;
+use serde::Serialize;
+use snap_coin::{blockchain_data_provider::BlockchainDataProvider, crypto::Hash};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use crate::{
+    color,
+    orphans::{SeenBlock, load_seen_blocks},
+    output::{Output, OutputFormat},
+    tables::{align_right, new_table},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TipStatus {
+    /// The tip of the main chain
+    Active,
+    /// The end of a branch that split off the main chain
+    Fork,
+    /// A branch whose fork point isn't in the watch log or on the main chain
+    Detached,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChainTip {
+    /// Unknown for detached branches
+    pub height: Option<usize>,
+    pub hash: Hash,
+    /// Blocks between the tip and the main chain, 0 for the active tip
+    pub branch_length: usize,
+    pub status: TipStatus,
+}
+
+/// The active tip, and the ends of side branches among the blocks of an `orphans watch` log.
+/// Nodes only serve their main chain, so forks are only known from what they announced.
+pub async fn chain_tips<P: BlockchainDataProvider + ?Sized>(
+    provider: &P,
+    seen: &[SeenBlock],
+) -> Result<Vec<ChainTip>> {
+    let height = provider.get_height().await?;
+    let tip_height = height
+        .checked_sub(1)
+        .ok_or_else(|| anyhow!("The node has no blocks yet"))?;
+    let tip = provider
+        .get_block_hash_by_height(tip_height)
+        .await?
+        .ok_or_else(|| anyhow!("The node has no block at its tip height {}", tip_height))?;
+    let mut tips = vec![ChainTip {
+        height: Some(tip_height),
+        hash: tip,
+        branch_length: 0,
+        status: TipStatus::Active,
+    }];
+
+    let mut side: HashMap<Hash, &SeenBlock> = HashMap::new();
+    for block in seen {
+        if provider.get_height_by_hash(block.hash).await?.is_none() {
+            side.insert(block.hash, block);
+        }
+    }
+    let parents: HashSet<Hash> = side.values().map(|block| block.previous).collect();
+    let mut ends: Vec<&SeenBlock> = side
+        .values()
+        .copied()
+        .filter(|block| !parents.contains(&block.hash))
+        .collect();
+    ends.sort_by_key(|block| block.seen);
+
+    for end in ends {
+        let mut branch_length = 1;
+        let mut parent = end.previous;
+        while let Some(block) = side.get(&parent) {
+            branch_length += 1;
+            parent = block.previous;
+        }
+        let fork_height = provider.get_height_by_hash(parent).await?;
+        tips.push(ChainTip {
+            height: fork_height.map(|fork| fork + branch_length),
+            hash: end.hash,
+            branch_length,
+            status: match fork_height {
+                Some(_) => TipStatus::Fork,
+                None => TipStatus::Detached,
+            },
+        });
+    }
+    Ok(tips)
+}
+
+pub fn tips_table(tips: &[ChainTip]) -> Table {
+    let mut table = new_table(&["Height", "Branch", "Status", "Hash"]);
+    for tip in tips {
+        table.add_row(vec![
+            tip.height
+                .map(|height| height.to_string())
+                .unwrap_or_else(|| "?".to_string()),
+            tip.branch_length.to_string(),
+            match tip.status {
+                TipStatus::Active => "active",
+                TipStatus::Fork => "fork",
+                TipStatus::Detached => "detached",
+            }
+            .to_string(),
+            color::hash(tip.hash.dump_base36()),
+        ]);
+    }
+    align_right(&mut table, &[0, 1]);
+    table
+}
+
+/// Print the chain tips, including the forks among the blocks logged to `log`
+pub async fn run_tips<P: BlockchainDataProvider + ?Sized>(
+    provider: &P,
+    log: Option<&Path>,
+    out: &Output,
+) -> Result<()> {
+    let seen = match log {
+        Some(path) => load_seen_blocks(path)?,
+        None => Vec::new(),
+    };
+    let tips = chain_tips(provider, &seen).await?;
+    match out.format {
+        OutputFormat::Text => println!("{}", tips_table(&tips)),
+        OutputFormat::Debug => println!("{:#?}", tips),
+        OutputFormat::Json => out.json(&tips)?,
+    }
+    Ok(())
+}
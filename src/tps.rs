@@ -0,0 +1,153 @@
+// tps.rs
+use anyhow::{Result, anyhow};
+use comfy_table::Table;
+use serde::Serialize;
+use std::{collections::BTreeMap, ops::Range, time::Duration};
+use tracing::info;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    chart::{self, PlotFiles, Series},
+    format_timestamp,
+    output::{Output, OutputFormat},
+    tables::{align_right, new_table},
+};
+
+/// Transactions of the blocks timestamped within one bucket of time
+#[derive(Debug, Serialize)]
+pub struct TpsBucket {
+    /// Unix time the bucket starts at, a multiple of the bucket length
+    pub start: u64,
+    /// Lowest height of the bucket's blocks
+    pub first_height: usize,
+    pub blocks: usize,
+    pub transactions: usize,
+    /// Seconds of the bucket the scan covers, less than the bucket length at the scan's ends
+    pub secs: u64,
+    pub tps: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TpsSeries {
+    pub bucket_secs: u64,
+    pub buckets: Vec<TpsBucket>,
+}
+
+impl TpsSeries {
+    /// TPS of every bucket against the first height in it
+    pub fn series(&self) -> Vec<Series> {
+        vec![Series {
+            name: format!("TPS per {}s", self.bucket_secs),
+            points: self
+                .buckets
+                .iter()
+                .map(|bucket| (bucket.first_height, bucket.tps))
+                .collect(),
+        }]
+    }
+}
+
+/// Group blocks starting at `start_height`, given as their timestamp and transaction count, into
+/// buckets of `bucket_secs` aligned to the unix epoch, so daily buckets start at midnight UTC
+pub fn tps_buckets(
+    start_height: usize,
+    blocks: &[(u64, usize)],
+    bucket_secs: u64,
+) -> Result<TpsSeries> {
+    if bucket_secs == 0 {
+        return Err(anyhow!("Buckets must be at least a second long"));
+    }
+    let mut buckets: BTreeMap<u64, TpsBucket> = BTreeMap::new();
+    for (offset, &(timestamp, transactions)) in blocks.iter().enumerate() {
+        let start = timestamp - timestamp % bucket_secs;
+        let bucket = buckets.entry(start).or_insert(TpsBucket {
+            start,
+            first_height: start_height + offset,
+            blocks: 0,
+            transactions: 0,
+            secs: bucket_secs,
+            tps: 0.0,
+        });
+        bucket.blocks += 1;
+        bucket.transactions += transactions;
+    }
+
+    // The scan starts and ends partway through its first and last buckets
+    let first = blocks.iter().map(|block| block.0).min().unwrap_or(0);
+    let last = blocks.iter().map(|block| block.0).max().unwrap_or(0);
+    let buckets = buckets
+        .into_values()
+        .map(|mut bucket| {
+            let end = (bucket.start + bucket_secs).min(last);
+            bucket.secs = end.saturating_sub(bucket.start.max(first)).max(1);
+            bucket.tps = bucket.transactions as f64 / bucket.secs as f64;
+            bucket
+        })
+        .collect();
+    Ok(TpsSeries {
+        bucket_secs,
+        buckets,
+    })
+}
+
+/// Transactions per second of the blocks at `heights` in buckets of `bucket_secs`
+pub async fn tps_series<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    heights: Range<usize>,
+    bucket_secs: u64,
+) -> Result<TpsSeries> {
+    info!(
+        "Scanning blocks {}..{} for transactions per {}s",
+        heights.start, heights.end, bucket_secs
+    );
+    let start = heights.start;
+    let mut blocks = Vec::with_capacity(heights.len());
+    for_each_block(provider, heights, |_, block| {
+        blocks.push((block.timestamp, block.transactions.len()));
+        Ok(())
+    })
+    .await?;
+    tps_buckets(start, &blocks, bucket_secs)
+}
+
+pub fn tps_table(series: &TpsSeries) -> Table {
+    let mut table = new_table(&["Start", "First Block", "Blocks", "TXs", "TPS"]);
+    for bucket in &series.buckets {
+        table.add_row(vec![
+            format_timestamp(bucket.start),
+            bucket.first_height.to_string(),
+            bucket.blocks.to_string(),
+            bucket.transactions.to_string(),
+            format!("{:.3}", bucket.tps),
+        ]);
+    }
+    align_right(&mut table, &[1, 2, 3, 4]);
+    table
+}
+
+/// Transactions per second of the past `blocks` blocks in buckets of `bucket`
+pub async fn run_tps<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    blocks: usize,
+    bucket: Duration,
+    sparkline: bool,
+    plots: &PlotFiles,
+    out: &Output,
+) -> Result<()> {
+    let height = provider.get_height().await?;
+    let series = tps_series(
+        provider,
+        height.saturating_sub(blocks)..height,
+        bucket.as_secs(),
+    )
+    .await?;
+    let chart_series = series.series();
+    plots.write(&chart_series)?;
+    match out.format {
+        OutputFormat::Text if sparkline => chart::print_sparklines(&chart_series),
+        OutputFormat::Text => println!("{}", tps_table(&series)),
+        OutputFormat::Debug => println!("{:#?}", series),
+        OutputFormat::Json => out.json(&series)?,
+    }
+    Ok(())
+}
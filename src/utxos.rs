@@ -0,0 +1,308 @@
+// utxos.rs
+use anyhow::Result;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use snap_coin::core::{
+    block::Block,
+    transaction::{TransactionId, TransactionOutput},
+};
+use std::{collections::HashMap, ops::Range};
+use tracing::info;
+
+use crate::{
+    blocks::{BlockRangeProvider, for_each_block},
+    color,
+    error::{ErrorKind, error},
+    format_snap,
+    ids::to_hex,
+    output::{Output, OutputFormat},
+};
+
+/// An unspent output and the height of the block that created it
+#[derive(Debug, Clone, Copy)]
+pub struct Utxo {
+    pub output: TransactionOutput,
+    pub height: usize,
+}
+
+/// Every unspent output as of some height. The node only answers for one address at a time, so the
+/// set is built by replaying the blocks.
+#[derive(Debug, Default)]
+pub struct UtxoSet {
+    utxos: HashMap<(TransactionId, usize), Utxo>,
+    value: u64,
+}
+
+impl UtxoSet {
+    /// Spend the outputs the block's inputs point at and add the block's new outputs.
+    /// Inputs spending outputs from before the replay started are skipped.
+    pub fn apply(&mut self, height: usize, block: &Block) {
+        for tx in &block.transactions {
+            for input in &tx.inputs {
+                if let Some(spent) = self
+                    .utxos
+                    .remove(&(input.transaction_id, input.output_index))
+                {
+                    self.value -= spent.output.amount;
+                }
+            }
+            let Some(tx_id) = tx.transaction_id else {
+                continue;
+            };
+            for (index, output) in tx.outputs.iter().enumerate() {
+                self.utxos.insert(
+                    (tx_id, index),
+                    Utxo {
+                        output: *output,
+                        height,
+                    },
+                );
+                self.value += output.amount;
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.utxos.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.utxos.is_empty()
+    }
+
+    /// Sum of all unspent outputs in nano
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&(TransactionId, usize), &Utxo)> {
+        self.utxos.iter()
+    }
+
+    /// Every UTXO in canonical order, by transaction id bytes then output index
+    pub fn sorted(&self) -> Vec<(&(TransactionId, usize), &Utxo)> {
+        let mut utxos: Vec<_> = self.utxos.iter().collect();
+        utxos.sort_by_key(|((tx_id, index), _)| (tx_id.dump_buf(), *index));
+        utxos
+    }
+
+    /// SHA-256 over every UTXO in canonical order, each encoded as its transaction id, output index,
+    /// owner, amount and creation height with the numbers as 8 byte little endian. Anyone replaying
+    /// the same blocks gets the same hash, whichever node served them.
+    pub fn commitment(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        for ((tx_id, index), utxo) in self.sorted() {
+            hasher.update(tx_id.dump_buf());
+            hasher.update((*index as u64).to_le_bytes());
+            hasher.update(utxo.output.receiver.dump_buf());
+            hasher.update(utxo.output.amount.to_le_bytes());
+            hasher.update((utxo.height as u64).to_le_bytes());
+        }
+        hasher.finalize().into()
+    }
+
+    /// Replay the blocks at `heights` into the set, calling `f` after every block
+    pub async fn replay<P: BlockRangeProvider + ?Sized>(
+        &mut self,
+        provider: &P,
+        heights: Range<usize>,
+        mut f: impl FnMut(usize, &Block, &UtxoSet),
+    ) -> Result<()> {
+        info!(
+            "Replaying blocks {}..{} into the UTXO set",
+            heights.start, heights.end
+        );
+        for_each_block(provider, heights, |height, block| {
+            self.apply(height, &block);
+            f(height, &block, self);
+            Ok(())
+        })
+        .await
+    }
+}
+
+/// The UTXO set at a height summed up in one hash, to compare with others
+#[derive(Debug, Serialize)]
+pub struct UtxoCommitment {
+    pub height: usize,
+    pub count: usize,
+    pub value: u64,
+    /// Hex of `UtxoSet::commitment`
+    pub commitment: String,
+}
+
+impl UtxoCommitment {
+    pub fn of(set: &UtxoSet, height: usize) -> Self {
+        UtxoCommitment {
+            height,
+            count: set.len(),
+            value: set.value(),
+            commitment: to_hex(&set.commitment()),
+        }
+    }
+}
+
+/// How the UTXO set changed over the last blocks of the replay
+#[derive(Debug, Serialize)]
+pub struct UtxoGrowth {
+    pub blocks: usize,
+    /// UTXOs added less UTXOs spent
+    pub count_change: i64,
+    pub per_block: f64,
+    /// At the rate the blocks came in, `None` when no time passed
+    pub per_day: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UtxoStats {
+    pub height: usize,
+    pub count: usize,
+    pub value: u64,
+    pub average_value: f64,
+    /// Outputs below this amount are counted as dust
+    pub dust_threshold: u64,
+    pub dust_count: usize,
+    pub dust_value: u64,
+    pub growth: UtxoGrowth,
+}
+
+/// Sum up `set` at `height`. `before` is the set's size `blocks` blocks earlier and `secs` the time
+/// those blocks took.
+pub fn utxo_stats(
+    set: &UtxoSet,
+    height: usize,
+    dust_threshold: u64,
+    before: usize,
+    blocks: usize,
+    secs: u64,
+) -> UtxoStats {
+    let (dust_count, dust_value) = set
+        .iter()
+        .filter(|(_, utxo)| utxo.output.amount < dust_threshold)
+        .fold((0, 0), |(count, value), (_, utxo)| {
+            (count + 1, value + utxo.output.amount)
+        });
+    let count_change = set.len() as i64 - before as i64;
+    UtxoStats {
+        height,
+        count: set.len(),
+        value: set.value(),
+        average_value: set.value() as f64 / set.len().max(1) as f64,
+        dust_threshold,
+        dust_count,
+        dust_value,
+        growth: UtxoGrowth {
+            blocks,
+            count_change,
+            per_block: count_change as f64 / blocks.max(1) as f64,
+            per_day: (secs > 0).then(|| count_change as f64 / secs as f64 * 86_400.0),
+        },
+    }
+}
+
+pub fn print_utxo_stats(stats: &UtxoStats) {
+    println!("UTXO set at height {}", stats.height);
+    println!(
+        "UTXOs: {}, total value: {} SNAP, average: {} SNAP",
+        stats.count,
+        color::amount(format_snap(stats.value)),
+        color::amount(format_snap(stats.average_value as u64))
+    );
+    println!(
+        "Dust below {} SNAP: {} UTXOs, {} SNAP",
+        format_snap(stats.dust_threshold),
+        stats.dust_count,
+        color::amount(format_snap(stats.dust_value))
+    );
+    let growth = &stats.growth;
+    print!(
+        "Growth over the last {} blocks: {:+} UTXOs, {:+.2} per block",
+        growth.blocks, growth.count_change, growth.per_block
+    );
+    match growth.per_day {
+        Some(per_day) => println!(", {:+.0} per day", per_day),
+        None => println!(),
+    }
+}
+
+pub fn print_utxo_commitment(commitment: &UtxoCommitment) {
+    println!(
+        "UTXO set at height {}: {} UTXOs, {} SNAP",
+        commitment.height,
+        commitment.count,
+        color::amount(format_snap(commitment.value))
+    );
+    println!("Commitment: {}", commitment.commitment);
+}
+
+/// The `--at-height` of the UTXO commands, the chain height by default
+pub async fn resolve_height<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    at_height: Option<usize>,
+) -> Result<usize> {
+    let height = provider.get_height().await?;
+    let at_height = at_height.unwrap_or(height);
+    if at_height > height {
+        return Err(error(
+            ErrorKind::NotFound,
+            format!("Height {} is above the chain height {}", at_height, height),
+        ));
+    }
+    Ok(at_height)
+}
+
+/// Replay the chain and print the UTXO set, its growth measured over the past `blocks` blocks
+pub async fn run_utxo_stats<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    blocks: usize,
+    dust: u64,
+    out: &Output,
+) -> Result<()> {
+    let height = provider.get_height().await?;
+    let growth_start = height.saturating_sub(blocks);
+    let mut set = UtxoSet::default();
+    let (mut before, mut first_timestamp, mut last_timestamp) = (0, 0, 0);
+    set.replay(provider, 0..height, |block_height, block, set| {
+        // The growth is timed from the block before the first measured one
+        if block_height + 1 == growth_start {
+            before = set.len();
+        }
+        if block_height + 1 == growth_start || block_height == 0 {
+            first_timestamp = block.timestamp;
+        }
+        last_timestamp = block.timestamp;
+    })
+    .await?;
+    let stats = utxo_stats(
+        &set,
+        height,
+        dust,
+        before,
+        height - growth_start,
+        last_timestamp.saturating_sub(first_timestamp),
+    );
+    match out.format {
+        OutputFormat::Text => print_utxo_stats(&stats),
+        OutputFormat::Debug => println!("{:#?}", stats),
+        OutputFormat::Json => out.json(&stats)?,
+    }
+    Ok(())
+}
+
+/// Replay the chain below `at_height` and print the commitment of the UTXO set left
+pub async fn run_utxo_commitment<P: BlockRangeProvider + ?Sized>(
+    provider: &P,
+    at_height: Option<usize>,
+    out: &Output,
+) -> Result<()> {
+    let at_height = resolve_height(provider, at_height).await?;
+    let mut set = UtxoSet::default();
+    set.replay(provider, 0..at_height, |_, _, _| {}).await?;
+    let commitment = UtxoCommitment::of(&set, at_height);
+    match out.format {
+        OutputFormat::Text => print_utxo_commitment(&commitment),
+        OutputFormat::Debug => println!("{:#?}", commitment),
+        OutputFormat::Json => out.json(&commitment)?,
+    }
+    Ok(())
+}
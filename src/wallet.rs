@@ -0,0 +1,361 @@
+// wallet.rs
+use anyhow::{Context, Result, anyhow};
+use comfy_table::Table;
+use serde::{Deserialize, Serialize};
+use snap_coin::{
+    blockchain_data_provider::BlockchainDataProvider,
+    core::transaction::{TransactionId, TransactionOutput},
+    crypto::{Hash, keys::Public},
+};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info};
+
+use crate::{
+    color,
+    config::{self, Config},
+    error::{ErrorKind, error},
+    format_snap, format_timestamp,
+    node::NodeClient,
+    output::{Output, OutputFormat},
+    price::{self, Quote},
+    tables::{align_right, new_table},
+};
+
+/// An address watched by the wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchAddress {
+    /// Base36 address
+    pub address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Watch-only wallet, a TOML file listing `[[addresses]]` with an `address` and optional `label`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Wallet {
+    #[serde(default)]
+    pub addresses: Vec<WatchAddress>,
+}
+
+/// `wallet.toml` next to the config file
+pub fn default_path() -> Option<PathBuf> {
+    config::default_path().map(|path| path.with_file_name("wallet.toml"))
+}
+
+/// The wallet file given on the command line, or the default one
+pub fn resolve_path(file: Option<&Path>) -> Result<PathBuf> {
+    file.map(Path::to_path_buf)
+        .or_else(default_path)
+        .ok_or_else(|| anyhow!("No config directory for the wallet file, pass --file"))
+}
+
+impl Wallet {
+    /// Load a wallet file, a missing one is an empty wallet
+    pub fn load(path: &Path) -> Result<Wallet> {
+        if !path.exists() {
+            return Ok(Wallet::default());
+        }
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Could not read wallet {}", path.display()))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("Could not parse wallet {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, toml::to_string(self)?)
+            .with_context(|| format!("Could not write wallet {}", path.display()))
+    }
+
+    /// Watch an address, relabeling it when it's already watched
+    pub fn add(&mut self, address: &str, label: Option<String>) -> Result<()> {
+        parse_address(address)?;
+        match self
+            .addresses
+            .iter_mut()
+            .find(|watched| watched.address == address)
+        {
+            Some(watched) => watched.label = label,
+            None => self.addresses.push(WatchAddress {
+                address: address.to_string(),
+                label,
+            }),
+        }
+        Ok(())
+    }
+
+    /// Every watched address with the name it's shown by, its wallet label or else its address book label
+    fn watched(&self, config: &Config) -> Result<Vec<(Public, String)>> {
+        if self.addresses.is_empty() {
+            return Err(error(
+                ErrorKind::NotFound,
+                "The wallet watches no addresses yet, add some with `wallet add`",
+            ));
+        }
+        self.addresses
+            .iter()
+            .map(|watched| {
+                let name = watched
+                    .label
+                    .clone()
+                    .or_else(|| config.display_label(&watched.address))
+                    .unwrap_or_else(|| watched.address.clone());
+                Ok((parse_address(&watched.address)?, name))
+            })
+            .collect()
+    }
+}
+
+fn parse_address(address: &str) -> Result<Public> {
+    Public::new_from_base36(address).ok_or_else(|| {
+        error(
+            ErrorKind::InvalidIdentifier,
+            format!("Wallet address {address} is not valid. Expected base36 address"),
+        )
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct AddressBalance {
+    pub address: String,
+    pub name: String,
+    pub balance: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WalletBalance {
+    pub addresses: Vec<AddressBalance>,
+    pub total: u64,
+}
+
+pub async fn wallet_balance(
+    client: &NodeClient,
+    wallet: &Wallet,
+    config: &Config,
+) -> Result<WalletBalance> {
+    let mut addresses = Vec::new();
+    for (public, name) in wallet.watched(config)? {
+        debug!("get_balance {}", public.dump_base36());
+        addresses.push(AddressBalance {
+            address: public.dump_base36(),
+            name,
+            balance: client.get_balance(public).await?,
+        });
+    }
+    Ok(WalletBalance {
+        total: addresses.iter().map(|address| address.balance).sum(),
+        addresses,
+    })
+}
+
+/// Balances per address and their total, with a column of their value at `fiat`'s price when given
+pub fn balance_table(balance: &WalletBalance, fiat: Option<&Quote>) -> Table {
+    let fiat_header = fiat.map(|quote| format!("Value ({})", quote.currency.to_uppercase()));
+    let mut headers = vec!["Address", "Balance (SNAP)"];
+    headers.extend(fiat_header.as_deref());
+    let mut table = new_table(&headers);
+    let row = |name: String, nano: u64| {
+        let mut row = vec![name, color::amount(format_snap(nano))];
+        row.extend(fiat.map(|quote| format!("{:.2}", quote.value(nano))));
+        row
+    };
+    for address in &balance.addresses {
+        table.add_row(row(color::hash(&address.name), address.balance));
+    }
+    table.add_row(row("Total".to_string(), balance.total));
+    align_right(&mut table, &[1, 2]);
+    table
+}
+
+#[derive(Debug, Serialize)]
+pub struct WalletUtxo {
+    pub name: String,
+    pub transaction_id: TransactionId,
+    pub index: usize,
+    pub output: TransactionOutput,
+}
+
+/// The UTXOs of every watched address, biggest first
+pub async fn wallet_utxos(
+    client: &NodeClient,
+    wallet: &Wallet,
+    config: &Config,
+) -> Result<Vec<WalletUtxo>> {
+    let mut utxos = Vec::new();
+    for (public, name) in wallet.watched(config)? {
+        debug!("get_available_transaction_outputs {}", public.dump_base36());
+        for (transaction_id, output, index) in
+            client.get_available_transaction_outputs(public).await?
+        {
+            utxos.push(WalletUtxo {
+                name: name.clone(),
+                transaction_id,
+                index,
+                output,
+            });
+        }
+    }
+    utxos.sort_by_key(|utxo| Reverse(utxo.output.amount));
+    Ok(utxos)
+}
+
+pub fn utxos_table(utxos: &[WalletUtxo]) -> Table {
+    let mut table = new_table(&["Address", "TX ID", "Index", "Amount (SNAP)"]);
+    for utxo in utxos {
+        table.add_row(vec![
+            utxo.name.clone(),
+            color::hash(utxo.transaction_id.dump_base36()),
+            utxo.index.to_string(),
+            color::amount(format_snap(utxo.output.amount)),
+        ]);
+    }
+    align_right(&mut table, &[2, 3]);
+    table
+}
+
+/// A transaction touching one or more watched addresses
+#[derive(Debug, Serialize)]
+pub struct WalletTransaction {
+    pub transaction_id: Hash,
+    pub timestamp: u64,
+    /// Names of the watched addresses it touches
+    pub addresses: Vec<String>,
+    /// Paid to the watched addresses, change included
+    pub received: u64,
+}
+
+/// The histories of every watched address interleaved by transaction time, oldest first.
+/// Every transaction is fetched once for its timestamp, however many watched addresses it touches.
+pub async fn wallet_history(
+    client: &NodeClient,
+    wallet: &Wallet,
+    config: &Config,
+) -> Result<Vec<WalletTransaction>> {
+    let watched = wallet.watched(config)?;
+    let mut touched: HashMap<Hash, Vec<usize>> = HashMap::new();
+    let mut order = Vec::new();
+    for (position, (public, _)) in watched.iter().enumerate() {
+        debug!("get_transactions_of_address {}", public.dump_base36());
+        for tx_id in client.get_transactions_of_address(*public).await? {
+            let positions = touched.entry(tx_id).or_default();
+            if positions.is_empty() {
+                order.push(tx_id);
+            }
+            positions.push(position);
+        }
+    }
+
+    let mut history = Vec::with_capacity(order.len());
+    for tx_id in order {
+        debug!("get_transaction {}", tx_id.dump_base36());
+        let Some(tx) = client.get_transaction(&tx_id).await? else {
+            continue;
+        };
+        let positions = &touched[&tx_id];
+        history.push(WalletTransaction {
+            transaction_id: tx_id,
+            timestamp: tx.timestamp,
+            addresses: positions
+                .iter()
+                .map(|position| watched[*position].1.clone())
+                .collect(),
+            received: tx
+                .outputs
+                .iter()
+                .filter(|output| {
+                    positions
+                        .iter()
+                        .any(|position| watched[*position].0 == output.receiver)
+                })
+                .map(|output| output.amount)
+                .sum(),
+        });
+    }
+    // Stable, so transactions of the same second keep their chain order
+    history.sort_by_key(|tx| tx.timestamp);
+    Ok(history)
+}
+
+pub fn history_table(history: &[WalletTransaction]) -> Table {
+    let mut table = new_table(&["Time", "TX ID", "Addresses", "Received (SNAP)"]);
+    for tx in history {
+        table.add_row(vec![
+            format_timestamp(tx.timestamp),
+            color::hash(tx.transaction_id.dump_base36()),
+            tx.addresses.join(", "),
+            color::amount(format_snap(tx.received)),
+        ]);
+    }
+    align_right(&mut table, &[3]);
+    table
+}
+
+/// Add an address to the wallet file, which never touches a node
+pub fn run_add(path: &Path, address: &str, label: Option<String>, config: &Config) -> Result<()> {
+    let address = config.resolve_address(address);
+    let mut wallet = Wallet::load(path)?;
+    wallet.add(address, label)?;
+    wallet.save(path)?;
+    info!("Watching {} in {}", address, path.display());
+    Ok(())
+}
+
+/// Print the balances of the wallet's addresses and their total
+pub async fn run_balance(
+    client: &NodeClient,
+    path: &Path,
+    config: &Config,
+    out: &Output,
+    fiat: Option<&Quote>,
+) -> Result<()> {
+    let balance = wallet_balance(client, &Wallet::load(path)?, config).await?;
+    match out.format {
+        OutputFormat::Text => println!("{}", balance_table(&balance, fiat)),
+        OutputFormat::Debug => println!("{:#?}", balance),
+        OutputFormat::Json => out.json(&price::with_fiat(
+            &balance,
+            fiat,
+            &[("total", balance.total)],
+        )?)?,
+    }
+    Ok(())
+}
+
+/// Print the unspent outputs of the wallet's addresses merged
+pub async fn run_utxos(
+    client: &NodeClient,
+    path: &Path,
+    config: &Config,
+    out: &Output,
+) -> Result<()> {
+    let utxos = wallet_utxos(client, &Wallet::load(path)?, config).await?;
+    match out.format {
+        OutputFormat::Text => println!("{}", utxos_table(&utxos)),
+        OutputFormat::Debug => println!("{:#?}", utxos),
+        OutputFormat::Json => out.json(&utxos)?,
+    }
+    Ok(())
+}
+
+/// Print the transactions of the wallet's addresses merged
+pub async fn run_history(
+    client: &NodeClient,
+    path: &Path,
+    config: &Config,
+    out: &Output,
+) -> Result<()> {
+    let history = wallet_history(client, &Wallet::load(path)?, config).await?;
+    match out.format {
+        OutputFormat::Text => println!("{}", history_table(&history)),
+        OutputFormat::Debug => println!("{:#?}", history),
+        OutputFormat::Json => out.json(&history)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,150 @@
+// accept.rs
+mod common;
+
+use snap_coin::{
+    api::requests::{Request, Response},
+    core::transaction::{Transaction, TransactionInput, TransactionOutput},
+    crypto::{Hash, Signature, keys::Private},
+};
+use snap_coin_utils::{accept::test_accept, node::NodeClient};
+
+/// A confirmed transaction paying `owner` 50 nano at output 0
+fn funding(owner: &Private) -> Transaction {
+    Transaction {
+        inputs: Vec::new(),
+        outputs: vec![TransactionOutput {
+            amount: 50,
+            receiver: owner.to_public(),
+        }],
+        transaction_id: Some(Hash::new_from_buf([1; 32])),
+        nonce: 0,
+        timestamp: 0,
+    }
+}
+
+/// Serve `funding` as the only UTXO of `owner` at the easiest difficulties, with `mempool` pending
+async fn mock_node(owner: Private, mempool: Vec<Transaction>) -> NodeClient {
+    let funding = funding(&owner);
+    let address = common::mock_node(move |request| {
+        Some(match request {
+            Request::AvailableUTXOs { address, .. } => Response::AvailableUTXOs {
+                available_inputs: if address == owner.to_public() {
+                    vec![(funding.transaction_id.unwrap(), funding.outputs[0], 0)]
+                } else {
+                    Vec::new()
+                },
+                next_page: None,
+            },
+            Request::Difficulty => Response::Difficulty {
+                transaction_difficulty: [255; 32],
+                block_difficulty: [255; 32],
+            },
+            Request::LiveTransactionDifficulty => Response::LiveTransactionDifficulty {
+                live_difficulty: [255; 32],
+            },
+            Request::Transaction { transaction_id } => Response::Transaction {
+                transaction: (funding.transaction_id == Some(transaction_id))
+                    .then(|| funding.clone()),
+            },
+            Request::Mempool { .. } => Response::Mempool {
+                mempool: mempool.clone(),
+                next_page: None,
+            },
+            _ => return None,
+        })
+    })
+    .await;
+    NodeClient::connect(address).await.unwrap()
+}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// Spend output `index` of the funding transaction into `amounts`, signed by `signer` and mined
+fn spend(owner: &Private, signer: &Private, index: usize, amounts: &[u64]) -> Transaction {
+    let mut tx = Transaction {
+        inputs: vec![TransactionInput {
+            transaction_id: Hash::new_from_buf([1; 32]),
+            output_index: index,
+            signature: None,
+            output_owner: owner.to_public(),
+        }],
+        outputs: amounts
+            .iter()
+            .map(|&amount| TransactionOutput {
+                amount,
+                receiver: Private::new_random().to_public(),
+            })
+            .collect(),
+        transaction_id: None,
+        nonce: 0,
+        timestamp: now(),
+    };
+    let buf = tx.get_input_signing_buf().unwrap();
+    let mut signer = *signer;
+    tx.inputs[0].signature = Some(Signature::new_signature(&mut signer, &buf));
+    tx.compute_pow(&[255; 32], None).unwrap();
+    tx
+}
+
+async fn reason(client: &NodeClient, tx: &Transaction) -> Option<String> {
+    let acceptance = test_accept(client, tx).await.unwrap();
+    assert_eq!(acceptance.accepted, acceptance.reason.is_none());
+    acceptance.reason
+}
+
+#[tokio::test]
+async fn valid_transactions_are_accepted() {
+    let owner = Private::new_random();
+    let client = mock_node(owner, Vec::new()).await;
+    assert_eq!(
+        reason(&client, &spend(&owner, &owner, 0, &[20, 30])).await,
+        None
+    );
+}
+
+#[tokio::test]
+async fn rejections_carry_the_node_reason() {
+    let owner = Private::new_random();
+    let client = mock_node(owner, Vec::new()).await;
+    let rejected = async |tx: Transaction| reason(&client, &tx).await.unwrap();
+
+    let mut unsigned = spend(&owner, &owner, 0, &[50]);
+    unsigned.inputs[0].signature = None;
+    assert!(rejected(unsigned).await.contains("missing signature"));
+
+    let forged = spend(&owner, &Private::new_random(), 0, &[50]);
+    assert!(rejected(forged).await.contains("signature is invalid"));
+
+    let unbalanced = spend(&owner, &owner, 0, &[60]);
+    assert!(rejected(unbalanced).await.contains("sum up"));
+
+    let zero = spend(&owner, &owner, 0, &[50, 0]);
+    assert!(rejected(zero).await.contains("cannot be zero"));
+
+    let out_of_range = spend(&owner, &owner, 3, &[50]);
+    assert!(rejected(out_of_range).await.contains("index invalid"));
+
+    let mut tampered = spend(&owner, &owner, 0, &[50]);
+    tampered.outputs[0].amount = 49;
+    assert!(rejected(tampered).await.contains("hash is invalid"));
+
+    let mut stale = spend(&owner, &owner, 0, &[50]);
+    stale.timestamp = now() - 1000;
+    assert!(rejected(stale.clone()).await.contains("expired"));
+    stale.timestamp = 0;
+    assert!(rejected(stale).await.contains("expired"));
+}
+
+#[tokio::test]
+async fn inputs_pending_in_the_mempool_are_double_spends() {
+    let owner = Private::new_random();
+    let pending = spend(&owner, &owner, 0, &[50]);
+    let client = mock_node(owner, vec![pending]).await;
+    let reason = reason(&client, &spend(&owner, &owner, 0, &[20, 30])).await;
+    assert!(reason.unwrap().contains("Double spending"));
+}
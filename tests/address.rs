@@ -0,0 +1,235 @@
+// address.rs
+mod common;
+
+use snap_coin::{
+    api::requests::{Request, Response},
+    blockchain_data_provider::BlockchainDataProvider,
+    core::transaction::TransactionOutput,
+    crypto::{Hash, keys::Public},
+};
+use snap_coin_utils::{
+    fixture::{Fixture, FixtureProvider},
+    miners::{block_miner, coinbase},
+    node::NodeClient,
+    query::{
+        AddressInfo, AddressSections, AddressUtxos, UtxoSort, address, address_maturity,
+        address_summary, utxo_confirmations,
+    },
+};
+
+use common::{chain_response, fixture};
+
+/// Three UTXOs returned out of chain order, the history dating them
+fn address_info() -> AddressInfo {
+    let receiver = Public::new_from_buf(&[7u8; 32]);
+    let (old, middle, new) = (Hash::new(b"old"), Hash::new(b"middle"), Hash::new(b"new"));
+    let utxo = |tx_id, amount, index| (tx_id, TransactionOutput { amount, receiver }, index);
+    AddressInfo {
+        address: receiver.dump_base36(),
+        balance: Some(60),
+        utxos: Some(AddressUtxos {
+            utxo_count: 3,
+            utxo_value: 60,
+            utxos: vec![utxo(middle, 30, 0), utxo(old, 10, 0), utxo(new, 20, 1)],
+        }),
+        history: Some(vec![old, middle, new]),
+    }
+}
+
+fn amounts(info: &AddressInfo) -> Vec<u64> {
+    let utxos = &info.utxos.as_ref().unwrap().utxos;
+    utxos.iter().map(|(_, output, _)| output.amount).collect()
+}
+
+#[test]
+fn utxos_sort_by_value_or_age() {
+    let mut info = address_info();
+    info.select_utxos(Some(UtxoSort::Value), 0, None);
+    assert_eq!(amounts(&info), [30, 20, 10]);
+
+    let mut info = address_info();
+    info.select_utxos(Some(UtxoSort::Age), 0, None);
+    assert_eq!(amounts(&info), [10, 30, 20]);
+
+    let mut info = address_info();
+    info.select_utxos(None, 0, None);
+    assert_eq!(amounts(&info), [30, 10, 20]);
+}
+
+#[test]
+fn utxos_page_without_touching_the_totals() {
+    let mut info = address_info();
+    info.select_utxos(Some(UtxoSort::Age), 1, Some(1));
+    assert_eq!(amounts(&info), [30]);
+    let utxos = info.utxos.unwrap();
+    assert_eq!(utxos.utxo_count, 3);
+    assert_eq!(utxos.utxo_value, 60);
+
+    let mut info = address_info();
+    info.select_utxos(None, 5, Some(2));
+    assert!(amounts(&info).is_empty());
+
+    // Without the history there is nothing to date them by
+    let mut info = address_info();
+    info.history = None;
+    info.select_utxos(Some(UtxoSort::Age), 0, None);
+    assert_eq!(amounts(&info), [30, 10, 20]);
+}
+
+/// Serve the fixture chain and the history of its addresses, every address holding `balance`
+async fn mock_node(fixture: Fixture, balance: u64) -> NodeClient {
+    let address = common::mock_node(move |request| match request {
+        Request::TransactionsOfAddress { address, .. } => Some(Response::TransactionsOfAddress {
+            transactions: fixture
+                .blocks
+                .iter()
+                .flat_map(|block| &block.transactions)
+                .filter(|tx| tx.contains_address(address))
+                .filter_map(|tx| tx.transaction_id)
+                .collect(),
+            next_page: None,
+        }),
+        Request::AvailableUTXOs { .. } => Some(Response::AvailableUTXOs {
+            available_inputs: Vec::new(),
+            next_page: None,
+        }),
+        Request::Balance { .. } => Some(Response::Balance { balance }),
+        request => chain_response(&fixture, &request),
+    })
+    .await;
+    NodeClient::connect(address).await.unwrap()
+}
+
+#[tokio::test]
+async fn summary_totals_the_history() {
+    let fixture = fixture();
+    let miner = Public::new_from_buf(&block_miner(&fixture.blocks[16]).unwrap());
+    let active: Vec<usize> = fixture
+        .blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, block)| {
+            block
+                .transactions
+                .iter()
+                .any(|tx| tx.contains_address(miner))
+        })
+        .map(|(index, _)| 560 + index)
+        .collect();
+    let received: u64 = fixture
+        .blocks
+        .iter()
+        .flat_map(|block| &block.transactions)
+        .flat_map(|tx| &tx.outputs)
+        .filter(|output| output.receiver == miner)
+        .map(|output| output.amount)
+        .sum();
+    let client = mock_node(fixture, 5).await;
+
+    let summary = address_summary(&client, &miner.dump_base36())
+        .await
+        .unwrap();
+    assert_eq!(summary.balance, 5);
+    assert_eq!(summary.received, received);
+    assert_eq!(summary.sent, received - 5);
+    assert_eq!(summary.first_seen, active.first().copied());
+    assert_eq!(summary.last_seen, active.last().copied());
+}
+
+#[tokio::test]
+async fn only_the_sections_asked_for_are_fetched() {
+    let fixture = fixture();
+    let miner = Public::new_from_buf(&block_miner(&fixture.blocks[16]).unwrap()).dump_base36();
+    let client = mock_node(fixture, 5).await;
+
+    let sections = AddressSections {
+        balance: true,
+        utxos: false,
+        history: false,
+    };
+    let info = address(&client, &miner, sections).await.unwrap();
+    assert_eq!(info.balance, Some(5));
+    assert!(info.utxos.is_none());
+    assert!(info.history.is_none());
+    let json = serde_json::to_value(&info).unwrap();
+    assert!(json.get("utxos").is_none());
+
+    let info = address(&client, &miner, AddressSections::ALL)
+        .await
+        .unwrap();
+    assert_eq!(info.utxos.unwrap().utxo_count, 0);
+    assert!(!info.history.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn utxos_are_dated_by_the_block_holding_them() {
+    let fixture = fixture();
+    let reward = |height: usize| {
+        let tx = coinbase(&fixture.blocks[height - 560]).unwrap();
+        (tx.transaction_id.unwrap(), tx.outputs[1], 1)
+    };
+    let (old, tip) = (reward(580), reward(599));
+    let missing = (Hash::new(b"missing"), tip.1, 0);
+    let client = mock_node(fixture, 5).await;
+
+    let confirmations = utxo_confirmations(&client, &[old, tip, missing, old])
+        .await
+        .unwrap();
+    assert_eq!(confirmations, [Some(20), Some(1), None, Some(20)]);
+}
+
+#[tokio::test]
+async fn recent_block_rewards_are_immature() {
+    let fixture = fixture();
+    let miner = Public::new_from_buf(&block_miner(&fixture.blocks[39]).unwrap());
+    // Rewards of the last 5 blocks, 596 to 599, are below a depth of 5
+    let recent: Vec<(usize, u64)> = (35..40)
+        .filter_map(|index| {
+            let reward = coinbase(&fixture.blocks[index]).unwrap();
+            let amount = reward
+                .outputs
+                .iter()
+                .filter(|output| output.receiver == miner)
+                .map(|output| output.amount)
+                .sum::<u64>();
+            (amount > 0).then_some((560 + index, amount))
+        })
+        .collect();
+    let provider = FixtureProvider::new(fixture);
+    let total: u64 = provider
+        .get_available_transaction_outputs(miner)
+        .await
+        .unwrap()
+        .iter()
+        .map(|(_, output, _)| output.amount)
+        .sum();
+
+    // Nothing waits without a maturity rule
+    let maturity = address_maturity(&provider, &miner.dump_base36(), 0)
+        .await
+        .unwrap();
+    assert_eq!((maturity.spendable, maturity.immature), (total, 0));
+    assert!(maturity.immature_outputs.is_empty());
+
+    let maturity = address_maturity(&provider, &miner.dump_base36(), 5)
+        .await
+        .unwrap();
+    let immature: u64 = recent.iter().map(|(_, amount)| amount).sum();
+    assert_eq!(maturity.immature, immature);
+    assert_eq!(maturity.spendable, total - immature);
+    let mut heights: Vec<usize> = maturity
+        .immature_outputs
+        .iter()
+        .map(|output| output.height)
+        .collect();
+    heights.dedup();
+    assert_eq!(
+        heights,
+        recent.iter().map(|(height, _)| *height).collect::<Vec<_>>()
+    );
+    let last = maturity.immature_outputs.last().unwrap();
+    assert_eq!(
+        (last.height, last.confirmations, last.unlock_height),
+        (599, 1, 604)
+    );
+}
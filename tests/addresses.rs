@@ -0,0 +1,65 @@
+// addresses.rs
+mod common;
+
+use std::collections::HashSet;
+
+use snap_coin_utils::{
+    addresses::{address_activity, address_buckets, block_addresses},
+    fixture::FixtureProvider,
+};
+
+use common::fixture;
+
+fn addresses(bytes: &[u8]) -> HashSet<[u8; 32]> {
+    bytes.iter().map(|&byte| [byte; 32]).collect()
+}
+
+#[test]
+fn addresses_are_counted_once_per_bucket_and_new_once_ever() {
+    let blocks = [
+        (100, addresses(&[1, 2])),
+        (150, addresses(&[2, 3])),
+        (200, addresses(&[1, 4])),
+        (450, addresses(&[4, 5])),
+    ];
+    let activity = address_buckets(10, &blocks, addresses(&[1]), 100).unwrap();
+
+    let buckets: Vec<(u64, usize, usize, usize, usize)> = activity
+        .buckets
+        .iter()
+        .map(|b| (b.start, b.first_height, b.blocks, b.active, b.new))
+        .collect();
+    assert_eq!(
+        buckets,
+        [(100, 10, 2, 3, 2), (200, 12, 1, 2, 1), (400, 13, 1, 2, 1),]
+    );
+    assert_eq!((activity.active, activity.new, activity.known), (5, 4, 5));
+
+    let series = activity.series();
+    assert_eq!(series[0].points, [(10, 3.0), (12, 2.0), (13, 2.0)]);
+    assert_eq!(series[1].points, [(10, 2.0), (12, 1.0), (13, 1.0)]);
+    assert!(address_buckets(10, &blocks, HashSet::new(), 0).is_err());
+}
+
+#[tokio::test]
+async fn earlier_blocks_only_mark_addresses_as_seen() {
+    let fixture = fixture();
+    let earlier: HashSet<[u8; 32]> = fixture.blocks[..20]
+        .iter()
+        .flat_map(block_addresses)
+        .collect();
+    let later: HashSet<[u8; 32]> = fixture.blocks[20..]
+        .iter()
+        .flat_map(block_addresses)
+        .collect();
+    let provider = FixtureProvider::new(fixture);
+
+    let activity = address_activity(&provider, 560..600, 580, 1 << 40)
+        .await
+        .unwrap();
+    assert_eq!(activity.buckets.len(), 1);
+    assert_eq!(activity.buckets[0].blocks, 20);
+    assert_eq!(activity.active, later.len());
+    assert_eq!(activity.new, later.difference(&earlier).count());
+    assert_eq!(activity.known, earlier.union(&later).count());
+}
@@ -0,0 +1,114 @@
+// anomalies.rs
+mod common;
+
+use snap_coin::crypto::keys::Public;
+use snap_coin_utils::{
+    anomalies::{AnomalyKind, AnomalyOptions, ScannedBlock, detect_anomalies, scan_blocks},
+    fixture::FixtureProvider,
+};
+
+use common::fixture;
+
+/// 40 blocks 20s apart at difficulty 100, mined by miner 1 then miner 2 taking over at height 20
+fn steady() -> Vec<ScannedBlock> {
+    (0..40)
+        .map(|height| ScannedBlock {
+            height,
+            timestamp: 1000 + height as u64 * 20,
+            difficulty: 100.0,
+            miner: Some([if height < 20 { 1 } else { 2 }; 32]),
+        })
+        .collect()
+}
+
+#[test]
+fn steady_blocks_only_flag_the_takeover() {
+    let anomalies = detect_anomalies(
+        &steady(),
+        &AnomalyOptions {
+            share_window: 20,
+            ..AnomalyOptions::default()
+        },
+    );
+    assert_eq!(anomalies.len(), 1);
+    assert_eq!(anomalies[0].height, 20);
+    assert_eq!(anomalies[0].kind, AnomalyKind::MinerShare);
+    assert_eq!(
+        anomalies[0].explanation,
+        format!(
+            "{} went from 0.0% to 100.0% of blocks 20..40",
+            Public::new_from_buf(&[2; 32]).dump_base36()
+        )
+    );
+
+    // Windows mixing both miners evenly show no jump
+    let options = AnomalyOptions {
+        share_window: 40,
+        ..AnomalyOptions::default()
+    };
+    assert!(detect_anomalies(&steady(), &options).is_empty());
+}
+
+#[test]
+fn intervals_difficulty_and_reversals_are_flagged() {
+    let mut blocks = steady();
+    // A 10 minute gap, everything after it shifted
+    for block in &mut blocks[10..] {
+        block.timestamp += 600;
+    }
+    // Block 25 claims to be older than block 24
+    blocks[25].timestamp = blocks[24].timestamp - 5;
+    blocks[30].difficulty = 300.0;
+    let options = AnomalyOptions {
+        share_window: 40,
+        ..AnomalyOptions::default()
+    };
+    let anomalies = detect_anomalies(&blocks, &options);
+
+    let found: Vec<(usize, AnomalyKind)> = anomalies.iter().map(|a| (a.height, a.kind)).collect();
+    assert_eq!(
+        found,
+        [
+            (10, AnomalyKind::Interval),
+            (25, AnomalyKind::TimestampReversal),
+            (30, AnomalyKind::Difficulty),
+            (31, AnomalyKind::Difficulty),
+        ]
+    );
+    assert!(
+        anomalies[0]
+            .explanation
+            .starts_with("620s after the previous block")
+    );
+    assert_eq!(
+        anomalies[1].explanation,
+        "timestamp is 5s before block 24's"
+    );
+    assert_eq!(
+        anomalies[2].explanation,
+        "difficulty went from 100.00 to 300.00, 3.00x"
+    );
+
+    // A looser difficulty threshold lets the jump through
+    let options = AnomalyOptions {
+        difficulty_jump: 4.0,
+        ..options
+    };
+    assert_eq!(detect_anomalies(&blocks, &options).len(), 2);
+}
+
+#[tokio::test]
+async fn the_fixture_scans_into_blocks() {
+    let fixture = fixture();
+    let provider = FixtureProvider::new(fixture);
+    let blocks = scan_blocks(&provider, 560..600).await.unwrap();
+    assert_eq!(blocks.len(), 40);
+    assert_eq!(blocks[0].height, 560);
+    assert_eq!(format!("{:.2}", blocks[0].difficulty), "26.31");
+    // The fixture's block times stay within 12s..30s, so difficulty moves at most 20% a block
+    let options = AnomalyOptions {
+        share_window: 40,
+        ..AnomalyOptions::default()
+    };
+    assert!(detect_anomalies(&blocks, &options).is_empty());
+}
@@ -0,0 +1,56 @@
+// audit.rs
+mod common;
+
+use snap_coin_utils::{
+    audit::{audit_retargets, expected_difficulties},
+    fixture::FixtureProvider,
+    normalize_difficulty,
+};
+
+use common::{fixture, provider};
+
+#[test]
+fn difficulty_follows_the_previous_interval() {
+    let fixture = fixture();
+    let mut previous = fixture.blocks[1].clone();
+    let base = normalize_difficulty(&previous.meta.block_pow_difficulty);
+    previous.timestamp = 1000;
+
+    // A block taking twice the target time eases the target as far as it may, by 20%
+    let (slow, _) = expected_difficulties(&previous, 960);
+    assert!((normalize_difficulty(&slow) / base - 1.0 / 1.2).abs() < 1e-3);
+    // Right on the target leaves it alone
+    let (steady, _) = expected_difficulties(&previous, 980);
+    assert_eq!(steady, previous.meta.block_pow_difficulty);
+    // A fast block makes the next one harder
+    let (fast, _) = expected_difficulties(&previous, 990);
+    assert!(normalize_difficulty(&fast) > base);
+}
+
+#[tokio::test]
+async fn blocks_off_the_rules_are_flagged() {
+    let provider = provider();
+    let audit = audit_retargets(&provider, 562..600).await.unwrap();
+    assert_eq!(audit.checked(), 38);
+    assert!(audit.mismatches.is_empty());
+
+    let mut tampered = fixture();
+    let copied = tampered.blocks[19].meta.block_pow_difficulty;
+    tampered.blocks[20].meta.block_pow_difficulty = copied;
+    let provider = FixtureProvider::new(tampered);
+    let audit = audit_retargets(&provider, 562..600).await.unwrap();
+    let heights: Vec<usize> = audit.mismatches.iter().map(|m| m.height).collect();
+    // The tampered block, and the next one whose adjustment starts from its difficulty
+    assert_eq!(heights, [580, 581]);
+    let mismatch = &audit.mismatches[0];
+    assert_eq!(
+        mismatch.actual_block_difficulty,
+        normalize_difficulty(&copied)
+    );
+    assert_ne!(
+        mismatch.expected_block_difficulty,
+        mismatch.actual_block_difficulty
+    );
+
+    assert!(audit_retargets(&provider, 1..600).await.is_err());
+}
@@ -0,0 +1,220 @@
+// averages.rs
+mod common;
+
+use serde_json::json;
+use snap_coin::economics::total_reward;
+use snap_coin_utils::{
+    averages::{
+        ConfidenceInterval, Inflation, OutlierKind, calculate_chain_stats,
+        calculate_chain_stats_range, calculate_header_stats, find_outliers,
+    },
+    compare::compare_stats,
+    fixture::Fixture,
+    normalize_difficulty,
+};
+
+use common::{fixture, provider};
+
+#[test]
+fn fixture_round_trips_through_json() {
+    let path = std::env::temp_dir().join(format!("snap-coin-utils-{}.json", std::process::id()));
+    fixture().save(&path).unwrap();
+    let loaded = Fixture::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    let (loaded, recorded) = (loaded.unwrap(), fixture());
+
+    assert_eq!(loaded.start_height, recorded.start_height);
+    assert_eq!(loaded.block_difficulty, recorded.block_difficulty);
+    let hashes = |fixture: &Fixture| -> Vec<_> {
+        fixture.blocks.iter().map(|block| block.meta.hash).collect()
+    };
+    assert_eq!(hashes(&loaded), hashes(&recorded));
+}
+
+#[tokio::test]
+async fn inflation_json_of_the_fixture() {
+    let fixture = fixture();
+    let issued: u64 = fixture
+        .blocks
+        .iter()
+        .flat_map(|b| &b.transactions)
+        .filter(|tx| tx.inputs.is_empty())
+        .flat_map(|tx| &tx.outputs)
+        .map(|output| output.amount)
+        .sum();
+    let block_time = (fixture.blocks[39].timestamp - fixture.blocks[0].timestamp) as f64 / 39.0;
+    let supply = total_reward(600);
+    let stats = calculate_chain_stats(&provider(), 40).await.unwrap();
+
+    assert_eq!(
+        serde_json::to_value(Inflation::new(&stats, 40)).unwrap(),
+        json!({
+            "blocks": 40,
+            "issued": issued,
+            "circulating_supply": supply,
+            "annual_inflation":
+                issued as f64 / 40.0 * (365.25 * 24.0 * 60.0 * 60.0 / block_time) / supply as f64,
+        })
+    );
+}
+
+#[tokio::test]
+async fn chain_stats_of_the_fixture() {
+    let fixture = fixture();
+    let stats = calculate_chain_stats(&provider(), 40).await.unwrap();
+
+    let transactions: usize = fixture.blocks.iter().map(|b| b.transactions.len()).sum();
+    let coinbase_amounts: u64 = fixture
+        .blocks
+        .iter()
+        .flat_map(|b| &b.transactions)
+        .filter(|tx| tx.inputs.is_empty())
+        .flat_map(|tx| &tx.outputs)
+        .map(|output| output.amount)
+        .sum();
+    let duration = fixture.blocks[39].timestamp - fixture.blocks[0].timestamp;
+
+    assert_eq!(stats.start_height, 560);
+    assert_eq!(stats.block_time.average, duration as f64 / 39.0);
+    assert_eq!(stats.avg_txs_per_block, transactions as f64 / 40.0);
+    assert_eq!(stats.tps, transactions as f64 / duration as f64);
+    assert_eq!(stats.issued, coinbase_amounts);
+    let difficulties: f64 = fixture
+        .blocks
+        .iter()
+        .map(|b| normalize_difficulty(&b.meta.block_pow_difficulty))
+        .sum();
+    assert!((stats.avg_block_difficulty - difficulties / 40.0).abs() < 1e-9);
+    assert_eq!(stats.block_time_series.len(), 39);
+    assert_eq!(stats.block_size_series.len(), 40);
+    let sizes: f64 = stats.block_size_series.iter().sum();
+    assert!((sizes / 40.0 - stats.avg_block_size_bytes).abs() < 1e-9);
+    let txs = fixture.blocks[5].transactions.len() as f64;
+    assert_eq!(stats.utilization_series[5], txs / 500.0 * 100.0);
+    assert!((stats.avg_utilization - stats.avg_txs_per_block / 5.0).abs() < 1e-9);
+    assert_eq!(stats.pattern_mix.total(), transactions);
+    assert_eq!(stats.pattern_mix.coinbase, 40);
+    assert_eq!(
+        stats
+            .top_miners
+            .iter()
+            .map(|(_, blocks)| blocks)
+            .sum::<usize>(),
+        40
+    );
+}
+
+#[tokio::test]
+async fn chain_stats_need_two_blocks() {
+    assert!(calculate_chain_stats(&provider(), 1).await.is_err());
+}
+
+#[tokio::test]
+async fn compare_halves_of_the_fixture() {
+    let first = calculate_chain_stats_range(&provider(), 560..580)
+        .await
+        .unwrap();
+    let second = calculate_chain_stats_range(&provider(), 580..600)
+        .await
+        .unwrap();
+    let comparison = compare_stats(&first, &second);
+
+    assert_eq!(comparison.a, 560..580);
+    assert_eq!(comparison.b, 580..600);
+    let block_time = &comparison.values[0];
+    assert_eq!(block_time.a, first.block_time.average);
+    assert_eq!(block_time.b, second.block_time.average);
+    let expected =
+        (second.block_time.average - first.block_time.average) / first.block_time.average * 100.0;
+    assert_eq!(block_time.delta_percent, Some(expected));
+
+    let whole = calculate_chain_stats(&provider(), 40).await.unwrap();
+    let same = compare_stats(&whole, &whole);
+    assert!(
+        same.values
+            .iter()
+            .all(|v| v.delta_percent.unwrap_or(0.0) == 0.0)
+    );
+}
+
+#[tokio::test]
+async fn header_stats_match_full_stats() {
+    let headers = calculate_header_stats(&provider(), 40).await.unwrap();
+    let full = calculate_chain_stats(&provider(), 40).await.unwrap();
+
+    assert_eq!(headers.start_height, full.start_height);
+    assert_eq!(headers.block_time.average, full.block_time.average);
+    assert_eq!(headers.block_time.median, full.block_time.median);
+    assert_eq!(headers.avg_block_difficulty, full.avg_block_difficulty);
+    assert_eq!(headers.avg_tx_difficulty, full.avg_tx_difficulty);
+    assert_eq!(headers.block_time_series, full.block_time_series);
+}
+
+#[test]
+fn outliers_are_found_in_every_series() {
+    let mut block_diffs = vec![100.0; 20];
+    let mut tx_diffs = vec![50.0; 20];
+    let mut block_times = vec![20.0; 19];
+    block_diffs[4] = 400.0;
+    tx_diffs[12] = 1.0;
+    block_times[7] = 300.0;
+    let outliers = find_outliers(1000, &block_diffs, &tx_diffs, &block_times, 3.0);
+
+    let found: Vec<(usize, OutlierKind, f64)> = outliers
+        .iter()
+        .map(|o| (o.height, o.kind, o.value))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (1004, OutlierKind::BlockDifficulty, 400.0),
+            // Intervals start at the second block
+            (1008, OutlierKind::BlockTime, 300.0),
+            (1012, OutlierKind::TxDifficulty, 1.0),
+        ]
+    );
+    assert!(outliers[0].z_score > 3.0);
+    assert!(outliers[2].z_score < -3.0);
+
+    // Flat series have no outliers however low the threshold
+    assert!(find_outliers(1000, &[5.0; 4], &[5.0; 4], &[20.0; 3], 0.0).is_empty());
+}
+
+#[test]
+fn confidence_intervals_widen_for_small_samples() {
+    // Mean 20, sample standard deviation 2, standard error 1 with 4 samples
+    let small = ConfidenceInterval::of_mean(&[18.0, 22.0, 18.0, 22.0]);
+    let margin = 3.182 * 2.0 / 2.0 * (4.0f64 / 3.0).sqrt();
+    assert!((small.margin - margin).abs() < 1e-9);
+    assert!((small.low - (20.0 - margin)).abs() < 1e-9);
+    assert_eq!(small.samples, 4);
+    assert!(!small.adequate);
+    assert!(small.note().unwrap().starts_with("only 4 samples"));
+
+    // Plenty of samples close together are adequate, the same count spread wide is not
+    let tight: Vec<f64> = (0..100).map(|i| 20.0 + (i % 2) as f64).collect();
+    let tight = ConfidenceInterval::of_mean(&tight);
+    assert!(tight.adequate);
+    assert_eq!(tight.note(), None);
+    let wide: Vec<f64> = (0..100).map(|i| (i % 2) as f64 * 40.0).collect();
+    let wide = ConfidenceInterval::of_mean(&wide);
+    assert!(!wide.adequate);
+    assert!(wide.note().unwrap().contains("within 20%"));
+
+    // A constant ratio has no spread
+    let ratio = ConfidenceInterval::of_ratio(&[2.0, 4.0, 6.0], &[10.0, 20.0, 30.0]);
+    assert_eq!((ratio.low, ratio.high, ratio.margin), (0.2, 0.2, 0.0));
+}
+
+#[tokio::test]
+async fn chain_stats_carry_confidence_intervals() {
+    let stats = calculate_chain_stats(&provider(), 40).await.unwrap();
+    let block_time = &stats.block_time.confidence;
+    assert_eq!(block_time.samples, 39);
+    assert!(block_time.low < stats.block_time.average);
+    assert!(block_time.high > stats.block_time.average);
+    assert!((block_time.high + block_time.low - 2.0 * stats.block_time.average).abs() < 1e-9);
+    let tps = &stats.tps_confidence;
+    assert_eq!(tps.samples, 39);
+    assert!(tps.low < stats.tps && stats.tps < tps.high);
+}
@@ -0,0 +1,28 @@
+// bench.rs
+mod common;
+
+use std::time::Duration;
+
+use snap_coin_utils::{bench::bench_node, load::Sample};
+
+use common::{chain_response, fixture, mock_node, provider};
+
+#[tokio::test]
+async fn measures_every_concurrency_level() {
+    let sample = Sample::take(&provider(), 10).await.unwrap();
+    assert_eq!(sample.heights.len(), 10);
+    assert!(!sample.transactions.is_empty());
+    let fixture = fixture();
+    let address = mock_node(move |request| chain_response(&fixture, &request)).await;
+
+    let levels = bench_node(address, sample, &[1, 3], Duration::from_millis(200))
+        .await
+        .unwrap();
+    assert_eq!(levels.len(), 2);
+    for level in &levels {
+        assert!(level.blocks_per_second > 0.0);
+        assert!(level.transactions_per_second > 0.0);
+        assert_eq!(level.errors, 0);
+    }
+    assert_eq!(levels[1].concurrency, 3);
+}
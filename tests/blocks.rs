@@ -0,0 +1,36 @@
+// blocks.rs
+mod common;
+
+use std::num::NonZeroUsize;
+
+use snap_coin_utils::{blocks::BlockRangeProvider, limits::set_max_in_flight, node::NodeClient};
+
+use common::{chain_response, fixture, mock_node};
+
+#[tokio::test]
+async fn client_fetches_block_ranges_pipelined() {
+    let fixture = fixture();
+    let expected: Vec<_> = fixture.blocks[5..35]
+        .iter()
+        .map(|block| block.meta.hash)
+        .collect();
+    let address = mock_node(move |request| chain_response(&fixture, &request)).await;
+    let client = NodeClient::connect(address).await.unwrap();
+
+    let blocks = client.get_blocks_range(565..595).await.unwrap();
+    let hashes: Vec<_> = blocks.iter().map(|block| block.meta.hash).collect();
+    assert_eq!(hashes, expected);
+
+    assert!(client.get_blocks_range(590..610).await.is_err());
+}
+
+#[tokio::test]
+async fn pipelining_respects_max_in_flight() {
+    let fixture = fixture();
+    let address = mock_node(move |request| chain_response(&fixture, &request)).await;
+    let client = NodeClient::connect(address).await.unwrap();
+
+    set_max_in_flight(NonZeroUsize::new(3).unwrap());
+    let blocks = client.get_blocks_range(560..600).await.unwrap();
+    assert_eq!(blocks.len(), 40);
+}
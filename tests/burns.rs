@@ -0,0 +1,34 @@
+// burns.rs
+mod common;
+
+use snap_coin_utils::burns::{calculate_burns, chart_series};
+
+use common::{fixture, provider};
+
+#[tokio::test]
+async fn burns_are_summed_per_block_and_over_all_time() {
+    let fixture = fixture();
+    // The fresh receivers of the transfers in 570 and 597 never spend, like burn addresses
+    let burn = |height: usize| fixture.blocks[height - 560].transactions[0].outputs[0];
+    let (early, late) = (burn(570), burn(597));
+    let burn_addresses = [early.receiver, late.receiver];
+
+    let stats = calculate_burns(&provider(), &burn_addresses, 10)
+        .await
+        .unwrap();
+    assert_eq!(stats.burn_addresses, burn_addresses);
+    assert_eq!(stats.total_burned, early.amount + late.amount);
+    assert_eq!(stats.window_burned, late.amount);
+    let burned: Vec<_> = stats
+        .series
+        .iter()
+        .map(|point| (point.height, point.burned))
+        .collect();
+    let mut expected: Vec<_> = (590..600).map(|height| (height, 0)).collect();
+    expected[7].1 = late.amount;
+    assert_eq!(burned, expected);
+
+    let series = chart_series(&stats);
+    assert_eq!(series.name, "Burned (SNAP)");
+    assert_eq!(series.points[7], (597, 93.1));
+}
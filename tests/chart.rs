@@ -0,0 +1,56 @@
+// chart.rs
+use snap_coin_utils::chart::{Series, sparkline, write_plot_data};
+
+#[test]
+fn empty_series_draw_nothing() {
+    assert_eq!(sparkline(&[], 10), "");
+    assert_eq!(sparkline(&[], 0), "");
+}
+
+#[test]
+fn short_series_get_one_glyph_per_value() {
+    assert_eq!(sparkline(&[1.0, 2.0, 3.0], 10), "▁▅█");
+    assert_eq!(sparkline(&[3.0, 0.0, 7.0, 1.0], 4), "▄▁█▂");
+}
+
+#[test]
+fn constant_series_stay_on_the_baseline() {
+    assert_eq!(sparkline(&[5.0; 4], 10), "▁▁▁▁");
+    assert_eq!(sparkline(&[5.0; 4], 2), "▁▁");
+    assert_eq!(sparkline(&[-2.5], 1), "▁");
+}
+
+#[test]
+fn neighbours_are_averaged_to_fit_the_width() {
+    // Buckets of two: 1, 5 and the lone 14
+    assert_eq!(sparkline(&[0.0, 2.0, 4.0, 6.0, 14.0], 3), "▁▃█");
+    // A zero width still draws the whole series as one averaged glyph
+    assert_eq!(sparkline(&[0.0, 2.0, 4.0], 0), "▁");
+    assert_eq!(
+        sparkline(&[0.0, 8.0, 0.0, 8.0, 0.0, 8.0, 7.0, 7.0], 4),
+        "▁▁▁█"
+    );
+}
+
+#[test]
+fn plot_data_has_a_row_per_height_and_gaps_for_missing_values() {
+    let dir = std::env::temp_dir().join(format!("snap-coin-utils-chart-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("plot.csv");
+    let series = [
+        Series::from_heights("Block Time (s)", 600, &[20.5, 31.0, 18.25]),
+        Series {
+            name: "Transactions".to_string(),
+            points: vec![(599, 4.0), (601, 0.0)],
+        },
+    ];
+    write_plot_data(&path, &series).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "block,Block Time (s),Transactions\n599,,4\n600,20.5,\n601,31,0\n602,18.25,\n"
+    );
+
+    write_plot_data(&path, &[]).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "block\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
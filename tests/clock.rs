@@ -0,0 +1,102 @@
+// clock.rs
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use snap_coin_utils::clock::{NodeTip, ntp_offset, time_check};
+use tokio::net::UdpSocket;
+
+fn tip(name: &str, height: usize, tip_timestamp: u64) -> NodeTip {
+    NodeTip {
+        name: name.to_string(),
+        address: "127.0.0.1:3003".parse().unwrap(),
+        height,
+        tip_timestamp,
+    }
+}
+
+#[test]
+fn tips_are_compared_with_each_other_and_the_clock() {
+    let tips = vec![
+        tip("c", 101, 10_020),
+        tip("a", 100, 10_000),
+        tip("b", 101, 10_020),
+    ];
+    // The local clock is 5s behind
+    let check = time_check(tips, 10_025.0, Some(5.0), 60).unwrap();
+    assert_eq!(check.now, 10_030.0);
+    let nodes: Vec<(&str, f64, i64, bool)> = check
+        .nodes
+        .iter()
+        .map(|n| {
+            (
+                n.tip.name.as_str(),
+                n.tip_age,
+                n.offset_from_median,
+                n.ahead,
+            )
+        })
+        .collect();
+    assert_eq!(
+        nodes,
+        [
+            ("a", 30.0, -20, false),
+            ("b", 10.0, 0, false),
+            ("c", 10.0, 0, false),
+        ]
+    );
+    assert_eq!((check.median_tip_timestamp, check.spread), (10_020, 20));
+    assert!(!check.skewed_majority);
+
+    // Two of three nodes following a chain timestamped 2 minutes ahead
+    let tips = vec![
+        tip("a", 100, 10_000),
+        tip("b", 105, 10_150),
+        tip("c", 105, 10_150),
+    ];
+    let check = time_check(tips, 10_030.0, None, 60).unwrap();
+    assert_eq!(check.ahead, 2);
+    assert!(check.skewed_majority);
+    assert_eq!(check.nodes[0].offset_from_median, -150);
+
+    assert!(time_check(Vec::new(), 0.0, None, 60).is_err());
+}
+
+/// NTP timestamp of unix time `secs`
+fn ntp_timestamp(secs: f64) -> [u8; 8] {
+    let ntp = secs + 2_208_988_800.0;
+    let mut bytes = [0; 8];
+    bytes[..4].copy_from_slice(&(ntp.trunc() as u32).to_be_bytes());
+    bytes[4..].copy_from_slice(&((ntp.fract() * 2f64.powi(32)) as u32).to_be_bytes());
+    bytes
+}
+
+#[tokio::test]
+async fn the_local_clock_offset_comes_from_sntp() {
+    let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address = server.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        let mut request = [0; 48];
+        let (_, client) = server.recv_from(&mut request).await.unwrap();
+        // A server whose clock is 100s ahead, answering in server mode
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64()
+            + 100.0;
+        let mut response = [0; 48];
+        response[0] = 0b00_100_100;
+        response[32..40].copy_from_slice(&ntp_timestamp(now));
+        response[40..48].copy_from_slice(&ntp_timestamp(now));
+        server.send_to(&response, client).await.unwrap();
+    });
+    let offset = ntp_offset(&address, Duration::from_secs(5)).await.unwrap();
+    assert!((offset - 100.0).abs() < 0.5, "{offset}");
+
+    // Nobody answering times out
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address = silent.local_addr().unwrap().to_string();
+    assert!(
+        ntp_offset(&address, Duration::from_millis(100))
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,134 @@
+// common/mod.rs
+//! Chain data, a mock node and temporary directories shared by the integration tests. Each test file is its own crate and
+//! uses only part of this module.
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use snap_coin::{
+    api::requests::{Request, Response},
+    core::block::Block,
+    full_node::node_state::ChainEvent,
+};
+use snap_coin_utils::fixture::{Fixture, FixtureProvider};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream},
+};
+
+/// Blocks 560..600 of a synthetic chain, not a recording of the network. The chain is mined from
+/// genesis by `examples/generate_fixture.rs` under snap-coin's consensus rules.
+pub const FIXTURE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/chain.bin");
+
+pub fn fixture() -> Fixture {
+    Fixture::load(Path::new(FIXTURE)).unwrap()
+}
+
+pub fn provider() -> FixtureProvider {
+    FixtureProvider::new(fixture())
+}
+
+/// An empty directory of the test `name`, cleared of what an earlier run left
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("snap-coin-utils-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Accept connections on a free local port, serving each in its own task
+async fn listen<F>(serve: impl Fn(TcpStream) -> F + Send + 'static) -> SocketAddr
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(serve(stream));
+        }
+    });
+    address
+}
+
+/// A node answering each request with what `handler` returns for it, in order like a node does.
+/// `None` hangs up.
+pub async fn mock_node(
+    handler: impl Fn(Request) -> Option<Response> + Send + Sync + 'static,
+) -> SocketAddr {
+    let handler = Arc::new(handler);
+    listen(move |mut stream| {
+        let handler = handler.clone();
+        async move {
+            while let Ok(request) = Request::decode_from_stream(&mut stream).await {
+                let Some(response) = handler(request) else {
+                    return;
+                };
+                if stream.write_all(&response.encode().unwrap()).await.is_err() {
+                    return;
+                }
+            }
+        }
+    })
+    .await
+}
+
+/// A node announcing `blocks` to every subscriber of its chain events, then hanging up
+pub async fn announcing_node(blocks: Vec<Block>) -> SocketAddr {
+    let blocks = Arc::new(blocks);
+    listen(move |mut stream| {
+        let blocks = blocks.clone();
+        async move {
+            if let Ok(Request::SubscribeToChainEvents) =
+                Request::decode_from_stream(&mut stream).await
+            {
+                for block in blocks.iter().cloned() {
+                    let event = Response::ChainEvent {
+                        event: ChainEvent::Block { block },
+                    };
+                    stream.write_all(&event.encode().unwrap()).await.unwrap();
+                }
+            }
+        }
+    })
+    .await
+}
+
+/// What a node holding the `fixture` chain answers to a request for its height, blocks or
+/// transactions, `None` for other requests
+pub fn chain_response(fixture: &Fixture, request: &Request) -> Option<Response> {
+    let block = |height: u64| {
+        (height as usize)
+            .checked_sub(fixture.start_height)
+            .and_then(|index| fixture.blocks.get(index))
+    };
+    Some(match *request {
+        Request::Height => Response::Height {
+            height: (fixture.start_height + fixture.blocks.len()) as u64,
+        },
+        Request::BlockHash { height } => Response::BlockHash {
+            hash: block(height).and_then(|block| block.meta.hash),
+        },
+        Request::Block { block_hash } => Response::Block {
+            block: fixture
+                .blocks
+                .iter()
+                .find(|block| block.meta.hash == Some(block_hash))
+                .cloned(),
+        },
+        Request::Transaction { transaction_id } => Response::Transaction {
+            transaction: fixture
+                .blocks
+                .iter()
+                .flat_map(|block| &block.transactions)
+                .find(|tx| tx.transaction_id == Some(transaction_id))
+                .cloned(),
+        },
+        _ => return None,
+    })
+}
@@ -0,0 +1,67 @@
+// conformance.rs
+mod common;
+
+use snap_coin_utils::{
+    conformance::{ConformanceStatus, block_timestamps, conformance},
+    fixture::FixtureProvider,
+};
+
+use common::fixture;
+
+#[test]
+fn periods_are_graded_by_their_deviation_from_the_target() {
+    // The block before height 100, then 4 blocks at 20s, 4 at 23s and 2 at 40s
+    let mut timestamps = vec![1000];
+    for interval in [20, 20, 20, 20, 23, 23, 23, 23, 40, 40] {
+        timestamps.push(timestamps.last().unwrap() + interval);
+    }
+    let report = conformance(100, &timestamps, 4, 10.0, 25.0).unwrap();
+
+    let periods: Vec<(usize, usize, f64, ConformanceStatus)> = report
+        .periods
+        .iter()
+        .map(|p| (p.start_height, p.end_height, p.average_block_time, p.status))
+        .collect();
+    assert_eq!(
+        periods,
+        [
+            (100, 104, 20.0, ConformanceStatus::Pass),
+            (104, 108, 23.0, ConformanceStatus::Warn),
+            (108, 110, 40.0, ConformanceStatus::Fail),
+        ]
+    );
+    assert!((report.periods[1].deviation - 15.0).abs() < 1e-9);
+    assert_eq!(report.periods[2].deviation, 100.0);
+    assert_eq!(
+        (report.overall.start_height, report.overall.end_height),
+        (100, 110)
+    );
+    assert_eq!(report.overall.average_block_time, 25.2);
+    assert_eq!(report.overall.status, ConformanceStatus::Fail);
+
+    // Blocks coming too fast deviate just the same
+    assert_eq!(
+        ConformanceStatus::of(-30.0, 10.0, 25.0),
+        ConformanceStatus::Fail
+    );
+    assert!(conformance(100, &[1000], 4, 10.0, 25.0).is_err());
+}
+
+#[tokio::test]
+async fn the_fixture_keeps_to_the_target() {
+    let fixture = fixture();
+    let provider = FixtureProvider::new(fixture);
+    let timestamps = block_timestamps(&provider, 561..600).await.unwrap();
+    assert_eq!(timestamps.len(), 40);
+
+    let report = conformance(561, &timestamps, 10, 10.0, 25.0).unwrap();
+    assert_eq!(report.periods.len(), 4);
+    assert_eq!(report.periods[3].end_height, 600);
+    assert!(
+        report
+            .periods
+            .iter()
+            .all(|period| period.status == ConformanceStatus::Pass)
+    );
+    assert!(block_timestamps(&provider, 0..10).await.is_err());
+}
@@ -0,0 +1,89 @@
+// connection.rs
+mod common;
+
+use snap_coin::{
+    api::requests::{Request, Response},
+    blockchain_data_provider::BlockchainDataProvider,
+};
+use snap_coin_utils::{
+    connection::{ConnectionEvent, ReconnectingClient},
+    node::NodeClient,
+    pool::NodePool,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::net::TcpListener;
+
+use common::mock_node;
+
+/// A node that answers every other height request and hangs up on the rest, so each connection
+/// of a test asking one height at a time serves a single one
+async fn flaky_node() -> std::net::SocketAddr {
+    let hang_up = AtomicBool::new(false);
+    mock_node(move |request| {
+        let answer = !hang_up.fetch_xor(true, Ordering::SeqCst);
+        (answer && matches!(request, Request::Height)).then_some(Response::Height { height: 42 })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn reconnects_after_the_connection_drops() {
+    let address = flaky_node().await;
+    let client = NodeClient::connect(address).await.unwrap();
+    let connection = ReconnectingClient::new(client);
+    let mut events = connection.subscribe();
+    let height = async |client: &NodeClient| Ok(client.get_height().await?);
+
+    assert_eq!(connection.run(height).await.unwrap(), 42);
+    assert!(connection.run(height).await.is_err());
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        ConnectionEvent::Disconnected { .. }
+    ));
+
+    assert_eq!(connection.run(height).await.unwrap(), 42);
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        ConnectionEvent::Connected { node } if node == address
+    ));
+}
+
+/// An address nothing listens on
+async fn dead_node() -> std::net::SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap()
+}
+
+#[tokio::test]
+async fn pool_skips_unreachable_nodes() {
+    let dead = dead_node().await.to_string();
+    let live = flaky_node().await;
+    let pool = NodePool::resolve([dead.as_str(), &live.to_string(), &live.to_string()])
+        .await
+        .unwrap();
+    assert_eq!(pool.len(), 2);
+    assert_eq!(pool.connect().await.unwrap().node(), live);
+}
+
+#[tokio::test]
+async fn reconnects_to_the_next_node_of_the_pool() {
+    let first = flaky_node().await;
+    let second = flaky_node().await;
+    let connection =
+        ReconnectingClient::with_pool(NodeClient::connect(first).await.unwrap(), &[first, second]);
+    let mut events = connection.subscribe();
+    let height = async |client: &NodeClient| Ok(client.get_height().await?);
+
+    assert_eq!(connection.run(height).await.unwrap(), 42);
+    assert!(connection.run(height).await.is_err());
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        ConnectionEvent::Disconnected { node, .. } if node == first
+    ));
+
+    assert_eq!(connection.run(height).await.unwrap(), 42);
+    assert!(matches!(
+        events.recv().await.unwrap(),
+        ConnectionEvent::Connected { node } if node == second
+    ));
+}
@@ -0,0 +1,87 @@
+// convert.rs
+use snap_coin_utils::convert::{
+    Conversion, DifficultySpec, convert, convert_difficulty, parse_difficulty, parse_hashrate,
+    parse_hr,
+};
+
+fn amount(value: &str) -> (String, u64) {
+    match convert(value).unwrap() {
+        Conversion::Amount { snap, base } => (snap, base),
+        other => panic!("{value} converted to {other:?}"),
+    }
+}
+
+fn difficulty(value: &str) -> (String, f64) {
+    match convert(value).unwrap() {
+        Conversion::Difficulty { target, difficulty } => (target, difficulty),
+        other => panic!("{value} converted to {other:?}"),
+    }
+}
+
+#[test]
+fn amounts_convert_both_ways() {
+    let expected = ("1.50000000".to_string(), 150_000_000);
+    assert_eq!(amount("1.5snap"), expected);
+    assert_eq!(amount("1.5 SNAP"), expected);
+    assert_eq!(amount("150000000base"), expected);
+    assert_eq!(amount("150000000nano"), expected);
+    assert_eq!(amount("1base").0, "0.00000001");
+    for invalid in ["1.5", "1.000000001snap", "1.5base", "-1base", "snap"] {
+        assert!(convert(invalid).is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn targets_and_difficulties_convert_both_ways() {
+    let easiest = "f".repeat(64);
+    assert_eq!(
+        difficulty(&format!("{easiest}target")),
+        (easiest.clone(), 1.0)
+    );
+    assert_eq!(difficulty("1diff").0, easiest);
+
+    let (target, value) = difficulty(&format!("0x00{}target", "f".repeat(62)));
+    assert_eq!(target, format!("00{}", "f".repeat(62)));
+    assert!((value - 256.0).abs() < 1e-9);
+    let (target, value) = difficulty("256difficulty");
+    assert_eq!(target, format!("00{}", "f".repeat(62)));
+    assert!((value - 256.0).abs() < 1e-9);
+
+    // Short targets are padded, as leading zeros are
+    assert_eq!(difficulty("ff target").0, format!("{}ff", "0".repeat(62)));
+    for invalid in ["0target", "gtarget", "0.5diff", "infdiff"] {
+        assert!(convert(invalid).is_err(), "{invalid}");
+    }
+    assert!(convert(&format!("1{easiest}target")).is_err());
+}
+
+#[test]
+fn difficulties_convert_to_hashrates() {
+    let at = |spec| convert_difficulty(spec, 20.0).unwrap();
+    let target = format!("00{}", "f".repeat(62));
+    let from_target = at(DifficultySpec::Target(&target));
+    assert!((from_target.difficulty - 256.0).abs() < 1e-9);
+    assert!((from_target.hashrate - 12.8).abs() < 1e-9);
+    assert_eq!(at(DifficultySpec::Difficulty(256.0)), from_target);
+    assert_eq!(
+        at(DifficultySpec::Hashrate(12.8)).target,
+        from_target.target
+    );
+
+    // A hashrate over a longer time between blocks is a higher difficulty
+    let slower = convert_difficulty(DifficultySpec::Hashrate(12.8), 40.0).unwrap();
+    assert!((slower.difficulty - 512.0).abs() < 1e-9);
+    assert!(convert_difficulty(DifficultySpec::Difficulty(2.0), 0.0).is_err());
+
+    assert_eq!(parse_hr("2.5K"), Some(2500.0));
+    assert_eq!(parse_hr("1.5 MH/s"), Some(1_500_000.0));
+    assert_eq!(parse_hr("3g"), Some(3e9));
+    assert_eq!(parse_hr("12"), Some(12.0));
+    for invalid in ["", "K", "-1", "0", "1.5X", "é"] {
+        assert_eq!(parse_hr(invalid), None, "{invalid}");
+    }
+    assert!(parse_difficulty("0.5").is_err());
+    assert_eq!(parse_hashrate("5MH").unwrap(), 5e6);
+    assert_eq!(parse_hashrate("5 mh/s").unwrap(), 5e6);
+    assert!(parse_hashrate("H").is_err());
+}
@@ -0,0 +1,41 @@
+// counts.rs
+use snap_coin_utils::{counts::AddressCounter, limits::SpillConfig};
+
+fn address(i: u8) -> [u8; 32] {
+    [i; 32]
+}
+
+/// Address `i` appears `i` times, interleaved so every run holds a part of most counts
+fn count_all(counter: &mut AddressCounter) {
+    for round in 1..=20u8 {
+        for i in round..=20 {
+            counter.add(address(i)).unwrap();
+        }
+    }
+}
+
+#[test]
+fn spilled_counts_match_in_memory_counts() {
+    let dir = std::env::temp_dir().join(format!("snap-coin-utils-counts-{}", std::process::id()));
+    let mut spilled = AddressCounter::with_spill(Some(SpillConfig {
+        dir: dir.clone(),
+        threshold: 3,
+    }));
+    let mut in_memory = AddressCounter::with_spill(None);
+    count_all(&mut spilled);
+    count_all(&mut in_memory);
+    assert!(std::fs::read_dir(&dir).unwrap().count() > 1);
+
+    let top = spilled.top(5).unwrap();
+    assert_eq!(top, in_memory.top(5).unwrap());
+    assert_eq!(
+        top,
+        (16..=20)
+            .rev()
+            .map(|i| (address(i), i as usize))
+            .collect::<Vec<_>>()
+    );
+    // Runs are removed once the counter is gone
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+    std::fs::remove_dir(&dir).unwrap();
+}
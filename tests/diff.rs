@@ -0,0 +1,57 @@
+// diff.rs
+mod common;
+
+use snap_coin_utils::{
+    diff::diff_blocks,
+    mempool::{MempoolSnapshot, diff_mempools},
+};
+
+use common::fixture;
+
+#[test]
+fn block_is_identical_to_itself() {
+    let fixture = fixture();
+    let diff = diff_blocks(&fixture.blocks[0], &fixture.blocks[0]).unwrap();
+    assert!(diff.is_identical());
+    assert_eq!(
+        diff.shared_transactions,
+        fixture.blocks[0].transactions.len()
+    );
+}
+
+#[test]
+fn consecutive_blocks_differ() {
+    let fixture = fixture();
+    let (a, b) = (&fixture.blocks[0], &fixture.blocks[1]);
+    let diff = diff_blocks(a, b).unwrap();
+
+    assert!(!diff.is_identical());
+    let field = |name| diff.fields.iter().find(|f| f.field == name).unwrap();
+    assert!(field("Hash").differs());
+    assert!(field("Timestamp").differs());
+    assert!(field("Block Difficulty").differs());
+    assert_eq!(diff.only_in_a.len(), a.transactions.len());
+    assert_eq!(diff.only_in_b.len(), b.transactions.len());
+}
+
+#[test]
+fn mempool_snapshots_report_added_and_removed() {
+    let fixture = fixture();
+    let txs: Vec<_> = fixture.blocks[..3]
+        .iter()
+        .flat_map(|b| b.transactions.clone())
+        .collect();
+    let snapshot = |height, transactions: &[_]| MempoolSnapshot {
+        timestamp: 0,
+        height,
+        transactions: transactions.to_vec(),
+    };
+    let id = |i: usize| txs[i].transaction_id.unwrap().dump_base36();
+
+    let diff = diff_mempools(&snapshot(10, &txs[..3]), &snapshot(11, &txs[1..]));
+    assert_eq!(diff.removed, vec![id(0)]);
+    assert_eq!(diff.added, (3..txs.len()).map(id).collect::<Vec<_>>());
+    assert_eq!(diff.unchanged, 2);
+    assert!(diff.confirmed.is_empty());
+    assert_eq!((diff.from_height, diff.to_height), (10, 11));
+}
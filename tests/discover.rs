@@ -0,0 +1,48 @@
+// discover.rs
+mod common;
+
+use snap_coin::api::requests::{Request, Response};
+use snap_coin_utils::discover::{discover, parse_hosts};
+use std::{net::IpAddr, time::Duration};
+use tokio::net::TcpListener;
+
+use common::mock_node;
+
+/// A node answering height requests with `height`, returning its port
+async fn node(height: u64) -> u16 {
+    mock_node(move |request| {
+        matches!(request, Request::Height).then_some(Response::Height { height })
+    })
+    .await
+    .port()
+}
+
+#[test]
+fn networks_expand_to_their_hosts() {
+    let hosts = parse_hosts("192.168.1.0/30").unwrap();
+    let hosts: Vec<_> = hosts.iter().map(IpAddr::to_string).collect();
+    assert_eq!(hosts, ["192.168.1.1", "192.168.1.2"]);
+    assert_eq!(parse_hosts("10.0.0.0/16").unwrap().len(), 65534);
+    assert_eq!(parse_hosts("::1").unwrap().len(), 1);
+    assert!(parse_hosts("10.0.0.0/8").is_err());
+}
+
+#[tokio::test]
+async fn finds_nodes_listening_on_the_probed_ports() {
+    let low = node(10).await;
+    let high = node(20).await;
+    let closed = TcpListener::bind("127.0.0.1:0")
+        .await
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let hosts = parse_hosts("127.0.0.1").unwrap();
+    let nodes = discover(&hosts, &[low, closed, high], Duration::from_secs(1)).await;
+    let found: Vec<_> = nodes
+        .iter()
+        .map(|node| (node.address.port(), node.height))
+        .collect();
+    assert_eq!(found, [(high, 20), (low, 10)]);
+}
@@ -0,0 +1,76 @@
+// duration.rs
+use std::{process::Command, time::Duration};
+
+use snap_coin_utils::{parse_duration, parse_nonzero_duration};
+
+#[test]
+fn durations_take_a_unit_or_default_to_seconds() {
+    assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+    assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+    assert_eq!(parse_duration("15m").unwrap(), Duration::from_secs(900));
+    assert_eq!(
+        parse_duration(" 2d ").unwrap(),
+        Duration::from_secs(172_800)
+    );
+    assert!(parse_duration("5w").is_err());
+    assert!(parse_duration("h").is_err());
+}
+
+#[test]
+fn durations_too_long_to_count_are_rejected() {
+    let error = parse_duration(&format!("{}d", u64::MAX / 60)).unwrap_err();
+    assert!(error.to_string().contains("too long"), "{error}");
+    assert_eq!(
+        parse_duration(&format!("{}s", u64::MAX)).unwrap(),
+        Duration::from_secs(u64::MAX)
+    );
+}
+
+#[test]
+fn periods_have_to_be_longer_than_zero() {
+    assert_eq!(
+        parse_nonzero_duration("1ms").unwrap(),
+        Duration::from_millis(1)
+    );
+    for zero in ["0", "0s", "0ms", "0h"] {
+        assert!(parse_nonzero_duration(zero).is_err(), "{zero}");
+    }
+}
+
+/// Run the binary with `args` and check clap refuses a zero period before anything starts
+fn assert_zero_refused(args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_snap-coin-utils"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("has to be longer than 0"), "{stderr}");
+}
+
+#[test]
+fn snapshots_every_zero_seconds_are_refused() {
+    assert_zero_refused(&["snapshot", "--every", "0s", "--out-dir", "."]);
+}
+
+#[test]
+fn monitoring_every_zero_seconds_is_refused() {
+    assert_zero_refused(&["monitor", "--state", "state.json", "--interval", "0"]);
+}
+
+#[test]
+fn tailing_every_zero_seconds_is_refused() {
+    assert_zero_refused(&["tail", "txs", "--interval", "0ms"]);
+}
+
+#[test]
+fn screening_every_zero_seconds_is_refused() {
+    assert_zero_refused(&[
+        "screen",
+        "--list",
+        "list.txt",
+        "--watch",
+        "--interval",
+        "0s",
+    ]);
+}
@@ -0,0 +1,33 @@
+// eta.rs
+use snap_coin_utils::eta::{format_secs, mine_eta};
+
+#[test]
+fn waits_follow_the_exponential_distribution() {
+    let mut target = [255; 32];
+    target[0] = 0;
+    let eta = mine_eta(&target, 10.0);
+
+    // The reward transaction eases the 256 hash difficulty slightly
+    assert!(eta.block_difficulty < 256.0 && eta.block_difficulty > 250.0);
+    assert!((eta.expected_secs - eta.block_difficulty / 10.0).abs() < 1e-9);
+    assert!((eta.blocks_per_day * eta.expected_secs - 86_400.0).abs() < 1e-6);
+
+    let secs: Vec<f64> = eta.quantiles.iter().map(|quantile| quantile.secs).collect();
+    assert!((secs[0] - eta.expected_secs * 2f64.ln()).abs() < 1e-9);
+    assert!((secs[1] - eta.expected_secs * 10f64.ln()).abs() < 1e-9);
+    assert!(secs.windows(2).all(|pair| pair[0] < pair[1]));
+
+    // Twice the hashrate halves every wait
+    let faster = mine_eta(&target, 20.0);
+    assert!((faster.expected_secs * 2.0 - eta.expected_secs).abs() < 1e-9);
+    assert!(faster.network_share > eta.network_share);
+}
+
+#[test]
+fn durations_show_their_two_biggest_units() {
+    assert_eq!(format_secs(0.4), "0s");
+    assert_eq!(format_secs(59.0), "59s");
+    assert_eq!(format_secs(725.0), "12m 5s");
+    assert_eq!(format_secs(7200.0), "2h 0m");
+    assert_eq!(format_secs(273_600.0), "3d 4h");
+}
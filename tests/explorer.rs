@@ -0,0 +1,86 @@
+// explorer.rs
+mod common;
+
+use std::process::Command;
+
+use snap_coin::crypto::keys::Public;
+use snap_coin_utils::{
+    explorer::{ExplorerConfig, address_url, block_url, fill, tx_url},
+    fixture::FixtureProvider,
+};
+
+use common::{FIXTURE, fixture};
+
+fn config() -> ExplorerConfig {
+    ExplorerConfig {
+        block: Some("https://explorer.example.com/block/{height}?hash={hash}".to_string()),
+        tx: Some("https://explorer.example.com/tx/{id}".to_string()),
+        address: None,
+    }
+}
+
+#[test]
+fn templates_fill_every_placeholder() {
+    assert_eq!(
+        fill("/{a}/{b}/{a}", &[("a", "1"), ("b", "2")]),
+        "/1/2/1".to_string()
+    );
+    assert_eq!(fill("/{c}", &[("a", "1")]), "/{c}");
+}
+
+#[tokio::test]
+async fn block_pages_are_found_by_height_or_hash() {
+    let fixture = fixture();
+    let hash = fixture.blocks[16].meta.hash.unwrap().dump_base36();
+    let provider = FixtureProvider::new(fixture);
+    let expected = format!("https://explorer.example.com/block/576?hash={}", hash);
+    assert_eq!(
+        block_url(&config(), &provider, "576").await.unwrap(),
+        expected
+    );
+    assert_eq!(
+        block_url(&config(), &provider, &hash).await.unwrap(),
+        expected
+    );
+    assert!(block_url(&config(), &provider, "9999").await.is_err());
+}
+
+#[test]
+fn transaction_and_address_pages_check_their_identifiers() {
+    let id = Public::new_from_buf(&[7; 32]).dump_base36();
+    assert_eq!(
+        tx_url(&config(), &id).unwrap(),
+        format!("https://explorer.example.com/tx/{}", id)
+    );
+    assert!(tx_url(&config(), "not base36!").is_err());
+    // No address page configured
+    let e = address_url(&config(), &id).unwrap_err();
+    assert!(
+        e.to_string()
+            .contains("`address` in the [explorer] section")
+    );
+}
+
+#[test]
+fn url_prints_the_permalink_instead_of_the_block() {
+    let dir = std::env::temp_dir().join(format!("snap-coin-utils-explorer-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config_path = dir.join("config.toml");
+    std::fs::write(
+        &config_path,
+        "[explorer]\nblock = \"https://explorer.example.com/block/{height}\"\n",
+    )
+    .unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_snap-coin-utils"))
+        .args(["--fixture", FIXTURE, "--config"])
+        .arg(&config_path)
+        .args(["block", "576", "--url"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "https://explorer.example.com/block/576\n"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,125 @@
+// export.rs
+mod common;
+
+use snap_coin::crypto::Hash;
+use snap_coin_utils::{
+    export::{export_difficulty, export_utxos},
+    fixture::FixtureProvider,
+    normalize_difficulty,
+};
+use std::process::Command;
+
+use common::{FIXTURE, fixture};
+
+#[tokio::test]
+async fn difficulties_are_written_one_row_per_block() {
+    let fixture = fixture();
+    let blocks = fixture.blocks.clone();
+    let provider = FixtureProvider::new(fixture);
+
+    let mut csv = Vec::new();
+    let rows = export_difficulty(&provider, 560..600, &mut csv)
+        .await
+        .unwrap();
+    assert_eq!(rows, 40);
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 41);
+    assert_eq!(
+        lines[0],
+        "height,timestamp,block_difficulty,transaction_difficulty"
+    );
+    let last: Vec<&str> = lines[40].split(',').collect();
+    let block = &blocks[39];
+    assert_eq!(last[0], "599");
+    assert_eq!(last[1], block.timestamp.to_string());
+    assert_eq!(
+        last[2].parse::<f64>().unwrap(),
+        normalize_difficulty(&block.meta.block_pow_difficulty)
+    );
+    assert_eq!(
+        last[3].parse::<f64>().unwrap(),
+        normalize_difficulty(&block.meta.tx_pow_difficulty)
+    );
+
+    // Heights the chain doesn't have fail the export
+    let mut csv = Vec::new();
+    assert!(
+        export_difficulty(&provider, 590..610, &mut csv)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn utxos_are_written_sorted_by_outpoint() {
+    let fixture = fixture();
+    let transactions: Vec<_> = fixture
+        .blocks
+        .iter()
+        .flat_map(|block| &block.transactions)
+        .collect();
+    let outputs: usize = transactions.iter().map(|tx| tx.outputs.len()).sum();
+    // Rewards mined in the range and spent later in it are not exported
+    let spent = transactions
+        .iter()
+        .flat_map(|tx| &tx.inputs)
+        .filter(|input| {
+            transactions
+                .iter()
+                .any(|tx| tx.transaction_id == Some(input.transaction_id))
+        })
+        .count();
+    let first = fixture.blocks[0].transactions[0].clone();
+    let provider = FixtureProvider::new(fixture);
+
+    let mut csv = Vec::new();
+    let rows = export_utxos(&provider, 560..600, &mut csv).await.unwrap();
+    assert_eq!(rows, outputs - spent);
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), rows + 1);
+    assert_eq!(lines[0], "transaction_id,output_index,owner,amount,height");
+
+    let row = format!(
+        "{},0,{},{},560",
+        first.transaction_id.unwrap().dump_base36(),
+        first.outputs[0].receiver.dump_base36(),
+        first.outputs[0].amount
+    );
+    assert!(lines.contains(&row.as_str()));
+    let outpoints: Vec<([u8; 32], usize)> = lines[1..]
+        .iter()
+        .map(|line| {
+            let fields: Vec<&str> = line.split(',').collect();
+            (
+                Hash::new_from_base36(fields[0]).unwrap().dump_buf(),
+                fields[1].parse().unwrap(),
+            )
+        })
+        .collect();
+    assert!(outpoints.windows(2).all(|pair| pair[0] < pair[1]));
+
+    // The same height exports the same bytes
+    let mut again = Vec::new();
+    export_utxos(&provider, 560..600, &mut again).await.unwrap();
+    assert_eq!(String::from_utf8(again).unwrap(), csv);
+}
+
+#[test]
+fn exports_take_their_file_apart_from_the_global_out() {
+    let dir = std::env::temp_dir().join(format!("snap-coin-utils-export-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (csv, out) = (dir.join("difficulty.csv"), dir.join("out.txt"));
+    let status = Command::new(env!("CARGO_BIN_EXE_snap-coin-utils"))
+        .args(["--fixture", FIXTURE, "--out"])
+        .arg(&out)
+        .args(["export", "difficulty", "--range", "590..600", "--file"])
+        .arg(&csv)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert_eq!(std::fs::read_to_string(&csv).unwrap().lines().count(), 11);
+    assert!(!out.exists() || std::fs::read_to_string(&out).unwrap().is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,44 @@
+// fees.rs
+use snap_coin_utils::fees::{fee_market, percentile};
+
+#[test]
+fn percentiles_take_the_nearest_rank() {
+    let sorted = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0];
+    assert_eq!(percentile(&sorted, 10.0), Some(1.0));
+    assert_eq!(percentile(&sorted, 50.0), Some(5.0));
+    assert_eq!(percentile(&sorted, 90.0), Some(9.0));
+    assert_eq!(percentile(&sorted, 0.0), Some(1.0));
+    assert_eq!(percentile(&[], 50.0), None);
+}
+
+#[test]
+fn blocks_and_the_window_get_their_own_spread() {
+    let blocks = vec![
+        vec![Some(4.0), Some(1.0), None],
+        Vec::new(),
+        vec![Some(2.0), Some(8.0), Some(3.0)],
+    ];
+    let market = fee_market(100, &blocks);
+    assert_eq!(market.end_height, 103);
+
+    let first = &market.blocks[0].rates;
+    assert_eq!((first.transactions, first.unresolved), (3, 1));
+    assert_eq!(
+        (first.min, first.median, first.max),
+        (Some(1.0), Some(1.0), Some(4.0))
+    );
+
+    let empty = &market.blocks[1];
+    assert_eq!(empty.height, 101);
+    assert_eq!((empty.rates.min, empty.rates.median), (None, None));
+    assert!(empty.rates.percentiles.is_empty());
+
+    let window = &market.window;
+    assert_eq!((window.transactions, window.unresolved), (6, 1));
+    assert_eq!(
+        (window.min, window.median, window.max),
+        (Some(1.0), Some(3.0), Some(8.0))
+    );
+    let rates: Vec<f64> = window.percentiles.iter().map(|p| p.fee_rate).collect();
+    assert_eq!(rates, [1.0, 2.0, 3.0, 4.0, 8.0]);
+}
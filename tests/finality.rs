@@ -0,0 +1,58 @@
+// finality.rs
+mod common;
+
+use snap_coin_utils::{
+    finality::{confirmations_needed, finality, parse_fraction, reversal_probability},
+    fixture::FixtureProvider,
+    query::transaction_height,
+};
+
+use common::{fixture, provider};
+
+#[test]
+fn reversal_probability_matches_the_whitepaper() {
+    // Section 11 of the Bitcoin whitepaper, given to 7 decimals
+    let close = |a: f64, b: f64| (a - b).abs() < 5e-8;
+    assert!(close(reversal_probability(0.1, 1), 0.2045873));
+    assert!(close(reversal_probability(0.1, 5), 0.0009137));
+    assert!(close(reversal_probability(0.1, 10), 0.0000012));
+    assert!(close(reversal_probability(0.3, 10), 0.0416605));
+    assert_eq!(reversal_probability(0.1, 0), 1.0);
+    assert_eq!(reversal_probability(0.5, 100), 1.0);
+}
+
+#[test]
+fn confirmations_for_an_assurance_level() {
+    // The whitepaper's table of z for P < 0.1%
+    assert_eq!(confirmations_needed(0.10, 0.999), Some(5));
+    assert_eq!(confirmations_needed(0.25, 0.999), Some(15));
+    assert_eq!(confirmations_needed(0.45, 0.999), Some(340));
+    assert_eq!(confirmations_needed(0.5, 0.999), None);
+}
+
+#[test]
+fn fractions_and_percentages() {
+    assert_eq!(parse_fraction("0.25").unwrap(), 0.25);
+    assert_eq!(parse_fraction("10%").unwrap(), 0.1);
+    assert!(parse_fraction("1").is_err());
+    assert!(parse_fraction("ten").is_err());
+}
+
+#[tokio::test]
+async fn finality_of_a_fixture_block() {
+    let provider = provider();
+    let result = finality(&provider, 590, 0.1, 0.999).await.unwrap();
+    assert_eq!(result.confirmations, 10);
+    assert_eq!(result.confirmations_needed, Some(5));
+    assert_eq!(result.remaining, Some(0));
+    assert!(result.reversal_probability < 1e-5);
+    assert!(finality(&provider, 600, 0.1, 0.999).await.is_err());
+}
+
+#[tokio::test]
+async fn transactions_are_found_in_their_block() {
+    let fixture = fixture();
+    let tx = fixture.blocks[15].transactions.last().unwrap().clone();
+    let provider = FixtureProvider::new(fixture);
+    assert_eq!(transaction_height(&provider, &tx).await.unwrap(), Some(575));
+}
@@ -0,0 +1,155 @@
+// grafana.rs
+mod common;
+
+use serde_json::{Value, json};
+use snap_coin_utils::{
+    grafana::{Metric, SeriesCache, datapoints, handle, parse_rfc3339, serve},
+    normalize_difficulty,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use common::{fixture, provider};
+
+/// A query over all of time in one second buckets
+fn query(target: &str) -> Vec<u8> {
+    serde_json::to_vec(&json!({
+        "range": { "from": "1970-01-01T00:00:00.000Z", "to": "2100-01-01T00:00:00.000Z" },
+        "intervalMs": 1000,
+        "maxDataPoints": 10_000_000_000u64,
+        "targets": [{ "target": target, "refId": "A", "type": "timeserie" }],
+    }))
+    .unwrap()
+}
+
+#[test]
+fn grafana_times_parse_as_utc() {
+    assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z").unwrap(), 0);
+    assert_eq!(
+        parse_rfc3339("2025-10-09T14:11:17.866Z").unwrap(),
+        1_760_019_077
+    );
+    assert_eq!(parse_rfc3339("2000-02-29T00:00:00Z").unwrap(), 951_782_400);
+    assert!(parse_rfc3339("2025-10-09 14:11:17").is_err());
+    assert!(parse_rfc3339("2025-13-09T14:11:17Z").is_err());
+}
+
+#[tokio::test]
+async fn cache_keeps_the_last_blocks_with_their_intervals() {
+    let fixture = fixture();
+    let provider = provider();
+    let mut cache = SeriesCache::new(10);
+    cache.refresh(&provider).await.unwrap();
+    let points = cache.points();
+    assert_eq!(points.len(), 10);
+    assert_eq!(points.front().unwrap().height, 590);
+    assert_eq!(points.front().unwrap().interval, None);
+    assert_eq!(
+        points.back().unwrap().interval,
+        Some(fixture.blocks[39].timestamp - fixture.blocks[38].timestamp)
+    );
+    // Nothing new to fetch on the next refresh
+    cache.refresh(&provider).await.unwrap();
+    assert_eq!(cache.points().len(), 10);
+}
+
+#[tokio::test]
+async fn buckets_aggregate_every_metric() {
+    let fixture = fixture();
+    let provider = provider();
+    let mut cache = SeriesCache::new(40);
+    cache.refresh(&provider).await.unwrap();
+    let first = fixture.blocks[0].timestamp;
+    let last = fixture.blocks[39].timestamp;
+
+    // A single bucket holding every block
+    let everything = |metric| datapoints(cache.points(), metric, 0, u64::MAX, u64::MAX / 2);
+    assert_eq!(everything(Metric::Height), [[599.0, 0.0]]);
+    let difficulties: f64 = fixture
+        .blocks
+        .iter()
+        .map(|b| normalize_difficulty(&b.meta.block_pow_difficulty))
+        .sum();
+    assert!((everything(Metric::Difficulty)[0][0] - difficulties / 40.0).abs() < 1e-9);
+    let transactions: usize = fixture.blocks.iter().map(|b| b.transactions.len()).sum();
+    assert_eq!(
+        everything(Metric::Tps)[0][0],
+        transactions as f64 / (u64::MAX / 2) as f64
+    );
+    // The first block's interval is unknown, the rest average to the span over 39 blocks
+    assert_eq!(
+        everything(Metric::BlockTime)[0][0],
+        (last - first) as f64 / 39.0
+    );
+
+    // One bucket per block, limited to the time range
+    let heights = datapoints(cache.points(), Metric::Height, first, last, 1);
+    assert_eq!(heights.len(), 40);
+    assert_eq!(heights[0], [560.0, (first * 1000) as f64]);
+    let later = datapoints(cache.points(), Metric::Height, first + 1, last, 1);
+    assert_eq!(later.len(), 39);
+}
+
+#[tokio::test]
+async fn requests_follow_the_simple_json_contract() {
+    let provider = provider();
+    let mut cache = SeriesCache::new(40);
+    assert_eq!(
+        handle(&provider, &mut cache, "GET", "/", b"").await,
+        (200, json!("OK"))
+    );
+    assert_eq!(
+        handle(&provider, &mut cache, "POST", "/search", b"{}").await,
+        (200, json!(["height", "difficulty", "tps", "block_time"]))
+    );
+
+    let (status, body) = handle(&provider, &mut cache, "POST", "/query", &query("height")).await;
+    assert_eq!(status, 200);
+    assert_eq!(body[0]["target"], "height");
+    assert_eq!(body[0]["datapoints"].as_array().unwrap().len(), 40);
+
+    let (status, body) = handle(&provider, &mut cache, "POST", "/query", &query("hashrate")).await;
+    assert_eq!(status, 400);
+    assert!(body["error"].as_str().unwrap().contains("hashrate"));
+    assert_eq!(
+        handle(&provider, &mut cache, "POST", "/annotations", b"{}").await,
+        (200, json!([]))
+    );
+    assert_eq!(
+        handle(&provider, &mut cache, "GET", "/missing", b"")
+            .await
+            .0,
+        404
+    );
+}
+
+#[tokio::test]
+async fn server_answers_over_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let provider = provider();
+    tokio::spawn(async move { serve(&provider, listener, 40).await });
+
+    let body = query("difficulty");
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    let head = format!(
+        "POST /query HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await.unwrap();
+    stream.write_all(&body).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.starts_with("HTTP/1.1 200 OK"));
+    assert!(head.contains("Access-Control-Allow-Origin: *"));
+    let body: Value = serde_json::from_str(body).unwrap();
+    let first = fixture().blocks[0].clone();
+    assert_eq!(
+        body[0]["datapoints"][0][0],
+        normalize_difficulty(&first.meta.block_pow_difficulty)
+    );
+}
@@ -0,0 +1,83 @@
+// growth.rs
+mod common;
+
+use snap_coin_utils::{
+    fixture::FixtureProvider,
+    growth::{BlockSize, block_sizes, format_bytes, growth, parse_bytes},
+};
+
+use common::fixture;
+
+fn block(timestamp: u64, bytes: usize, transactions: usize, transaction_bytes: usize) -> BlockSize {
+    BlockSize {
+        timestamp,
+        bytes,
+        transactions,
+        transaction_bytes,
+    }
+}
+
+#[test]
+fn projections_grow_at_the_observed_block_rate() {
+    // A block a minute, 200 byte blocks of which 100 are a single transaction
+    let blocks: Vec<BlockSize> = (0..4)
+        .map(|i| block(60 * i, 200, 1, 100))
+        .chain([block(240, 400, 3, 300)])
+        .collect();
+    let projected = growth(1000, &blocks, 2, None, 0, &[1, 10]).unwrap();
+
+    let windows: Vec<(usize, usize, f64)> = projected
+        .windows
+        .iter()
+        .map(|window| {
+            (
+                window.start_height,
+                window.end_height,
+                window.bytes_per_block,
+            )
+        })
+        .collect();
+    assert_eq!(
+        windows,
+        [(995, 997, 200.0), (997, 999, 200.0), (999, 1000, 400.0)]
+    );
+    assert_eq!(projected.bytes_per_block, 240.0);
+    assert_eq!(projected.blocks_per_day, 1440.0);
+    // 100 bytes of block fields and 500 transactions of 100 bytes
+    assert_eq!(projected.full_block_bytes, 100.0 + 500.0 * 100.0);
+
+    assert!(projected.size_estimated);
+    assert_eq!(projected.size, 240_000.0);
+    let day = &projected.projections[0];
+    assert_eq!((day.days, day.date), (1, 86_400));
+    assert_eq!(day.current_bytes, 240_000.0 + 1440.0 * 240.0);
+    assert_eq!(day.full_bytes, 240_000.0 + 1440.0 * 50_100.0);
+
+    let given = growth(1000, &blocks, 2, Some(1_000_000), 0, &[1]).unwrap();
+    assert!(!given.size_estimated);
+    assert_eq!(given.projections[0].current_bytes, 1e6 + 1440.0 * 240.0);
+}
+
+#[test]
+fn sizes_parse_and_print_in_binary_units() {
+    assert_eq!(parse_bytes("512").unwrap(), 512);
+    assert_eq!(parse_bytes("20G").unwrap(), 20 << 30);
+    assert_eq!(parse_bytes("1.5TiB").unwrap(), 3 << 39);
+    assert_eq!(parse_bytes("4 kb").unwrap(), 4096);
+    assert!(parse_bytes("4X").is_err());
+    assert!(parse_bytes("G").is_err());
+
+    assert_eq!(format_bytes(512.0), "512 B");
+    assert_eq!(format_bytes(1536.0), "1.50 KiB");
+    assert_eq!(format_bytes((20u64 << 30) as f64), "20.00 GiB");
+}
+
+#[tokio::test]
+async fn fixture_blocks_are_sized_like_their_encoding() {
+    let fixture = fixture();
+    let provider = FixtureProvider::new(fixture);
+    let sizes = block_sizes(&provider, 560..600).await.unwrap();
+    assert_eq!(sizes.len(), 40);
+    assert!(sizes.iter().all(|size| size.transaction_bytes < size.bytes));
+    assert!(growth(600, &sizes, 10, None, 0, &[30]).is_ok());
+}
@@ -0,0 +1,45 @@
+// hashing.rs
+mod common;
+
+use snap_coin::crypto::Hash;
+use snap_coin_utils::{
+    hashing::{HashInput, hash},
+    ids::to_hex,
+};
+
+use common::fixture;
+
+#[test]
+fn raw_bytes_hash_as_the_protocol_does() {
+    let report = hash(HashInput::Raw, b"snap").unwrap();
+    let expected = Hash::new(b"snap");
+    assert_eq!(report.base36, expected.dump_base36());
+    assert_eq!(report.hex, to_hex(&*expected));
+    assert_eq!(
+        (report.hashed_bytes, report.stored, report.matches),
+        (4, None, None)
+    );
+}
+
+#[test]
+fn blocks_and_transactions_are_checked_against_their_hashes() {
+    let fixture = fixture();
+    let mut block = fixture.blocks[0].clone();
+    for data in [
+        serde_json::to_vec(&block).unwrap(),
+        bincode::encode_to_vec(&block, bincode::config::standard()).unwrap(),
+    ] {
+        let report = hash(HashInput::Block, &data).unwrap();
+        assert_eq!(report.matches, Some(true));
+        assert_eq!(report.stored, Some(report.base36));
+    }
+
+    let tx = &block.transactions[0];
+    let report = hash(HashInput::Tx, &serde_json::to_vec(tx).unwrap()).unwrap();
+    assert_eq!(report.matches, Some(true));
+
+    block.nonce += 1;
+    let report = hash(HashInput::Block, &serde_json::to_vec(&block).unwrap()).unwrap();
+    assert_eq!(report.matches, Some(false));
+    assert!(hash(HashInput::Tx, b"{}").is_err());
+}
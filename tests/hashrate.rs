@@ -0,0 +1,54 @@
+// hashrate.rs
+mod common;
+
+use snap_coin_utils::{
+    fixture::FixtureProvider,
+    hashrate::{block_work, hashrate_series, rolling_hashrate},
+};
+
+use common::fixture;
+
+#[test]
+fn windows_divide_work_by_the_time_it_took() {
+    let works = [10.0, 20.0, 30.0, 40.0, 50.0];
+    let timestamps = [100, 110, 130, 130, 170];
+    let hashrates = rolling_hashrate(&works, &timestamps, 2, 1);
+    // The second block only has the first before it
+    assert_eq!(hashrates[0], Some(20.0 / 10.0));
+    assert_eq!(hashrates[1], Some(50.0 / 30.0));
+    assert_eq!(hashrates[2], Some(70.0 / 20.0));
+    assert_eq!(hashrates[3], Some(90.0 / 40.0));
+
+    // No time passed over the window
+    assert_eq!(
+        rolling_hashrate(&works, &timestamps, 1, 3),
+        [None, Some(50.0 / 40.0)]
+    );
+    assert_eq!(rolling_hashrate(&works, &timestamps, 3, 0)[0], None);
+}
+
+#[tokio::test]
+async fn series_covers_the_heights_asked_for() {
+    let fixture = fixture();
+    let blocks = fixture.blocks.clone();
+    let provider = FixtureProvider::new(fixture);
+    let series = hashrate_series(&provider, 570..600, 10).await.unwrap();
+    assert_eq!(series.points.len(), 30);
+    assert_eq!(series.points[0].height, 570);
+    assert_eq!(series.points[29].timestamp, blocks[39].timestamp);
+
+    let work: f64 = blocks[31..40].iter().map(block_work).sum::<f64>() + block_work(&blocks[30]);
+    let secs = blocks[39].timestamp - blocks[29].timestamp;
+    let latest = series.latest().unwrap().hashrate.unwrap();
+    assert!((latest - work / secs as f64).abs() < latest * 1e-9);
+    assert!(
+        series
+            .points
+            .iter()
+            .all(|point| point.difficulty_hashrate > 0.0)
+    );
+
+    let chart = series.series();
+    assert_eq!(chart.len(), 2);
+    assert_eq!(chart[1].points.len(), 30);
+}
@@ -0,0 +1,64 @@
+// ids.rs
+use snap_coin::crypto::{Hash, Signature, keys::Private};
+use snap_coin_utils::ids::{IdKind, decode, encode, parse_hex, split_tag, to_hex};
+
+#[test]
+fn identifiers_round_trip_through_hex() {
+    let private = Private::new_random();
+    let public = private.to_public();
+    let hash = Hash::new_from_buf([7; 32]);
+    let signature = Signature::new_signature(&mut private.clone(), b"data");
+    for (kind, base36, bytes) in [
+        (
+            IdKind::Address,
+            public.dump_base36(),
+            public.dump_buf().to_vec(),
+        ),
+        (
+            IdKind::Secret,
+            private.dump_base36(),
+            private.dump_buf().to_vec(),
+        ),
+        (IdKind::Block, hash.dump_base36(), hash.dump_buf().to_vec()),
+        (IdKind::Tx, hash.dump_base36(), hash.dump_buf().to_vec()),
+        (
+            IdKind::Signature,
+            signature.dump_base36(),
+            signature.dump_buf().to_vec(),
+        ),
+    ] {
+        let decoded = decode(kind, &base36).unwrap();
+        assert_eq!((decoded.kind, decoded.hex.clone()), (kind, to_hex(&bytes)));
+        assert_eq!(decoded.hex.len(), kind.byte_len() * 2);
+        let encoded = encode(kind, &parse_hex(&decoded.hex).unwrap()).unwrap();
+        assert_eq!(encoded, decoded);
+        assert_eq!(encoded.base36, base36);
+    }
+}
+
+#[test]
+fn leading_zero_bytes_are_kept() {
+    let decoded = decode(IdKind::Tx, "zz").unwrap();
+    assert_eq!(decoded.hex, format!("{}050f", "0".repeat(60)));
+    assert_eq!(
+        encode(IdKind::Tx, &parse_hex(&decoded.hex).unwrap())
+            .unwrap()
+            .base36,
+        "zz"
+    );
+}
+
+#[test]
+fn lengths_and_tags_are_checked() {
+    assert!(encode(IdKind::Address, &[0; 31]).is_err());
+    assert!(encode(IdKind::Signature, &[0; 32]).is_err());
+    assert!(decode(IdKind::Block, &"z".repeat(60)).is_err());
+    assert!(decode(IdKind::Block, "not base36!").is_err());
+    assert!(parse_hex("0x0").is_err());
+    assert_eq!(parse_hex("0x00ff").unwrap(), [0, 255]);
+
+    assert_eq!(split_tag("tx:abc"), (Some(IdKind::Tx), "abc"));
+    assert_eq!(split_tag("Address:abc"), (Some(IdKind::Address), "abc"));
+    assert_eq!(split_tag("abc"), (None, "abc"));
+    assert_eq!(split_tag("other:abc"), (None, "other:abc"));
+}
@@ -0,0 +1,116 @@
+// keystore.rs
+mod common;
+
+use snap_coin::crypto::keys::Private;
+use snap_coin_utils::{
+    error::ErrorKind,
+    keystore::{EncryptedKey, KDF, Keystore, MAX_ITERATIONS},
+    paper::PaperWallet,
+};
+
+use common::temp_dir;
+
+#[test]
+fn keys_unlock_only_with_their_password() {
+    let dir = temp_dir("unlock");
+    let keystore = Keystore::new(&dir);
+    let address = keystore.create("hot", "correct horse").unwrap();
+    assert_eq!(
+        keystore.unlock("hot", "correct horse").unwrap().to_public(),
+        address
+    );
+    assert!(keystore.unlock("hot", "battery staple").is_err());
+
+    // The file holds the address in the clear but not the secret
+    let private = keystore.unlock("hot", "correct horse").unwrap();
+    let contents = std::fs::read_to_string(dir.join("hot.toml")).unwrap();
+    assert!(contents.contains(&address.dump_base36()));
+    assert!(!contents.contains(&private.dump_base36()));
+
+    keystore
+        .change_password("hot", "correct horse", "staple")
+        .unwrap();
+    assert!(keystore.unlock("hot", "correct horse").is_err());
+    // An empty password would leave the key as good as unencrypted
+    assert!(keystore.change_password("hot", "staple", "").is_err());
+    assert!(keystore.create("empty", "").is_err());
+    assert_eq!(
+        keystore.unlock("hot", "staple").unwrap().to_public(),
+        address
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn imported_keys_are_listed_by_name() {
+    let dir = temp_dir("list");
+    let keystore = Keystore::new(&dir);
+    assert!(keystore.list().unwrap().keys.is_empty());
+
+    let private = Private::new_random();
+    keystore.import("cold", &private, "pw").unwrap();
+    keystore.create("a-hot_1", "pw").unwrap();
+    // Names are never reused or turned into paths outside the directory
+    assert!(keystore.import("cold", &private, "pw").is_err());
+    let error = keystore.create("../escape", "pw").unwrap_err();
+    assert_eq!(ErrorKind::of(&error), ErrorKind::InvalidIdentifier);
+    assert_eq!(
+        ErrorKind::of(&keystore.unlock("missing", "pw").unwrap_err()),
+        ErrorKind::NotFound
+    );
+
+    // A broken file is reported on its own, the other keys are still listed
+    std::fs::write(dir.join("broken.toml"), "address = ").unwrap();
+    let list = keystore.list().unwrap();
+    let names: Vec<_> = list.keys.iter().map(|key| key.name.as_str()).collect();
+    assert_eq!(names, ["a-hot_1", "cold"]);
+    assert_eq!(list.keys[1].address, private.to_public().dump_base36());
+    assert_eq!(list.unreadable.len(), 1);
+    assert_eq!(list.unreadable[0].name, "broken");
+    assert!(list.unreadable[0].error.contains("Could not parse key"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn encrypted_paper_wallets_decrypt_to_their_address() {
+    let paper = PaperWallet::generate(Some("paper")).unwrap();
+    assert!(paper.encrypted);
+    let key = EncryptedKey::from_compact(&paper.secret).unwrap();
+    assert!(key.decrypt("wrong").is_err());
+    let private = key.decrypt("paper").unwrap();
+    assert_eq!(private.to_public().dump_base36(), paper.address);
+
+    let html = paper.to_html().unwrap();
+    assert_eq!(html.matches("<svg").count(), 2);
+    assert!(html.contains(&paper.address) && html.contains(&paper.secret));
+
+    let plain = PaperWallet::generate(None).unwrap();
+    let private = Private::new_from_base36(&plain.secret).unwrap();
+    assert_eq!(private.to_public().dump_base36(), plain.address);
+}
+
+#[test]
+fn tampered_keys_and_costly_derivations_are_rejected() {
+    let private = Private::new_random();
+    let key = EncryptedKey::encrypt(&private, "pw").unwrap();
+    assert_eq!(key.kdf, KDF);
+    let compact = key.to_compact();
+    assert_eq!(compact.split('$').count(), 6);
+    let key = EncryptedKey::from_compact(&compact).unwrap();
+    assert!(key.decrypt("pw").unwrap() == private);
+
+    // Flipping a bit of the ciphertext fails the tag even with the right password
+    let mut tampered = EncryptedKey::from_compact(&compact).unwrap();
+    let last = tampered.secret.pop().unwrap();
+    tampered.secret.push(if last == '0' { '1' } else { '0' });
+    assert!(tampered.decrypt("pw").is_err());
+
+    // A paper wallet asking for 4 TiB of memory is refused before deriving anything
+    let mut costly = EncryptedKey::from_compact(&compact).unwrap();
+    costly.memory_kib = u32::MAX;
+    let error = costly.decrypt("pw").unwrap_err();
+    assert!(error.to_string().contains("above the limit"));
+    let mut costly = EncryptedKey::from_compact(&compact).unwrap();
+    costly.iterations = MAX_ITERATIONS + 1;
+    assert!(costly.decrypt("pw").is_err());
+}
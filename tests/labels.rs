@@ -0,0 +1,155 @@
+// labels.rs
+mod common;
+
+use std::fs;
+
+use snap_coin::crypto::keys::Public;
+use snap_coin_utils::{
+    config::Config,
+    labels::{BookEntry, Category, ImportSummary, LabelBook, parse_labels},
+};
+
+use common::temp_dir;
+
+fn address(byte: u8) -> String {
+    Public::new_from_buf(&[byte; 32]).dump_base36()
+}
+
+#[test]
+fn rows_parse_with_quotes_and_optional_categories() {
+    let csv = format!(
+        "address,label,category\n\
+         # community list\n\
+         {},Exchange A,Exchange\n\
+         {},\"Pool, the big one\",pool\n\
+         \n\
+         {},Faucet\n\
+         {},\"Say \"\"hi\"\"\",charity\n",
+        address(1),
+        address(2),
+        address(3),
+        address(4)
+    );
+    let entries = parse_labels(&csv).unwrap();
+    assert_eq!(entries.len(), 4);
+    assert_eq!(
+        entries[0],
+        BookEntry {
+            address: address(1),
+            label: "Exchange A".to_string(),
+            category: Some(Category::Exchange),
+        }
+    );
+    assert_eq!(entries[1].label, "Pool, the big one");
+    assert_eq!(entries[1].category, Some(Category::Pool));
+    assert_eq!(entries[2].category, None);
+    assert_eq!(entries[3].label, "Say \"hi\"");
+    assert_eq!(
+        entries[3].category,
+        Some(Category::Other("charity".to_string()))
+    );
+}
+
+#[test]
+fn invalid_rows_are_reported_with_their_line() {
+    let error = parse_labels("nope!,Label,pool").unwrap_err().to_string();
+    assert!(error.contains("Line 1"), "{error}");
+    let error = parse_labels(&format!("{},a,b,c", address(1)))
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("4 fields"), "{error}");
+    assert!(parse_labels(&format!("{},", address(1))).is_err());
+    assert!(parse_labels(&format!("{},\"open", address(1))).is_err());
+}
+
+#[test]
+fn imports_merge_into_the_book_and_survive_a_save() {
+    let dir = temp_dir("import");
+    let path = dir.join("labels.csv");
+    let mut book = LabelBook::load(&path).unwrap();
+    assert!(book.is_empty());
+
+    let first = parse_labels(&format!(
+        "{},Exchange A,exchange\n{},\"Pool, B\",pool",
+        address(1),
+        address(2)
+    ))
+    .unwrap();
+    assert_eq!(
+        book.import(first),
+        ImportSummary {
+            added: 2,
+            updated: 0,
+            unchanged: 0
+        }
+    );
+    let second = parse_labels(&format!(
+        "{},Exchange A,exchange\n{},Pool C,pool\n{},Burned,burn",
+        address(1),
+        address(2),
+        address(3)
+    ))
+    .unwrap();
+    assert_eq!(
+        book.import(second),
+        ImportSummary {
+            added: 1,
+            updated: 1,
+            unchanged: 1
+        }
+    );
+    book.save(&path).unwrap();
+
+    let loaded = LabelBook::load(&path).unwrap();
+    assert_eq!(loaded.len(), 3);
+    assert_eq!(loaded.get(&address(2)).unwrap().label, "Pool C");
+    assert_eq!(loaded.address_of("Burned"), Some(address(3).as_str()));
+    assert_eq!(
+        loaded.burn_addresses(),
+        vec![Public::new_from_buf(&[3; 32])]
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn config_uses_the_labels_next_to_it() {
+    let dir = temp_dir("config");
+    let config_path = dir.join("config.toml");
+    fs::write(
+        &config_path,
+        format!("[labels]\nmine = \"{}\"\n", address(1)),
+    )
+    .unwrap();
+    fs::write(
+        dir.join("labels.csv"),
+        format!(
+            "address,label,category\n{},Exchange A,exchange\n{},Burned,burn\n{},Renamed,pool\n",
+            address(2),
+            address(3),
+            address(1)
+        ),
+    )
+    .unwrap();
+    let config = Config::load(Some(&config_path)).unwrap();
+
+    assert_eq!(config.label_of(&address(2)), Some("Exchange A"));
+    assert_eq!(
+        config.display_label(&address(2)).as_deref(),
+        Some("Exchange A [exchange]")
+    );
+    // The config's own label wins, the imported category still shows
+    assert_eq!(
+        config.display_label(&address(1)).as_deref(),
+        Some("mine [pool]")
+    );
+    assert_eq!(config.display_label(&address(9)), None);
+    assert_eq!(config.resolve_address("Exchange A"), address(2));
+    assert_eq!(config.resolve_address("mine"), address(1));
+    assert!(
+        config
+            .burn_addresses()
+            .unwrap()
+            .contains(&Public::new_from_buf(&[3; 32]))
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
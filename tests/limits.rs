@@ -0,0 +1,24 @@
+// limits.rs
+use std::time::{Duration, Instant};
+
+use snap_coin_utils::limits::{acquire, set_rate_limit};
+
+#[tokio::test]
+async fn rate_limit_spaces_requests_after_the_burst() {
+    set_rate_limit(50.0);
+    let start = Instant::now();
+    // 50 fit in the burst, the other 10 take 10 / 50 = 0.2s
+    for _ in 0..60 {
+        acquire(1).await;
+    }
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(150), "{elapsed:?}");
+    assert!(elapsed < Duration::from_secs(2), "{elapsed:?}");
+
+    set_rate_limit(0.0);
+    let start = Instant::now();
+    for _ in 0..1000 {
+        acquire(1).await;
+    }
+    assert!(start.elapsed() < Duration::from_millis(100));
+}
@@ -0,0 +1,72 @@
+// luck.rs
+use snap_coin_utils::luck::{ShareEstimate, binomial_cdf, binomial_pmf, luck};
+
+const MINER: [u8; 32] = [1; 32];
+const OTHER: [u8; 32] = [2; 32];
+
+#[test]
+fn binomial_chances_add_up() {
+    assert!((binomial_pmf(4, 0.5, 2) - 0.375).abs() < 1e-12);
+    assert!((binomial_cdf(4, 0.5, 1) - 0.3125).abs() < 1e-12);
+    assert!((binomial_cdf(4, 0.5, 4) - 1.0).abs() < 1e-12);
+    // Long windows don't underflow to 0
+    assert!((binomial_cdf(20_000, 0.5, 10_000) - 0.5).abs() < 0.01);
+    assert_eq!(binomial_pmf(10, 0.0, 0), 1.0);
+    assert!((binomial_pmf(10, 1.0, 10) - 1.0).abs() < 1e-12);
+    assert_eq!(binomial_pmf(10, 1.0, 9), 0.0);
+}
+
+#[test]
+fn streaks_and_luck_come_from_the_window() {
+    // Won, won, lost x3, won, lost x4, with an unknown miner among them
+    let miners: Vec<Option<[u8; 32]>> = [1, 1, 0, 0, 0, 1, 0, 0, 2, 0]
+        .iter()
+        .map(|&kind| match kind {
+            1 => Some(MINER),
+            0 => Some(OTHER),
+            _ => None,
+        })
+        .collect();
+    let estimate = ShareEstimate::Baseline {
+        blocks: 40,
+        mined: 8,
+    };
+    let luck = luck(&MINER, "miner", 100, &miners, estimate);
+    assert_eq!((luck.start_height, luck.end_height), (100, 110));
+    assert_eq!((luck.mined, luck.share), (3, 0.2));
+    assert!((luck.expected - 2.0).abs() < 1e-12);
+    assert!((luck.luck.unwrap() - 150.0).abs() < 1e-9);
+    assert!((luck.std_dev - 1.6f64.sqrt()).abs() < 1e-12);
+    assert_eq!(
+        (
+            luck.longest_streak,
+            luck.longest_drought,
+            luck.current_drought
+        ),
+        (2, 4, 4)
+    );
+    assert!((luck.expected_drought.unwrap() - 4.0).abs() < 1e-12);
+    let exactly = binomial_pmf(10, 0.2, 3);
+    assert!((luck.probability_at_most + luck.probability_at_least - 1.0 - exactly).abs() < 1e-12);
+}
+
+#[test]
+fn hashrate_shares_are_capped_and_zero_shares_expect_nothing() {
+    let share = |hashrate| {
+        ShareEstimate::Hashrate {
+            hashrate,
+            network_hashrate: 100.0,
+        }
+        .share()
+    };
+    assert_eq!(share(25.0), 0.25);
+    assert_eq!(share(500.0), 1.0);
+
+    let none = ShareEstimate::Baseline {
+        blocks: 0,
+        mined: 0,
+    };
+    let luck = luck(&MINER, "miner", 0, &[Some(OTHER)], none);
+    assert_eq!((luck.luck, luck.expected_drought), (None, None));
+    assert_eq!(luck.probability_at_most, 1.0);
+}
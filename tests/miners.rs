@@ -0,0 +1,104 @@
+// miners.rs
+use snap_coin_utils::{
+    chart::{bar_lines, render_pie},
+    miners::{MinedBlock, miner_shares, miner_timings, miner_trend, share_slices, shares_table},
+};
+
+const A: Option<[u8; 32]> = Some([1; 32]);
+const B: Option<[u8; 32]> = Some([2; 32]);
+const C: Option<[u8; 32]> = Some([3; 32]);
+
+#[test]
+fn shares_sum_up_the_smaller_miners() {
+    let miners = [A, A, A, B, None, C];
+    let shares = miner_shares(100, &miners, 1);
+    assert_eq!(shares.end_height, 106);
+    assert_eq!(shares.miners.len(), 2);
+    assert_eq!(shares.miners[0].blocks, 3);
+    assert_eq!(shares.miners[0].share, 60.0);
+    assert!(shares.miners[1].miner.is_none());
+    assert_eq!(shares.miners[1].blocks, 2);
+}
+
+#[test]
+fn trend_follows_hashrate_moving_between_miners() {
+    // A mines everything at first, then B takes over
+    let miners = [A, A, A, A, B, B, B, B];
+    let trend = miner_trend(10, &miners, 4, 2, 5).unwrap();
+    assert_eq!(trend.window_ends, [13, 15, 17]);
+    assert_eq!(trend.miners.len(), 2);
+    assert_eq!(trend.miners[0].shares, [100.0, 50.0, 0.0]);
+    assert_eq!(trend.miners[1].shares, [0.0, 50.0, 100.0]);
+
+    assert!(miner_trend(10, &miners, 9, 1, 5).is_err());
+}
+
+#[test]
+fn timings_compare_each_miner_with_its_parents() {
+    let block = |miner, timestamp| MinedBlock { miner, timestamp };
+    // A mines every 40s, B stamps its blocks 5s before their parents, C is left to "Other"
+    let blocks = [
+        block(A, 1020),
+        block(B, 1015),
+        block(A, 1060),
+        block(C, 1080),
+        block(B, 1075),
+        block(A, 1100),
+    ];
+    let timings = miner_timings(50, Some(1000), &blocks, 2);
+    assert_eq!(timings.end_height, 56);
+    assert_eq!(timings.network_offset, Some(100.0 / 6.0));
+
+    let a = &timings.miners[0];
+    assert_eq!((a.blocks, a.mean_interval, a.backdated), (3, Some(40.0), 0));
+    assert_eq!(a.mean_offset, Some((20.0 + 45.0 + 25.0) / 3.0));
+    let b = &timings.miners[1];
+    assert_eq!(
+        (b.mean_interval, b.mean_offset, b.backdated),
+        (Some(60.0), Some(-5.0), 2)
+    );
+    let other = &timings.miners[2];
+    assert!(other.miner.is_none());
+    assert_eq!(
+        (other.blocks, other.mean_interval, other.mean_offset),
+        (1, None, Some(20.0))
+    );
+
+    // Without the parent of the window the first block has no offset
+    let timings = miner_timings(0, None, &blocks[..1], 2);
+    assert_eq!(timings.miners[0].mean_offset, None);
+}
+
+#[test]
+fn charts_name_miners_by_their_labels() {
+    let miners = [A, A, A, B, None, C];
+    let shares = miner_shares(100, &miners, 2);
+    let labelled = shares.miners[0].miner.clone().unwrap();
+    let slices = share_slices(&shares, |miner| (miner == labelled).then_some("pool-a"));
+    let names: Vec<&str> = slices.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names[0], "pool-a");
+    assert!(names[1].ends_with("...") && names[1].len() == 15);
+    assert_eq!(names[2], "Other");
+
+    let table = shares_table(&shares, |miner| {
+        (miner == labelled).then(|| "pool-a [pool]".to_string())
+    })
+    .to_string();
+    assert!(table.contains("Label") && table.contains("pool-a [pool]"));
+    assert!(
+        !shares_table(&shares, |_| None)
+            .to_string()
+            .contains("Label")
+    );
+
+    let lines = bar_lines(&slices, 10);
+    assert!(lines[0].starts_with("pool-a           ██████████  60.00%"));
+    // 20% of the biggest share's 10 glyphs is 3 and a third, rounded to 3 and 3 eighths
+    assert!(lines[1].contains("███▍        20.00%"));
+
+    let path = std::env::temp_dir().join(format!("miners-pie-{}.png", std::process::id()));
+    render_pie(&path, "Share of blocks", &slices).unwrap();
+    assert!(std::fs::metadata(&path).unwrap().len() > 0);
+    std::fs::remove_file(&path).unwrap();
+    assert!(render_pie(&path.with_extension("txt"), "Share", &slices).is_err());
+}
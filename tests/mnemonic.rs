@@ -0,0 +1,36 @@
+// mnemonic.rs
+use snap_coin_utils::{
+    error::ErrorKind,
+    mnemonic::{WORDS, generate, master_key, private_key},
+};
+
+fn unhex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+#[test]
+fn master_keys_match_slip10() {
+    // Test vector 1 of SLIP-0010 for ed25519
+    assert_eq!(
+        master_key(&unhex("000102030405060708090a0b0c0d0e0f")).to_vec(),
+        unhex("2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7")
+    );
+}
+
+#[test]
+fn phrases_restore_the_same_key() {
+    let phrase = generate();
+    assert_eq!(phrase.split_whitespace().count(), WORDS);
+    let key = private_key(&phrase).unwrap();
+    assert!(private_key(&format!("  {phrase}\n")).unwrap() == key);
+    assert!(private_key(&generate()).unwrap() != key);
+
+    // Shorter BIP39 phrases work too, a wrong checksum word doesn't
+    let twelve = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    assert!(private_key(twelve).is_ok());
+    let error = private_key(&twelve.replace("about", "abandon")).unwrap_err();
+    assert_eq!(ErrorKind::of(&error), ErrorKind::InvalidIdentifier);
+}
@@ -0,0 +1,305 @@
+// monitor.rs
+mod common;
+
+use snap_coin::{
+    core::transaction::{Transaction, TransactionInput, TransactionOutput},
+    crypto::{Hash, keys::Public},
+};
+use snap_coin_utils::{
+    fixture::{Fixture, FixtureProvider},
+    miners::coinbase,
+    monitor::{
+        MonitorEvent, MonitorState, Party, SeenTip, moved_amount, poll, poll_chain, poll_mempool,
+        whale,
+    },
+};
+
+use common::fixture;
+
+/// The fixture's block at `height` as the monitor records it
+fn seen(fixture: &Fixture, height: usize) -> SeenTip {
+    SeenTip {
+        height,
+        hash: fixture.blocks[height - fixture.start_height]
+            .meta
+            .hash
+            .unwrap(),
+    }
+}
+
+fn block_heights(events: &[MonitorEvent]) -> Vec<usize> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            MonitorEvent::Block { height, .. } => Some(*height),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn fresh_state_starts_at_the_tip() {
+    let fixture = fixture();
+    let tip = seen(&fixture, 599);
+    let provider = FixtureProvider::new(fixture);
+    let mut state = MonitorState::default();
+    assert!(poll_chain(&provider, &mut state).await.unwrap().is_empty());
+    assert_eq!(state.tip(), Some(tip));
+    // Nothing new on the next poll either
+    assert!(poll_chain(&provider, &mut state).await.unwrap().is_empty());
+    assert_eq!(state.recent, vec![tip]);
+}
+
+#[tokio::test]
+async fn restart_catches_up_from_the_saved_tip() {
+    let fixture = fixture();
+    let mut state = MonitorState {
+        recent: vec![seen(&fixture, 589), seen(&fixture, 590)],
+        ..Default::default()
+    };
+    let tip = seen(&fixture, 599);
+    let provider = FixtureProvider::new(fixture);
+    let events = poll_chain(&provider, &mut state).await.unwrap();
+    assert_eq!(block_heights(&events), (591..600).collect::<Vec<_>>());
+    assert_eq!(events.len(), 9);
+    assert_eq!(state.tip(), Some(tip));
+    assert_eq!(state.recent.len(), 11);
+}
+
+#[tokio::test]
+async fn replaced_blocks_are_reported_as_a_reorg() {
+    let fixture = fixture();
+    let stale = SeenTip {
+        height: 590,
+        hash: Hash::new_from_buf([7; 32]),
+    };
+    let mut state = MonitorState {
+        recent: vec![seen(&fixture, 588), seen(&fixture, 589), stale],
+        ..Default::default()
+    };
+    let provider = FixtureProvider::new(fixture);
+    let events = poll_chain(&provider, &mut state).await.unwrap();
+    assert_eq!(
+        events[0],
+        MonitorEvent::Reorg {
+            common_height: Some(589),
+            orphaned: 1,
+            old_tip: stale.hash.dump_base36(),
+        }
+    );
+    // The replacement block and everything after it follow
+    assert_eq!(block_heights(&events), (590..600).collect::<Vec<_>>());
+    assert!(!state.recent.contains(&stale));
+}
+
+#[tokio::test]
+async fn reorgs_deeper_than_the_recorded_blocks_have_no_common_height() {
+    let fixture = fixture();
+    let mut state = MonitorState {
+        recent: (595..598)
+            .map(|height| SeenTip {
+                height,
+                hash: Hash::new_from_buf([height as u8; 32]),
+            })
+            .collect(),
+        ..Default::default()
+    };
+    let provider = FixtureProvider::new(fixture);
+    let events = poll_chain(&provider, &mut state).await.unwrap();
+    assert!(matches!(
+        events[0],
+        MonitorEvent::Reorg {
+            common_height: None,
+            orphaned: 3,
+            ..
+        }
+    ));
+    assert_eq!(block_heights(&events), (595..600).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn watched_addresses_raise_alerts_for_confirmed_blocks() {
+    let fixture = fixture();
+    let coinbase = coinbase(&fixture.blocks[35]).unwrap().clone();
+    let miner = coinbase.outputs[1].receiver;
+    let mut state = MonitorState {
+        recent: vec![seen(&fixture, 594)],
+        ..Default::default()
+    };
+    state.watch(&[miner.dump_base36()]);
+    let provider = FixtureProvider::new(fixture);
+    let events = poll_chain(&provider, &mut state).await.unwrap();
+    assert!(
+        events.contains(&MonitorEvent::Alert {
+            address: miner.dump_base36(),
+            transaction: coinbase.transaction_id.unwrap().dump_base36(),
+            height: Some(595),
+            received: coinbase
+                .outputs
+                .iter()
+                .filter(|output| output.receiver == miner)
+                .map(|output| output.amount)
+                .sum(),
+            spent_outputs: 0,
+            coinbase: true,
+        })
+    );
+}
+
+#[test]
+fn mempool_changes_are_reported_once() {
+    let fixture = fixture();
+    let transactions = fixture.blocks[10].transactions.clone();
+    let mut state = MonitorState::default();
+
+    let events = poll_mempool(&mut state, &transactions).unwrap();
+    assert_eq!(
+        events,
+        vec![MonitorEvent::Mempool {
+            transactions: transactions.len(),
+            added: transactions.len(),
+            removed: 0,
+        }]
+    );
+    assert!(poll_mempool(&mut state, &transactions).unwrap().is_empty());
+    assert_eq!(
+        poll_mempool(&mut state, &[]).unwrap(),
+        vec![MonitorEvent::Mempool {
+            transactions: 0,
+            added: 0,
+            removed: transactions.len(),
+        }]
+    );
+}
+
+#[tokio::test]
+async fn polls_report_new_blocks_before_the_mempool() {
+    let fixture = fixture();
+    let transactions = fixture.blocks[10].transactions.clone();
+    let mut state = MonitorState {
+        recent: vec![seen(&fixture, 597)],
+        ..Default::default()
+    };
+    let provider = FixtureProvider::new(fixture);
+
+    let events = poll(&provider, &mut state, &transactions).await.unwrap();
+    assert_eq!(block_heights(&events), [598, 599]);
+    assert_eq!(
+        events.last(),
+        Some(&MonitorEvent::Mempool {
+            transactions: transactions.len(),
+            added: transactions.len(),
+            removed: 0,
+        })
+    );
+    assert!(
+        poll(&provider, &mut state, &transactions)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
+
+/// A transaction from `sender` paying 5 and 3 nano to two addresses, with 2 nano of change
+fn payment(sender: Public) -> Transaction {
+    let output = |amount, receiver| TransactionOutput { amount, receiver };
+    Transaction {
+        inputs: vec![TransactionInput {
+            transaction_id: Hash::new_from_buf([0x11; 32]),
+            output_index: 0,
+            signature: None,
+            output_owner: sender,
+        }],
+        outputs: vec![
+            output(5, Public::new_from_buf(&[0xb1; 32])),
+            output(2, sender),
+            output(3, Public::new_from_buf(&[0xb2; 32])),
+        ],
+        transaction_id: Some(Hash::new_from_buf([0x22; 32])),
+        nonce: 0,
+        timestamp: 0,
+    }
+}
+
+#[test]
+fn transactions_moving_the_min_amount_are_whales() {
+    let sender = Public::new_from_buf(&[0xa1; 32]);
+    let tx = payment(sender);
+    assert_eq!(moved_amount(&tx), 8);
+    assert_eq!(whale(&tx, None, 9), None);
+
+    let mut event = whale(&tx, Some(601), 8).unwrap();
+    event.label(|address| (address == sender.dump_base36()).then(|| "Exchange".to_string()));
+    let MonitorEvent::Whale {
+        height,
+        amount,
+        senders,
+        receivers,
+        ..
+    } = event
+    else {
+        panic!("not a whale event");
+    };
+    assert_eq!((height, amount), (Some(601), 8));
+    assert_eq!(
+        senders,
+        vec![Party {
+            address: sender.dump_base36(),
+            label: Some("Exchange".to_string()),
+            amount: None,
+        }]
+    );
+    assert_eq!(
+        receivers
+            .iter()
+            .map(|party| party.amount)
+            .collect::<Vec<_>>(),
+        vec![Some(5), Some(3)]
+    );
+
+    // Block rewards move nothing
+    let fixture = fixture();
+    let reward = coinbase(&fixture.blocks[0]).unwrap();
+    assert_eq!(whale(reward, Some(560), 1), None);
+
+    let mut state = MonitorState {
+        min_amount: Some(8),
+        ..Default::default()
+    };
+    let events = poll_mempool(&mut state, &[tx]).unwrap();
+    assert!(matches!(
+        events[1],
+        MonitorEvent::Whale {
+            height: None,
+            amount: 8,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn state_survives_a_restart() {
+    let fixture = fixture();
+    let dir = std::env::temp_dir().join(format!("snap-coin-utils-monitor-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("monitor.json");
+    assert!(MonitorState::load(&path).unwrap().recent.is_empty());
+
+    let address = coinbase(&fixture.blocks[0]).unwrap().outputs[0]
+        .receiver
+        .dump_base36();
+    let mut state = MonitorState {
+        recent: vec![seen(&fixture, 598), seen(&fixture, 599)],
+        ..Default::default()
+    };
+    assert_eq!(state.watch(&[address.clone(), address.clone()]), 1);
+    state.mempool.insert(seen(&fixture, 599).hash);
+    state.save(&path).unwrap();
+
+    let loaded = MonitorState::load(&path).unwrap();
+    assert_eq!(loaded.recent, state.recent);
+    assert_eq!(loaded.addresses, vec![address]);
+    assert_eq!(loaded.mempool, state.mempool);
+    assert!(!path.with_extension("tmp").exists());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,111 @@
+// notify.rs
+use snap_coin_utils::{
+    monitor::{MonitorEvent, Party},
+    notify::{DesktopNotification, for_monitor_event, for_screen_hit},
+    screen::{Direction, ScreenHit},
+};
+
+fn block(miner: Option<&str>) -> MonitorEvent {
+    MonitorEvent::Block {
+        height: 601,
+        hash: "hash".to_string(),
+        timestamp: 0,
+        transactions: 3,
+        miner: miner.map(str::to_string),
+    }
+}
+
+fn alert(height: Option<usize>, received: u64, coinbase: bool) -> MonitorEvent {
+    MonitorEvent::Alert {
+        address: "mine".to_string(),
+        transaction: "tx".to_string(),
+        height,
+        received,
+        spent_outputs: 0,
+        coinbase,
+    }
+}
+
+#[test]
+fn blocks_of_watched_miners_and_payments_notify() {
+    let addresses = vec!["mine".to_string()];
+    let label = |address: &str| (address == "mine").then(|| "Rig".to_string());
+
+    assert_eq!(
+        for_monitor_event(&block(Some("mine")), &addresses, label),
+        Some(DesktopNotification {
+            summary: "Block 601 mined".to_string(),
+            body: "Rig found a block with 3 transactions".to_string(),
+        })
+    );
+    assert_eq!(
+        for_monitor_event(&block(Some("other")), &addresses, label),
+        None
+    );
+    assert_eq!(for_monitor_event(&block(None), &addresses, label), None);
+
+    assert_eq!(
+        for_monitor_event(&alert(None, 150_000_000, false), &addresses, label),
+        Some(DesktopNotification {
+            summary: "Incoming payment".to_string(),
+            body: "1.50000000 SNAP to Rig".to_string(),
+        })
+    );
+    assert_eq!(
+        for_monitor_event(&alert(Some(601), 1, false), &addresses, label)
+            .unwrap()
+            .summary,
+        "Payment confirmed in block 601"
+    );
+    // Rewards are announced by their block and spends aren't payments
+    assert_eq!(
+        for_monitor_event(&alert(Some(601), 1, true), &addresses, label),
+        None
+    );
+    assert_eq!(
+        for_monitor_event(&alert(Some(601), 0, false), &addresses, label),
+        None
+    );
+}
+
+#[test]
+fn whale_transactions_notify_with_their_parties() {
+    let party = |address: &str, label: Option<&str>| Party {
+        address: address.to_string(),
+        label: label.map(str::to_string),
+        amount: None,
+    };
+    let whale = MonitorEvent::Whale {
+        transaction: "tx".to_string(),
+        height: Some(601),
+        amount: 50_000_000_000,
+        senders: vec![party("a", Some("Exchange"))],
+        receivers: vec![party("b", None), party("c", None), party("d", None)],
+    };
+    assert_eq!(
+        for_monitor_event(&whale, &[], |_| None),
+        Some(DesktopNotification {
+            summary: "Whale transaction in block 601".to_string(),
+            body: "500.00000000 SNAP from Exchange to b, c and 1 more".to_string(),
+        })
+    );
+}
+
+#[test]
+fn only_incoming_screening_hits_notify() {
+    let hit = |direction| ScreenHit {
+        height: None,
+        transaction: "tx".to_string(),
+        address: "listed".to_string(),
+        direction,
+        amount: Some(200_000_000),
+    };
+    assert_eq!(
+        for_screen_hit(&hit(Direction::Incoming), |_| None),
+        Some(DesktopNotification {
+            summary: "Watchlist address paid".to_string(),
+            body: "2.00000000 SNAP to listed in the mempool".to_string(),
+        })
+    );
+    assert_eq!(for_screen_hit(&hit(Direction::Outgoing), |_| None), None);
+}
@@ -0,0 +1,72 @@
+// orphans.rs
+mod common;
+
+use std::time::Duration;
+
+use snap_coin::crypto::Hash;
+use snap_coin_utils::{
+    fixture::FixtureProvider,
+    node::NodeClient,
+    orphans::{SeenBlock, load_seen_blocks, orphan_report, watch_blocks},
+};
+
+use common::{announcing_node, fixture};
+
+#[tokio::test]
+async fn watch_logs_announced_blocks() {
+    let path =
+        std::env::temp_dir().join(format!("snap-coin-utils-seen-{}.jsonl", std::process::id()));
+    let client = NodeClient::connect(announcing_node(fixture().blocks[..3].to_vec()).await)
+        .await
+        .unwrap();
+    // The subscription ends with an error once the node hangs up
+    assert!(watch_blocks(&client, &path).await.is_err());
+    let seen = load_seen_blocks(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    let seen = seen.unwrap();
+    let fixture = fixture();
+    assert_eq!(seen.len(), 3);
+    assert_eq!(Some(seen[2].hash), fixture.blocks[2].meta.hash);
+    assert_eq!(seen[2].previous, fixture.blocks[2].meta.previous_block);
+}
+
+#[tokio::test]
+async fn blocks_off_the_main_chain_are_orphans() {
+    let fixture = fixture();
+    let main = &fixture.blocks[10];
+    // Recorded blocks don't carry their parent hash, take it from the block before
+    let parent = fixture.blocks[9].meta.hash.unwrap();
+    let seen = [
+        SeenBlock {
+            seen: 1_000,
+            hash: main.meta.hash.unwrap(),
+            previous: parent,
+        },
+        // A competing block at the same height that lost
+        SeenBlock {
+            seen: 1_010,
+            hash: Hash::new(b"stale"),
+            previous: parent,
+        },
+        SeenBlock {
+            seen: 5_000,
+            hash: fixture.blocks[11].meta.hash.unwrap(),
+            previous: main.meta.hash.unwrap(),
+        },
+    ];
+    let report = orphan_report(
+        &FixtureProvider::new(fixture),
+        &seen,
+        Duration::from_secs(3600),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(report.orphans.len(), 1);
+    assert_eq!(report.orphans[0].height, Some(570));
+    assert_eq!(report.buckets.len(), 2);
+    assert_eq!(report.buckets[0].blocks, 2);
+    assert_eq!(report.buckets[0].rate, 50.0);
+    assert_eq!(report.buckets[1].orphans, 0);
+}
@@ -0,0 +1,85 @@
+// patterns.rs
+use snap_coin::{
+    core::transaction::{Transaction, TransactionInput, TransactionOutput},
+    crypto::{Hash, keys::Public},
+};
+use snap_coin_utils::patterns::{PatternMix, TransactionPattern, classify};
+
+fn address(byte: u8) -> Public {
+    Public::new_from_buf(&[byte; 32])
+}
+
+/// A transaction spending one input from every owner in `from` and paying every address in `to`
+fn transaction(from: &[u8], to: &[u8]) -> Transaction {
+    Transaction {
+        inputs: from
+            .iter()
+            .map(|&owner| TransactionInput {
+                transaction_id: Hash::new_from_buf([owner; 32]),
+                output_index: 0,
+                signature: None,
+                output_owner: address(owner),
+            })
+            .collect(),
+        outputs: to
+            .iter()
+            .map(|&receiver| TransactionOutput {
+                amount: 1,
+                receiver: address(receiver),
+            })
+            .collect(),
+        transaction_id: None,
+        nonce: 0,
+        timestamp: 0,
+    }
+}
+
+#[test]
+fn transactions_are_tagged_by_their_inputs_and_outputs() {
+    let cases = [
+        (transaction(&[], &[1, 2]), TransactionPattern::Coinbase),
+        (transaction(&[1], &[2, 1]), TransactionPattern::Payment),
+        (transaction(&[1], &[2]), TransactionPattern::Payment),
+        (transaction(&[1, 3], &[2, 1]), TransactionPattern::Payment),
+        (
+            transaction(&[1, 1, 3], &[2]),
+            TransactionPattern::Consolidation,
+        ),
+        (transaction(&[1], &[2, 3, 4, 1]), TransactionPattern::FanOut),
+        (transaction(&[1, 3], &[2, 3, 4]), TransactionPattern::Other),
+        // Paying only itself trumps the shape
+        (
+            transaction(&[1, 1, 1], &[1]),
+            TransactionPattern::SelfTransfer,
+        ),
+        (
+            transaction(&[1, 2], &[2, 1, 2]),
+            TransactionPattern::SelfTransfer,
+        ),
+    ];
+    for (tx, pattern) in &cases {
+        assert_eq!(classify(tx), *pattern, "{:?}", tx);
+    }
+}
+
+#[test]
+fn mixes_count_and_share_the_patterns() {
+    let mut mix = PatternMix::default();
+    assert_eq!(mix.share(TransactionPattern::Payment), 0.0);
+    assert_eq!(mix.summary(), "");
+    for tx in [
+        transaction(&[], &[1, 2]),
+        transaction(&[1], &[2, 1]),
+        transaction(&[3], &[2, 3]),
+        transaction(&[1, 3], &[4]),
+    ] {
+        mix.add(&tx);
+    }
+    assert_eq!(mix.total(), 4);
+    assert_eq!((mix.coinbase, mix.payment, mix.consolidation), (1, 2, 1));
+    assert_eq!(mix.share(TransactionPattern::Payment), 0.5);
+    assert_eq!(
+        mix.summary(),
+        "coinbase 1 (25.0%), payment 2 (50.0%), consolidation 1 (25.0%)"
+    );
+}
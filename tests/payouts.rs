@@ -0,0 +1,77 @@
+// payouts.rs
+mod common;
+
+use snap_coin::{
+    core::transaction::{Transaction, TransactionInput, TransactionOutput},
+    crypto::{Hash, keys::Public},
+};
+use snap_coin_utils::{
+    fixture::FixtureProvider,
+    miners::{block_miner, coinbase},
+    payouts::verify_payouts,
+};
+
+use common::fixture;
+
+/// A payment from `pool` of `amount` to `to`, with change back to the pool
+fn payment(pool: Public, to: Public, amount: u64) -> Transaction {
+    Transaction {
+        inputs: vec![TransactionInput {
+            transaction_id: Hash::new_from_buf([9; 32]),
+            output_index: 0,
+            signature: None,
+            output_owner: pool,
+        }],
+        outputs: vec![
+            TransactionOutput {
+                amount,
+                receiver: to,
+            },
+            TransactionOutput {
+                amount: 1,
+                receiver: pool,
+            },
+        ],
+        transaction_id: Some(Hash::new_from_buf([amount as u8; 32])),
+        nonce: 0,
+        timestamp: 0,
+    }
+}
+
+#[tokio::test]
+async fn payouts_are_checked_against_the_pool_rewards() {
+    let mut fixture = fixture();
+    let pool = Public::new_from_buf(&block_miner(&fixture.blocks[0]).unwrap());
+    let user = Public::new_from_buf(&[0xa7; 32]);
+    let (mined, rewards) = fixture
+        .blocks
+        .iter()
+        .filter(|block| block_miner(block) == Some(*pool.dump_buf()))
+        .map(|block| coinbase(block).unwrap().outputs[1].amount)
+        .fold((0, 0), |(n, sum), amount| (n + 1, sum + amount));
+
+    fixture.blocks[3].transactions.push(payment(pool, user, 40));
+    fixture.blocks[9].transactions.push(payment(pool, user, 60));
+    // Someone else paying the user is not a payout
+    let other = Public::new_from_buf(&[0xa8; 32]);
+    fixture.blocks[9]
+        .transactions
+        .push(payment(other, user, 500));
+
+    let provider = FixtureProvider::new(fixture);
+    let report = verify_payouts(&provider, 560..600, pool, user, 0.5, 0.1)
+        .await
+        .unwrap();
+    assert_eq!((report.blocks_mined, report.rewards), (mined, rewards));
+    assert_eq!(report.expected, (rewards as f64 * 0.45).floor() as u64);
+    assert_eq!(report.paid, 100);
+    let heights: Vec<usize> = report.payouts.iter().map(|payout| payout.height).collect();
+    assert_eq!(heights, [563, 569]);
+    assert_eq!(report.shortfall, report.expected - 100);
+
+    // Nothing owed, nothing short
+    let report = verify_payouts(&provider, 560..600, pool, user, 0.0, 0.0)
+        .await
+        .unwrap();
+    assert_eq!((report.expected, report.shortfall), (0, 0));
+}
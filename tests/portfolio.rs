@@ -0,0 +1,125 @@
+// portfolio.rs
+use std::collections::HashMap;
+
+use snap_coin::{
+    core::transaction::{Transaction, TransactionInput, TransactionOutput},
+    crypto::{Hash, keys::Public},
+};
+use snap_coin_utils::{
+    portfolio::{
+        DAY, Holding, Portfolio, WEEK, change_since, format_change, load_list, net_flow,
+        portfolio_table,
+    },
+    price::Quote,
+};
+
+fn transaction(
+    id: u8,
+    inputs: Vec<TransactionInput>,
+    outputs: Vec<TransactionOutput>,
+) -> Transaction {
+    Transaction {
+        inputs,
+        outputs,
+        transaction_id: Some(Hash::new_from_buf([id; 32])),
+        nonce: 0,
+        timestamp: 0,
+    }
+}
+
+fn output(amount: u64, receiver: Public) -> TransactionOutput {
+    TransactionOutput { amount, receiver }
+}
+
+#[test]
+fn lists_are_read_like_label_imports() {
+    let dir =
+        std::env::temp_dir().join(format!("snap-coin-utils-portfolio-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("treasury.csv");
+    let address = Public::new_from_buf(&[1; 32]).dump_base36();
+    std::fs::write(&path, format!("address,label\n{},\"Ops, hot\"\n", address)).unwrap();
+    let entries = load_list(&path).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].label, "Ops, hot");
+
+    std::fs::write(&path, "address,label\n").unwrap();
+    assert!(load_list(&path).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn flows_net_received_against_spent() {
+    let treasury = Public::new_from_buf(&[1; 32]);
+    let other = Public::new_from_buf(&[2; 32]);
+    let funding = transaction(1, vec![], vec![output(500, other), output(300, treasury)]);
+    let spend = transaction(
+        2,
+        vec![TransactionInput {
+            transaction_id: funding.transaction_id.unwrap(),
+            output_index: 1,
+            signature: None,
+            output_owner: treasury,
+        }],
+        vec![output(200, other), output(100, treasury)],
+    );
+
+    let mut known = HashMap::new();
+    assert_eq!(net_flow(&funding, &treasury, &known), Some(300));
+    assert_eq!(net_flow(&funding, &other, &known), Some(500));
+    // The spent output can't be priced without its transaction
+    assert_eq!(net_flow(&spend, &treasury, &known), None);
+    known.insert(funding.transaction_id.unwrap(), funding.clone());
+    assert_eq!(net_flow(&spend, &treasury, &known), Some(-200));
+    assert_eq!(net_flow(&spend, &other, &known), Some(200));
+}
+
+#[test]
+fn changes_count_back_from_now() {
+    let now = 10 * WEEK;
+    let flows = [
+        (now - WEEK - 1, 1_000),
+        (now - 3 * DAY, 500),
+        (now - DAY + 60, -200),
+        (now, 50),
+    ];
+    assert_eq!(change_since(&flows, now, DAY), -150);
+    assert_eq!(change_since(&flows, now, WEEK), 350);
+    assert_eq!(format_change(-150_000_000), "-1.50000000");
+    assert_eq!(format_change(0), "+0.00000000");
+}
+
+#[test]
+fn totals_sum_every_holding() {
+    let holding = |label: &str, balance, change_24h, change_7d| Holding {
+        address: Public::new_from_buf(&[label.len() as u8; 32]).dump_base36(),
+        label: label.to_string(),
+        balance,
+        change_24h,
+        change_7d,
+    };
+    let portfolio = Portfolio::new(
+        0,
+        vec![
+            holding("cold", 400_000_000, 0, 100_000_000),
+            holding("hot", 100_000_000, -50_000_000, -50_000_000),
+        ],
+    );
+    assert_eq!(portfolio.total, 500_000_000);
+    assert_eq!(portfolio.change_24h, -50_000_000);
+    assert_eq!(portfolio.change_7d, 50_000_000);
+
+    let quote = Quote {
+        currency: "eur".to_string(),
+        price: 2.0,
+        fetched: 0,
+    };
+    let table = portfolio_table(&portfolio, Some(&quote)).to_string();
+    assert!(table.contains("Value (EUR)"));
+    assert!(table.contains("10.00"));
+    assert!(
+        !portfolio_table(&portfolio, None)
+            .to_string()
+            .contains("Value")
+    );
+}
@@ -0,0 +1,116 @@
+// price.rs
+mod common;
+
+use std::{
+    io::{Read, Write},
+    net::TcpListener,
+    thread,
+};
+
+use serde_json::json;
+use snap_coin_utils::price::{
+    PriceCache, PriceConfig, Quote, annotate, extract_price, quote, with_fiat,
+};
+
+use common::temp_dir;
+
+fn usd(price: f64, fetched: u64) -> Quote {
+    Quote {
+        currency: "usd".to_string(),
+        price,
+        fetched,
+    }
+}
+
+/// A price API answering a single request with `body`
+fn serve_once(body: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0; 1024];
+        let _ = stream.read(&mut request).unwrap();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+    });
+    format!("http://{}/price?vs={{currency}}", address)
+}
+
+#[test]
+fn prices_are_read_at_the_pointer() {
+    let response = r#"{"snap-coin": {"usd": 0.25, "eur": "0.23"}}"#;
+    assert_eq!(extract_price(response, "/snap-coin/usd").unwrap(), 0.25);
+    assert_eq!(extract_price(response, "/snap-coin/eur").unwrap(), 0.23);
+    assert!(extract_price(response, "/snap-coin/gbp").is_err());
+    assert!(extract_price(response, "/snap-coin").is_err());
+    assert!(extract_price("<html>", "/price").is_err());
+}
+
+#[test]
+fn amounts_are_valued_at_the_quote() {
+    let quote = usd(0.25, 0);
+    assert_eq!(quote.value(400_000_000), 1.0);
+    assert_eq!(quote.format(4_000_000_000), "10.00 USD");
+    assert_eq!(quote.format(1_000), "0.000003 USD");
+    assert_eq!(annotate(Some(&quote), 400_000_000), " (1.00 USD)");
+    assert_eq!(annotate(None, 400_000_000), "");
+
+    let value = with_fiat(
+        &json!({"balance": 400_000_000}),
+        Some(&quote),
+        &[("balance", 400_000_000)],
+    )
+    .unwrap();
+    assert_eq!(value["fiat"]["currency"], "usd");
+    assert_eq!(value["fiat"]["balance"], 1.0);
+    let value = with_fiat(&json!({"balance": 1}), None, &[("balance", 1)]).unwrap();
+    assert_eq!(value, json!({"balance": 1}));
+}
+
+#[tokio::test]
+async fn cached_prices_are_used_within_the_ttl() {
+    let dir = temp_dir("ttl");
+    let path = dir.join("prices.json");
+    let mut cache = PriceCache::default();
+    cache.quotes.insert("usd".to_string(), usd(0.25, 1_000));
+    cache.save(&path).unwrap();
+
+    // No API is configured, so only the cache can answer
+    let config = PriceConfig::default();
+    assert_eq!(
+        quote(&config, &path, "USD", 1_000 + 599).await.unwrap(),
+        usd(0.25, 1_000)
+    );
+    assert!(quote(&config, &path, "usd", 1_000 + 600).await.is_err());
+    assert!(quote(&config, &path, "eur", 1_000).await.is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn stale_prices_are_fetched_again_and_cached() {
+    let dir = temp_dir("fetch");
+    let path = dir.join("prices.json");
+    let config = PriceConfig {
+        url: Some(serve_once(r#"{"price": {"usd": 0.5}}"#)),
+        pointer: "/price/{currency}".to_string(),
+        ttl: "1m".to_string(),
+    };
+    assert_eq!(
+        quote(&config, &path, "usd", 5_000).await.unwrap(),
+        usd(0.5, 5_000)
+    );
+    let cached = PriceCache::load(&path).unwrap();
+    assert_eq!(cached.quotes["usd"], usd(0.5, 5_000));
+
+    // The API is gone, an expired price is better than none
+    assert_eq!(
+        quote(&config, &path, "usd", 9_000).await.unwrap(),
+        usd(0.5, 5_000)
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,25 @@
+// probe.rs
+use std::time::Duration;
+
+use snap_coin_utils::probe::{NodeProbe, pick_fastest};
+
+fn node(name: &str, height: usize, latency_ms: u64) -> NodeProbe {
+    NodeProbe {
+        name: name.to_string(),
+        address: "127.0.0.1:3003".parse().unwrap(),
+        height,
+        latency: Duration::from_millis(latency_ms),
+    }
+}
+
+#[test]
+fn highest_node_wins_then_fastest() {
+    let probes = [
+        node("behind", 99, 5),
+        node("slow", 100, 80),
+        node("fast", 100, 20),
+    ];
+    assert_eq!(pick_fastest(&probes), Some(2));
+    assert_eq!(pick_fastest(&probes[..1]), Some(0));
+    assert_eq!(pick_fastest(&[]), None);
+}
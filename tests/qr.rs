@@ -0,0 +1,56 @@
+// qr.rs
+use snap_coin_utils::{paper::PaperWallet, qr::QrCode};
+
+#[test]
+fn svgs_draw_a_square_per_dark_module_inside_the_quiet_zone() {
+    let code = QrCode {
+        version: 1,
+        size: 3,
+        modules: vec![
+            vec![true, false, false],
+            vec![false, true, true],
+            vec![false, false, false],
+        ],
+    };
+    let svg = code.to_svg();
+    assert!(svg.contains("viewBox=\"0 0 11 11\""));
+    assert!(svg.contains("d=\"M4 4h1v1h-1zM5 5h1v1h-1zM6 5h1v1h-1z\""));
+}
+
+#[test]
+fn matrices_are_rows_of_modules() {
+    let paper = PaperWallet::generate(Some("paper")).unwrap();
+    let address = QrCode::encode(paper.address.as_bytes()).unwrap();
+    let secret = QrCode::encode(paper.secret.as_bytes()).unwrap();
+    for code in [&address, &secret] {
+        assert_eq!(code.size, code.version * 4 + 17);
+        assert_eq!(code.modules.len(), code.size);
+        assert!(code.modules.iter().all(|row| row.len() == code.size));
+        // The dark module is right of the bottom left finder, a transposed matrix misses it
+        assert!(code.modules[code.size - 8][8]);
+    }
+    // Addresses and encrypted secrets have fixed lengths, so every sheet prints the same symbols
+    assert_eq!((address.version, secret.version), (4, 9));
+}
+
+#[test]
+fn paper_wallets_keep_the_secret_below_the_cut() {
+    for password in [None, Some("paper")] {
+        let paper = PaperWallet::generate(password).unwrap();
+        let html = paper.to_html().unwrap();
+        let (address_half, secret_half) = html.split_once("<div class=\"cut\"></div>").unwrap();
+
+        let address_qr = QrCode::encode(paper.address.as_bytes()).unwrap().to_svg();
+        assert!(address_half.contains(&address_qr));
+        assert!(address_half.contains(&format!("<code>{}</code>", paper.address)));
+        assert!(!address_half.contains(&paper.secret));
+
+        let secret_qr = QrCode::encode(paper.secret.as_bytes()).unwrap().to_svg();
+        assert!(secret_half.contains(&secret_qr));
+        assert!(secret_half.contains(&format!("<code>{}</code>", paper.secret)));
+        assert_eq!(
+            secret_half.contains("Encrypted with a password"),
+            paper.encrypted
+        );
+    }
+}
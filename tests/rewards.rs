@@ -0,0 +1,60 @@
+// rewards.rs
+use snap_coin::economics::{HALVING_INTERVAL, get_block_reward, total_reward};
+use snap_coin_utils::rewards::supply_at;
+use std::process::Command;
+
+/// The table rows `rewards` prints, one per era, split into trimmed columns
+fn eras() -> Vec<Vec<String>> {
+    let output = Command::new(env!("CARGO_BIN_EXE_snap-coin-utils"))
+        .arg("rewards")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .skip(2)
+        .map(|line| {
+            line.split('|')
+                .map(|column| column.trim().to_string())
+                .collect()
+        })
+        .collect()
+}
+
+fn heights(row: &[String]) -> (usize, Option<usize>) {
+    let (start, end) = row[1].split_once(" - ").unwrap();
+    (start.parse().unwrap(), end.parse().ok())
+}
+
+#[test]
+fn eras_follow_each_other_and_halve() {
+    let eras = eras();
+    assert_eq!(heights(&eras[0]).0, 0);
+    for (i, pair) in eras.windows(2).enumerate() {
+        assert_eq!(pair[0][0], i.to_string());
+        let (start, end) = heights(&pair[0]);
+        assert_eq!(end, Some(start + HALVING_INTERVAL - 1));
+        assert_eq!(heights(&pair[1]).0, start + HALVING_INTERVAL);
+        let subsidy = |row: &[String]| row[2].parse::<f64>().unwrap();
+        assert!(subsidy(&pair[1]) < subsidy(&pair[0]));
+    }
+}
+
+#[test]
+fn the_tail_era_is_open_ended() {
+    let tail = eras().pop().unwrap();
+    assert_eq!(heights(&tail).1, None);
+    assert_eq!(tail[3], "tail emission");
+}
+
+#[test]
+fn supply_is_summed_by_era() {
+    for height in [0, 1, 600, HALVING_INTERVAL, HALVING_INTERVAL + 1] {
+        assert_eq!(supply_at(height), total_reward(height), "{height}");
+    }
+    // Far past the last halving the floor reward is paid by every block
+    let floor = 40 * HALVING_INTERVAL;
+    assert_eq!(get_block_reward(floor), 1);
+    assert_eq!(supply_at(floor + 1_000), supply_at(floor) + 1_000);
+}
@@ -0,0 +1,82 @@
+// richlist.rs
+mod common;
+
+use snap_coin_utils::{
+    fixture::FixtureProvider,
+    richlist::{RichEntry, RichList, diff_richlists},
+    utxos::UtxoSet,
+};
+
+use common::fixture;
+
+fn list(height: usize, entries: &[(&str, u64)]) -> RichList {
+    RichList {
+        timestamp: 0,
+        height,
+        supply: entries.iter().map(|(_, balance)| balance).sum(),
+        entries: entries
+            .iter()
+            .map(|(address, balance)| RichEntry {
+                address: address.to_string(),
+                balance: *balance,
+            })
+            .collect(),
+    }
+}
+
+#[tokio::test]
+async fn rich_lists_sum_the_utxos_of_each_owner() {
+    let fixture = fixture();
+    let provider = FixtureProvider::new(fixture);
+    let mut set = UtxoSet::default();
+    set.replay(&provider, 560..600, |_, _, _| {}).await.unwrap();
+
+    let full = RichList::from_utxos(&set, 600, 1, usize::MAX);
+    assert_eq!(full.supply, set.value());
+    assert_eq!(
+        full.entries.iter().map(|entry| entry.balance).sum::<u64>(),
+        set.value()
+    );
+    assert!(
+        full.entries
+            .windows(2)
+            .all(|pair| pair[0].balance >= pair[1].balance)
+    );
+
+    let top = RichList::from_utxos(&set, 600, 1, 3);
+    assert_eq!(top.entries, full.entries[..3]);
+
+    let dir = std::env::temp_dir().join(format!("snap-coin-utils-richlist-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("richlist.json");
+    top.save(&path).unwrap();
+    let loaded = RichList::load(&path).unwrap();
+    assert_eq!((loaded.height, loaded.entries), (600, top.entries));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn diffs_rank_changes_and_list_entrants_and_dropouts() {
+    let old = list(100, &[("a", 500), ("b", 400), ("c", 300), ("d", 200)]);
+    let new = list(200, &[("c", 900), ("a", 450), ("e", 420), ("b", 400)]);
+    let diff = diff_richlists(&old, &new);
+    assert_eq!((diff.from_height, diff.to_height), (100, 200));
+    assert_eq!(diff.increases.len(), 1);
+    assert_eq!(
+        (diff.increases[0].address.as_str(), diff.increases[0].change),
+        ("c", 600)
+    );
+    assert_eq!(diff.decreases.len(), 1);
+    assert_eq!(
+        (diff.decreases[0].before, diff.decreases[0].after),
+        (500, 450)
+    );
+    assert_eq!(diff.entered[0].address, "e");
+    assert_eq!(
+        diff.dropped,
+        [RichEntry {
+            address: "d".to_string(),
+            balance: 200
+        }]
+    );
+}
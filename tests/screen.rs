@@ -0,0 +1,201 @@
+// screen.rs
+mod common;
+
+use snap_coin::{
+    core::transaction::{Transaction, TransactionInput, TransactionOutput},
+    crypto::{Hash, keys::Public},
+};
+use snap_coin_utils::{
+    fixture::FixtureProvider,
+    miners::coinbase,
+    screen::{
+        Direction, KnownOutputs, WatchState, Watchlist, load_watchlist, screen_blocks,
+        screen_transaction,
+    },
+};
+
+use common::fixture;
+
+fn spend(owner: Public, spent: &[(Hash, usize)], outputs: Vec<TransactionOutput>) -> Transaction {
+    Transaction {
+        inputs: spent
+            .iter()
+            .map(|&(transaction_id, output_index)| TransactionInput {
+                transaction_id,
+                output_index,
+                signature: None,
+                output_owner: owner,
+            })
+            .collect(),
+        outputs,
+        transaction_id: Some(Hash::new_from_buf([0x5c; 32])),
+        nonce: 0,
+        timestamp: 0,
+    }
+}
+
+#[test]
+fn watchlists_skip_comments_and_notes() {
+    let dir = std::env::temp_dir().join(format!("snap-coin-utils-screen-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("watchlist.txt");
+    let first = Public::new_from_buf(&[1; 32]);
+    let second = Public::new_from_buf(&[2; 32]);
+    std::fs::write(
+        &path,
+        format!(
+            "# sanctioned\n{}\n\n{}, seized wallet # 2025\n",
+            first.dump_base36(),
+            second.dump_base36()
+        ),
+    )
+    .unwrap();
+    let watchlist = load_watchlist(&path).unwrap();
+    assert_eq!(watchlist.len(), 2);
+    assert!(watchlist.contains(&first) && watchlist.contains(&second));
+    assert!(!watchlist.contains(&Public::new_from_buf(&[3; 32])));
+
+    std::fs::write(&path, format!("{}\nnot-an-address\n", first.dump_base36())).unwrap();
+    let error = load_watchlist(&path).unwrap_err().to_string();
+    assert!(error.starts_with("Line 2 of"), "{}", error);
+
+    std::fs::write(&path, "# nothing yet\n").unwrap();
+    assert!(load_watchlist(&path).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn hits_carry_direction_and_amount() {
+    let watched = Public::new_from_buf(&[1; 32]);
+    let other = Public::new_from_buf(&[2; 32]);
+    let mut watchlist = Watchlist::default();
+    watchlist.insert(watched);
+    let funding = Hash::new_from_buf([9; 32]);
+    let tx = spend(
+        watched,
+        &[(funding, 0), (funding, 1)],
+        vec![
+            TransactionOutput {
+                amount: 70,
+                receiver: other,
+            },
+            TransactionOutput {
+                amount: 20,
+                receiver: watched,
+            },
+        ],
+    );
+
+    let known = KnownOutputs::from([((funding, 0), 40), ((funding, 1), 60)]);
+    let hits = screen_transaction(&tx, Some(601), &watchlist, &known);
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0].direction, Direction::Incoming);
+    assert_eq!(hits[0].amount, Some(20));
+    assert_eq!(hits[1].direction, Direction::Outgoing);
+    assert_eq!(hits[1].amount, Some(100));
+    assert_eq!(hits[1].address, watched.dump_base36());
+    assert_eq!(hits[1].height, Some(601));
+
+    // One spent output that can't be priced leaves the outgoing amount unknown
+    let known = KnownOutputs::from([((funding, 0), 40)]);
+    let hits = screen_transaction(&tx, None, &watchlist, &known);
+    assert_eq!(hits[1].amount, None);
+
+    let mut unrelated = Watchlist::default();
+    unrelated.insert(Public::new_from_buf(&[3; 32]));
+    assert!(screen_transaction(&tx, None, &unrelated, &known).is_empty());
+}
+
+#[tokio::test]
+async fn blocks_price_spends_of_outputs_seen_in_the_range() {
+    let fixture = fixture();
+    let first = coinbase(&fixture.blocks[0]).unwrap().clone();
+    let owner = first.outputs[1].receiver;
+    let spent = first.outputs[1].amount;
+    let provider = FixtureProvider::new(fixture);
+    let mut watchlist = Watchlist::default();
+    watchlist.insert(owner);
+
+    let mut known = KnownOutputs::new();
+    let hits = screen_blocks(&provider, None, 560..600, &watchlist, &mut known)
+        .await
+        .unwrap();
+    assert_eq!(hits[0].height, Some(560));
+    assert_eq!(hits[0].direction, Direction::Incoming);
+    let outgoing: Vec<_> = hits
+        .iter()
+        .filter(|hit| hit.direction == Direction::Outgoing)
+        .collect();
+    // The miner of 560 spends its rewards of 560 and 575 ten blocks later
+    let heights: Vec<_> = outgoing.iter().map(|hit| hit.height).collect();
+    assert_eq!(heights, [Some(570), Some(585)]);
+    assert_eq!(outgoing[0].amount, Some(spent));
+
+    // Starting after the funding block, the spent output is unknown without a node
+    let mut known = KnownOutputs::new();
+    let hits = screen_blocks(&provider, None, 561..600, &watchlist, &mut known)
+        .await
+        .unwrap();
+    let outgoing = hits
+        .iter()
+        .find(|hit| hit.direction == Direction::Outgoing)
+        .unwrap();
+    assert_eq!(outgoing.amount, None);
+}
+
+#[tokio::test]
+async fn watch_polls_report_each_block_and_mempool_transaction_once() {
+    let fixture = fixture();
+    let first = coinbase(&fixture.blocks[0]).unwrap().clone();
+    let owner = first.outputs[1].receiver;
+    let pending = spend(
+        owner,
+        &[(first.transaction_id.unwrap(), 1)],
+        vec![TransactionOutput {
+            amount: 10,
+            receiver: Public::new_from_buf(&[2; 32]),
+        }],
+    );
+    let provider = FixtureProvider::new(fixture);
+    let mut watchlist = Watchlist::default();
+    watchlist.insert(owner);
+
+    // The first poll screens the last `blocks` blocks: the rewards every 5 blocks from 580 and
+    // the transfer of 585 paying change back
+    let mut state = WatchState::default();
+    let hits = state
+        .poll(&provider, None, &[], &watchlist, 20)
+        .await
+        .unwrap();
+    let heights: Vec<_> = hits.iter().map(|hit| hit.height).collect();
+    assert_eq!(
+        heights,
+        [
+            Some(580),
+            Some(585),
+            Some(585),
+            Some(585),
+            Some(590),
+            Some(595)
+        ]
+    );
+    assert_eq!(state.next, Some(600));
+
+    // Without new blocks only unseen mempool transactions are reported, and only once
+    let mempool = [pending];
+    let hits = state
+        .poll(&provider, None, &mempool, &watchlist, 20)
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].height, None);
+    assert_eq!(hits[0].direction, Direction::Outgoing);
+    assert_eq!(hits[0].amount, None);
+    assert!(
+        state
+            .poll(&provider, None, &mempool, &watchlist, 20)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
@@ -0,0 +1,52 @@
+// select.rs
+use serde_json::{Deserializer, Value, json};
+use std::process::{Command, Output};
+
+/// Run `rewards --format json --select <expr>`, the emission schedule being an array of eras
+fn rewards_select(expr: &str) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_snap-coin-utils"))
+        .args(["rewards", "--format", "json", "--select", expr])
+        .output()
+        .unwrap()
+}
+
+fn select(expr: &str) -> Vec<Value> {
+    let output = rewards_select(expr);
+    assert!(output.status.success());
+    Deserializer::from_slice(&output.stdout)
+        .into_iter()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn fields_and_indexes() {
+    assert_eq!(select(".[0].start_height"), vec![json!(0)]);
+    assert_eq!(select("[1].era"), vec![json!(1)]);
+    assert_eq!(select(".[0]")[0]["era"], json!(0));
+}
+
+#[test]
+fn iteration_fans_out() {
+    let eras = select(".[].era");
+    assert!(eras.len() > 1);
+    for (i, era) in eras.iter().enumerate() {
+        assert_eq!(*era, json!(i));
+    }
+}
+
+#[test]
+fn missing_values_are_null() {
+    assert_eq!(select(".[0].missing"), vec![Value::Null]);
+    assert_eq!(select(".[100000]"), vec![Value::Null]);
+}
+
+#[test]
+fn invalid_expressions_are_rejected() {
+    for expr in ["era", ".[", ".[x]", ".[-1]"] {
+        assert!(
+            !rewards_select(expr).status.success(),
+            "{expr} was accepted"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+// send.rs
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use snap_coin::{
+    api::requests::{Request, Response},
+    core::transaction::{Transaction, TransactionOutput},
+    crypto::{Hash, keys::Private},
+    economics::EXPIRATION_TIME,
+};
+use snap_coin_utils::{
+    node::NodeClient,
+    parse_snap,
+    send::{Rebroadcast, estimate, find_sent, plan, rebroadcast, record_sent, select_coins},
+};
+
+/// UTXOs of 10, 50 and 30 nano
+fn utxos(owner: &Private) -> Vec<(Hash, TransactionOutput, usize)> {
+    [10, 50, 30]
+        .into_iter()
+        .enumerate()
+        .map(|(i, amount)| {
+            let output = TransactionOutput {
+                amount,
+                receiver: owner.to_public(),
+            };
+            (Hash::new_from_buf([i as u8; 32]), output, i)
+        })
+        .collect()
+}
+
+/// Serve the UTXOs of `owner` at the easiest transaction difficulty. Submitted transactions
+/// wait in the mempool, and `confirmed` ones are served as mined.
+async fn mock_node(
+    owner: Private,
+    submitted: Arc<Mutex<Vec<Transaction>>>,
+    confirmed: Vec<Transaction>,
+) -> NodeClient {
+    let address = common::mock_node(move |request| {
+        Some(match request {
+            Request::AvailableUTXOs { .. } => Response::AvailableUTXOs {
+                available_inputs: utxos(&owner),
+                next_page: None,
+            },
+            Request::Difficulty => Response::Difficulty {
+                transaction_difficulty: [255; 32],
+                block_difficulty: [255; 32],
+            },
+            Request::LiveTransactionDifficulty => Response::LiveTransactionDifficulty {
+                live_difficulty: [255; 32],
+            },
+            Request::NewTransaction { new_transaction } => {
+                submitted.lock().unwrap().push(new_transaction);
+                Response::NewTransaction { status: Ok(()) }
+            }
+            Request::Transaction { transaction_id } => Response::Transaction {
+                transaction: confirmed
+                    .iter()
+                    .find(|tx| tx.transaction_id == Some(transaction_id))
+                    .cloned(),
+            },
+            Request::Mempool { .. } => Response::Mempool {
+                mempool: submitted.lock().unwrap().clone(),
+                next_page: None,
+            },
+            _ => return None,
+        })
+    })
+    .await;
+    NodeClient::connect(address).await.unwrap()
+}
+
+#[test]
+fn amounts_parse_without_rounding() {
+    assert_eq!(parse_snap("12.5").unwrap(), 1_250_000_000);
+    assert_eq!(parse_snap("0.00000001").unwrap(), 1);
+    assert_eq!(parse_snap("3").unwrap(), 300_000_000);
+    assert_eq!(parse_snap(".5").unwrap(), 50_000_000);
+    for invalid in ["", ".", "1.000000001", "-1", "1e3", "1.2.3"] {
+        assert!(parse_snap(invalid).is_err(), "{invalid}");
+    }
+}
+
+#[test]
+fn biggest_coins_are_spent_first() {
+    let owner = Private::new_random();
+    let amounts = |amount| -> Vec<u64> {
+        select_coins(utxos(&owner), amount)
+            .unwrap()
+            .iter()
+            .map(|input| input.amount)
+            .collect()
+    };
+    assert_eq!(amounts(40), [50]);
+    assert_eq!(amounts(50), [50]);
+    assert_eq!(amounts(60), [50, 30]);
+    assert_eq!(amounts(90), [50, 30, 10]);
+    assert!(select_coins(utxos(&owner), 91).is_err());
+}
+
+#[tokio::test]
+async fn plans_sign_into_balanced_transactions() {
+    let owner = Private::new_random();
+    let receiver = Private::new_random().to_public();
+    let submitted = Arc::new(Mutex::new(Vec::new()));
+    let client = mock_node(owner, submitted.clone(), Vec::new()).await;
+
+    let plan = plan(&client, owner.to_public(), receiver, 60)
+        .await
+        .unwrap();
+    assert_eq!((plan.input_value, plan.change, plan.fee), (80, 20, 0));
+    assert!(plan.hash_rate > 0.0);
+    assert!(plan.build(&Private::new_random()).is_err());
+
+    let tx = plan.build(&owner).unwrap();
+    assert!(tx.transaction_id.is_some());
+    assert_eq!(tx.inputs.len(), 2);
+    assert_eq!(
+        tx.outputs.iter().map(|output| output.amount).sum::<u64>(),
+        plan.input_value
+    );
+    let signing_buf = tx.get_input_signing_buf().unwrap();
+    for input in &tx.inputs {
+        let signature = input.signature.unwrap();
+        assert!(
+            signature
+                .validate_with_public(&owner.to_public(), &signing_buf)
+                .unwrap()
+        );
+    }
+    // The size estimated before signing holds for the mined transaction
+    let size = snap_coin_utils::fees::transaction_size(&tx).unwrap();
+    assert!(size <= plan.size);
+    // and so does the estimate for any transaction of that shape
+    let estimated = estimate(&client, 2, 2).await.unwrap();
+    assert!(size <= estimated.size);
+    assert_eq!(estimated.minimum_fee, 0);
+    assert!(estimated.estimated_pow_secs > 0.0);
+    assert!(estimate(&client, 0, 1).await.is_err());
+    assert!(estimate(&client, 100, 51).await.is_err());
+
+    client.submit_transaction(tx).await.unwrap().unwrap();
+    assert_eq!(submitted.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn rebroadcasts_only_what_the_node_lost() {
+    let owner = Private::new_random();
+    let receiver = Private::new_random().to_public();
+    let submitted = Arc::new(Mutex::new(Vec::new()));
+    let client = mock_node(owner, submitted.clone(), Vec::new()).await;
+    let plan = plan(&client, owner.to_public(), receiver, 5).await.unwrap();
+    let (tx, mined) = (plan.build(&owner).unwrap(), plan.build(&owner).unwrap());
+
+    // The sent log finds transactions by id
+    let log = std::env::temp_dir().join(format!("sent-{}.jsonl", std::process::id()));
+    let _ = std::fs::remove_file(&log);
+    let id = tx.transaction_id.unwrap();
+    assert!(find_sent(&log, &id).unwrap().is_none());
+    record_sent(&log, &mined).unwrap();
+    record_sent(&log, &tx).unwrap();
+    assert_eq!(find_sent(&log, &id).unwrap().unwrap().nonce, tx.nonce);
+    std::fs::remove_file(&log).unwrap();
+
+    assert_eq!(
+        rebroadcast(&client, tx.clone()).await.unwrap(),
+        Rebroadcast::Resubmitted
+    );
+    assert_eq!(
+        rebroadcast(&client, tx.clone()).await.unwrap(),
+        Rebroadcast::InMempool
+    );
+    let mut expired = tx.clone();
+    expired.timestamp -= EXPIRATION_TIME + 1;
+    expired.transaction_id = mined.transaction_id;
+    assert_eq!(
+        rebroadcast(&client, expired).await.unwrap(),
+        Rebroadcast::Expired
+    );
+
+    let client = mock_node(owner, Arc::new(Mutex::new(Vec::new())), vec![mined.clone()]).await;
+    assert_eq!(
+        rebroadcast(&client, mined).await.unwrap(),
+        Rebroadcast::Confirmed
+    );
+    assert_eq!(submitted.lock().unwrap().len(), 1);
+}
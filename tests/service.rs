@@ -0,0 +1,78 @@
+// service.rs
+use std::ffi::OsString;
+
+use snap_coin_utils::service::{
+    PidFile, detached_args, process_running, request_stop, running_pid, stop_requested,
+    syslog_priority,
+};
+use tracing::Level;
+
+#[test]
+fn detached_monitors_keep_the_rest_of_the_command_line() {
+    let command_line: Vec<OsString> = [
+        "snap-coin-utils",
+        "node:3003",
+        "monitor",
+        "--state",
+        "/var/lib/snap/state.json",
+        "--daemon",
+        "--pid-file",
+        "/run/snap.pid",
+    ]
+    .iter()
+    .map(OsString::from)
+    .collect();
+    assert_eq!(
+        detached_args(&command_line, &["--daemon"], &["--detached"]),
+        [
+            "node:3003",
+            "monitor",
+            "--state",
+            "/var/lib/snap/state.json",
+            "--pid-file",
+            "/run/snap.pid",
+            "--detached",
+        ]
+        .map(OsString::from)
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn pid_files_refuse_a_second_monitor() {
+    let dir = std::env::temp_dir().join(format!("snap-coin-utils-service-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("monitor.pid");
+    assert_eq!(running_pid(&path).unwrap(), None);
+
+    let pid_file = PidFile::create(&path).unwrap();
+    assert_eq!(running_pid(&path).unwrap(), Some(std::process::id()));
+    drop(pid_file);
+    assert!(!path.exists());
+
+    // init always runs, a pid past the kernel's limit never does
+    assert!(process_running(1));
+    assert!(!process_running(0));
+    std::fs::write(&path, "1\n").unwrap();
+    assert!(PidFile::create(&path).is_err());
+    std::fs::write(&path, format!("{}\n", i32::MAX)).unwrap();
+    assert_eq!(running_pid(&path).unwrap(), None);
+    drop(PidFile::create(&path).unwrap());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn syslog_messages_use_the_daemon_facility() {
+    assert_eq!(syslog_priority(Level::ERROR), 27);
+    assert_eq!(syslog_priority(Level::WARN), 28);
+    assert_eq!(syslog_priority(Level::INFO), 30);
+    assert_eq!(syslog_priority(Level::TRACE), 31);
+}
+
+#[tokio::test]
+async fn stop_requests_wake_the_daemon_even_when_early() {
+    request_stop();
+    tokio::time::timeout(std::time::Duration::from_secs(1), stop_requested())
+        .await
+        .unwrap();
+}
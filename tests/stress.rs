@@ -0,0 +1,51 @@
+// stress.rs
+use std::{collections::BTreeMap, time::Duration};
+
+use snap_coin_utils::{
+    load::{Load, Query, QueryStats},
+    stress::{percentile, stress_report},
+};
+
+#[test]
+fn percentiles_use_the_nearest_rank() {
+    let latencies: Vec<_> = (1..=100).map(Duration::from_millis).collect();
+    assert_eq!(percentile(&latencies, 50.0), Duration::from_millis(50));
+    assert_eq!(percentile(&latencies, 99.0), Duration::from_millis(99));
+    assert_eq!(percentile(&latencies, 100.0), Duration::from_millis(100));
+    assert_eq!(percentile(&latencies[..1], 90.0), Duration::from_millis(1));
+    assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+}
+
+#[test]
+fn report_counts_errors_per_query() {
+    let mut queries = BTreeMap::new();
+    queries.insert(
+        Query::Blocks,
+        QueryStats {
+            latencies: vec![Duration::from_millis(30), Duration::from_millis(10)],
+            errors: 2,
+        },
+    );
+    queries.insert(
+        Query::Addrs,
+        QueryStats {
+            latencies: vec![Duration::from_millis(5)],
+            errors: 0,
+        },
+    );
+    let load = Load {
+        elapsed: Duration::from_secs(2),
+        queries,
+    };
+
+    let report = stress_report(&load, 4);
+    assert_eq!(report.queries.len(), 2);
+    let blocks = &report.queries[0];
+    assert_eq!(blocks.query, Query::Blocks);
+    assert_eq!(blocks.requests, 4);
+    assert_eq!(blocks.error_rate, 50.0);
+    assert_eq!(blocks.throughput, 2.0);
+    assert_eq!(blocks.p50_ms, 10.0);
+    assert_eq!(blocks.max_ms, 30.0);
+    assert_eq!(report.queries[1].errors, 0);
+}
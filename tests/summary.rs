@@ -0,0 +1,69 @@
+// summary.rs
+mod common;
+
+use std::process::Command;
+
+use snap_coin::crypto::keys::Public;
+use snap_coin_utils::{
+    config::Config, fixture::FixtureProvider, miners::block_miner, summary::block_summary,
+};
+
+use common::{FIXTURE, fixture};
+
+#[tokio::test]
+async fn summary_of_a_fixture_block() {
+    let fixture = fixture();
+    let block = fixture.blocks[16].clone();
+    let miner = Public::new_from_buf(&block_miner(&block).unwrap()).dump_base36();
+    let mut config = Config::default();
+    config.labels.insert("pool".to_string(), miner.clone());
+    let provider = FixtureProvider::new(fixture);
+
+    let summary = block_summary(&provider, &block, &config).await.unwrap();
+    assert_eq!(summary.height, Some(576));
+    assert_eq!(summary.confirmations, Some(24));
+    assert_eq!(summary.miner, Some(miner));
+    assert_eq!(summary.miner_label.as_deref(), Some("pool"));
+    assert_eq!(summary.transactions, block.transactions.len());
+    let output_value: u64 = block
+        .transactions
+        .iter()
+        .flat_map(|tx| &tx.outputs)
+        .map(|output| output.amount)
+        .sum();
+    assert_eq!(summary.output_value, output_value);
+    assert!(summary.size > 0);
+    assert!(summary.subsidy.is_some());
+
+    // Blocks off the main chain have no height to know the subsidy of
+    let mut stale = block.clone();
+    stale.meta.hash = None;
+    let summary = block_summary(&provider, &stale, &config).await.unwrap();
+    assert_eq!(summary.height, None);
+    assert_eq!(summary.subsidy, None);
+    assert_eq!(summary.fees, None);
+}
+
+/// Run `block` on the fixture in a POSIX `TZ` time zone, returning stdout
+fn show_block(tz: &str, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_snap-coin-utils"))
+        .args(["--fixture", FIXTURE, "--color", "never", "block", "576"])
+        .args(args)
+        .env("TZ", tz)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn timestamps_are_local_unless_raw() {
+    let utc = show_block("UTC0", &["--summary"]);
+    assert!(utc.contains("Timestamp: 2025-10-09 12:13:50 +00:00"));
+    let ist = show_block("IST-5:30", &["--summary"]);
+    assert!(ist.contains("Timestamp: 2025-10-09 17:43:50 +05:30"));
+
+    let raw = show_block("UTC0", &["--raw"]);
+    assert!(raw.contains("timestamp: 1760012030"));
+    assert!(!raw.contains("Confirmations"));
+}
@@ -0,0 +1,65 @@
+// systemd.rs
+use std::time::Duration;
+
+use snap_coin_utils::systemd::watchdog_interval;
+
+#[test]
+fn watchdog_is_pinged_at_half_its_interval() {
+    assert_eq!(
+        watchdog_interval(Some("30000000"), None, 42),
+        Some(Duration::from_secs(15))
+    );
+    assert_eq!(
+        watchdog_interval(Some("30000000"), Some("42"), 42),
+        Some(Duration::from_secs(15))
+    );
+}
+
+#[test]
+fn tiny_watchdogs_are_pinged_every_millisecond() {
+    assert_eq!(
+        watchdog_interval(Some("1"), None, 42),
+        Some(Duration::from_millis(1))
+    );
+    assert_eq!(
+        watchdog_interval(Some("2500"), None, 42),
+        Some(Duration::from_micros(1250))
+    );
+}
+
+#[test]
+fn watchdog_of_another_process_or_off_is_ignored() {
+    assert_eq!(watchdog_interval(Some("30000000"), Some("7"), 42), None);
+    assert_eq!(watchdog_interval(None, None, 42), None);
+    assert_eq!(watchdog_interval(Some("0"), None, 42), None);
+    assert_eq!(watchdog_interval(Some("soon"), None, 42), None);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn notifications_reach_path_and_abstract_sockets() {
+    use snap_coin_utils::systemd::notify_socket;
+    use std::{
+        ffi::OsStr,
+        os::{
+            linux::net::SocketAddrExt,
+            unix::net::{SocketAddr, UnixDatagram},
+        },
+    };
+
+    let path = std::env::temp_dir().join(format!("snap-coin-utils-notify-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let socket = UnixDatagram::bind(&path).unwrap();
+    notify_socket(path.as_os_str(), "READY=1").unwrap();
+    let mut buf = [0u8; 64];
+    let received = socket.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..received], b"READY=1");
+    std::fs::remove_file(&path).unwrap();
+
+    let name = format!("snap-coin-utils-notify-{}", std::process::id());
+    let socket =
+        UnixDatagram::bind_addr(&SocketAddr::from_abstract_name(name.as_bytes()).unwrap()).unwrap();
+    notify_socket(OsStr::new(&format!("@{name}")), "WATCHDOG=1").unwrap();
+    let received = socket.recv(&mut buf).unwrap();
+    assert_eq!(&buf[..received], b"WATCHDOG=1");
+}
@@ -0,0 +1,77 @@
+// tail.rs
+mod common;
+
+use snap_coin_utils::{
+    fixture::FixtureProvider,
+    monitor::MonitorEvent,
+    tail::{TxFeed, large_transactions, tx_line},
+};
+
+use common::{fixture, provider};
+
+#[tokio::test]
+async fn only_transactions_above_the_threshold_are_streamed() {
+    let fixture = fixture();
+    let transfers: Vec<_> = fixture
+        .blocks
+        .iter()
+        .flat_map(|block| &block.transactions)
+        .filter(|tx| !tx.inputs.is_empty())
+        .cloned()
+        .collect();
+    let provider = FixtureProvider::new(fixture);
+
+    // Block 597 carries the only transfer moving more than 93 SNAP, its change is left out
+    let found = large_transactions(&provider, 560..600, 9_300_000_000)
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    let MonitorEvent::Whale { height, amount, .. } = &found[0] else {
+        panic!("not a whale event");
+    };
+    assert_eq!((*height, *amount), (Some(597), 9_310_000_000));
+    let tx = transfers.last().unwrap();
+    assert_eq!(
+        tx_line(&found[0]).unwrap(),
+        format!(
+            "{} {}→{} 93.10000000 SNAP #597",
+            tx.transaction_id.unwrap().dump_base36(),
+            tx.inputs[0].output_owner.dump_base36(),
+            tx.outputs[0].receiver.dump_base36()
+        )
+    );
+
+    // Without a threshold every transfer is streamed, block rewards never are
+    let all = large_transactions(&provider, 560..600, 0).await.unwrap();
+    assert_eq!(all.len(), transfers.len());
+    assert!(
+        all.iter()
+            .all(|event| matches!(event, MonitorEvent::Whale { .. }))
+    );
+}
+
+#[tokio::test]
+async fn the_feed_picks_up_where_the_last_poll_stopped() {
+    let provider = provider();
+    let mut feed = TxFeed::default();
+    // The first poll looks `blocks` back from the tip
+    let found = feed.poll(&provider, 10, 9_300_000_000).await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(feed.next, Some(600));
+    // Nothing was mined since
+    assert!(
+        feed.poll(&provider, 10, 9_300_000_000)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    // Too few blocks back to reach 597
+    let mut feed = TxFeed::default();
+    assert!(
+        feed.poll(&provider, 2, 9_300_000_000)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+}
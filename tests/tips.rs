@@ -0,0 +1,48 @@
+// tips.rs
+mod common;
+
+use snap_coin::crypto::Hash;
+use snap_coin_utils::{
+    fixture::FixtureProvider,
+    orphans::SeenBlock,
+    tips::{TipStatus, chain_tips},
+};
+
+use common::fixture;
+
+fn seen(seen: u64, hash: Hash, previous: Hash) -> SeenBlock {
+    SeenBlock {
+        seen,
+        hash,
+        previous,
+    }
+}
+
+#[tokio::test]
+async fn side_branches_end_in_fork_tips() {
+    let fixture = fixture();
+    let main = |i: usize| fixture.blocks[i].meta.hash.unwrap();
+    let (stale_a, stale_b) = (Hash::new(b"stale a"), Hash::new(b"stale b"));
+    let log = [
+        seen(1, main(10), main(9)),
+        // Two blocks built on 569 that lost to the main chain
+        seen(2, stale_a, main(9)),
+        seen(3, stale_b, stale_a),
+        seen(4, Hash::new(b"lost"), Hash::new(b"unknown parent")),
+    ];
+    let expected_tip = main(39);
+    let tips = chain_tips(&FixtureProvider::new(fixture), &log)
+        .await
+        .unwrap();
+
+    assert_eq!(tips.len(), 3);
+    assert_eq!(tips[0].status, TipStatus::Active);
+    assert_eq!(tips[0].height, Some(599));
+    assert_eq!(tips[0].hash, expected_tip);
+    assert_eq!(tips[1].status, TipStatus::Fork);
+    assert_eq!(tips[1].hash, stale_b);
+    assert_eq!(tips[1].height, Some(571));
+    assert_eq!(tips[1].branch_length, 2);
+    assert_eq!(tips[2].status, TipStatus::Detached);
+    assert_eq!(tips[2].height, None);
+}
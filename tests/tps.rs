@@ -0,0 +1,49 @@
+// tps.rs
+mod common;
+
+use snap_coin_utils::{
+    fixture::FixtureProvider,
+    tps::{tps_buckets, tps_series},
+};
+
+use common::fixture;
+
+#[test]
+fn blocks_fall_in_epoch_aligned_buckets() {
+    // Three blocks in the 3600..7200 bucket, two in the next, the scan starting at 4000
+    let blocks = [(4000, 2), (5000, 3), (7100, 5), (7300, 10), (9000, 20)];
+    let series = tps_buckets(50, &blocks, 3600).unwrap();
+    assert_eq!(series.buckets.len(), 2);
+
+    let first = &series.buckets[0];
+    assert_eq!((first.start, first.first_height), (3600, 50));
+    assert_eq!((first.blocks, first.transactions), (3, 10));
+    assert_eq!(first.secs, 7200 - 4000);
+    assert_eq!(first.tps, 10.0 / 3200.0);
+
+    // The scan ends at the last block, partway through the bucket
+    let last = &series.buckets[1];
+    assert_eq!(
+        (last.start, last.first_height, last.transactions),
+        (7200, 53, 30)
+    );
+    assert_eq!(last.secs, 9000 - 7200);
+
+    assert_eq!(
+        series.series()[0].points,
+        [(50, 10.0 / 3200.0), (53, 30.0 / 1800.0)]
+    );
+    assert!(tps_buckets(0, &blocks, 0).is_err());
+}
+
+#[tokio::test]
+async fn every_fixture_transaction_is_bucketed() {
+    let fixture = fixture();
+    let transactions: usize = fixture.blocks.iter().map(|b| b.transactions.len()).sum();
+    let provider = FixtureProvider::new(fixture);
+    let series = tps_series(&provider, 560..600, 60).await.unwrap();
+    let bucketed: usize = series.buckets.iter().map(|b| b.transactions).sum();
+    assert_eq!(bucketed, transactions);
+    assert_eq!(series.buckets.iter().map(|b| b.blocks).sum::<usize>(), 40);
+    assert_eq!(series.buckets[0].first_height, 560);
+}
@@ -0,0 +1,134 @@
+// transactions.rs
+mod common;
+
+use snap_coin::{
+    api::requests::{Request, Response},
+    core::transaction::{Transaction, TransactionOutput},
+    crypto::Hash,
+};
+use snap_coin_utils::{
+    error::ErrorKind,
+    fees::transaction_value,
+    fixture::Fixture,
+    node::NodeClient,
+    query::{TransactionStatus, transaction_info},
+};
+
+use common::{chain_response, fixture};
+
+/// Serve the fixture chain, with `mempool` waiting to be mined and `older` transactions from
+/// before the fixture
+async fn mock_node(
+    fixture: Fixture,
+    mempool: Vec<Transaction>,
+    older: Vec<Transaction>,
+) -> NodeClient {
+    let address = common::mock_node(move |request| match request {
+        Request::Transaction { transaction_id } => Some(Response::Transaction {
+            transaction: fixture
+                .blocks
+                .iter()
+                .flat_map(|block| &block.transactions)
+                .chain(&older)
+                .find(|tx| tx.transaction_id == Some(transaction_id))
+                .cloned(),
+        }),
+        Request::Mempool { .. } => Some(Response::Mempool {
+            mempool: mempool.clone(),
+            next_page: None,
+        }),
+        request => chain_response(&fixture, &request),
+    })
+    .await;
+    NodeClient::connect(address).await.unwrap()
+}
+
+#[tokio::test]
+async fn transactions_report_their_block_or_the_mempool() {
+    let fixture = fixture();
+    let block = fixture.blocks[15].clone();
+    let confirmed = block.transactions.last().unwrap().clone();
+    let mut pending = confirmed.clone();
+    pending.transaction_id = Some(Hash::new(b"pending"));
+    let client = mock_node(fixture, vec![pending.clone()], Vec::new()).await;
+
+    let id = confirmed.transaction_id.unwrap().dump_base36();
+    let info = transaction_info(&client, &id).await.unwrap();
+    match info.status {
+        TransactionStatus::Confirmed {
+            height,
+            block: hash,
+            confirmations,
+        } => {
+            assert_eq!(height, 575);
+            assert_eq!(Some(hash), block.meta.hash);
+            assert_eq!(confirmations, 25);
+        }
+        status => panic!("Expected a confirmed transaction, got {:?}", status),
+    }
+
+    let id = pending.transaction_id.unwrap().dump_base36();
+    let info = transaction_info(&client, &id).await.unwrap();
+    assert!(matches!(info.status, TransactionStatus::Mempool));
+
+    let id = Hash::new(b"missing").dump_base36();
+    let error = transaction_info(&client, &id).await.unwrap_err();
+    assert_eq!(ErrorKind::of(&error), ErrorKind::NotFound);
+}
+
+#[test]
+fn fee_is_what_the_outputs_leave_of_the_inputs() {
+    let fixture = fixture();
+    let tx = fixture.blocks[16].transactions[0].clone();
+    let output_value: u64 = tx.outputs.iter().map(|output| output.amount).sum();
+    let spent = TransactionOutput {
+        amount: output_value + 500,
+        receiver: tx.inputs[0].output_owner,
+    };
+
+    let value = transaction_value(&tx, &[Some(spent)]).unwrap();
+    assert_eq!(value.input_value, Some(output_value + 500));
+    assert_eq!(value.output_value, output_value);
+    assert_eq!(value.fee, Some(500));
+    assert!(value.size > 0);
+    assert_eq!(value.fee_rate, Some(500.0 / value.size as f64));
+    // Change back to the spender isn't transferred
+    let change: u64 = tx
+        .outputs
+        .iter()
+        .filter(|output| output.receiver == tx.inputs[0].output_owner)
+        .map(|output| output.amount)
+        .sum();
+    assert_eq!(value.transferred, output_value - change);
+
+    let unresolved = transaction_value(&tx, &[None]).unwrap();
+    assert_eq!(unresolved.fee, None);
+    let coinbase = &fixture.blocks[16].transactions[1];
+    assert_eq!(transaction_value(coinbase, &[]).unwrap().fee, None);
+}
+
+#[tokio::test]
+async fn inputs_resolve_to_the_outputs_they_spend() {
+    let fixture = fixture();
+    let tx = fixture.blocks[1].transactions[0].clone();
+    let input = &tx.inputs[0];
+    let spent = TransactionOutput {
+        amount: tx.outputs.iter().map(|output| output.amount).sum(),
+        receiver: input.output_owner,
+    };
+    // A transaction from before the fixture with the spent output at the input's index
+    let mut older = fixture.blocks[0].transactions[0].clone();
+    older.transaction_id = Some(input.transaction_id);
+    older.outputs = vec![spent; input.output_index + 1];
+    let client = mock_node(fixture, Vec::new(), vec![older]).await;
+
+    let info = transaction_info(&client, &tx.transaction_id.unwrap().dump_base36())
+        .await
+        .unwrap();
+    assert_eq!(info.spent.len(), 1);
+    let resolved = info.spent[0].as_ref().unwrap();
+    assert_eq!(resolved.amount, spent.amount);
+    assert_eq!(resolved.receiver, spent.receiver);
+    assert_eq!(info.value.input_value, Some(spent.amount));
+    assert_eq!(info.value.fee, Some(0));
+}
@@ -0,0 +1,137 @@
+// utxos.rs
+mod common;
+
+use snap_coin::{
+    core::transaction::{Transaction, TransactionInput, TransactionOutput},
+    crypto::{Hash, keys::Public},
+};
+use snap_coin_utils::{
+    fixture::FixtureProvider,
+    miners::coinbase,
+    utxos::{UtxoCommitment, UtxoSet, utxo_stats},
+};
+
+use common::fixture;
+
+#[tokio::test]
+async fn replaying_blocks_spends_and_adds_outputs() {
+    let mut fixture = fixture();
+    let first = coinbase(&fixture.blocks[0]).unwrap().clone();
+    let first_id = first.transaction_id.unwrap();
+    let owner = first.outputs[0].receiver;
+    // Spend the first block's dev fee into a dust output and change
+    fixture.blocks[5].transactions.push(Transaction {
+        inputs: vec![TransactionInput {
+            transaction_id: first_id,
+            output_index: 0,
+            signature: None,
+            output_owner: owner,
+        }],
+        outputs: vec![
+            TransactionOutput {
+                amount: 1,
+                receiver: Public::new_from_buf(&[0xa7; 32]),
+            },
+            TransactionOutput {
+                amount: first.outputs[0].amount - 1,
+                receiver: owner,
+            },
+        ],
+        transaction_id: Some(Hash::new_from_buf([0x55; 32])),
+        nonce: 0,
+        timestamp: 0,
+    });
+    let transactions: Vec<_> = fixture
+        .blocks
+        .iter()
+        .flat_map(|block| &block.transactions)
+        .collect();
+    let (created, value) = transactions
+        .iter()
+        .flat_map(|tx| &tx.outputs)
+        .fold((0, 0), |(n, sum), output| (n + 1, sum + output.amount));
+    // Outputs created in the range and spent later in it, the fixture's own transfers spend
+    // rewards mined ten blocks earlier
+    let (spent_count, spent) = transactions
+        .iter()
+        .flat_map(|tx| &tx.inputs)
+        .filter_map(|input| {
+            let funding = transactions
+                .iter()
+                .find(|tx| tx.transaction_id == Some(input.transaction_id))?;
+            Some(funding.outputs[input.output_index].amount)
+        })
+        .fold((0, 0), |(n, sum), amount| (n + 1, sum + amount));
+
+    let provider = FixtureProvider::new(fixture);
+    let mut set = UtxoSet::default();
+    let mut sizes = Vec::new();
+    set.replay(&provider, 560..600, |height, _, set| {
+        sizes.push((height, set.len()))
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(set.len(), created - spent_count);
+    assert_eq!(set.value(), value - spent);
+    assert!(set.iter().all(|(outpoint, _)| *outpoint != (first_id, 0)));
+    let dust = set
+        .iter()
+        .find(|(outpoint, _)| outpoint.0 == Hash::new_from_buf([0x55; 32]))
+        .unwrap()
+        .1;
+    assert_eq!(dust.height, 565);
+    assert_eq!(sizes.len(), 40);
+    assert_eq!(sizes[39], (599, set.len()));
+
+    let stats = utxo_stats(&set, 600, 2, sizes[29].1, 10, 500);
+    assert_eq!((stats.count, stats.value), (set.len(), value - spent));
+    assert_eq!(
+        stats.average_value,
+        (value - spent) as f64 / set.len() as f64
+    );
+    assert_eq!((stats.dust_count, stats.dust_value), (1, 1));
+    assert_eq!(utxo_stats(&set, 600, 1, 0, 0, 0).dust_count, 0);
+    let change = set.len() as i64 - sizes[29].1 as i64;
+    assert_eq!(stats.growth.count_change, change);
+    assert_eq!(stats.growth.per_block, change as f64 / 10.0);
+    assert_eq!(stats.growth.per_day, Some(change as f64 / 500.0 * 86_400.0));
+}
+
+#[test]
+fn an_empty_set_has_no_average_or_growth_rate() {
+    let stats = utxo_stats(&UtxoSet::default(), 0, 1, 0, 0, 0);
+    assert_eq!((stats.count, stats.value, stats.average_value), (0, 0, 0.0));
+    assert_eq!((stats.dust_count, stats.growth.count_change), (0, 0));
+    assert_eq!(stats.growth.per_day, None);
+}
+
+#[tokio::test]
+async fn commitments_match_only_for_the_same_set() {
+    let mut changed = fixture();
+    changed.blocks[10].transactions[0].outputs[0].amount += 1;
+
+    let mut commitments = Vec::new();
+    for fixture in [fixture(), changed] {
+        let provider = FixtureProvider::new(fixture);
+        let mut set = UtxoSet::default();
+        set.replay(&provider, 560..600, |_, _, _| {}).await.unwrap();
+        commitments.push(UtxoCommitment::of(&set, 600));
+        // Replaying the blocks in another set gives the same hash
+        let mut again = UtxoSet::default();
+        again
+            .replay(&provider, 560..600, |_, _, _| {})
+            .await
+            .unwrap();
+        assert_eq!(again.commitment(), set.commitment());
+    }
+    assert_eq!(commitments[0].commitment.len(), 64);
+    assert_eq!(commitments[0].count, commitments[1].count);
+    assert_ne!(commitments[0].commitment, commitments[1].commitment);
+
+    // An empty set hashes nothing
+    assert_eq!(
+        UtxoCommitment::of(&UtxoSet::default(), 0).commitment,
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+}
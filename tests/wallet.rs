@@ -0,0 +1,156 @@
+// wallet.rs
+mod common;
+
+use std::collections::HashSet;
+
+use snap_coin::{
+    api::requests::{Request, Response},
+    core::transaction::{Transaction, TransactionOutput},
+    crypto::keys::Public,
+};
+use snap_coin_utils::{
+    config::Config,
+    node::NodeClient,
+    wallet::{Wallet, wallet_balance, wallet_history, wallet_utxos},
+};
+
+use common::fixture;
+
+/// Serve the transactions of the fixture, every address holding 10 nano in one UTXO
+async fn mock_node(transactions: Vec<Transaction>) -> NodeClient {
+    let address = common::mock_node(move |request| {
+        Some(match request {
+            Request::Transaction { transaction_id } => Response::Transaction {
+                transaction: transactions
+                    .iter()
+                    .find(|tx| tx.transaction_id == Some(transaction_id))
+                    .cloned(),
+            },
+            Request::TransactionsOfAddress { address, .. } => Response::TransactionsOfAddress {
+                transactions: transactions
+                    .iter()
+                    .filter(|tx| tx.contains_address(address))
+                    .filter_map(|tx| tx.transaction_id)
+                    .collect(),
+                next_page: None,
+            },
+            Request::AvailableUTXOs { address, .. } => Response::AvailableUTXOs {
+                available_inputs: vec![(
+                    transactions[0].transaction_id.unwrap(),
+                    TransactionOutput {
+                        amount: 10,
+                        receiver: address,
+                    },
+                    0,
+                )],
+                next_page: None,
+            },
+            Request::Balance { .. } => Response::Balance { balance: 10 },
+            _ => return None,
+        })
+    })
+    .await;
+    NodeClient::connect(address).await.unwrap()
+}
+
+/// A wallet watching the sender and the first receiver of the fixture's transfers
+fn wallet(transactions: &[Transaction]) -> (Wallet, Public, Public) {
+    let transfer = transactions
+        .iter()
+        .find(|tx| !tx.inputs.is_empty())
+        .unwrap();
+    let (sender, receiver) = (
+        transfer.inputs[0].output_owner,
+        transfer.outputs[0].receiver,
+    );
+    let mut wallet = Wallet::default();
+    wallet
+        .add(&sender.dump_base36(), Some("sender".to_string()))
+        .unwrap();
+    wallet.add(&receiver.dump_base36(), None).unwrap();
+    (wallet, sender, receiver)
+}
+
+fn transactions() -> Vec<Transaction> {
+    let fixture = fixture();
+    fixture
+        .blocks
+        .into_iter()
+        .flat_map(|block| block.transactions)
+        .collect()
+}
+
+#[test]
+fn wallet_files_round_trip() {
+    let path = std::env::temp_dir().join(format!("wallet-{}.toml", std::process::id()));
+    let (mut wallet, sender, _) = wallet(&transactions());
+    // Adding a watched address again only relabels it
+    wallet
+        .add(&sender.dump_base36(), Some("hot".to_string()))
+        .unwrap();
+    assert!(wallet.add("not base36!", None).is_err());
+    wallet.save(&path).unwrap();
+
+    let loaded = Wallet::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.addresses.len(), 2);
+    assert_eq!(loaded.addresses[0].label.as_deref(), Some("hot"));
+    assert_eq!(loaded.addresses[1].label, None);
+    assert!(Wallet::load(&path).unwrap().addresses.is_empty());
+}
+
+#[tokio::test]
+async fn balances_and_utxos_add_up_across_addresses() {
+    let transactions = transactions();
+    let (wallet, _, receiver) = wallet(&transactions);
+    let client = mock_node(transactions).await;
+    let mut config = Config::default();
+    config
+        .labels
+        .insert("savings".to_string(), receiver.dump_base36());
+
+    let balance = wallet_balance(&client, &wallet, &config).await.unwrap();
+    assert_eq!(balance.total, 20);
+    let names: Vec<_> = balance.addresses.iter().map(|a| a.name.as_str()).collect();
+    // Without a wallet label the address book one is used
+    assert_eq!(names, ["sender", "savings"]);
+
+    let utxos = wallet_utxos(&client, &wallet, &config).await.unwrap();
+    assert_eq!(utxos.len(), 2);
+    assert!(
+        wallet_balance(&client, &Wallet::default(), &config)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn history_interleaves_transactions_once() {
+    let transactions = transactions();
+    let (wallet, sender, receiver) = wallet(&transactions);
+    let expected: HashSet<_> = transactions
+        .iter()
+        .filter(|tx| tx.contains_address(sender) || tx.contains_address(receiver))
+        .filter_map(|tx| tx.transaction_id)
+        .collect();
+    let client = mock_node(transactions).await;
+
+    let history = wallet_history(&client, &wallet, &Config::default())
+        .await
+        .unwrap();
+    assert_eq!(history.len(), expected.len());
+    assert!(
+        history
+            .windows(2)
+            .all(|pair| pair[0].timestamp <= pair[1].timestamp)
+    );
+    assert!(
+        history
+            .iter()
+            .all(|tx| expected.contains(&tx.transaction_id))
+    );
+    // A transfer between two watched addresses names both
+    if sender != receiver {
+        assert!(history.iter().any(|tx| tx.addresses.len() == 2));
+    }
+}